@@ -72,11 +72,11 @@ pub struct Conditioner {
 
 impl Conditioner {
     pub fn build(&self) -> LinkConditionerConfig {
-        LinkConditionerConfig {
-            incoming_latency: Duration::from_millis(self.latency_ms as u64),
-            incoming_jitter: Duration::from_millis(self.jitter_ms as u64),
-            incoming_loss: self.packet_loss,
-        }
+        LinkConditionerConfig::new(
+            Duration::from_millis(self.latency_ms as u64),
+            Duration::from_millis(self.jitter_ms as u64),
+            self.packet_loss,
+        )
     }
 }
 
@@ -119,7 +119,7 @@ pub struct ClientSettings {
     pub(crate) conditioner: Option<Conditioner>,
 }
 
-#[derive(Copy, Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct SharedSettings {
     /// An id to identify the protocol version
     pub protocol_id: u64,
@@ -145,11 +145,11 @@ pub(crate) fn build_server_netcode_config(
     transport_config: server::ServerTransport,
 ) -> server::NetConfig {
     let conditioner = conditioner.map_or(None, |c| {
-        Some(LinkConditionerConfig {
-            incoming_latency: Duration::from_millis(c.latency_ms as u64),
-            incoming_jitter: Duration::from_millis(c.jitter_ms as u64),
-            incoming_loss: c.packet_loss,
-        })
+        Some(LinkConditionerConfig::new(
+            Duration::from_millis(c.latency_ms as u64),
+            Duration::from_millis(c.jitter_ms as u64),
+            c.packet_loss,
+        ))
     });
     let netcode_config = server::NetcodeConfig::default()
         .with_protocol_id(shared.protocol_id)
@@ -157,7 +157,9 @@ pub(crate) fn build_server_netcode_config(
     let io_config = server::IoConfig {
         transport: transport_config,
         conditioner,
-        compression: shared.compression,
+        compression: shared.compression.clone(),
+        recorder: None,
+        capture: None,
     };
     server::NetConfig::Netcode {
         config: netcode_config,
@@ -262,7 +264,9 @@ pub(crate) fn build_client_netcode_config(
     let io_config = client::IoConfig {
         transport: transport_config,
         conditioner,
-        compression: shared.compression,
+        compression: shared.compression.clone(),
+        recorder: None,
+        capture: None,
     };
     client::NetConfig::Netcode {
         auth,
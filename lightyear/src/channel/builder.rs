@@ -4,11 +4,14 @@ use bevy::utils::Duration;
 use lightyear_macros::ChannelInternal;
 
 use crate::channel::receivers::ordered_reliable::OrderedReliableReceiver;
+use crate::channel::receivers::ordered_streams::OrderedStreamsReceiver;
+use crate::channel::receivers::redundant_unreliable::RedundantUnreliableReceiver;
 use crate::channel::receivers::sequenced_reliable::SequencedReliableReceiver;
 use crate::channel::receivers::sequenced_unreliable::SequencedUnreliableReceiver;
 use crate::channel::receivers::unordered_reliable::UnorderedReliableReceiver;
 use crate::channel::receivers::unordered_unreliable::UnorderedUnreliableReceiver;
 use crate::channel::receivers::ChannelReceiver;
+use crate::channel::senders::redundant_unreliable::RedundantUnreliableSender;
 use crate::channel::senders::reliable::ReliableSender;
 use crate::channel::senders::sequenced_unreliable::SequencedUnreliableSender;
 use crate::channel::senders::unordered_unreliable::UnorderedUnreliableSender;
@@ -83,27 +86,53 @@ impl ChannelContainer {
         match settings.mode {
             ChannelMode::UnorderedUnreliableWithAcks => {
                 receiver = UnorderedUnreliableReceiver::new().into();
-                sender = UnorderedUnreliableWithAcksSender::new(settings.send_frequency).into();
+                sender = UnorderedUnreliableWithAcksSender::new(settings.send_frequency)
+                    .with_message_ttl(settings.message_ttl)
+                    .into();
             }
             ChannelMode::UnorderedUnreliable => {
                 receiver = UnorderedUnreliableReceiver::new().into();
-                sender = UnorderedUnreliableSender::new(settings.send_frequency).into();
+                sender = UnorderedUnreliableSender::new(settings.send_frequency)
+                    .with_message_ttl(settings.message_ttl)
+                    .into();
+            }
+            ChannelMode::UnorderedUnreliableRedundant(redundancy_settings) => {
+                receiver = RedundantUnreliableReceiver::new().into();
+                sender =
+                    RedundantUnreliableSender::new(redundancy_settings, settings.send_frequency)
+                        .with_message_ttl(settings.message_ttl)
+                        .into();
             }
             ChannelMode::SequencedUnreliable => {
                 receiver = SequencedUnreliableReceiver::new().into();
-                sender = SequencedUnreliableSender::new(settings.send_frequency).into();
+                sender = SequencedUnreliableSender::new(settings.send_frequency)
+                    .with_message_ttl(settings.message_ttl)
+                    .into();
             }
             ChannelMode::UnorderedReliable(reliable_settings) => {
                 receiver = UnorderedReliableReceiver::new().into();
-                sender = ReliableSender::new(reliable_settings, settings.send_frequency).into();
+                sender = ReliableSender::new(reliable_settings, settings.send_frequency)
+                    .with_message_ttl(settings.message_ttl)
+                    .into();
             }
             ChannelMode::SequencedReliable(reliable_settings) => {
                 receiver = SequencedReliableReceiver::new().into();
-                sender = ReliableSender::new(reliable_settings, settings.send_frequency).into();
+                sender = ReliableSender::new(reliable_settings, settings.send_frequency)
+                    .with_message_ttl(settings.message_ttl)
+                    .into();
             }
             ChannelMode::OrderedReliable(reliable_settings) => {
                 receiver = OrderedReliableReceiver::new().into();
-                sender = ReliableSender::new(reliable_settings, settings.send_frequency).into();
+                sender = ReliableSender::new(reliable_settings, settings.send_frequency)
+                    .with_message_ttl(settings.message_ttl)
+                    .into();
+            }
+            ChannelMode::OrderedReliableStreams(reliable_settings) => {
+                receiver = OrderedStreamsReceiver::new().into();
+                sender = ReliableSender::new(reliable_settings, settings.send_frequency)
+                    .with_message_ttl(settings.message_ttl)
+                    .with_streams()
+                    .into();
             }
         }
         Self {
@@ -125,6 +154,15 @@ pub struct ChannelSettings {
     pub send_frequency: Duration,
     /// Sets the priority of the channel. The final priority of a message will be `MessagePriority * ChannelPriority`
     pub priority: f32,
+    /// How long a message can wait in the send buffers of the channel.
+    ///
+    /// Once the time-to-live of a message elapses, the message is dropped if it wasn't sent yet.
+    /// On reliable channels, the message also stops being resent, and a
+    /// [`MessageExpired`](crate::shared::events::components::MessageExpired) event is emitted.
+    /// It can be overridden for a given message with `send_message_with_ttl`.
+    ///
+    /// If None, the messages never expire.
+    pub message_ttl: Option<Duration>,
 }
 
 impl Default for ChannelSettings {
@@ -133,6 +171,7 @@ impl Default for ChannelSettings {
             mode: ChannelMode::UnorderedUnreliable,
             send_frequency: Duration::default(),
             priority: 1.0,
+            message_ttl: None,
         }
     }
 }
@@ -146,6 +185,10 @@ pub enum ChannelMode {
     UnorderedUnreliableWithAcks,
     /// Messages may arrive out-of-order, or not at all
     UnorderedUnreliable,
+    /// Same as unordered unreliable, but each message is also sent in the next packets of the channel,
+    /// so that an isolated packet loss doesn't lose the message (without waiting for a resend like
+    /// reliable channels). The copies of a message are discarded by the receiver.
+    UnorderedUnreliableRedundant(RedundancySettings),
     /// Same as unordered unreliable, but only the newest message is ever accepted, older messages
     /// are ignored
     SequencedUnreliable,
@@ -156,17 +199,28 @@ pub enum ChannelMode {
     SequencedReliable(ReliableSettings),
     /// Messages will arrive in the correct order at the destination
     OrderedReliable(ReliableSettings),
+    /// Same as ordered reliable, but the messages are only ordered within their stream.
+    ///
+    /// Each message is sent on a [`StreamId`] (for example one stream per entity or per conversation),
+    /// so a lost message only delays the messages of its own stream.
+    /// The messages sent without a stream id use the stream 0.
+    OrderedReliableStreams(ReliableSettings),
 }
 
+/// Identifier of an ordering stream within a [`ChannelMode::OrderedReliableStreams`] channel
+pub type StreamId = u32;
+
 impl ChannelMode {
     pub fn is_reliable(&self) -> bool {
         match self {
             ChannelMode::UnorderedUnreliableWithAcks => false,
             ChannelMode::UnorderedUnreliable => false,
+            ChannelMode::UnorderedUnreliableRedundant(_) => false,
             ChannelMode::SequencedUnreliable => false,
             ChannelMode::UnorderedReliable(_) => true,
             ChannelMode::SequencedReliable(_) => true,
             ChannelMode::OrderedReliable(_) => true,
+            ChannelMode::OrderedReliableStreams(_) => true,
         }
     }
 
@@ -175,10 +229,25 @@ impl ChannelMode {
         match self {
             ChannelMode::UnorderedUnreliableWithAcks => true,
             ChannelMode::UnorderedUnreliable => false,
+            // the acks are used to measure the packet loss
+            ChannelMode::UnorderedUnreliableRedundant(_) => true,
             ChannelMode::SequencedUnreliable => false,
             ChannelMode::UnorderedReliable(_) => true,
             ChannelMode::SequencedReliable(_) => true,
             ChannelMode::OrderedReliable(_) => true,
+            ChannelMode::OrderedReliableStreams(_) => true,
+        }
+    }
+
+    /// Returns true if the packets of the channel should be sent on a reliable stream of the
+    /// transport, when the transport has some
+    pub(crate) fn uses_transport_stream(&self) -> bool {
+        match self {
+            ChannelMode::UnorderedReliable(settings)
+            | ChannelMode::SequencedReliable(settings)
+            | ChannelMode::OrderedReliable(settings)
+            | ChannelMode::OrderedReliableStreams(settings) => settings.transport_stream,
+            _ => false,
         }
     }
 }
@@ -197,6 +266,12 @@ pub struct ReliableSettings {
     pub rtt_resend_factor: f32,
     /// Minimum duration to wait before resending a packet if it has not been acked
     pub rtt_resend_min_delay: Duration,
+    /// If true and the transport has reliable streams (QUIC), the packets of the channel are sent
+    /// on a stream of the transport dedicated to this channel instead of datagrams.
+    ///
+    /// The messages are then considered acked as soon as they are written to the stream,
+    /// and are never resent by lightyear.
+    pub transport_stream: bool,
 }
 
 impl Default for ReliableSettings {
@@ -204,6 +279,7 @@ impl Default for ReliableSettings {
         Self {
             rtt_resend_factor: 1.5,
             rtt_resend_min_delay: Duration::default(),
+            transport_stream: true,
         }
     }
 }
@@ -215,6 +291,27 @@ impl ReliableSettings {
     }
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub struct RedundancySettings {
+    /// Maximum number of times each message is sent again in the next packets of the channel
+    pub redundancy: u8,
+    /// If true, the number of copies adapts to the packet loss measured on the channel: each message
+    /// is sent just enough times so that the probability of losing all the copies is very low (at least
+    /// once, and at most `redundancy` times).
+    ///
+    /// If false, each message is always sent again `redundancy` times.
+    pub adaptive: bool,
+}
+
+impl Default for RedundancySettings {
+    fn default() -> Self {
+        Self {
+            redundancy: 3,
+            adaptive: true,
+        }
+    }
+}
+
 /// Default channel to replicate entity actions.
 /// This is an Unordered Reliable channel.
 /// (SpawnEntity, DespawnEntity, InsertComponent, RemoveComponent)
@@ -244,3 +341,13 @@ pub struct InputChannel;
 /// Channel to send messages related to Authority transfers
 /// This is an Ordered Reliable channel
 pub struct AuthorityChannel;
+
+#[derive(ChannelInternal)]
+/// Channel to send the responses to requests
+/// This is an Unordered Reliable channel
+pub struct RpcChannel;
+
+#[derive(ChannelInternal)]
+/// Channel to send the acks of delta-compressed resource updates
+/// This is an Unordered Unreliable channel
+pub struct ResourceAckChannel;
@@ -4,7 +4,6 @@ use bytes::Bytes;
 use tracing::trace;
 
 use crate::packet::message::{FragmentData, MessageId};
-use crate::packet::packet::FRAGMENT_SIZE;
 use crate::prelude::Tick;
 use crate::shared::time_manager::WrappedTime;
 
@@ -33,6 +32,12 @@ impl FragmentReceiver {
         })
     }
 
+    /// Discard the fragments received for a message, for example because the message expired
+    /// on the sender side
+    pub fn discard(&mut self, message_id: MessageId) {
+        self.fragment_messages.remove(&message_id);
+    }
+
     /// Receive a fragment of a FragmentData message.
     ///
     /// When we complete the final message by aggregating all fragments, we will return the
@@ -53,7 +58,7 @@ impl FragmentReceiver {
         // completed the fragmented message!
         if let Some(payload) = fragment_message.receive_fragment(
             fragment.fragment_id as usize,
-            fragment.bytes.as_ref(),
+            fragment.bytes,
             current_time,
         ) {
             self.fragment_messages.remove(&fragment.message_id);
@@ -70,8 +75,10 @@ pub struct FragmentConstructor {
     num_fragments: usize,
     num_received_fragments: usize,
     received: Vec<bool>,
-    // bytes: Bytes,
-    bytes: Vec<u8>,
+    /// The fragments received so far. We don't copy them into a single buffer right away
+    /// because the fragment size depends on the MTU of the sender, so the offset of a fragment
+    /// is only known once all the previous fragments are received.
+    fragments: Vec<Bytes>,
 
     tick: Tick,
     last_received: Option<WrappedTime>,
@@ -83,7 +90,7 @@ impl FragmentConstructor {
             num_fragments,
             num_received_fragments: 0,
             received: vec![false; num_fragments],
-            bytes: vec![0; num_fragments * FRAGMENT_SIZE],
+            fragments: vec![Bytes::new(); num_fragments],
             tick,
             last_received: None,
         }
@@ -92,32 +99,22 @@ impl FragmentConstructor {
     pub fn receive_fragment(
         &mut self,
         fragment_index: usize,
-        bytes: &[u8],
+        bytes: Bytes,
         received_time: Option<WrappedTime>,
     ) -> Option<(Tick, Bytes)> {
         self.last_received = received_time;
 
-        let is_last_fragment = fragment_index == self.num_fragments - 1;
-
         // TODO: check sizes?
 
         if !self.received[fragment_index] {
             self.received[fragment_index] = true;
             self.num_received_fragments += 1;
-
-            if is_last_fragment {
-                let len = (self.num_fragments - 1) * FRAGMENT_SIZE + bytes.len();
-                self.bytes.resize(len, 0);
-            }
-
-            let start = fragment_index * FRAGMENT_SIZE;
-            let end = start + bytes.len();
-            self.bytes[start..end].copy_from_slice(bytes);
+            self.fragments[fragment_index] = bytes;
         }
 
         if self.num_received_fragments == self.num_fragments {
             trace!("Received all fragments!");
-            let payload = std::mem::take(&mut self.bytes);
+            let payload = std::mem::take(&mut self.fragments).concat();
             return Some((self.tick, payload.into()));
         }
 
@@ -128,6 +125,7 @@ impl FragmentConstructor {
 #[cfg(test)]
 mod tests {
     use crate::channel::senders::fragment_sender::FragmentSender;
+    use crate::packet::packet::FRAGMENT_SIZE;
 
     use super::*;
 
@@ -149,4 +147,31 @@ mod tests {
             Some((Tick(0), message_bytes.clone()))
         );
     }
+
+    /// The fragment size depends on the MTU discovered by the sender
+    #[test]
+    fn test_receiver_bigger_fragments() {
+        let mut receiver = FragmentReceiver::new();
+        let mut sender = FragmentSender::new();
+        sender.fragment_size = FRAGMENT_SIZE + 200;
+        let message_bytes = Bytes::from_iter((0..3 * FRAGMENT_SIZE).map(|i| i as u8));
+        let fragments = sender
+            .build_fragments(MessageId(0), None, message_bytes.clone())
+            .unwrap();
+        assert_eq!(fragments.len(), 3);
+
+        // receive the last fragment first
+        assert_eq!(
+            receiver.receive_fragment(fragments[2].clone(), Tick(0), None),
+            None
+        );
+        assert_eq!(
+            receiver.receive_fragment(fragments[0].clone(), Tick(0), None),
+            None
+        );
+        assert_eq!(
+            receiver.receive_fragment(fragments[1].clone(), Tick(0), None),
+            Some((Tick(0), message_bytes))
+        );
+    }
 }
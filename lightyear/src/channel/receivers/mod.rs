@@ -14,6 +14,12 @@ pub(crate) mod fragment_receiver;
 /// Receive messages in an Ordered Reliable manner
 pub(crate) mod ordered_reliable;
 
+/// Receive messages in an Ordered Reliable manner, within independent streams
+pub(crate) mod ordered_streams;
+
+/// Receive messages in an Unordered Unreliable manner, discarding the copies of the messages
+pub(crate) mod redundant_unreliable;
+
 /// Receive messages in an Sequenced Reliable manner
 pub(crate) mod sequenced_reliable;
 
@@ -47,6 +53,8 @@ pub enum ChannelReceiver {
     UnorderedUnreliable(unordered_unreliable::UnorderedUnreliableReceiver),
     SequencedUnreliable(sequenced_unreliable::SequencedUnreliableReceiver),
     OrderedReliable(ordered_reliable::OrderedReliableReceiver),
+    OrderedStreams(ordered_streams::OrderedStreamsReceiver),
     SequencedReliable(sequenced_reliable::SequencedReliableReceiver),
     UnorderedReliable(unordered_reliable::UnorderedReliableReceiver),
+    RedundantUnreliable(redundant_unreliable::RedundantUnreliableReceiver),
 }
@@ -52,6 +52,9 @@ impl ChannelReceive for OrderedReliableReceiver {
         if let btree_map::Entry::Vacant(entry) = self.recv_message_buffer.entry(message_id) {
             match message.data {
                 MessageData::Single(single) => {
+                    if single.bytes.is_empty() {
+                        self.fragment_receiver.discard(message_id);
+                    }
                     entry.insert((message.remote_sent_tick, single.bytes));
                 }
                 MessageData::Fragment(fragment) => {
@@ -73,15 +76,20 @@ impl ChannelReceive for OrderedReliableReceiver {
     /// until we have received the message we are waiting for (the next expected MessageId)
     /// This assumes that the sender sends all message ids sequentially.
     fn read_message(&mut self) -> Option<(Tick, Bytes)> {
-        // Check if we have received the message we are waiting for
-        let message = self
-            .recv_message_buffer
-            .remove(&self.pending_recv_message_id)?;
-
-        // if we have finally received the message we are waiting for, return it and
-        // wait for the next one
-        self.pending_recv_message_id += 1;
-        Some(message)
+        loop {
+            // Check if we have received the message we are waiting for
+            let message = self
+                .recv_message_buffer
+                .remove(&self.pending_recv_message_id)?;
+
+            // if we have finally received the message we are waiting for, return it and
+            // wait for the next one
+            self.pending_recv_message_id += 1;
+            // skip the messages that expired on the sender side
+            if !message.1.is_empty() {
+                return Some(message);
+            }
+        }
     }
 }
 
@@ -143,4 +151,27 @@ mod tests {
         );
         Ok(())
     }
+
+    /// Messages that expired on the sender side are replaced with empty messages, which are skipped
+    #[test]
+    fn test_ordered_reliable_receiver_expired_message() -> Result<(), PacketError> {
+        let mut receiver = OrderedReliableReceiver::new();
+        let mut expired = SingleData::new(None, Bytes::new());
+        let mut single = SingleData::new(None, Bytes::from("hello"));
+        expired.id = Some(MessageId(0));
+        single.id = Some(MessageId(1));
+        receiver.buffer_recv(ReceiveMessage {
+            data: single.clone().into(),
+            remote_sent_tick: Tick(1),
+        })?;
+        assert_eq!(receiver.read_message(), None);
+
+        receiver.buffer_recv(ReceiveMessage {
+            data: expired.into(),
+            remote_sent_tick: Tick(2),
+        })?;
+        assert_eq!(receiver.read_message(), Some((Tick(1), single.bytes)));
+        assert_eq!(receiver.pending_recv_message_id, MessageId(2));
+        Ok(())
+    }
 }
@@ -0,0 +1,168 @@
+use std::collections::VecDeque;
+
+use bevy::utils::{HashMap, HashSet};
+use bytes::Bytes;
+use tracing::error;
+
+use super::error::Result;
+use crate::channel::receivers::unordered_reliable::UnorderedReliableReceiver;
+use crate::channel::receivers::ChannelReceive;
+use crate::packet::message::{MessageId, ReceiveMessage, StreamHeader};
+use crate::prelude::Tick;
+use crate::serialize::reader::Reader;
+use crate::serialize::ToBytes;
+use crate::shared::tick_manager::TickManager;
+use crate::shared::time_manager::TimeManager;
+
+/// Ordered Reliable receiver with multiple streams: make sure that all messages are received,
+/// and return them in order within each stream.
+///
+/// A message that is waiting for a lost message of its stream doesn't block the other streams.
+/// Each message points to the previous message of its stream, so no state is kept for the streams
+/// that don't have a message waiting.
+#[derive(Debug)]
+pub struct OrderedStreamsReceiver {
+    /// The message ids are shared by all the streams, so we first receive the messages reliably
+    /// in any order (the stream headers are only used for ordering)
+    receiver: UnorderedReliableReceiver,
+    /// Messages waiting for the previous message of their stream, indexed by the id of that previous message
+    waiting_messages: HashMap<MessageId, (MessageId, Tick, Bytes)>,
+    /// Ids of the messages in `waiting_messages`
+    waiting_ids: HashSet<MessageId>,
+    /// Messages that are ready to be read, in order within each stream
+    ready_messages: VecDeque<(Tick, Bytes)>,
+}
+
+impl OrderedStreamsReceiver {
+    pub fn new() -> Self {
+        Self {
+            receiver: UnorderedReliableReceiver::new(),
+            waiting_messages: HashMap::default(),
+            waiting_ids: HashSet::default(),
+            ready_messages: VecDeque::new(),
+        }
+    }
+
+    /// Make a message that was received reliably wait for the previous message of its stream,
+    /// or move it (and the messages of the stream that were waiting for it) to the buffer of messages ready to be read
+    fn receive_stream_message(&mut self, message_id: MessageId, tick: Tick, bytes: Bytes) {
+        let mut reader = Reader::from(bytes);
+        let header = match StreamHeader::from_bytes(&mut reader) {
+            Ok(header) => header,
+            Err(e) => {
+                error!(?e, "could not read the stream header of a message");
+                return;
+            }
+        };
+        let content = reader.split_len(reader.remaining());
+        if let Some(previous) = header.previous {
+            // the previous message of the stream has not been read yet
+            if !self.receiver.has_received(previous) || self.waiting_ids.contains(&previous) {
+                self.waiting_ids.insert(message_id);
+                self.waiting_messages
+                    .insert(previous, (message_id, tick, content));
+                return;
+            }
+        }
+        let mut next = Some((message_id, tick, content));
+        while let Some((message_id, tick, content)) = next {
+            self.waiting_ids.remove(&message_id);
+            // skip the messages that expired on the sender side
+            if !content.is_empty() {
+                self.ready_messages.push_back((tick, content));
+            }
+            next = self.waiting_messages.remove(&message_id);
+        }
+    }
+}
+
+impl ChannelReceive for OrderedStreamsReceiver {
+    fn update(&mut self, time_manager: &TimeManager, tick_manager: &TickManager) {
+        self.receiver.update(time_manager, tick_manager);
+    }
+
+    /// Queues a received message in an internal buffer
+    fn buffer_recv(&mut self, message: ReceiveMessage) -> Result<()> {
+        self.receiver.buffer_recv(message)
+    }
+
+    /// Reads a message from the internal buffer to get its content.
+    /// A message is returned once all the previous messages of its stream have been returned.
+    fn read_message(&mut self) -> Option<(Tick, Bytes)> {
+        while let Some((message_id, tick, bytes)) = self.receiver.read_message_with_id() {
+            self.receive_stream_message(message_id, tick, bytes);
+        }
+        self.ready_messages.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::message::SingleData;
+    use crate::prelude::PacketError;
+
+    fn stream_message(
+        message_id: u16,
+        previous: Option<u16>,
+        content: &'static str,
+    ) -> ReceiveMessage {
+        let mut bytes = vec![];
+        StreamHeader {
+            previous: previous.map(MessageId),
+        }
+        .to_bytes(&mut bytes)
+        .unwrap();
+        bytes.extend_from_slice(content.as_bytes());
+        ReceiveMessage {
+            data: SingleData::new(Some(MessageId(message_id)), bytes.into()).into(),
+            remote_sent_tick: Tick(message_id),
+        }
+    }
+
+    #[test]
+    fn test_ordered_streams_receiver() -> std::result::Result<(), PacketError> {
+        let mut receiver = OrderedStreamsReceiver::new();
+
+        // the first message of stream 1 is lost: the next message of stream 1 is buffered
+        receiver.buffer_recv(stream_message(1, Some(0), "b"))?;
+        assert_eq!(receiver.read_message(), None);
+
+        // the messages of stream 2 are not blocked by the lost message of stream 1
+        receiver.buffer_recv(stream_message(2, None, "c"))?;
+        assert_eq!(receiver.read_message(), Some((Tick(2), Bytes::from("c"))));
+        assert_eq!(receiver.read_message(), None);
+
+        // the lost message is resent: the messages of stream 1 are returned in order
+        receiver.buffer_recv(stream_message(0, None, "a"))?;
+        assert_eq!(receiver.read_message(), Some((Tick(0), Bytes::from("a"))));
+        assert_eq!(receiver.read_message(), Some((Tick(1), Bytes::from("b"))));
+        assert_eq!(receiver.read_message(), None);
+
+        // duplicates are ignored
+        receiver.buffer_recv(stream_message(1, Some(0), "b"))?;
+        assert_eq!(receiver.read_message(), None);
+
+        // a message whose previous message was already read is returned directly
+        receiver.buffer_recv(stream_message(3, Some(2), "d"))?;
+        assert_eq!(receiver.read_message(), Some((Tick(3), Bytes::from("d"))));
+
+        // no state is kept for the streams once their messages have been read
+        assert!(receiver.waiting_messages.is_empty());
+        assert!(receiver.waiting_ids.is_empty());
+        Ok(())
+    }
+
+    /// Messages that expired on the sender side only keep their stream header, and are skipped
+    #[test]
+    fn test_ordered_streams_receiver_expired_message() -> std::result::Result<(), PacketError> {
+        let mut receiver = OrderedStreamsReceiver::new();
+        receiver.buffer_recv(stream_message(1, Some(0), "b"))?;
+        assert_eq!(receiver.read_message(), None);
+
+        receiver.buffer_recv(stream_message(0, None, ""))?;
+        assert_eq!(receiver.read_message(), Some((Tick(1), Bytes::from("b"))));
+        assert_eq!(receiver.read_message(), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+use bytes::Bytes;
+use std::collections::VecDeque;
+
+use bevy::utils::HashSet;
+
+use crate::channel::receivers::error::ChannelReceiveError;
+use crate::channel::receivers::fragment_receiver::FragmentReceiver;
+use crate::channel::receivers::ChannelReceive;
+use crate::packet::message::{MessageData, MessageId, ReceiveMessage};
+use crate::prelude::Tick;
+use crate::shared::tick_manager::TickManager;
+use crate::shared::time_manager::{TimeManager, WrappedTime};
+
+const DISCARD_AFTER: chrono::Duration = chrono::Duration::milliseconds(3000);
+
+/// Number of message ids that we remember to discard the copies of the messages that were already received.
+///
+/// The copies of a message are sent in consecutive packets, so they are received shortly after the message.
+const RECEIVED_IDS_WINDOW: usize = 1024;
+
+/// Unordered unreliable receiver for channels that send each message several times:
+/// only the first copy of each message is returned
+#[derive(Debug)]
+pub struct RedundantUnreliableReceiver {
+    recv_message_buffer: VecDeque<(Tick, Bytes)>,
+    fragment_receiver: FragmentReceiver,
+    /// Ids of the most recent messages that were received
+    received_message_ids: HashSet<MessageId>,
+    /// Same ids, in the order in which they were received, to forget the oldest ones
+    received_message_ids_order: VecDeque<MessageId>,
+    current_time: WrappedTime,
+}
+
+impl RedundantUnreliableReceiver {
+    pub fn new() -> Self {
+        Self {
+            recv_message_buffer: VecDeque::new(),
+            fragment_receiver: FragmentReceiver::new(),
+            received_message_ids: HashSet::default(),
+            received_message_ids_order: VecDeque::new(),
+            current_time: WrappedTime::default(),
+        }
+    }
+
+    /// Returns true if the message is received for the first time
+    fn is_new_message(&mut self, message_id: MessageId) -> bool {
+        if !self.received_message_ids.insert(message_id) {
+            return false;
+        }
+        self.received_message_ids_order.push_back(message_id);
+        if self.received_message_ids_order.len() > RECEIVED_IDS_WINDOW {
+            let oldest = self.received_message_ids_order.pop_front().unwrap();
+            self.received_message_ids.remove(&oldest);
+        }
+        true
+    }
+}
+
+impl ChannelReceive for RedundantUnreliableReceiver {
+    fn update(&mut self, time_manager: &TimeManager, _: &TickManager) {
+        self.current_time = time_manager.current_time();
+        self.fragment_receiver
+            .cleanup(self.current_time - DISCARD_AFTER);
+    }
+
+    fn buffer_recv(&mut self, message: ReceiveMessage) -> Result<(), ChannelReceiveError> {
+        match message.data {
+            MessageData::Single(single) => {
+                let message_id = single.id.ok_or(ChannelReceiveError::MissingMessageId)?;
+                if self.is_new_message(message_id) {
+                    self.recv_message_buffer
+                        .push_back((message.remote_sent_tick, single.bytes));
+                }
+            }
+            // fragmented messages are only sent once
+            MessageData::Fragment(fragment) => {
+                if let Some(data) = self.fragment_receiver.receive_fragment(
+                    fragment,
+                    message.remote_sent_tick,
+                    Some(self.current_time),
+                ) {
+                    self.recv_message_buffer.push_back(data);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn read_message(&mut self) -> Option<(Tick, Bytes)> {
+        self.recv_message_buffer.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::packet::message::SingleData;
+
+    use super::*;
+
+    #[test]
+    fn test_redundant_unreliable_receiver() -> Result<(), ChannelReceiveError> {
+        let mut receiver = RedundantUnreliableReceiver::new();
+        let single = SingleData::new(Some(MessageId(0)), Bytes::from("hello"));
+
+        receiver.buffer_recv(ReceiveMessage {
+            data: single.clone().into(),
+            remote_sent_tick: Tick(1),
+        })?;
+        // the copy of the message is ignored
+        receiver.buffer_recv(ReceiveMessage {
+            data: single.clone().into(),
+            remote_sent_tick: Tick(2),
+        })?;
+        assert_eq!(receiver.read_message(), Some((Tick(1), single.bytes)));
+        assert_eq!(receiver.read_message(), None);
+
+        // the oldest ids are forgotten
+        for i in 1..=RECEIVED_IDS_WINDOW as u16 {
+            assert!(receiver.is_new_message(MessageId(i)));
+        }
+        assert_eq!(receiver.received_message_ids.len(), RECEIVED_IDS_WINDOW);
+        assert!(!receiver.received_message_ids.contains(&MessageId(0)));
+        Ok(())
+    }
+}
@@ -58,6 +58,9 @@ impl ChannelReceive for SequencedReliableReceiver {
         if let btree_map::Entry::Vacant(entry) = self.recv_message_buffer.entry(message_id) {
             match message.data {
                 MessageData::Single(single) => {
+                    if single.bytes.is_empty() {
+                        self.fragment_receiver.discard(message_id);
+                    }
                     entry.insert((message.remote_sent_tick, single.bytes));
                 }
                 MessageData::Fragment(fragment) => {
@@ -77,7 +80,8 @@ impl ChannelReceive for SequencedReliableReceiver {
         // keep popping messages until we get one that is more recent than the last one we processed
         loop {
             let (message_id, message) = self.recv_message_buffer.pop_first()?;
-            if message_id >= self.most_recent_message_id {
+            // skip the messages that expired on the sender side
+            if message_id >= self.most_recent_message_id && !message.1.is_empty() {
                 return Some(message);
             }
         }
@@ -36,6 +36,37 @@ impl UnorderedReliableReceiver {
             received_message_ids: HashSet::new(),
         }
     }
+
+    /// Returns true if the message with this id has already been received
+    pub(crate) fn has_received(&self, message_id: MessageId) -> bool {
+        message_id < self.pending_recv_message_id || self.received_message_ids.contains(&message_id)
+    }
+
+    /// Reads a message from the internal buffer, along with its message id
+    pub(crate) fn read_message_with_id(&mut self) -> Option<(MessageId, Tick, Bytes)> {
+        loop {
+            // return if there are no messages in the buffer
+            let (message_id, data) = self.recv_message_buffer.pop_first()?;
+
+            // this was the message we were waiting for (as a reliable receiver)
+            if self.pending_recv_message_id == message_id {
+                // update the pending message id (skip through all message ids we have already received out of order)
+                while self
+                    .received_message_ids
+                    .contains(&self.pending_recv_message_id)
+                {
+                    self.received_message_ids
+                        .remove(&self.pending_recv_message_id);
+                    self.pending_recv_message_id += 1;
+                }
+            }
+
+            // receive oldest message in the buffer, skipping the messages that expired on the sender side
+            if !data.1.is_empty() {
+                return Some((message_id, data.0, data.1));
+            }
+        }
+    }
 }
 
 impl ChannelReceive for UnorderedReliableReceiver {
@@ -60,6 +91,8 @@ impl ChannelReceive for UnorderedReliableReceiver {
                 MessageData::Single(single) => {
                     // receive the message if we haven't received it already
                     if !self.received_message_ids.contains(&message_id) {
+                        // a fragmented message that expired on the sender side is replaced with a single message
+                        self.fragment_receiver.discard(message_id);
                         self.received_message_ids.insert(message_id);
                         entry.insert((message.remote_sent_tick, single.bytes));
                     }
@@ -83,24 +116,8 @@ impl ChannelReceive for UnorderedReliableReceiver {
     }
 
     fn read_message(&mut self) -> Option<(Tick, Bytes)> {
-        // return if there are no messages in the buffer
-        let (message_id, data) = self.recv_message_buffer.pop_first()?;
-
-        // this was the message we were waiting for (as a reliable receiver)
-        if self.pending_recv_message_id == message_id {
-            // update the pending message id (skip through all message ids we have already received out of order)
-            while self
-                .received_message_ids
-                .contains(&self.pending_recv_message_id)
-            {
-                self.received_message_ids
-                    .remove(&self.pending_recv_message_id);
-                self.pending_recv_message_id += 1;
-            }
-        }
-
-        // receive oldest message in the buffer
-        Some(data)
+        self.read_message_with_id()
+            .map(|(_, tick, bytes)| (tick, bytes))
     }
 }
 
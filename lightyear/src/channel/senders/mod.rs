@@ -1,22 +1,50 @@
 use std::collections::VecDeque;
 
+use bevy::utils::Duration;
 use bytes::Bytes;
 use crossbeam_channel::Receiver;
 use enum_dispatch::enum_dispatch;
 
+use crate::channel::builder::StreamId;
 use crate::packet::message::{MessageAck, MessageId, SendMessage};
 use crate::serialize::SerializationError;
 use crate::shared::ping::manager::PingManager;
 use crate::shared::tick_manager::TickManager;
-use crate::shared::time_manager::TimeManager;
+use crate::shared::time_manager::{TimeManager, WrappedTime};
 
 pub(crate) mod fragment_ack_receiver;
 pub(crate) mod fragment_sender;
+pub(crate) mod redundant_unreliable;
 pub(crate) mod reliable;
 pub(crate) mod sequenced_unreliable;
 pub(crate) mod unordered_unreliable;
 pub(crate) mod unordered_unreliable_with_acks;
 
+/// Options of a single message, which override the settings of its channel
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SendOptions {
+    /// Time-to-live of the message
+    pub ttl: Option<Duration>,
+    /// Ordering stream of the message. It is ignored by the channels that don't have multiple streams.
+    pub stream_id: Option<StreamId>,
+}
+
+impl SendOptions {
+    pub fn with_ttl(ttl: Duration) -> Self {
+        Self {
+            ttl: Some(ttl),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_stream(stream_id: StreamId) -> Self {
+        Self {
+            stream_id: Some(stream_id),
+            ..Default::default()
+        }
+    }
+}
+
 // TODO: separate trait into multiple traits
 // - buffer send should be public
 // - all other methods should be private
@@ -33,13 +61,15 @@ pub trait ChannelSend {
     );
 
     /// Queues a message to be transmitted.
-    /// The priority of the message needs to be specified
+    /// The priority of the message needs to be specified.
+    /// The `options` override the settings of the channel for this message.
     ///
     /// Returns the MessageId of the message that was queued, if there is one
     fn buffer_send(
         &mut self,
         message: Bytes,
         priority: f32,
+        options: SendOptions,
     ) -> Result<Option<MessageId>, SerializationError>;
 
     /// Reads from the buffer of messages to send to prepare a list of Packets
@@ -58,6 +88,28 @@ pub trait ChannelSend {
 
     /// Send nacks to the subscribers of nacks
     fn send_nacks(&mut self, nack: MessageId);
+
+    /// Set the maximum number of bytes for a message before it is fragmented
+    fn set_fragment_size(&mut self, fragment_size: usize);
+
+    /// Returns the ids of the messages that expired before being acked since the last call
+    fn drain_expired(&mut self) -> Vec<MessageId>;
+
+    /// Called once for each packet containing messages of this channel that was acked or lost,
+    /// so that the channel can estimate its packet loss
+    fn receive_packet_outcome(&mut self, lost: bool);
+}
+
+/// Take the messages from the send buffer, dropping the ones whose time-to-live has elapsed
+pub(crate) fn take_unexpired(
+    buffer: &mut VecDeque<(SendMessage, Option<WrappedTime>)>,
+    current_time: WrappedTime,
+) -> VecDeque<SendMessage> {
+    std::mem::take(buffer)
+        .into_iter()
+        .filter(|(_, expires_at)| !expires_at.is_some_and(|expires_at| expires_at <= current_time))
+        .map(|(message, _)| message)
+        .collect()
 }
 
 /// Enum dispatch lets us derive ChannelSend on each enum variant
@@ -68,4 +120,5 @@ pub enum ChannelSender {
     UnorderedUnreliable(unordered_unreliable::UnorderedUnreliableSender),
     SequencedUnreliable(sequenced_unreliable::SequencedUnreliableSender),
     Reliable(reliable::ReliableSender),
+    RedundantUnreliable(redundant_unreliable::RedundantUnreliableSender),
 }
@@ -0,0 +1,385 @@
+use bevy::prelude::{Timer, TimerMode};
+use bevy::utils::{Duration, HashMap};
+use std::collections::VecDeque;
+
+use bytes::Bytes;
+use crossbeam_channel::{Receiver, Sender};
+use tracing::trace;
+
+use crate::channel::builder::RedundancySettings;
+use crate::channel::senders::fragment_sender::FragmentSender;
+use crate::channel::senders::{take_unexpired, ChannelSend, SendOptions};
+use crate::packet::message::{MessageAck, MessageData, MessageId, SendMessage, SingleData};
+use crate::serialize::SerializationError;
+use crate::shared::ping::manager::PingManager;
+use crate::shared::tick_manager::TickManager;
+use crate::shared::time_manager::{TimeManager, WrappedTime};
+
+const DISCARD_AFTER: chrono::Duration = chrono::Duration::milliseconds(3000);
+
+/// With adaptive redundancy, we send just enough copies of a message so that the probability
+/// of losing all of them is below this value
+const TARGET_RESIDUAL_LOSS: f32 = 0.001;
+
+/// Smoothing factor of the moving average of the packet loss
+const LOSS_SMOOTHING: f32 = 0.05;
+
+/// A message that will be sent again in the next packets of the channel
+#[derive(Debug)]
+struct RedundantMessage {
+    data: SingleData,
+    priority: f32,
+    /// Number of copies of the message that still have to be sent
+    remaining_copies: u8,
+    expires_at: Option<WrappedTime>,
+}
+
+/// Copies of a message that were sent, and that haven't been acked yet
+#[derive(Debug)]
+struct SentMessage {
+    sent_time: WrappedTime,
+    copies_sent: u8,
+    copies_lost: u8,
+}
+
+/// A sender that sends each message several times in consecutive packets, so that the receiver can
+/// recover from isolated packet losses without waiting for a resend.
+///
+/// Fragmented messages are only sent once.
+#[derive(Debug)]
+pub struct RedundantUnreliableSender {
+    settings: RedundancySettings,
+    /// list of single messages that we want to fit into packets and send, with their expiration time
+    single_messages_to_send: VecDeque<(SendMessage, Option<WrappedTime>)>,
+    /// list of fragmented messages that we want to fit into packets and send, with their expiration time
+    fragmented_messages_to_send: VecDeque<(SendMessage, Option<WrappedTime>)>,
+    /// Messages that have already been sent, and that will be sent again in the next packets
+    redundant_messages: VecDeque<RedundantMessage>,
+    /// Messages that were sent but that haven't been acked yet
+    sent_messages: HashMap<MessageId, SentMessage>,
+    /// Message id to use for the next message to be sent
+    next_send_message_id: MessageId,
+    /// Used to split a message into fragments if the message is too big
+    fragment_sender: FragmentSender,
+    /// Moving average of the fraction of the packets of the channel that are lost
+    loss: f32,
+    /// List of senders that want to be notified when a message is acked
+    ack_senders: Vec<Sender<MessageId>>,
+    /// List of senders that want to be notified when a message is lost
+    nack_senders: Vec<Sender<MessageId>>,
+    current_time: WrappedTime,
+    /// Default time-to-live of the messages
+    message_ttl: Option<Duration>,
+    /// Internal timer to determine if the channel is ready to send messages
+    timer: Option<Timer>,
+}
+
+impl RedundantUnreliableSender {
+    pub(crate) fn new(settings: RedundancySettings, send_frequency: Duration) -> Self {
+        let timer = if send_frequency == Duration::default() {
+            None
+        } else {
+            Some(Timer::new(send_frequency, TimerMode::Repeating))
+        };
+        Self {
+            settings,
+            single_messages_to_send: VecDeque::new(),
+            fragmented_messages_to_send: VecDeque::new(),
+            redundant_messages: VecDeque::new(),
+            sent_messages: HashMap::default(),
+            next_send_message_id: MessageId::default(),
+            fragment_sender: FragmentSender::new(),
+            loss: 0.0,
+            ack_senders: Vec::new(),
+            nack_senders: Vec::new(),
+            current_time: WrappedTime::default(),
+            message_ttl: None,
+            timer,
+        }
+    }
+
+    pub(crate) fn with_message_ttl(mut self, message_ttl: Option<Duration>) -> Self {
+        self.message_ttl = message_ttl;
+        self
+    }
+
+    /// Number of additional copies of each message that are sent
+    pub(crate) fn redundancy(&self) -> u8 {
+        if !self.settings.adaptive {
+            return self.settings.redundancy;
+        }
+        // find the smallest number of copies so that the probability of losing the message
+        // and all its copies is low enough
+        let mut redundancy = self.settings.redundancy.min(1);
+        while redundancy < self.settings.redundancy
+            && self.loss.powi(redundancy as i32 + 1) > TARGET_RESIDUAL_LOSS
+        {
+            redundancy += 1;
+        }
+        redundancy
+    }
+}
+
+impl ChannelSend for RedundantUnreliableSender {
+    fn update(&mut self, time_manager: &TimeManager, _: &PingManager, _: &TickManager) {
+        self.current_time = time_manager.current_time();
+        let cleanup_time = self.current_time - DISCARD_AFTER;
+        self.sent_messages
+            .retain(|_, sent_message| sent_message.sent_time > cleanup_time);
+        if let Some(timer) = &mut self.timer {
+            timer.tick(time_manager.delta());
+        }
+    }
+
+    /// Add a new message to the buffer of messages to be sent.
+    /// This is a client-facing function, to be called when you want to send a message
+    fn buffer_send(
+        &mut self,
+        message: Bytes,
+        priority: f32,
+        options: SendOptions,
+    ) -> Result<Option<MessageId>, SerializationError> {
+        let expires_at = options
+            .ttl
+            .or(self.message_ttl)
+            .map(|ttl| self.current_time + ttl);
+        let message_id = self.next_send_message_id;
+        if message.len() > self.fragment_sender.fragment_size {
+            for fragment in self
+                .fragment_sender
+                .build_fragments(message_id, None, message)?
+            {
+                self.fragmented_messages_to_send.push_back((
+                    SendMessage {
+                        data: MessageData::Fragment(fragment),
+                        priority,
+                    },
+                    expires_at,
+                ));
+            }
+        } else {
+            let single_data = SingleData::new(Some(message_id), message);
+            self.single_messages_to_send.push_back((
+                SendMessage {
+                    data: MessageData::Single(single_data),
+                    priority,
+                },
+                expires_at,
+            ));
+        }
+        self.next_send_message_id += 1;
+        Ok(Some(message_id))
+    }
+
+    /// Take messages from the buffer of messages to be sent, and add the copies of the messages
+    /// that were sent previously
+    fn send_packet(&mut self) -> (VecDeque<SendMessage>, VecDeque<SendMessage>) {
+        if self.timer.as_ref().is_some_and(|t| !t.finished()) {
+            return (VecDeque::new(), VecDeque::new());
+        }
+        let current_time = self.current_time;
+        let mut single_messages = VecDeque::new();
+        // send the copies of the previous messages
+        self.redundant_messages.retain(|message| {
+            !message
+                .expires_at
+                .is_some_and(|expires_at| expires_at <= current_time)
+        });
+        for message in self.redundant_messages.iter_mut() {
+            message.remaining_copies -= 1;
+            if let Some(sent_message) = message
+                .data
+                .id
+                .and_then(|id| self.sent_messages.get_mut(&id))
+            {
+                sent_message.copies_sent += 1;
+            }
+            single_messages.push_back(SendMessage {
+                data: MessageData::Single(message.data.clone()),
+                priority: message.priority,
+            });
+        }
+        self.redundant_messages
+            .retain(|message| message.remaining_copies > 0);
+
+        // send the new messages, and keep them to send them again in the next packets
+        let redundancy = self.redundancy();
+        for (message, expires_at) in std::mem::take(&mut self.single_messages_to_send) {
+            if expires_at.is_some_and(|expires_at| expires_at <= current_time) {
+                continue;
+            }
+            if let MessageData::Single(data) = &message.data {
+                if let Some(message_id) = data.id {
+                    self.sent_messages.insert(
+                        message_id,
+                        SentMessage {
+                            sent_time: current_time,
+                            copies_sent: 1,
+                            copies_lost: 0,
+                        },
+                    );
+                }
+                if redundancy > 0 {
+                    self.redundant_messages.push_back(RedundantMessage {
+                        data: data.clone(),
+                        priority: message.priority,
+                        remaining_copies: redundancy,
+                        expires_at,
+                    });
+                }
+            }
+            single_messages.push_back(message);
+        }
+        (
+            single_messages,
+            take_unexpired(&mut self.fragmented_messages_to_send, current_time),
+        )
+    }
+
+    /// A copy of a message was received: notify the subscribers the first time a message is acked
+    fn receive_ack(&mut self, ack: &MessageAck) {
+        if ack.fragment_id.is_some() {
+            return;
+        }
+        // the other copies of the message don't need to be sent anymore
+        self.redundant_messages
+            .retain(|message| message.data.id != Some(ack.message_id));
+        if self.sent_messages.remove(&ack.message_id).is_some() {
+            for sender in &self.ack_senders {
+                sender.send(ack.message_id).unwrap();
+            }
+        }
+    }
+
+    /// Create a new receiver that will receive a message id when a message is acked
+    fn subscribe_acks(&mut self) -> Receiver<MessageId> {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        self.ack_senders.push(sender);
+        receiver
+    }
+
+    /// Create a new receiver that will receive a message id when a sent message on this channel
+    /// has been lost by the remote peer
+    fn subscribe_nacks(&mut self) -> Receiver<MessageId> {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        self.nack_senders.push(sender);
+        receiver
+    }
+
+    /// A copy of a message was lost: notify the subscribers once all the copies of the message are lost
+    fn send_nacks(&mut self, nack: MessageId) {
+        // the message was already acked or reported as lost
+        let Some(sent_message) = self.sent_messages.get_mut(&nack) else {
+            return;
+        };
+        sent_message.copies_lost += 1;
+        if sent_message.copies_lost < sent_message.copies_sent
+            || self
+                .redundant_messages
+                .iter()
+                .any(|message| message.data.id == Some(nack))
+        {
+            return;
+        }
+        trace!(message_id = ?nack, "all the copies of the message were lost");
+        self.sent_messages.remove(&nack);
+        for sender in &self.nack_senders {
+            sender.send(nack).unwrap();
+        }
+    }
+
+    fn set_fragment_size(&mut self, fragment_size: usize) {
+        self.fragment_sender.fragment_size = fragment_size;
+    }
+
+    fn drain_expired(&mut self) -> Vec<MessageId> {
+        vec![]
+    }
+
+    /// Update the packet loss estimate (a packet counts once, however many copies it contains)
+    fn receive_packet_outcome(&mut self, lost: bool) {
+        self.loss *= 1.0 - LOSS_SMOOTHING;
+        if lost {
+            self.loss += LOSS_SMOOTHING;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sent_ids(messages: &VecDeque<SendMessage>) -> Vec<MessageId> {
+        messages
+            .iter()
+            .map(|message| message.data.message_id().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_redundant_unreliable_sender() {
+        let mut sender = RedundantUnreliableSender::new(
+            RedundancySettings {
+                redundancy: 2,
+                adaptive: false,
+            },
+            Duration::default(),
+        );
+        let nacks = sender.subscribe_nacks();
+
+        sender
+            .buffer_send(Bytes::from("a"), 1.0, SendOptions::default())
+            .unwrap();
+        let (single, _) = sender.send_packet();
+        assert_eq!(sent_ids(&single), vec![MessageId(0)]);
+
+        // the message is sent again in the next 2 packets, along with the new messages
+        sender
+            .buffer_send(Bytes::from("b"), 1.0, SendOptions::default())
+            .unwrap();
+        let (single, _) = sender.send_packet();
+        assert_eq!(sent_ids(&single), vec![MessageId(0), MessageId(1)]);
+        let (single, _) = sender.send_packet();
+        assert_eq!(sent_ids(&single), vec![MessageId(0), MessageId(1)]);
+
+        // once a copy of a message is acked, the message is not sent again
+        sender.receive_ack(&MessageAck {
+            message_id: MessageId(1),
+            fragment_id: None,
+        });
+        let (single, _) = sender.send_packet();
+        assert!(single.is_empty());
+
+        // the message is only lost once all its copies are lost
+        sender.send_nacks(MessageId(0));
+        sender.send_nacks(MessageId(0));
+        assert!(nacks.try_recv().is_err());
+        sender.send_nacks(MessageId(0));
+        assert_eq!(nacks.try_recv().unwrap(), MessageId(0));
+
+        // the loss of a copy of a message that was already acked or lost is ignored
+        sender.send_nacks(MessageId(1));
+        sender.send_nacks(MessageId(0));
+        assert!(nacks.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_adaptive_redundancy() {
+        let mut sender =
+            RedundantUnreliableSender::new(RedundancySettings::default(), Duration::default());
+        assert_eq!(sender.redundancy(), 1);
+
+        // 10% packet loss
+        for i in 0..500 {
+            sender.receive_packet_outcome(i % 10 == 0);
+        }
+        // 0.1^3 <= 0.001 < 0.1^2
+        assert_eq!(sender.redundancy(), 2);
+
+        // no packet loss
+        for _ in 0..500 {
+            sender.receive_packet_outcome(false);
+        }
+        assert_eq!(sender.redundancy(), 1);
+    }
+}
@@ -1,4 +1,5 @@
 use bevy::prelude::{Timer, TimerMode};
+use bevy::utils::HashMap;
 use std::collections::VecDeque;
 use std::collections::{BTreeMap, HashSet};
 
@@ -7,11 +8,13 @@ use bytes::Bytes;
 use crossbeam_channel::{Receiver, Sender};
 use tracing::trace;
 
-use crate::channel::builder::ReliableSettings;
+use crate::channel::builder::{ReliableSettings, StreamId};
 use crate::channel::senders::fragment_sender::FragmentSender;
-use crate::channel::senders::ChannelSend;
-use crate::packet::message::{FragmentData, MessageAck, MessageId, SendMessage, SingleData};
-use crate::serialize::SerializationError;
+use crate::channel::senders::{ChannelSend, SendOptions};
+use crate::packet::message::{
+    FragmentData, MessageAck, MessageId, SendMessage, SingleData, StreamHeader,
+};
+use crate::serialize::{SerializationError, ToBytes};
 use crate::shared::ping::manager::PingManager;
 use crate::shared::tick_manager::TickManager;
 use crate::shared::time_manager::{TimeManager, WrappedTime};
@@ -40,6 +43,23 @@ pub struct UnackedMessageWithPriority {
     pub unacked_message: UnackedMessage,
     pub base_priority: f32,
     pub accumulated_priority: f32,
+    /// Time after which the message is not sent anymore
+    pub expires_at: Option<WrappedTime>,
+    /// Header of the ordering stream of the message (empty if the channel doesn't have multiple streams)
+    ///
+    /// It is still sent if the message expires, so that the receiver can skip the message in its stream.
+    pub stream_header: Bytes,
+    /// Ordering stream of the message, if the channel has multiple streams
+    pub stream_id: Option<StreamId>,
+}
+
+/// An ordering stream that still has messages that haven't been acked
+#[derive(Debug, Default)]
+struct SendStream {
+    /// Id of the last message sent on the stream
+    last_message_id: Option<MessageId>,
+    /// Number of messages of the stream that haven't been acked yet
+    unacked: usize,
 }
 
 /// A sender that makes sure to resend messages until it receives an ack
@@ -69,6 +89,15 @@ pub struct ReliableSender {
     ack_senders: Vec<Sender<MessageId>>,
     /// List of senders that want to be notified when a message is lost
     nack_senders: Vec<Sender<MessageId>>,
+    /// Default time-to-live of the messages
+    message_ttl: Option<Duration>,
+    /// Messages that expired before being acked
+    expired_messages: Vec<MessageId>,
+    /// Ordering streams that have unacked messages, if the channel has multiple streams.
+    ///
+    /// A stream is removed once all its messages are acked: the receiver has received all of them,
+    /// so the next message of the stream doesn't need to wait for a previous message.
+    streams: Option<HashMap<StreamId, SendStream>>,
     current_rtt: Duration,
     current_time: WrappedTime,
     /// Internal timer to determine if the channel is ready to send messages
@@ -95,12 +124,95 @@ impl ReliableSender {
             fragment_sender: FragmentSender::new(),
             ack_senders: vec![],
             nack_senders: vec![],
+            message_ttl: None,
+            expired_messages: vec![],
+            streams: None,
             current_rtt: Duration::default(),
             current_time: WrappedTime::default(),
             timer,
             priority_multiplier: 1.0,
         }
     }
+
+    /// Id that will be assigned to the next message buffered on this channel
+    pub(crate) fn next_message_id(&self) -> MessageId {
+        self.next_send_message_id
+    }
+
+    pub(crate) fn with_message_ttl(mut self, message_ttl: Option<Duration>) -> Self {
+        self.message_ttl = message_ttl;
+        self
+    }
+
+    /// Order the messages within independent streams instead of ordering all the messages of the channel
+    pub(crate) fn with_streams(mut self) -> Self {
+        self.streams = Some(HashMap::default());
+        self
+    }
+
+    /// Write the header of the ordering stream of the message in front of the message.
+    ///
+    /// Returns the message with its header, and the header alone
+    fn add_stream_header(
+        &mut self,
+        message: Bytes,
+        message_id: MessageId,
+        stream_id: StreamId,
+    ) -> Result<(Bytes, Bytes), SerializationError> {
+        let Some(streams) = &mut self.streams else {
+            return Ok((message, Bytes::new()));
+        };
+        let stream = streams.entry(stream_id).or_default();
+        let header = StreamHeader {
+            previous: stream.last_message_id.replace(message_id),
+        };
+        stream.unacked += 1;
+        let mut buffer = Vec::with_capacity(header.len() + message.len());
+        header.to_bytes(&mut buffer)?;
+        buffer.extend_from_slice(&message);
+        let message = Bytes::from(buffer);
+        let stream_header = message.slice(..header.len());
+        Ok((message, stream_header))
+    }
+
+    /// Remove a message that was acked, and the state of its stream if all the messages of the stream are acked
+    fn remove_acked_message(&mut self, message_id: MessageId) {
+        let Some(unacked_message) = self.unacked_messages.remove(&message_id) else {
+            return;
+        };
+        let (Some(streams), Some(stream_id)) = (&mut self.streams, unacked_message.stream_id)
+        else {
+            return;
+        };
+        if let Some(stream) = streams.get_mut(&stream_id) {
+            stream.unacked -= 1;
+            if stream.unacked == 0 {
+                streams.remove(&stream_id);
+            }
+        }
+    }
+
+    /// Stop sending the messages whose time-to-live has elapsed.
+    ///
+    /// The receiver still expects to receive every message id, so the content of the message
+    /// is replaced with an empty payload that the receiver will skip (only the stream header is kept).
+    fn expire_messages(&mut self) {
+        for (message_id, unacked_message) in self.unacked_messages.iter_mut() {
+            if !unacked_message
+                .expires_at
+                .is_some_and(|expires_at| expires_at <= self.current_time)
+            {
+                continue;
+            }
+            trace!(?message_id, "reliable message expired before being acked");
+            unacked_message.expires_at = None;
+            unacked_message.unacked_message = UnackedMessage::Single {
+                bytes: unacked_message.stream_header.clone(),
+                last_sent: None,
+            };
+            self.expired_messages.push(*message_id);
+        }
+    }
 }
 
 impl ChannelSend for ReliableSender {
@@ -117,6 +229,7 @@ impl ChannelSend for ReliableSender {
                 self.priority_multiplier
             );
         }
+        self.expire_messages();
     }
 
     /// Add a new message to the buffer of messages to be sent.
@@ -125,8 +238,15 @@ impl ChannelSend for ReliableSender {
         &mut self,
         message: Bytes,
         priority: f32,
+        options: SendOptions,
     ) -> Result<Option<MessageId>, SerializationError> {
         let message_id = self.next_send_message_id;
+        let stream_id = self
+            .streams
+            .is_some()
+            .then(|| options.stream_id.unwrap_or_default());
+        let (message, stream_header) =
+            self.add_stream_header(message, message_id, stream_id.unwrap_or_default())?;
         let unacked_message = if message.len() > self.fragment_sender.fragment_size {
             let fragments = self
                 .fragment_sender
@@ -153,6 +273,12 @@ impl ChannelSend for ReliableSender {
             // store with 0.0 accumulated priority because priority gets accumulated when we collect the messages
             // for sending (even the first time the message is sent)
             accumulated_priority: 0.0,
+            expires_at: options
+                .ttl
+                .or(self.message_ttl)
+                .map(|ttl| self.current_time + ttl),
+            stream_header,
+            stream_id,
         };
         self.unacked_messages
             .insert(message_id, unacked_message_with_priority);
@@ -281,8 +407,12 @@ impl ChannelSend for ReliableSender {
                 message_ack.message_id
             );
             match &mut unacked_message.unacked_message {
-                UnackedMessage::Single { .. } => {
+                UnackedMessage::Single { bytes, .. } => {
                     if message_ack.fragment_id.is_some() {
+                        // the message was fragmented, but it expired and was replaced with an empty message
+                        if *bytes == unacked_message.stream_header {
+                            return;
+                        }
                         panic!(
                             "Received a message ack for a fragment but message is a single message"
                         )
@@ -290,7 +420,7 @@ impl ChannelSend for ReliableSender {
                     for sender in &self.ack_senders {
                         sender.send(message_ack.message_id).unwrap();
                     }
-                    self.unacked_messages.remove(&message_ack.message_id);
+                    self.remove_acked_message(message_ack.message_id);
                 }
                 UnackedMessage::Fragmented(fragment_acks) => {
                     let Some(fragment_id) = message_ack.fragment_id else {
@@ -301,7 +431,7 @@ impl ChannelSend for ReliableSender {
                         // TODO: use a variable to keep track of this?
                         // all fragments were acked
                         if fragment_acks.iter().all(|f| f.acked) {
-                            self.unacked_messages.remove(&message_ack.message_id);
+                            self.remove_acked_message(message_ack.message_id);
                             for sender in &self.ack_senders {
                                 sender.send(message_ack.message_id).unwrap();
                             }
@@ -333,6 +463,16 @@ impl ChannelSend for ReliableSender {
             sender.send(nack).unwrap();
         }
     }
+
+    fn set_fragment_size(&mut self, fragment_size: usize) {
+        self.fragment_sender.fragment_size = fragment_size;
+    }
+
+    fn drain_expired(&mut self) -> Vec<MessageId> {
+        std::mem::take(&mut self.expired_messages)
+    }
+
+    fn receive_packet_outcome(&mut self, _: bool) {}
 }
 
 #[cfg(test)]
@@ -351,6 +491,7 @@ mod tests {
             ReliableSettings {
                 rtt_resend_factor: 1.5,
                 rtt_resend_min_delay: Duration::from_millis(100),
+                transport_stream: false,
             },
             Duration::default(),
         );
@@ -359,7 +500,9 @@ mod tests {
 
         // Buffer a new message
         let message1 = Bytes::from("hello");
-        sender.buffer_send(message1.clone(), 1.0).unwrap();
+        sender
+            .buffer_send(message1.clone(), 1.0, SendOptions::default())
+            .unwrap();
         assert_eq!(sender.unacked_messages.len(), 1);
         assert_eq!(sender.next_send_message_id, MessageId(1));
         // Collect the messages to be sent
@@ -396,4 +539,131 @@ mod tests {
         let (single, _) = sender.send_packet();
         assert_eq!(single.len(), 0);
     }
+
+    #[test]
+    fn test_reliable_sender_message_ttl() {
+        let mut sender = ReliableSender::new(ReliableSettings::default(), Duration::default())
+            .with_message_ttl(Some(Duration::from_millis(100)));
+        sender.current_time = WrappedTime::new(0);
+
+        sender
+            .buffer_send(Bytes::from("hello"), 1.0, SendOptions::default())
+            .unwrap();
+        // the ttl of the channel can be overridden for a given message
+        sender
+            .buffer_send(
+                Bytes::from("world"),
+                1.0,
+                SendOptions::with_ttl(Duration::from_secs(1)),
+            )
+            .unwrap();
+        let (single, _) = sender.send_packet();
+        assert_eq!(single.len(), 2);
+
+        // the first message expires before being acked
+        sender.current_time += Duration::from_millis(200);
+        sender.expire_messages();
+        assert_eq!(sender.drain_expired(), vec![MessageId(0)]);
+        assert_eq!(sender.drain_expired(), vec![]);
+
+        // an empty message is sent instead, so that the receiver doesn't wait for the message
+        let (single, _) = sender.send_packet();
+        assert_eq!(single.len(), 2);
+        assert_eq!(
+            single.front().unwrap().data,
+            SingleData::new(Some(MessageId(0)), Bytes::new()).into()
+        );
+
+        sender.receive_ack(&MessageAck {
+            message_id: MessageId(0),
+            fragment_id: None,
+        });
+        assert_eq!(sender.unacked_messages.len(), 1);
+    }
+
+    #[test]
+    fn test_reliable_sender_streams() {
+        let mut sender =
+            ReliableSender::new(ReliableSettings::default(), Duration::default()).with_streams();
+        sender.current_time = WrappedTime::new(0);
+
+        let stream_message = |previous: Option<u16>, content: &'static str| {
+            let mut bytes = vec![];
+            StreamHeader {
+                previous: previous.map(MessageId),
+            }
+            .to_bytes(&mut bytes)
+            .unwrap();
+            bytes.extend_from_slice(content.as_bytes());
+            Bytes::from(bytes)
+        };
+
+        // each message points to the previous message of its stream
+        sender
+            .buffer_send(Bytes::from("a"), 1.0, SendOptions::with_stream(1))
+            .unwrap();
+        sender
+            .buffer_send(Bytes::from("b"), 1.0, SendOptions::with_stream(2))
+            .unwrap();
+        sender
+            .buffer_send(Bytes::from("c"), 1.0, SendOptions::with_stream(1))
+            .unwrap();
+        // messages without a stream id use the stream 0
+        sender
+            .buffer_send(Bytes::from("d"), 1.0, SendOptions::default())
+            .unwrap();
+        let (single, _) = sender.send_packet();
+        assert_eq!(
+            single.into_iter().map(|m| m.data).collect::<Vec<_>>(),
+            vec![
+                SingleData::new(Some(MessageId(0)), stream_message(None, "a")).into(),
+                SingleData::new(Some(MessageId(1)), stream_message(None, "b")).into(),
+                SingleData::new(Some(MessageId(2)), stream_message(Some(0), "c")).into(),
+                SingleData::new(Some(MessageId(3)), stream_message(None, "d")).into(),
+            ]
+        );
+
+        // an expired message keeps its stream header so that the receiver can skip it
+        sender
+            .buffer_send(
+                Bytes::from("e"),
+                1.0,
+                SendOptions {
+                    ttl: Some(Duration::from_millis(100)),
+                    stream_id: Some(2),
+                },
+            )
+            .unwrap();
+        sender.current_time += Duration::from_millis(200);
+        sender.expire_messages();
+        assert_eq!(sender.drain_expired(), vec![MessageId(4)]);
+        let UnackedMessage::Single { bytes, .. } =
+            &sender.unacked_messages[&MessageId(4)].unacked_message
+        else {
+            panic!("expected a single message");
+        };
+        assert_eq!(bytes, &stream_message(Some(1), ""));
+
+        // a stream is removed once all its messages are acked
+        for message_id in [0, 2] {
+            sender.receive_ack(&MessageAck {
+                message_id: MessageId(message_id),
+                fragment_id: None,
+            });
+        }
+        let streams = sender.streams.as_ref().unwrap();
+        assert!(!streams.contains_key(&1));
+        assert_eq!(streams.len(), 2);
+
+        // the next message of the stream doesn't wait for a previous message
+        sender
+            .buffer_send(Bytes::from("f"), 1.0, SendOptions::with_stream(1))
+            .unwrap();
+        let UnackedMessage::Single { bytes, .. } =
+            &sender.unacked_messages[&MessageId(5)].unacked_message
+        else {
+            panic!("expected a single message");
+        };
+        assert_eq!(bytes, &stream_message(None, "f"));
+    }
 }
@@ -6,21 +6,21 @@ use bytes::Bytes;
 use crossbeam_channel::{Receiver, Sender};
 
 use crate::channel::senders::fragment_sender::FragmentSender;
-use crate::channel::senders::ChannelSend;
+use crate::channel::senders::{take_unexpired, ChannelSend, SendOptions};
 use crate::packet::message::{MessageAck, MessageData, MessageId, SendMessage, SingleData};
 use crate::serialize::SerializationError;
 use crate::shared::ping::manager::PingManager;
 use crate::shared::tick_manager::TickManager;
-use crate::shared::time_manager::TimeManager;
+use crate::shared::time_manager::{TimeManager, WrappedTime};
 
 /// A sender that simply sends the messages without checking if they were received
 /// Same as UnorderedUnreliableSender, but includes ordering information (MessageId)
 #[derive(Debug)]
 pub struct SequencedUnreliableSender {
-    /// list of single messages that we want to fit into packets and send
-    single_messages_to_send: VecDeque<SendMessage>,
-    /// list of fragmented messages that we want to fit into packets and send
-    fragmented_messages_to_send: VecDeque<SendMessage>,
+    /// list of single messages that we want to fit into packets and send, with their expiration time
+    single_messages_to_send: VecDeque<(SendMessage, Option<WrappedTime>)>,
+    /// list of fragmented messages that we want to fit into packets and send, with their expiration time
+    fragmented_messages_to_send: VecDeque<(SendMessage, Option<WrappedTime>)>,
 
     /// Message id to use for the next message to be sent
     next_send_message_id: MessageId,
@@ -28,6 +28,9 @@ pub struct SequencedUnreliableSender {
     fragment_sender: FragmentSender,
     /// List of senders that want to be notified when a message is lost
     nack_senders: Vec<Sender<MessageId>>,
+    current_time: WrappedTime,
+    /// Default time-to-live of the messages
+    message_ttl: Option<Duration>,
     /// Internal timer to determine if the channel is ready to send messages
     timer: Option<Timer>,
 }
@@ -45,13 +48,21 @@ impl SequencedUnreliableSender {
             next_send_message_id: MessageId(0),
             fragment_sender: FragmentSender::new(),
             nack_senders: vec![],
+            current_time: WrappedTime::default(),
+            message_ttl: None,
             timer,
         }
     }
+
+    pub(crate) fn with_message_ttl(mut self, message_ttl: Option<Duration>) -> Self {
+        self.message_ttl = message_ttl;
+        self
+    }
 }
 
 impl ChannelSend for SequencedUnreliableSender {
     fn update(&mut self, time_manager: &TimeManager, _: &PingManager, _: &TickManager) {
+        self.current_time = time_manager.current_time();
         if let Some(timer) = &mut self.timer {
             timer.tick(time_manager.delta());
         }
@@ -63,24 +74,35 @@ impl ChannelSend for SequencedUnreliableSender {
         &mut self,
         message: Bytes,
         priority: f32,
+        options: SendOptions,
     ) -> Result<Option<MessageId>, SerializationError> {
+        let expires_at = options
+            .ttl
+            .or(self.message_ttl)
+            .map(|ttl| self.current_time + ttl);
         let message_id = self.next_send_message_id;
         if message.len() > self.fragment_sender.fragment_size {
             for fragment in self
                 .fragment_sender
                 .build_fragments(message_id, None, message)?
             {
-                self.fragmented_messages_to_send.push_back(SendMessage {
-                    data: MessageData::Fragment(fragment),
-                    priority,
-                });
+                self.fragmented_messages_to_send.push_back((
+                    SendMessage {
+                        data: MessageData::Fragment(fragment),
+                        priority,
+                    },
+                    expires_at,
+                ));
             }
         } else {
             let single_data = SingleData::new(Some(message_id), message);
-            self.single_messages_to_send.push_back(SendMessage {
-                data: MessageData::Single(single_data),
-                priority,
-            });
+            self.single_messages_to_send.push_back((
+                SendMessage {
+                    data: MessageData::Single(single_data),
+                    priority,
+                },
+                expires_at,
+            ));
         }
         self.next_send_message_id += 1;
         Ok(Some(message_id))
@@ -93,8 +115,8 @@ impl ChannelSend for SequencedUnreliableSender {
             return (VecDeque::new(), VecDeque::new());
         }
         (
-            std::mem::take(&mut self.single_messages_to_send),
-            std::mem::take(&mut self.fragmented_messages_to_send),
+            take_unexpired(&mut self.single_messages_to_send, self.current_time),
+            take_unexpired(&mut self.fragmented_messages_to_send, self.current_time),
         )
         // let messages_to_send = std::mem::take(&mut self.messages_to_send);
         // let (remaining_messages_to_send, _) =
@@ -122,6 +144,16 @@ impl ChannelSend for SequencedUnreliableSender {
             sender.send(nack).unwrap();
         }
     }
+
+    fn set_fragment_size(&mut self, fragment_size: usize) {
+        self.fragment_sender.fragment_size = fragment_size;
+    }
+
+    fn drain_expired(&mut self) -> Vec<MessageId> {
+        vec![]
+    }
+
+    fn receive_packet_outcome(&mut self, _: bool) {}
 }
 
 #[cfg(test)]
@@ -133,7 +165,9 @@ mod tests {
         let mut sender = SequencedUnreliableSender::new(Duration::from_secs(1));
         assert!(sender.timer.as_ref().is_some_and(|t| !t.finished()));
 
-        sender.buffer_send(Bytes::from("hello"), 1.0).unwrap();
+        sender
+            .buffer_send(Bytes::from("hello"), 1.0, SendOptions::default())
+            .unwrap();
 
         // we do not send because we didn't reach the timer
         let (single, _) = sender.send_packet();
@@ -152,4 +186,36 @@ mod tests {
         let (single, _) = sender.send_packet();
         assert_eq!(single.len(), 1);
     }
+
+    #[test]
+    fn test_sequenced_unreliable_sender_message_ttl() {
+        let mut sender = SequencedUnreliableSender::new(Duration::from_secs(1))
+            .with_message_ttl(Some(Duration::from_millis(500)));
+        sender
+            .buffer_send(Bytes::from("hello"), 1.0, SendOptions::default())
+            .unwrap();
+        sender
+            .buffer_send(
+                Bytes::from("world"),
+                1.0,
+                SendOptions::with_ttl(Duration::from_secs(2)),
+            )
+            .unwrap();
+
+        let mut time_manager = TimeManager::default();
+        time_manager.update(Duration::from_secs(1));
+        sender.update(
+            &time_manager,
+            &PingManager::new(PingConfig::default()),
+            &TickManager::from_config(TickConfig::new(Duration::from_secs(1))),
+        );
+
+        // the first message expired before the channel was ready to send
+        let (single, _) = sender.send_packet();
+        assert_eq!(single.len(), 1);
+        assert_eq!(
+            single.front().unwrap().data,
+            SingleData::new(Some(MessageId(1)), Bytes::from("world")).into()
+        );
+    }
 }
@@ -7,21 +7,21 @@ use bytes::Bytes;
 use crossbeam_channel::{Receiver, Sender};
 
 use crate::channel::senders::fragment_sender::FragmentSender;
-use crate::channel::senders::ChannelSend;
+use crate::channel::senders::{take_unexpired, ChannelSend, SendOptions};
 use crate::packet::message::{MessageAck, MessageData, MessageId, SendMessage, SingleData};
 use crate::serialize::SerializationError;
 use crate::shared::ping::manager::PingManager;
 use crate::shared::tick_manager::TickManager;
-use crate::shared::time_manager::TimeManager;
+use crate::shared::time_manager::{TimeManager, WrappedTime};
 
 /// A sender that simply sends the messages without checking if they were received
 /// Does not include any ordering information
 #[derive(Debug)]
 pub struct UnorderedUnreliableSender {
-    /// list of single messages that we want to fit into packets and send
-    single_messages_to_send: VecDeque<SendMessage>,
-    /// list of fragmented messages that we want to fit into packets and send
-    fragmented_messages_to_send: VecDeque<SendMessage>,
+    /// list of single messages that we want to fit into packets and send, with their expiration time
+    single_messages_to_send: VecDeque<(SendMessage, Option<WrappedTime>)>,
+    /// list of fragmented messages that we want to fit into packets and send, with their expiration time
+    fragmented_messages_to_send: VecDeque<(SendMessage, Option<WrappedTime>)>,
     /// Fragmented messages need an id (so they can be reconstructed), this keeps track
     /// of the next id to use
     next_send_fragmented_message_id: MessageId,
@@ -29,6 +29,9 @@ pub struct UnorderedUnreliableSender {
     fragment_sender: FragmentSender,
     /// List of senders that want to be notified when a message is lost
     nack_senders: Vec<Sender<MessageId>>,
+    current_time: WrappedTime,
+    /// Default time-to-live of the messages
+    message_ttl: Option<Duration>,
     /// Internal timer to determine if the channel is ready to send messages
     timer: Option<Timer>,
 }
@@ -46,13 +49,21 @@ impl UnorderedUnreliableSender {
             next_send_fragmented_message_id: MessageId::default(),
             fragment_sender: FragmentSender::new(),
             nack_senders: vec![],
+            current_time: WrappedTime::default(),
+            message_ttl: None,
             timer,
         }
     }
+
+    pub(crate) fn with_message_ttl(mut self, message_ttl: Option<Duration>) -> Self {
+        self.message_ttl = message_ttl;
+        self
+    }
 }
 
 impl ChannelSend for UnorderedUnreliableSender {
     fn update(&mut self, time_manager: &TimeManager, _: &PingManager, _: &TickManager) {
+        self.current_time = time_manager.current_time();
         if let Some(timer) = &mut self.timer {
             timer.tick(time_manager.delta());
         }
@@ -64,26 +75,37 @@ impl ChannelSend for UnorderedUnreliableSender {
         &mut self,
         message: Bytes,
         priority: f32,
+        options: SendOptions,
     ) -> Result<Option<MessageId>, SerializationError> {
+        let expires_at = options
+            .ttl
+            .or(self.message_ttl)
+            .map(|ttl| self.current_time + ttl);
         if message.len() > self.fragment_sender.fragment_size {
             for fragment in self.fragment_sender.build_fragments(
                 self.next_send_fragmented_message_id,
                 None,
                 message,
             )? {
-                self.fragmented_messages_to_send.push_back(SendMessage {
-                    data: MessageData::Fragment(fragment),
-                    priority,
-                });
+                self.fragmented_messages_to_send.push_back((
+                    SendMessage {
+                        data: MessageData::Fragment(fragment),
+                        priority,
+                    },
+                    expires_at,
+                ));
             }
             self.next_send_fragmented_message_id += 1;
             Ok(Some(self.next_send_fragmented_message_id - 1))
         } else {
             let single_data = SingleData::new(None, message);
-            self.single_messages_to_send.push_back(SendMessage {
-                data: MessageData::Single(single_data),
-                priority,
-            });
+            self.single_messages_to_send.push_back((
+                SendMessage {
+                    data: MessageData::Single(single_data),
+                    priority,
+                },
+                expires_at,
+            ));
             Ok(None)
         }
     }
@@ -94,8 +116,8 @@ impl ChannelSend for UnorderedUnreliableSender {
             return (VecDeque::new(), VecDeque::new());
         }
         (
-            std::mem::take(&mut self.single_messages_to_send),
-            std::mem::take(&mut self.fragmented_messages_to_send),
+            take_unexpired(&mut self.single_messages_to_send, self.current_time),
+            take_unexpired(&mut self.fragmented_messages_to_send, self.current_time),
         )
         // let messages_to_send = std::mem::take(&mut self.messages_to_send);
         // let (remaining_messages_to_send, _) =
@@ -123,6 +145,16 @@ impl ChannelSend for UnorderedUnreliableSender {
             sender.send(nack).unwrap();
         }
     }
+
+    fn set_fragment_size(&mut self, fragment_size: usize) {
+        self.fragment_sender.fragment_size = fragment_size;
+    }
+
+    fn drain_expired(&mut self) -> Vec<MessageId> {
+        vec![]
+    }
+
+    fn receive_packet_outcome(&mut self, _: bool) {}
 }
 
 #[cfg(test)]
@@ -7,7 +7,7 @@ use crossbeam_channel::{Receiver, Sender};
 
 use crate::channel::senders::fragment_ack_receiver::FragmentAckReceiver;
 use crate::channel::senders::fragment_sender::FragmentSender;
-use crate::channel::senders::ChannelSend;
+use crate::channel::senders::{take_unexpired, ChannelSend, SendOptions};
 use crate::packet::message::{MessageAck, MessageData, MessageId, SendMessage, SingleData};
 use crate::serialize::SerializationError;
 use crate::shared::ping::manager::PingManager;
@@ -21,10 +21,10 @@ const DISCARD_AFTER: chrono::Duration = chrono::Duration::milliseconds(3000);
 /// Which can let us track if a message was acked
 #[derive(Debug)]
 pub struct UnorderedUnreliableWithAcksSender {
-    /// list of single messages that we want to fit into packets and send
-    single_messages_to_send: VecDeque<SendMessage>,
-    /// list of fragmented messages that we want to fit into packets and send
-    fragmented_messages_to_send: VecDeque<SendMessage>,
+    /// list of single messages that we want to fit into packets and send, with their expiration time
+    single_messages_to_send: VecDeque<(SendMessage, Option<WrappedTime>)>,
+    /// list of fragmented messages that we want to fit into packets and send, with their expiration time
+    fragmented_messages_to_send: VecDeque<(SendMessage, Option<WrappedTime>)>,
     /// Message id to use for the next message to be sent
     next_send_message_id: MessageId,
     /// Used to split a message into fragments if the message is too big
@@ -39,6 +39,8 @@ pub struct UnorderedUnreliableWithAcksSender {
     /// was acked
     fragment_ack_receiver: FragmentAckReceiver,
     current_time: WrappedTime,
+    /// Default time-to-live of the messages
+    message_ttl: Option<Duration>,
     /// Internal timer to determine if the channel is ready to send messages
     timer: Option<Timer>,
 }
@@ -59,9 +61,15 @@ impl UnorderedUnreliableWithAcksSender {
             nack_senders: Vec::new(),
             fragment_ack_receiver: FragmentAckReceiver::new(),
             current_time: WrappedTime::default(),
+            message_ttl: None,
             timer,
         }
     }
+
+    pub(crate) fn with_message_ttl(mut self, message_ttl: Option<Duration>) -> Self {
+        self.message_ttl = message_ttl;
+        self
+    }
 }
 
 impl ChannelSend for UnorderedUnreliableWithAcksSender {
@@ -80,7 +88,12 @@ impl ChannelSend for UnorderedUnreliableWithAcksSender {
         &mut self,
         message: Bytes,
         priority: f32,
+        options: SendOptions,
     ) -> Result<Option<MessageId>, SerializationError> {
+        let expires_at = options
+            .ttl
+            .or(self.message_ttl)
+            .map(|ttl| self.current_time + ttl);
         let message_id = self.next_send_message_id;
         if message.len() > self.fragment_sender.fragment_size {
             let fragments = self
@@ -89,17 +102,23 @@ impl ChannelSend for UnorderedUnreliableWithAcksSender {
             self.fragment_ack_receiver
                 .add_new_fragment_to_wait_for(message_id, fragments.len());
             for fragment in fragments {
-                self.fragmented_messages_to_send.push_back(SendMessage {
-                    data: MessageData::Fragment(fragment),
-                    priority,
-                });
+                self.fragmented_messages_to_send.push_back((
+                    SendMessage {
+                        data: MessageData::Fragment(fragment),
+                        priority,
+                    },
+                    expires_at,
+                ));
             }
         } else {
             let single_data = SingleData::new(Some(message_id), message);
-            self.single_messages_to_send.push_back(SendMessage {
-                data: MessageData::Single(single_data),
-                priority,
-            });
+            self.single_messages_to_send.push_back((
+                SendMessage {
+                    data: MessageData::Single(single_data),
+                    priority,
+                },
+                expires_at,
+            ));
         }
         self.next_send_message_id += 1;
         Ok(Some(message_id))
@@ -111,8 +130,8 @@ impl ChannelSend for UnorderedUnreliableWithAcksSender {
             return (VecDeque::new(), VecDeque::new());
         }
         (
-            std::mem::take(&mut self.single_messages_to_send),
-            std::mem::take(&mut self.fragmented_messages_to_send),
+            take_unexpired(&mut self.single_messages_to_send, self.current_time),
+            take_unexpired(&mut self.fragmented_messages_to_send, self.current_time),
         )
         // let messages_to_send = std::mem::take(&mut self.messages_to_send);
         // let (remaining_messages_to_send, _) =
@@ -163,6 +182,16 @@ impl ChannelSend for UnorderedUnreliableWithAcksSender {
             sender.send(nack).unwrap();
         }
     }
+
+    fn set_fragment_size(&mut self, fragment_size: usize) {
+        self.fragment_sender.fragment_size = fragment_size;
+    }
+
+    fn drain_expired(&mut self) -> Vec<MessageId> {
+        vec![]
+    }
+
+    fn receive_packet_outcome(&mut self, _: bool) {}
 }
 
 #[cfg(test)]
@@ -180,7 +209,7 @@ mod tests {
 
         // single message
         let message_id = sender
-            .buffer_send(Bytes::from("hello"), 1.0)
+            .buffer_send(Bytes::from("hello"), 1.0, SendOptions::default())
             .unwrap()
             .unwrap();
         assert_eq!(message_id, MessageId(0));
@@ -196,7 +225,10 @@ mod tests {
         // fragment message
         const NUM_BYTES: usize = (FRAGMENT_SIZE as f32 * 1.5) as usize;
         let bytes = Bytes::from(vec![0; NUM_BYTES]);
-        let message_id = sender.buffer_send(bytes, 1.0).unwrap().unwrap();
+        let message_id = sender
+            .buffer_send(bytes, 1.0, SendOptions::default())
+            .unwrap()
+            .unwrap();
         assert_eq!(message_id, MessageId(1));
         let mut expected = FragmentAckReceiver::new();
         expected.add_new_fragment_to_wait_for(message_id, 2);
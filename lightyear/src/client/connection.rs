@@ -7,16 +7,19 @@ use bytes::Bytes;
 use tracing::{debug, trace, trace_span};
 
 use crate::channel::builder::{
-    EntityActionsChannel, EntityUpdatesChannel, PingChannel, PongChannel,
+    EntityActionsChannel, EntityUpdatesChannel, PingChannel, PongChannel, StreamId,
 };
 
 use crate::channel::receivers::ChannelReceive;
-use crate::channel::senders::ChannelSend;
+use crate::channel::senders::{ChannelSend, SendOptions};
 use crate::client::config::ClientConfig;
 use crate::client::error::ClientError;
 use crate::client::sync::SyncConfig;
+use crate::connection::client::NetConfig;
 use crate::connection::netcode::MAX_PACKET_SIZE;
+use crate::packet::message::MessageId;
 use crate::packet::message_manager::MessageManager;
+use crate::packet::mtu::MtuDiscoveryConfig;
 use crate::packet::packet_builder::{Payload, RecvPayload};
 use crate::packet::priority_manager::PriorityConfig;
 use crate::prelude::client::PredictionConfig;
@@ -39,6 +42,7 @@ use crate::shared::replication::receive::ReplicationReceiver;
 use crate::shared::replication::send::ReplicationSender;
 use crate::shared::replication::{EntityActionsMessage, EntityUpdatesMessage, ReplicationSend};
 use crate::shared::replication::{ReplicationPeer, ReplicationReceive};
+use crate::shared::rpc::RpcManager;
 use crate::shared::sets::ClientMarker;
 use crate::shared::tick_manager::Tick;
 use crate::shared::tick_manager::TickManager;
@@ -87,7 +91,14 @@ pub struct ConnectionManager {
     /// We use this so that:
     /// - in host server mode, we deserialize the bytes and push them to the server's Message Events queue directly
     /// - in non-host server mode, we buffer the bytes to the message manager as usual
-    pub(crate) messages_to_send: Vec<(Bytes, ChannelKind)>,
+    ///
+    /// Each message can have options (time-to-live, ordering stream) that override the settings of the channel.
+    pub(crate) messages_to_send: Vec<(Bytes, ChannelKind, SendOptions)>,
+    /// True if this is the local client of a host-server, whose messages are not buffered in the
+    /// message manager
+    local_client: bool,
+    /// Requests sent to the server that are waiting for a response
+    pub(crate) rpc: RpcManager<()>,
 }
 
 // NOTE: useful when we sometimes need to create a temporary fake ConnectionManager
@@ -108,6 +119,7 @@ impl Default for ConnectionManager {
                 &ChannelRegistry::default(),
                 0.0,
                 PriorityConfig::default(),
+                MtuDiscoveryConfig::default(),
             ),
             delta_manager: DeltaManager::default(),
             replication_sender,
@@ -120,6 +132,8 @@ impl Default for ConnectionManager {
             received_messages: HashMap::default(),
             writer: Writer::with_capacity(0),
             messages_to_send: Vec::default(),
+            local_client: false,
+            rpc: RpcManager::default(),
         }
     }
 }
@@ -132,12 +146,14 @@ impl ConnectionManager {
         channel_registry: &ChannelRegistry,
         client_config: &ClientConfig,
     ) -> Self {
-        let bandwidth_cap_enabled = client_config.packet.bandwidth_cap_enabled;
+        let bandwidth_cap_enabled = client_config.packet.bandwidth_cap_enabled
+            || client_config.packet.congestion_control.enabled;
         // create the message manager and the channels
         let mut message_manager = MessageManager::new(
             channel_registry,
             client_config.packet.nack_rtt_multiple,
             client_config.packet.into(),
+            client_config.packet.mtu_discovery,
         );
         // get notified when a replication-update message gets acked/nacked
         let entity_updates_sender = &mut message_manager
@@ -173,6 +189,8 @@ impl ConnectionManager {
             received_messages: HashMap::default(),
             writer: Writer::with_capacity(MAX_PACKET_SIZE),
             messages_to_send: Vec::default(),
+            local_client: matches!(client_config.net, NetConfig::Local { .. }),
+            rpc: RpcManager::default(),
         }
     }
 
@@ -182,6 +200,17 @@ impl ConnectionManager {
         self.sync_manager.is_synced()
     }
 
+    /// Return the maximum size of the packets sent to the server, as found by the path MTU discovery
+    pub fn mtu(&self) -> usize {
+        self.message_manager.mtu()
+    }
+
+    /// Return the current estimate of the bandwidth available to send packets to the server
+    /// (in bytes per second), if congestion control is enabled
+    pub fn bandwidth_estimate(&self) -> Option<u32> {
+        self.message_manager.bandwidth_estimate()
+    }
+
     /// Returns true if we received a new server packet on this frame
     pub(crate) fn received_new_server_tick(&self) -> bool {
         self.sync_manager.duration_since_latest_received_server_tick == Duration::default()
@@ -242,6 +271,70 @@ impl ConnectionManager {
         self.send_message_to_target::<C, M>(message, NetworkTarget::None)
     }
 
+    /// Send a [`Message`] to the server using a specific [`Channel`]
+    ///
+    /// The message is dropped if it cannot be sent before `ttl` elapses (this overrides the
+    /// [`message_ttl`](crate::prelude::ChannelSettings::message_ttl) of the channel)
+    ///
+    /// Returns the [`MessageId`] of the message if the channel is reliable, so that it can be matched
+    /// with a [`MessageExpired`](crate::prelude::client::MessageExpired) event.
+    pub fn send_message_with_ttl<C: Channel, M: Message>(
+        &mut self,
+        message: &mut M,
+        ttl: Duration,
+    ) -> Result<Option<MessageId>, ClientError> {
+        let channel_kind = ChannelKind::of::<C>();
+        let options = SendOptions::with_ttl(ttl);
+        // messages from the local client are not buffered in the MessageManager
+        if self.local_client {
+            self.erased_send_message_with_options(
+                message,
+                channel_kind,
+                NetworkTarget::None,
+                options,
+            )?;
+            return Ok(None);
+        }
+        // buffer the message right away to get its id, after the messages that are already
+        // queued so that they keep their order
+        self.buffer_messages_to_send()?;
+        let message_bytes = self.serialize_message(message, NetworkTarget::None)?;
+        Ok(self
+            .message_manager
+            .buffer_send_with_options(message_bytes, channel_kind, options)?)
+    }
+
+    /// Send a [`Message`] to the server using a specific [`Channel`], with [`SendOptions`] that override
+    /// the settings of the channel for this message
+    pub fn send_message_with_options<C: Channel, M: Message>(
+        &mut self,
+        message: &mut M,
+        options: SendOptions,
+    ) -> Result<(), ClientError> {
+        self.erased_send_message_with_options(
+            message,
+            ChannelKind::of::<C>(),
+            NetworkTarget::None,
+            options,
+        )
+    }
+
+    /// Send a [`Message`] to the server on a stream of a [`ChannelMode::OrderedReliableStreams`](crate::prelude::ChannelMode::OrderedReliableStreams) channel
+    ///
+    /// The message is only ordered with the other messages of the same stream.
+    pub fn send_message_to_stream<C: Channel, M: Message>(
+        &mut self,
+        message: &mut M,
+        stream_id: StreamId,
+    ) -> Result<(), ClientError> {
+        self.erased_send_message_with_options(
+            message,
+            ChannelKind::of::<C>(),
+            NetworkTarget::None,
+            SendOptions::with_stream(stream_id),
+        )
+    }
+
     /// Send a [`Message`] to the server using a specific [`Channel`]
     ///
     /// The message will be sent to the server and re-broadcasted to all clients that match the [`NetworkTarget`]
@@ -250,16 +343,34 @@ impl ConnectionManager {
         message: &mut M,
         target: NetworkTarget,
     ) -> Result<(), ClientError> {
-        self.erased_send_message_to_target(message, ChannelKind::of::<C>(), target)
+        self.erased_send_message_with_options(
+            message,
+            ChannelKind::of::<C>(),
+            target,
+            SendOptions::default(),
+        )
     }
 
     /// Serialize a message and buffer it internally so that it can be sent later
-    fn erased_send_message_to_target<M: Message>(
+    fn erased_send_message_with_options<M: Message>(
         &mut self,
         message: &M,
         channel_kind: ChannelKind,
         target: NetworkTarget,
+        options: SendOptions,
     ) -> Result<(), ClientError> {
+        let message_bytes = self.serialize_message(message, target)?;
+        // TODO: emit logs/metrics about the message being buffered?
+        self.messages_to_send
+            .push((message_bytes, channel_kind, options));
+        Ok(())
+    }
+
+    fn serialize_message<M: Message>(
+        &mut self,
+        message: &M,
+        target: NetworkTarget,
+    ) -> Result<Bytes, ClientError> {
         // write the target first
         // NOTE: this is ok to do because most of the time (without rebroadcast, this just adds 1 byte)
         target.to_bytes(&mut self.writer)?;
@@ -269,11 +380,21 @@ impl ConnectionManager {
             &mut self.writer,
             Some(&mut self.replication_receiver.remote_entity_map.local_to_remote),
         )?;
-        let message_bytes = self.writer.split();
+        Ok(self.writer.split())
+    }
 
-        // TODO: emit logs/metrics about the message being buffered?
-        self.messages_to_send.push((message_bytes, channel_kind));
-        Ok(())
+    /// Buffer the queued messages into the message manager
+    fn buffer_messages_to_send(&mut self) -> Result<(), ClientError> {
+        self.messages_to_send
+            .drain(..)
+            .try_for_each(|(message_bytes, channel_kind, options)| {
+                self.message_manager.buffer_send_with_options(
+                    message_bytes,
+                    channel_kind,
+                    options,
+                )?;
+                Ok::<(), ClientError>(())
+            })
     }
 
     pub(crate) fn buffer_replication_messages(
@@ -318,7 +439,7 @@ impl ConnectionManager {
         // go through messages_to_send, deserialize them and make the server receive them
         self.messages_to_send
             .drain(..)
-            .try_for_each(|(message_bytes, channel_kind)| {
+            .try_for_each(|(message_bytes, channel_kind, _)| {
                 server_manager
                     .connection_mut(local_client_id)?
                     .receive_message(
@@ -363,13 +484,7 @@ impl ConnectionManager {
             })?;
 
         // buffer the messages into the message manager
-        self.messages_to_send
-            .drain(..)
-            .try_for_each(|(message_bytes, channel_kind)| {
-                self.message_manager
-                    .buffer_send(message_bytes, channel_kind)?;
-                Ok::<(), ClientError>(())
-            })?;
+        self.buffer_messages_to_send()?;
 
         // get the payloads from the message manager
         let payloads = self.message_manager.send_packets(tick_manager.tick());
@@ -551,7 +666,7 @@ impl MessageSend for ConnectionManager {
         channel_kind: ChannelKind,
         target: NetworkTarget,
     ) -> Result<(), ClientError> {
-        self.erased_send_message_to_target(message, channel_kind, target)
+        self.erased_send_message_with_options(message, channel_kind, target, SendOptions::default())
     }
 }
 
@@ -591,8 +706,15 @@ impl ReplicationSend for ConnectionManager {
 
 #[cfg(test)]
 mod tests {
-    use crate::prelude::{client, server, ClientConnectionManager, RemoteEntityMap};
-    use crate::tests::protocol::EntityMessage;
+    use bevy::prelude::Events;
+    use bevy::utils::Duration;
+
+    use crate::channel::builder::RpcChannel;
+    use crate::packet::message::MessageId;
+    use crate::prelude::{
+        client, server, ChannelKind, ClientConnectionManager, NetworkTarget, RemoteEntityMap,
+    };
+    use crate::tests::protocol::{EntityMessage, StringMessage};
     use crate::tests::stepper::BevyStepper;
 
     /// Check that we can map entities from the local world to the remote world
@@ -644,4 +766,48 @@ mod tests {
         assert!(RemoteEntityMap::is_mapped(message.0));
         assert_eq!(RemoteEntityMap::mark_unmapped(message.0), server_entity);
     }
+
+    /// Check that a reliable message that is not acked before its time-to-live elapses
+    /// emits a `MessageExpired` event
+    #[test]
+    fn test_message_expired() {
+        let mut stepper = BevyStepper::default();
+        let mut manager = stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<ClientConnectionManager>();
+        // a message already queued on the channel, or buffered directly in the message manager,
+        // offsets the id of the next one
+        manager
+            .send_message::<RpcChannel, _>(&mut StringMessage("a".to_string()))
+            .unwrap();
+        let message_bytes = manager
+            .serialize_message(&StringMessage("b".to_string()), NetworkTarget::None)
+            .unwrap();
+        manager
+            .message_manager
+            .buffer_send(message_bytes, ChannelKind::of::<RpcChannel>())
+            .unwrap();
+        let message_id = manager
+            .send_message_with_ttl::<RpcChannel, _>(
+                &mut StringMessage("c".to_string()),
+                Duration::ZERO,
+            )
+            .unwrap();
+        assert_eq!(message_id, Some(MessageId(2)));
+        let mut expired = vec![];
+        for _ in 0..3 {
+            stepper.frame_step();
+            expired.extend(
+                stepper
+                    .client_app
+                    .world_mut()
+                    .resource_mut::<Events<client::MessageExpired>>()
+                    .drain(),
+            );
+        }
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].channel(), ChannelKind::of::<RpcChannel>());
+        assert_eq!(Some(expired[0].message_id()), message_id);
+    }
 }
@@ -1,7 +1,7 @@
 use crate::client::connection::ConnectionManager;
 use crate::client::prediction::diagnostics::PredictionDiagnosticsPlugin;
 use bevy::app::{App, Plugin, PostUpdate};
-use bevy::diagnostic::Diagnostics;
+use bevy::diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
 use bevy::prelude::{not, Condition, IntoSystemConfigs, Real, Res, ResMut, Time};
 use bevy::time::common_conditions::on_timer;
 use bevy::utils::Duration;
@@ -17,6 +17,11 @@ pub struct ClientDiagnosticsPlugin {
     flush_interval: Duration,
 }
 
+impl ClientDiagnosticsPlugin {
+    /// Maximum size of the packets sent to the server, as found by the path MTU discovery
+    pub const MTU: DiagnosticPath = DiagnosticPath::const_new("connection.mtu");
+}
+
 impl Default for ClientDiagnosticsPlugin {
     fn default() -> Self {
         Self {
@@ -39,15 +44,25 @@ fn ping_diagnostics_system(connection: Res<ConnectionManager>, diagnostics: Diag
     PingDiagnosticsPlugin::add_measurements(&connection.ping_manager, diagnostics);
 }
 
+fn connection_diagnostics_system(connection: Res<ConnectionManager>, mut diagnostics: Diagnostics) {
+    diagnostics.add_measurement(&ClientDiagnosticsPlugin::MTU, || connection.mtu() as f64);
+}
+
 impl Plugin for ClientDiagnosticsPlugin {
     fn build(&self, app: &mut App) {
         {
             let ping_plugin = PingDiagnosticsPlugin::default();
             let flush_interval = ping_plugin.flush_interval;
+            let history_len = ping_plugin.history_len;
             app.add_plugins(ping_plugin);
+            app.register_diagnostic(
+                Diagnostic::new(Self::MTU)
+                    .with_suffix("bytes")
+                    .with_max_history_length(history_len),
+            );
             app.add_systems(
                 PostUpdate,
-                ping_diagnostics_system.run_if(
+                (ping_diagnostics_system, connection_diagnostics_system).run_if(
                     on_timer(flush_interval).and_then(not(is_host_server.or_else(is_disconnected))),
                 ),
             );
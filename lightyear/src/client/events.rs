@@ -14,7 +14,7 @@
 //! ```
 
 use bevy::app::{App, Plugin, PreUpdate};
-use bevy::prelude::{Component, Event, IntoSystemConfigs};
+use bevy::prelude::{Component, Event, EventWriter, IntoSystemConfigs, ResMut};
 
 use crate::client::connection::ConnectionManager;
 use crate::connection::client::DisconnectReason;
@@ -33,11 +33,31 @@ impl Plugin for ClientEventsPlugin {
             // EVENTS
             .add_event::<ConnectEvent>()
             .add_event::<DisconnectEvent>()
+            .add_event::<MessageExpired>()
             // PLUGIN
-            .add_plugins(EventsPlugin::<ConnectionManager>::default());
+            .add_plugins(EventsPlugin::<ConnectionManager>::default())
+            // SYSTEMS
+            .add_systems(
+                PreUpdate,
+                emit_message_expired_events.in_set(InternalMainSet::<ClientMarker>::EmitEvents),
+            );
     }
 }
 
+/// Emit a [`MessageExpired`] event for each message sent to the server that expired before being acked
+fn emit_message_expired_events(
+    mut connection_manager: ResMut<ConnectionManager>,
+    mut events: EventWriter<MessageExpired>,
+) {
+    events.send_batch(
+        connection_manager
+            .message_manager
+            .drain_expired_messages()
+            .into_iter()
+            .map(|(channel, message_id)| MessageExpired::new(channel, message_id, ())),
+    );
+}
+
 pub(crate) fn emit_replication_events<C: Component>(app: &mut App) {
     app.add_event::<ComponentUpdateEvent<C>>();
     app.add_event::<ComponentInsertEvent<C>>();
@@ -85,3 +105,11 @@ pub type ComponentInsertEvent<C> = crate::shared::events::components::ComponentI
 pub type ComponentRemoveEvent<C> = crate::shared::events::components::ComponentRemoveEvent<C, ()>;
 /// Bevy [`Event`] emitted on the client when a (non-replication) message is received
 pub type MessageEvent<M> = crate::shared::events::components::MessageEvent<M, ()>;
+/// Bevy [`Event`] emitted on the client when a message sent to the server expired before being acked
+pub type MessageExpired = crate::shared::events::components::MessageExpired<()>;
+/// Bevy [`Event`] emitted on the client when a request is received from the server
+pub type RequestEvent<Req> = crate::shared::rpc::RequestEvent<Req, ()>;
+/// Bevy [`Event`] emitted on the client when a request sent to the server completes
+pub type ResponseEvent<Req, Resp> = crate::shared::rpc::ResponseEvent<Req, Resp, ()>;
+/// Bevy [`Event`] triggered on the client when a trigger is received from the server
+pub type RemoteTrigger<E> = crate::shared::trigger::RemoteTrigger<E, ()>;
@@ -6,22 +6,30 @@ use crate::transport::dummy::DummyIo;
 use crate::transport::error::Result;
 use crate::transport::io::{BaseIo, IoStats};
 use crate::transport::local::LocalChannelBuilder;
+use crate::transport::middleware::capture::Capture;
 #[cfg(feature = "zstd")]
 use crate::transport::middleware::compression::zstd::compression::ZstdCompressor;
 #[cfg(feature = "zstd")]
 use crate::transport::middleware::compression::zstd::decompression::ZstdDecompressor;
-use crate::transport::middleware::conditioner::LinkConditioner;
-use crate::transport::middleware::PacketReceiverWrapper;
+use crate::transport::middleware::conditioner::{LinkConditioner, LinkConditionerHandle};
+use crate::transport::middleware::{PacketReceiverWrapper, PacketSenderWrapper};
+#[cfg(all(feature = "quic", not(target_family = "wasm")))]
+use crate::transport::quic::{client::QuicClientSocketBuilder, CertificateDer};
+use crate::transport::replay::ReplayBuilder;
 #[cfg(not(target_family = "wasm"))]
 use crate::transport::udp::UdpSocketBuilder;
+#[cfg(unix)]
+use crate::transport::unix::UnixSocketClientBuilder;
 #[cfg(feature = "websocket")]
 use crate::transport::websocket::client::WebSocketClientSocketBuilder;
 #[cfg(feature = "webtransport")]
 use crate::transport::webtransport::client::WebTransportClientSocketBuilder;
-use crate::transport::{BoxedReceiver, Transport, LOCAL_SOCKET};
+use crate::transport::{BoxedReceiver, BoxedSender, Transport, LOCAL_SOCKET};
 use bevy::prelude::TypePath;
 use crossbeam_channel::{Receiver, Sender};
 use std::net::SocketAddr;
+#[cfg(unix)]
+use std::path::PathBuf;
 
 /// Use this to configure the [`Transport`] that will be used to establish a connection with the
 /// server.
@@ -39,15 +47,40 @@ pub enum ClientTransport {
         #[cfg(target_family = "wasm")]
         certificate_digest: String,
     },
+    /// Use a native QUIC connection as a transport layer
+    #[cfg(all(feature = "quic", not(target_family = "wasm")))]
+    QuicClient {
+        client_addr: SocketAddr,
+        server_addr: SocketAddr,
+        /// Name used to validate the server's certificate (for example `localhost`)
+        server_name: String,
+        /// Certificate of the server, which is trusted by the client (it can be self-signed)
+        server_certificate: CertificateDer<'static>,
+    },
     /// Use [`WebSocket`](https://developer.mozilla.org/en-US/docs/Web/API/WebSocket) as a transport
     #[cfg(feature = "websocket")]
     WebSocketClient { server_addr: SocketAddr },
+    /// Use a Unix datagram socket, to connect to a server running on the same machine.
+    ///
+    /// The packets are always sent to `server_path`: the server address of the connect token is not used.
+    #[cfg(unix)]
+    UnixSocket {
+        /// Path of the socket of the client
+        client_path: PathBuf,
+        /// Path of the socket of the server
+        server_path: PathBuf,
+    },
     /// Use a crossbeam_channel as a transport. This is useful for testing.
     /// This is mostly for clients.
     LocalChannel {
         recv: Receiver<Vec<u8>>,
         send: Sender<Vec<u8>>,
     },
+    /// Replay the packets received in a [`Capture`], at their original timing.
+    /// The packets sent are dropped.
+    ///
+    /// Use the connect token of the capture to authenticate, so that netcode can decrypt the packets.
+    Replay(Capture),
     /// Dummy transport if the connection handles its own io (for example steam sockets)
     Dummy,
 }
@@ -77,15 +110,38 @@ impl ClientTransport {
                 server_addr,
                 certificate_digest,
             }),
+            #[cfg(all(feature = "quic", not(target_family = "wasm")))]
+            ClientTransport::QuicClient {
+                client_addr,
+                server_addr,
+                server_name,
+                server_certificate,
+            } => ClientTransportBuilderEnum::QuicClient(QuicClientSocketBuilder {
+                client_addr,
+                server_addr,
+                server_name,
+                server_certificate,
+            }),
             #[cfg(feature = "websocket")]
             ClientTransport::WebSocketClient { server_addr } => {
                 ClientTransportBuilderEnum::WebSocketClient(WebSocketClientSocketBuilder {
                     server_addr,
                 })
             }
+            #[cfg(unix)]
+            ClientTransport::UnixSocket {
+                client_path,
+                server_path,
+            } => ClientTransportBuilderEnum::UnixSocket(UnixSocketClientBuilder {
+                client_path,
+                server_path,
+            }),
             ClientTransport::LocalChannel { recv, send } => {
                 ClientTransportBuilderEnum::LocalChannel(LocalChannelBuilder { recv, send })
             }
+            ClientTransport::Replay(capture) => {
+                ClientTransportBuilderEnum::Replay(ReplayBuilder { capture })
+            }
             ClientTransport::Dummy => ClientTransportBuilderEnum::Dummy(DummyIo),
         }
     }
@@ -108,28 +164,33 @@ impl SharedIoConfig<ClientTransport> {
     pub fn connect(self) -> Result<Io> {
         let (transport, state, io_rx, network_tx) = self.transport.build().connect()?;
         let local_addr = transport.local_addr();
-        #[allow(unused_mut)]
-        let (mut sender, receiver) = transport.split();
-        #[allow(unused_mut)]
-        let mut receiver: BoxedReceiver = if let Some(conditioner_config) = self.conditioner {
-            let conditioner = LinkConditioner::new(conditioner_config);
-            Box::new(conditioner.wrap(receiver))
-        } else {
-            Box::new(receiver)
-        };
+        let (sender, receiver) = transport.split();
+        let conditioner = self.conditioner.map(LinkConditionerHandle::new);
+        let (mut sender, mut receiver): (BoxedSender, BoxedReceiver) =
+            if let Some(conditioner) = &conditioner {
+                let outgoing = LinkConditioner::outgoing(conditioner.clone());
+                let incoming = LinkConditioner::incoming(conditioner.clone());
+                (
+                    Box::new(PacketSenderWrapper::wrap(outgoing, sender)),
+                    Box::new(PacketReceiverWrapper::wrap(incoming, receiver)),
+                )
+            } else {
+                (sender, receiver)
+            };
         match self.compression {
             CompressionConfig::None => {}
             #[cfg(feature = "zstd")]
             CompressionConfig::Zstd { level } => {
-                use crate::transport::middleware::PacketSenderWrapper;
                 let compressor = ZstdCompressor::new(level);
                 sender = Box::new(compressor.wrap(sender));
                 let decompressor = ZstdDecompressor::new();
                 receiver = Box::new(decompressor.wrap(receiver));
             }
+            // the dictionary compression is applied to the netcode payloads, before encryption
+            #[cfg(feature = "zstd")]
+            CompressionConfig::ZstdDictionary { .. } => {}
             #[cfg(feature = "lz4")]
             CompressionConfig::Lz4 => {
-                use crate::transport::middleware::PacketSenderWrapper;
                 let compressor =
                     crate::transport::middleware::compression::lz4::Compressor::default();
                 sender = Box::new(compressor.wrap(sender));
@@ -138,6 +199,11 @@ impl SharedIoConfig<ClientTransport> {
                 receiver = Box::new(decompressor.wrap(receiver));
             }
         }
+        // the capture is applied last so that it records the packets as seen by netcode
+        if let Some(capture) = self.capture {
+            sender = Box::new(PacketSenderWrapper::wrap(capture.clone(), sender));
+            receiver = Box::new(PacketReceiverWrapper::wrap(capture, receiver));
+        }
         Ok(BaseIo {
             local_addr,
             sender,
@@ -148,6 +214,7 @@ impl SharedIoConfig<ClientTransport> {
                 event_sender: network_tx,
                 event_receiver: io_rx,
             },
+            conditioner,
         })
     }
 }
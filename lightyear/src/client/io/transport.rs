@@ -3,8 +3,13 @@ use crate::transport::dummy::DummyIo;
 use crate::transport::error::Error as TransportError;
 use crate::transport::io::IoState;
 use crate::transport::local::{LocalChannel, LocalChannelBuilder};
+#[cfg(all(feature = "quic", not(target_family = "wasm")))]
+use crate::transport::quic::client::{QuicClientSocket, QuicClientSocketBuilder};
+use crate::transport::replay::{ReplayBuilder, ReplayTransport};
 #[cfg(not(target_family = "wasm"))]
 use crate::transport::udp::{UdpSocket, UdpSocketBuilder};
+#[cfg(unix)]
+use crate::transport::unix::{UnixSocketClient, UnixSocketClientBuilder};
 #[cfg(feature = "websocket")]
 use crate::transport::websocket::client::{WebSocketClientSocket, WebSocketClientSocketBuilder};
 #[cfg(feature = "webtransport")]
@@ -39,9 +44,14 @@ pub(crate) enum ClientTransportBuilderEnum {
     UdpSocket(UdpSocketBuilder),
     #[cfg(feature = "webtransport")]
     WebTransportClient(WebTransportClientSocketBuilder),
+    #[cfg(all(feature = "quic", not(target_family = "wasm")))]
+    QuicClient(QuicClientSocketBuilder),
     #[cfg(feature = "websocket")]
     WebSocketClient(WebSocketClientSocketBuilder),
+    #[cfg(unix)]
+    UnixSocket(UnixSocketClientBuilder),
     LocalChannel(LocalChannelBuilder),
+    Replay(ReplayBuilder),
     Dummy(DummyIo),
 }
 
@@ -52,8 +62,13 @@ pub(crate) enum ClientTransportEnum {
     UdpSocket(UdpSocket),
     #[cfg(feature = "webtransport")]
     WebTransportClient(WebTransportClientSocket),
+    #[cfg(all(feature = "quic", not(target_family = "wasm")))]
+    QuicClient(QuicClientSocket),
     #[cfg(feature = "websocket")]
     WebSocketClient(WebSocketClientSocket),
+    #[cfg(unix)]
+    UnixSocket(UnixSocketClient),
     LocalChannel(LocalChannel),
+    Replay(ReplayTransport),
     Dummy(DummyIo),
 }
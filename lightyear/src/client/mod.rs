@@ -26,6 +26,8 @@ pub(crate) mod io;
 pub(crate) mod message;
 pub mod networking;
 pub mod replication;
+pub(crate) mod rpc;
+pub(crate) mod trigger;
 
 pub mod error;
 pub mod run_conditions;
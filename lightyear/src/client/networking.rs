@@ -24,11 +24,15 @@ use crate::prelude::{
     is_host_server, ChannelRegistry, MainSet, MessageRegistry, TickManager, TimeManager,
 };
 use crate::protocol::component::ComponentRegistry;
+use crate::protocol::fingerprint::ProtocolFingerprint;
+use crate::protocol::version::PeerVersions;
 use crate::server::clients::ControlledEntities;
 use crate::shared::config::Mode;
 use crate::shared::replication::components::Replicated;
 use crate::shared::sets::{ClientMarker, InternalMainSet};
 use crate::transport::io::IoState;
+use crate::transport::middleware::conditioner::LinkConditionerProfile;
+use crate::transport::PacketSender;
 
 #[derive(Default)]
 pub(crate) struct ClientNetworkingPlugin;
@@ -93,6 +97,11 @@ impl Plugin for ClientNetworkingPlugin {
                         .in_set(InternalMainSet::<ClientMarker>::Send),
                     // TODO: update virtual time with Time<Real> so we have more accurate time at Send time.
                     sync_update.in_set(SyncSet),
+                    // the link conditioner can hold back packets, so we flush them every frame
+                    (update_link_conditioner, flush_io)
+                        .chain()
+                        .after(InternalMainSet::<ClientMarker>::Send)
+                        .run_if(not(is_host_server.or_else(is_disconnected))),
                 ),
             );
 
@@ -104,6 +113,7 @@ impl Plugin for ClientNetworkingPlugin {
             OnEnter(NetworkingState::Connected),
             (
                 on_connect.run_if(not(is_host_server)),
+                apply_link_conditioner_profile.run_if(not(is_host_server)),
                 on_connect_host_server.run_if(is_host_server),
             ),
         );
@@ -214,6 +224,9 @@ pub(crate) fn send(
 ) {
     trace!("Send packets to server");
     // SEND_PACKETS: send buffered packets to io
+    connection
+        .message_manager
+        .set_transport_capabilities(netcode.transport_capabilities());
     let packet_bytes = connection
         .send_packets(time_manager.as_ref(), tick_manager.as_ref())
         .unwrap();
@@ -222,11 +235,51 @@ pub(crate) fn send(
             error!("Error sending packet: {}", e);
         });
     }
+    for (channel_id, packet_byte) in connection.message_manager.take_stream_payloads() {
+        let _ = netcode
+            .send_on_stream(packet_byte.as_slice(), channel_id)
+            .map_err(|e| {
+                error!("Error sending packet: {}", e);
+            });
+    }
 
     // no need to clear the connection, because we already std::mem::take it
     // client.connection.clear();
 }
 
+/// Apply the [`LinkConditionerProfile`] to the io when it is inserted, changed or removed
+fn update_link_conditioner(
+    profile: Option<Res<LinkConditionerProfile>>,
+    mut has_profile: Local<bool>,
+    netclient: Res<ClientConnection>,
+) {
+    let changed = profile.as_ref().is_some_and(|p| p.is_changed());
+    let removed = profile.is_none() && *has_profile;
+    *has_profile = profile.is_some();
+    if changed || removed {
+        apply_link_conditioner_profile(profile, netclient);
+    }
+}
+
+/// Start the [`LinkConditionerProfile`] on the io of the new connection
+fn apply_link_conditioner_profile(
+    profile: Option<Res<LinkConditionerProfile>>,
+    netclient: Res<ClientConnection>,
+) {
+    if let Some(io) = netclient.io() {
+        io.set_conditioner_profile(profile.as_deref().cloned());
+    }
+}
+
+/// Send the packets that were buffered in the io
+fn flush_io(mut netclient: ResMut<ClientConnection>) {
+    if let Some(io) = netclient.io_mut() {
+        let _ = io
+            .flush()
+            .inspect_err(|e| error!("Error flushing io: {}", e));
+    }
+}
+
 /// Send messages in host-server mode
 /// We cannot use the normal `send` function because there is no IO available
 pub(crate) fn send_host_server(
@@ -290,7 +343,7 @@ pub(crate) fn sync_update(
 }
 
 /// Bevy [`State`] representing the networking state of the client.
-#[derive(States, Default, Debug, Clone, Copy, PartialEq, Eq, Hash,Reflect)]
+#[derive(States, Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
 pub enum NetworkingState {
     /// The client is disconnected from the server. The receive/send packets systems do not run.
     #[default]
@@ -384,7 +437,7 @@ fn on_connect_host_server(
     // spawn an entity for the client
     let client_entity = commands.spawn(ControlledEntities::default()).id();
     // start a server connection for that client (which will also send a ConnectEvent on the server)
-    server_manager.add(netcode.id(), client_entity);
+    server_manager.add(netcode.id(), client_entity, PeerVersions::default());
     server_manager
         .connection_mut(netcode.id())
         .unwrap()
@@ -469,7 +522,12 @@ fn rebuild_client_connection(world: &mut World) {
     // drop the previous client connection to make sure we release any resources before creating the new one
     world.remove_resource::<ClientConnection>();
     // insert the new client connection
-    let client_connection = client_config.net.build_client();
+    let mut client_connection = client_config.net.build_client();
+    client_connection.set_protocol_fingerprint(ProtocolFingerprint::new(
+        world.resource::<ComponentRegistry>(),
+        world.resource::<MessageRegistry>(),
+        world.resource::<ChannelRegistry>(),
+    ));
     world.insert_resource(client_connection);
 }
 
@@ -19,6 +19,7 @@ use crate::client::prediction::plugin::PredictionPlugin;
 use crate::client::replication::{
     receive::ClientReplicationReceivePlugin, send::ClientReplicationSendPlugin,
 };
+use crate::protocol::NetIdMode;
 use crate::shared::plugin::SharedPlugin;
 
 use super::config::ClientConfig;
@@ -38,11 +39,23 @@ use super::config::ClientConfig;
 /// - [`InterpolationPlugin`]: Handles the interpolation systems. This can be disabled if you don't need it.
 pub struct ClientPlugins {
     pub config: ClientConfig,
+    net_id_mode: NetIdMode,
 }
 
 impl ClientPlugins {
     pub fn new(config: ClientConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            net_id_mode: NetIdMode::default(),
+        }
+    }
+
+    /// Set how the net ids of the registered messages and components are assigned.
+    ///
+    /// The server must use the same [`NetIdMode`].
+    pub fn with_net_id_mode(mut self, net_id_mode: NetIdMode) -> Self {
+        self.net_id_mode = net_id_mode;
+        self
     }
 }
 
@@ -55,6 +68,7 @@ impl PluginGroup for ClientPlugins {
         let builder = builder
             .add(SetupPlugin {
                 config: self.config,
+                net_id_mode: self.net_id_mode,
             })
             .add(ClientEventsPlugin)
             .add(ClientNetworkingPlugin)
@@ -73,6 +87,7 @@ impl PluginGroup for ClientPlugins {
 
 struct SetupPlugin {
     config: ClientConfig,
+    net_id_mode: NetIdMode,
 }
 
 // TODO: override `ready` and `finish` to make sure that the transport/backend is connected
@@ -89,9 +104,9 @@ impl Plugin for SetupPlugin {
         if !app.is_plugin_added::<SharedPlugin>() {
             app
                 // PLUGINS
-                .add_plugins(SharedPlugin {
-                    config: self.config.shared,
-                });
+                .add_plugins(
+                    SharedPlugin::new(self.config.shared).with_net_id_mode(self.net_id_mode),
+                );
         }
     }
 }
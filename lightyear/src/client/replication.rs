@@ -74,7 +74,7 @@ pub(crate) mod receive {
 
 pub(crate) mod send {
     use super::*;
-    use bevy::ecs::component::ComponentTicks;
+    use bevy::ecs::component::{ComponentId, ComponentTicks};
 
     use crate::connection::client::ClientConnection;
 
@@ -88,8 +88,9 @@ pub(crate) mod send {
     use crate::protocol::component::ComponentKind;
 
     use crate::shared::replication::components::{
-        InitialReplicated, Replicating, ReplicationGroupId,
+        InitialReplicated, Replicating, ReplicationFrequency, ReplicationGroupId,
     };
+    use crate::shared::replication::send::ComponentFrequency;
 
     use crate::shared::replication::archetypes::{
         get_erased_component, ClientReplicatedArchetypes,
@@ -347,6 +348,17 @@ pub(crate) mod send {
                             replicated_component.id,
                         )
                     };
+                    let frequency = replicated_component.frequency.and_then(|id| {
+                        entity_ref
+                            .get_by_id(id)
+                            .ok()
+                            // SAFETY: we know the archetype has the ReplicationFrequency<C> component
+                            // which is repr(C), so it has the same memory layout for any C
+                            .map(|ptr| unsafe {
+                                ptr.deref::<ReplicationFrequency<()>>()
+                                    .to_tick_frequency(tick_manager.config.tick_duration)
+                            })
+                    });
                     let _ = replicate_component_update(
                         tick_manager.tick(),
                         &component_registry,
@@ -358,6 +370,7 @@ pub(crate) mod send {
                         group_id,
                         replicated_component.delta_compression,
                         replicated_component.replicate_once,
+                        frequency,
                         &system_ticks,
                         &mut sender,
                     )
@@ -459,6 +472,7 @@ pub(crate) mod send {
         group_id: ReplicationGroupId,
         delta_compression: bool,
         replicate_once: bool,
+        frequency: Option<ComponentFrequency>,
         system_ticks: &SystemChangeTick,
         sender: &mut ConnectionManager,
     ) -> Result<(), ReplicationError> {
@@ -532,22 +546,19 @@ pub(crate) mod send {
                     .prepare_component_insert(entity, group_id, raw_data);
             } else {
                 trace!(?entity, "send update");
-                let send_tick = sender
-                    .replication_sender
-                    .group_channels
-                    .entry(group_id)
-                    .or_default()
-                    .send_tick;
-
                 // send the update for all changes newer than the last send bevy tick for the group
-                if send_tick.map_or(true, |c| {
-                    component_ticks
-                        .changed
-                        .is_newer_than(c, system_ticks.this_run())
-                }) {
+                // (or for the component, if it is sent at its own frequency)
+                if sender.replication_sender.should_send_component_update(
+                    entity,
+                    group_id,
+                    component_kind,
+                    frequency,
+                    component_ticks.changed,
+                    system_ticks.this_run(),
+                    current_tick,
+                ) {
                     trace!(
                         change_tick = ?component_ticks.changed,
-                        ?send_tick,
                         current_tick = ?system_ticks.this_run(),
                         "prepare entity update changed check"
                     );
@@ -586,6 +597,16 @@ pub(crate) mod send {
                             .replication_sender
                             .prepare_component_update(entity, group_id, raw_data);
                     }
+                    if let Some(frequency) = frequency {
+                        sender
+                            .replication_sender
+                            .prepare_frequency_component_update(
+                                entity,
+                                group_id,
+                                component_kind,
+                                frequency,
+                            );
+                    }
                 }
             }
         }
@@ -623,6 +644,44 @@ pub(crate) mod send {
         }
     }
 
+    /// Send component remove for components that were registered through reflection.
+    ///
+    /// The observer watches the component of the reflected type, which is only known at runtime.
+    pub(crate) fn send_reflect_component_removed(
+        trigger: Trigger<OnRemove>,
+        registry: Res<ComponentRegistry>,
+        mut sender: ResMut<ConnectionManager>,
+        // only remove the component for entities that are being actively replicated
+        query: Query<&ReplicationGroup, (With<Replicating>, With<ReplicateToServer>)>,
+    ) {
+        let Some(kind) = trigger
+            .components()
+            .iter()
+            .find_map(|component_id| registry.reflect_net_id(*component_id))
+        else {
+            return;
+        };
+        if let Ok(group) = query.get(trigger.entity()) {
+            let group_id = group.group_id(Some(trigger.entity()));
+            // convert the entity to a network entity (possibly mapped)
+            let entity = sender
+                .replication_receiver
+                .remote_entity_map
+                .to_remote(trigger.entity());
+            trace!(?entity, ?kind, "Sending RemoveComponent");
+            sender
+                .replication_sender
+                .prepare_component_remove(entity, group_id, kind);
+        }
+    }
+
+    pub(crate) fn register_replicate_reflect_component_send(
+        world: &mut World,
+        component_id: ComponentId,
+    ) {
+        world.spawn(Observer::new(send_reflect_component_removed).with_component(component_id));
+    }
+
     pub(crate) fn register_replicate_component_send<C: Component>(app: &mut App) {
         // TODO: what if we remove and add within one replication_interval?
         app.add_observer(send_component_removed::<C>);
@@ -0,0 +1,102 @@
+//! Send requests to the server and respond to the requests received from the server
+use bevy::prelude::{
+    resource_exists, App, EventWriter, Events, IntoSystemConfigs, PreUpdate, Res, ResMut,
+};
+use bevy::utils::Duration;
+
+use crate::channel::builder::RpcChannel;
+use crate::client::connection::ConnectionManager;
+use crate::client::error::ClientError;
+use crate::client::events::{MessageEvent, RequestEvent, ResponseEvent};
+use crate::connection::client::ClientConnection;
+use crate::prelude::client::is_connected;
+use crate::prelude::{Channel, Message, TimeManager};
+use crate::shared::rpc::{RequestHandle, RpcRequest, RpcResponse};
+use crate::shared::sets::{ClientMarker, InternalMainSet};
+
+impl ConnectionManager {
+    /// Send a request to the server using a specific [`Channel`].
+    ///
+    /// The returned [`RequestHandle`] identifies the [`ResponseEvent`] that will be emitted when the
+    /// response is received, or when the request fails because it timed out or because the client
+    /// got disconnected.
+    pub fn send_request<C: Channel, Req: Message>(
+        &mut self,
+        request: Req,
+        timeout: Duration,
+    ) -> Result<RequestHandle<Req>, ClientError> {
+        let id = self.rpc.next_id();
+        self.send_message::<C, _>(&mut RpcRequest { id, request })?;
+        Ok(self.rpc.add::<Req>(id, (), timeout))
+    }
+
+    /// Respond to a request that was received from the server
+    pub fn respond<Req: Message, Resp: Message>(
+        &mut self,
+        handle: RequestHandle<Req>,
+        response: Resp,
+    ) -> Result<(), ClientError> {
+        self.send_message::<RpcChannel, _>(&mut RpcResponse::<Req, Resp>::new(
+            handle.id(),
+            response,
+        ))
+    }
+}
+
+/// Convert the requests received from the server into [`RequestEvent`]s
+fn receive_requests<Req: Message>(
+    mut messages: ResMut<Events<MessageEvent<RpcRequest<Req>>>>,
+    mut events: EventWriter<RequestEvent<Req>>,
+) {
+    events.send_batch(messages.drain().map(|event| {
+        RequestEvent::new(
+            event.message.request,
+            RequestHandle::new(event.message.id, ()),
+        )
+    }));
+}
+
+/// Emit a [`ResponseEvent`] for each response received from the server, and for each request that
+/// failed
+fn receive_responses<Req: Message, Resp: Message>(
+    netclient: Option<Res<ClientConnection>>,
+    time_manager: Res<TimeManager>,
+    mut connection: ResMut<ConnectionManager>,
+    mut messages: ResMut<Events<MessageEvent<RpcResponse<Req, Resp>>>>,
+    mut events: EventWriter<ResponseEvent<Req, Resp>>,
+) {
+    for event in messages.drain() {
+        if let Some(handle) = connection.rpc.receive::<Req>(event.message.id, ()) {
+            events.send(ResponseEvent::new(handle, Ok(event.message.response)));
+        }
+    }
+    let connected = is_connected(netclient);
+    for (handle, error) in connection
+        .rpc
+        .fail::<Req>(time_manager.delta(), |_| connected)
+    {
+        events.send(ResponseEvent::new(handle, Err(error)));
+    }
+}
+
+/// Register the systems to receive requests of type `Req` from the server
+pub(crate) fn add_client_receive_request<Req: Message>(app: &mut App) {
+    app.add_event::<RequestEvent<Req>>();
+    app.add_systems(
+        PreUpdate,
+        receive_requests::<Req>
+            .after(InternalMainSet::<ClientMarker>::EmitEvents)
+            .run_if(is_connected),
+    );
+}
+
+/// Register the systems to receive the responses to the requests of type `Req` sent to the server
+pub(crate) fn add_client_receive_response<Req: Message, Resp: Message>(app: &mut App) {
+    app.add_event::<ResponseEvent<Req, Resp>>();
+    app.add_systems(
+        PreUpdate,
+        receive_responses::<Req, Resp>
+            .after(InternalMainSet::<ClientMarker>::EmitEvents)
+            .run_if(resource_exists::<ConnectionManager>),
+    );
+}
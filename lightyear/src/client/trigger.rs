@@ -0,0 +1,60 @@
+//! Send triggers to the server and trigger the events received from the server
+use bevy::prelude::{App, Commands, Entity, Event, Events, IntoSystemConfigs, PreUpdate, ResMut};
+use tracing::error;
+
+use crate::client::connection::ConnectionManager;
+use crate::client::error::ClientError;
+use crate::client::events::{MessageEvent, RemoteTrigger};
+use crate::prelude::client::is_connected;
+use crate::prelude::{Channel, Message};
+use crate::shared::sets::{ClientMarker, InternalMainSet};
+use crate::shared::trigger::TriggerMessage;
+
+impl ConnectionManager {
+    /// Send a trigger to the server using a specific [`Channel`].
+    ///
+    /// The event will be triggered on the server's entities that correspond to `targets`,
+    /// or as a global trigger if `targets` is empty.
+    pub fn send_trigger<C: Channel, E: Event + Message>(
+        &mut self,
+        trigger: E,
+        targets: impl IntoIterator<Item = Entity>,
+    ) -> Result<(), ClientError> {
+        self.send_message::<C, _>(&mut TriggerMessage {
+            trigger,
+            target_entities: targets.into_iter().collect(),
+        })
+    }
+}
+
+/// Trigger a [`RemoteTrigger`] for each trigger received from the server
+fn read_triggers<E: Event + Message>(
+    mut commands: Commands,
+    mut messages: ResMut<Events<MessageEvent<TriggerMessage<E>>>>,
+) {
+    for event in messages.drain() {
+        if event.message.has_unmapped_targets() {
+            error!(
+                "Skipping trigger {} because some of its target entities could not be mapped",
+                std::any::type_name::<E>()
+            );
+            continue;
+        }
+        let trigger = RemoteTrigger::new(event.message.trigger, ());
+        if event.message.target_entities.is_empty() {
+            commands.trigger(trigger);
+        } else {
+            commands.trigger_targets(trigger, event.message.target_entities);
+        }
+    }
+}
+
+/// Register the system to receive triggers of type `E` from the server
+pub(crate) fn add_client_receive_trigger<E: Event + Message>(app: &mut App) {
+    app.add_systems(
+        PreUpdate,
+        read_triggers::<E>
+            .after(InternalMainSet::<ClientMarker>::EmitEvents)
+            .run_if(is_connected),
+    );
+}
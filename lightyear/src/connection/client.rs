@@ -21,7 +21,10 @@ use crate::prelude::client::ClientTransport;
 #[cfg(all(feature = "steam", not(target_family = "wasm")))]
 use crate::prelude::LinkConditionerConfig;
 use crate::prelude::{generate_key, Key};
+use crate::protocol::channel::ChannelId;
+use crate::protocol::fingerprint::ProtocolFingerprint;
 use crate::transport::config::SharedIoConfig;
+use crate::transport::TransportCapabilities;
 
 #[derive(Debug)]
 pub enum ConnectionState {
@@ -57,6 +60,23 @@ pub trait NetClient: Send + Sync {
     /// Send a packet to the server
     fn send(&mut self, buf: &[u8]) -> Result<(), ConnectionError>;
 
+    /// Send a packet to the server on the reliable stream of the transport dedicated to the
+    /// channel `channel_id`.
+    ///
+    /// Connections whose transport has no streams send it like any other packet.
+    fn send_on_stream(
+        &mut self,
+        buf: &[u8],
+        _channel_id: ChannelId,
+    ) -> Result<(), ConnectionError> {
+        self.send(buf)
+    }
+
+    /// Capabilities of the transport, in terms of the packets passed to [`NetClient::send`]
+    fn transport_capabilities(&self) -> TransportCapabilities {
+        TransportCapabilities::default()
+    }
+
     /// Get the id of the client
     fn id(&self) -> ClientId;
 
@@ -91,6 +111,8 @@ pub struct ClientConnection {
 pub enum DisconnectReason {
     Transport(crate::transport::error::Error),
     Netcode(super::netcode::ClientState),
+    /// The server denied the connection request
+    Denied(super::server::DeniedReason),
     #[cfg(all(feature = "steam", not(target_family = "wasm")))]
     Steam(steamworks::networking_types::NetConnectionEnd),
 }
@@ -151,6 +173,7 @@ impl NetConfig {
                     client: netcode,
                     io_config,
                     io: None,
+                    compression: None,
                 };
                 ClientConnection {
                     client: NetClientDispatch::Netcode(client),
@@ -186,6 +209,17 @@ impl NetConfig {
     }
 }
 
+impl ClientConnection {
+    /// Set the [`ProtocolFingerprint`] that will be checked by the server during the connection handshake
+    pub(crate) fn set_protocol_fingerprint(&mut self, fingerprint: ProtocolFingerprint) {
+        if let NetClientDispatch::Netcode(client) = &mut self.client {
+            client.client.set_protocol_fingerprint(
+                fingerprint.with_compression(&client.io_config.compression),
+            );
+        }
+    }
+}
+
 impl NetClient for ClientConnection {
     fn connect(&mut self) -> Result<(), ConnectionError> {
         self.client.connect()
@@ -211,6 +245,14 @@ impl NetClient for ClientConnection {
         self.client.send(buf)
     }
 
+    fn send_on_stream(&mut self, buf: &[u8], channel_id: ChannelId) -> Result<(), ConnectionError> {
+        self.client.send_on_stream(buf, channel_id)
+    }
+
+    fn transport_capabilities(&self) -> TransportCapabilities {
+        self.client.transport_capabilities()
+    }
+
     fn id(&self) -> ClientId {
         self.client.id()
     }
@@ -8,9 +8,14 @@ use crate::connection::client::{
     ConnectionError, ConnectionState, DisconnectReason, IoConfig, NetClient,
 };
 use crate::connection::id;
+use crate::connection::server::DeniedReason;
 use crate::packet::packet_builder::RecvPayload;
+use crate::protocol::channel::ChannelId;
+use crate::protocol::fingerprint::ProtocolFingerprint;
+use crate::protocol::version::MAX_VERSIONED_TYPES;
 use crate::transport::io::IoState;
-use crate::transport::{PacketReceiver, PacketSender, LOCAL_SOCKET};
+use crate::transport::middleware::compression::PayloadCompression;
+use crate::transport::{PacketReceiver, PacketSender, TransportCapabilities, LOCAL_SOCKET};
 use crate::utils::pool::Pool;
 
 use super::{
@@ -18,10 +23,13 @@ use super::{
     error::{Error, Result},
     packet::{
         DisconnectPacket, KeepAlivePacket, Packet, PayloadPacket, RequestPacket, ResponsePacket,
+        STREAM_SEQUENCE_BIT,
     },
+    payload_capabilities,
     replay::ReplayProtection,
     token::{ChallengeToken, ConnectToken},
-    utils, ClientId, MAX_PACKET_SIZE, MAX_PKT_BUF_SIZE, PACKET_SEND_RATE_SEC,
+    utils, ClientId, MAX_PAYLOAD_SIZE, MAX_PKT_BUF_SIZE, MAX_STREAM_PAYLOAD_SIZE,
+    MAX_STREAM_PKT_BUF_SIZE, PACKET_SEND_RATE_SEC,
 };
 
 type Callback<Ctx> = Box<dyn FnMut(ClientState, ClientState, &mut Ctx) + Send + Sync + 'static>;
@@ -186,6 +194,8 @@ pub struct NetcodeClient<Ctx = ()> {
     last_receive_time: f64,
     server_addr_idx: usize,
     sequence: u64,
+    /// Sequence of the packets sent on the reliable streams of the transport
+    stream_sequence: u64,
     challenge_token_sequence: u64,
     challenge_token_data: [u8; ChallengeToken::SIZE],
     token: ConnectToken,
@@ -194,6 +204,8 @@ pub struct NetcodeClient<Ctx = ()> {
     should_disconnect_state: ClientState,
     packet_queue: VecDeque<RecvPayload>,
     buffer_pool: Pool<Vec<u8>>,
+    protocol_fingerprint: Option<ProtocolFingerprint>,
+    denied_reason: Option<DeniedReason>,
     cfg: ClientConfig<Ctx>,
 }
 
@@ -221,6 +233,7 @@ impl<Ctx> NetcodeClient<Ctx> {
             last_receive_time: f64::NEG_INFINITY,
             server_addr_idx: 0,
             sequence: 0,
+            stream_sequence: STREAM_SEQUENCE_BIT,
             challenge_token_sequence: 0,
             challenge_token_data: [0u8; ChallengeToken::SIZE],
             token,
@@ -229,6 +242,8 @@ impl<Ctx> NetcodeClient<Ctx> {
             should_disconnect_state: ClientState::Disconnected,
             packet_queue: VecDeque::new(),
             buffer_pool: Pool::new(10, || vec![0u8; MAX_PKT_BUF_SIZE]),
+            protocol_fingerprint: None,
+            denied_reason: None,
             cfg,
         })
     }
@@ -310,6 +325,7 @@ impl<Ctx> NetcodeClient<Ctx> {
     }
     fn reset(&mut self, new_state: ClientState) {
         self.sequence = 0;
+        self.stream_sequence = STREAM_SEQUENCE_BIT;
         self.start_time = 0.0;
         self.server_addr_idx = 0;
         self.set_state(new_state);
@@ -325,9 +341,15 @@ impl<Ctx> NetcodeClient<Ctx> {
                 debug!("client sending connection request packet to server");
                 RequestPacket::create(
                     self.token.protocol_id,
+                    self.protocol_fingerprint
+                        .as_ref()
+                        .map_or(0, |fingerprint| fingerprint.hash()),
                     self.token.expire_timestamp,
                     self.token.nonce,
                     self.token.private_data,
+                    self.protocol_fingerprint
+                        .as_ref()
+                        .map_or(vec![], |fingerprint| fingerprint.versions().to_vec()),
                 )
             }
             ClientState::SendingChallengeResponse => {
@@ -352,16 +374,40 @@ impl<Ctx> NetcodeClient<Ctx> {
         Ok(())
     }
     fn send_packet(&mut self, packet: Packet, io: &mut Io) -> Result<()> {
-        let mut buf = [0u8; MAX_PKT_BUF_SIZE];
-        let size = packet.write(
-            &mut buf,
-            self.sequence,
-            &self.token.client_to_server_key,
-            self.token.protocol_id,
-        )?;
-        io.send(&buf[..size], &self.server_addr())?;
+        self.send_packet_on_stream(packet, None, io)
+    }
+    /// Send the packet as a datagram, or on the reliable stream of the channel `stream`
+    fn send_packet_on_stream(
+        &mut self,
+        packet: Packet,
+        stream: Option<ChannelId>,
+        io: &mut Io,
+    ) -> Result<()> {
+        match stream {
+            Some(channel_id) => {
+                let mut buf = vec![0u8; MAX_STREAM_PKT_BUF_SIZE];
+                let size = packet.write(
+                    &mut buf,
+                    self.stream_sequence,
+                    &self.token.client_to_server_key,
+                    self.token.protocol_id,
+                )?;
+                io.send_on_stream(&buf[..size], &self.server_addr(), channel_id)?;
+                self.stream_sequence += 1;
+            }
+            None => {
+                let mut buf = [0u8; MAX_PKT_BUF_SIZE];
+                let size = packet.write(
+                    &mut buf,
+                    self.sequence,
+                    &self.token.client_to_server_key,
+                    self.token.protocol_id,
+                )?;
+                io.send(&buf[..size], &self.server_addr())?;
+                self.sequence += 1;
+            }
+        }
         self.last_send_time = self.time;
-        self.sequence += 1;
         Ok(())
     }
 
@@ -378,10 +424,17 @@ impl<Ctx> NetcodeClient<Ctx> {
                 Packet::Denied(pkt),
                 ClientState::SendingConnectionRequest | ClientState::SendingChallengeResponse,
             ) => {
-                error!(
-                    "client connection denied by server. Reason: {:?}",
-                    pkt.reason
-                );
+                let mut reason = pkt.reason;
+                if let (DeniedReason::ProtocolMismatch(mismatch), Some(fingerprint)) =
+                    (&mut reason, &self.protocol_fingerprint)
+                {
+                    let entry = fingerprint.first_difference(mismatch);
+                    error!("client connection denied by server. The client and server protocols differ: {entry}");
+                    mismatch.entry = Some(entry);
+                } else {
+                    error!("client connection denied by server. Reason: {:?}", reason);
+                }
+                self.denied_reason = Some(reason);
                 self.should_disconnect = true;
                 self.should_disconnect_state = ClientState::ConnectionDenied;
             }
@@ -523,6 +576,7 @@ impl<Ctx> NetcodeClient<Ctx> {
     /// This function does not perform any IO, it only readies the client to send/receive packets on the next call to [`update`](NetcodeClient::update). <br>
     pub fn connect(&mut self) {
         self.reset_connection();
+        self.denied_reason = None;
         self.set_state(ClientState::SendingConnectionRequest);
         info!(
             "client connecting to server {} [{}/{}]",
@@ -598,16 +652,37 @@ impl<Ctx> NetcodeClient<Ctx> {
 
     /// Sends a packet to the server.
     ///
-    /// The provided buffer must be smaller than [`MAX_PACKET_SIZE`].
+    /// The provided buffer must not be bigger than `MAX_PAYLOAD_SIZE` (1447 bytes).
     pub fn send(&mut self, buf: &[u8], io: &mut Io) -> Result<()> {
+        self.send_payload(buf, None, io)
+    }
+
+    /// Sends a packet to the server on the reliable stream of the transport dedicated to the
+    /// channel `channel_id`, or as a datagram if the transport has no streams.
+    ///
+    /// The provided buffer must not be bigger than `MAX_STREAM_PAYLOAD_SIZE` (64487 bytes) if the
+    /// transport has streams, or `MAX_PAYLOAD_SIZE` (1447 bytes) otherwise.
+    pub fn send_on_stream(&mut self, buf: &[u8], channel_id: ChannelId, io: &mut Io) -> Result<()> {
+        self.send_payload(buf, Some(channel_id), io)
+    }
+
+    fn send_payload(&mut self, buf: &[u8], stream: Option<ChannelId>, io: &mut Io) -> Result<()> {
         if self.state != ClientState::Connected {
             trace!("tried to send but not connected");
             return Ok(());
         }
-        if buf.len() > MAX_PACKET_SIZE {
-            return Err(Error::SizeMismatch(MAX_PACKET_SIZE, buf.len()));
+        // only use the stream sequences if the packet is really sent on a stream, since they
+        // are not protected against replays
+        let stream = stream.filter(|_| io.capabilities(&self.server_addr()).reliable_streams);
+        let max_size = if stream.is_some() {
+            MAX_STREAM_PAYLOAD_SIZE
+        } else {
+            MAX_PAYLOAD_SIZE
+        };
+        if buf.len() > max_size {
+            return Err(Error::SizeMismatch(max_size, buf.len()));
         }
-        self.send_packet(PayloadPacket::create(buf), io)?;
+        self.send_packet_on_stream(PayloadPacket::create(buf), stream, io)?;
         Ok(())
     }
     /// Disconnects the client from the server.
@@ -631,6 +706,14 @@ impl<Ctx> NetcodeClient<Ctx> {
     pub fn state(&self) -> ClientState {
         self.state
     }
+    /// Returns the reason why the server denied the last connection request, if it did.
+    pub fn denied_reason(&self) -> Option<&DeniedReason> {
+        self.denied_reason.as_ref()
+    }
+    /// Set the fingerprint of the protocol, whose hash is sent to the server in the connection request
+    pub(crate) fn set_protocol_fingerprint(&mut self, fingerprint: ProtocolFingerprint) {
+        self.protocol_fingerprint = Some(fingerprint);
+    }
     /// Returns true if the client is in an error state.
     pub fn is_error(&self) -> bool {
         self.state < ClientState::Disconnected
@@ -660,13 +743,28 @@ pub(crate) mod connection {
         pub client: NetcodeClient<Ctx>,
         pub io_config: IoConfig,
         pub io: Option<Io>,
+        /// Compression of the payloads, created from the `io_config` when connecting
+        pub(crate) compression: Option<PayloadCompression>,
     }
 
     impl<Ctx: Send + Sync> NetClient for Client<Ctx> {
         fn connect(&mut self) -> Result<(), ConnectionError> {
+            // the versions are sent in the connection request, which has to fit in a packet
+            if let Some(fingerprint) = &self.client.protocol_fingerprint {
+                if fingerprint.versions().len() > MAX_VERSIONED_TYPES {
+                    return Err(Error::TooManyVersionedTypes(fingerprint.versions().len()).into());
+                }
+            }
             let io_config = self.io_config.clone();
+            self.compression = Some(PayloadCompression::new(
+                &io_config.compression,
+                io_config.recorder.clone(),
+            )?);
             let io = io_config.connect()?;
             self.io = Some(io);
+            if let Some(capture) = &self.io_config.capture {
+                capture.record_connect_token(&self.client.token);
+            }
             self.client.connect();
             Ok(())
         }
@@ -690,6 +788,15 @@ pub(crate) mod connection {
                     ConnectionState::Connecting
                 }
                 ClientState::Connected => ConnectionState::Connected,
+                ClientState::ConnectionDenied if self.client.denied_reason.is_some() => {
+                    ConnectionState::Disconnected {
+                        reason: self
+                            .client
+                            .denied_reason
+                            .clone()
+                            .map(DisconnectReason::Denied),
+                    }
+                }
                 _ => ConnectionState::Disconnected {
                     reason: Some(DisconnectReason::Netcode(self.client.state)),
                 },
@@ -705,15 +812,48 @@ pub(crate) mod connection {
         }
 
         fn recv(&mut self) -> Option<RecvPayload> {
-            self.client.recv()
+            let payload = self.client.recv()?;
+            let Some(compression) = self.compression.as_mut() else {
+                return Some(payload);
+            };
+            compression
+                .decompress(payload)
+                .inspect_err(|e| error!("error decompressing payload: {:?}", e))
+                .ok()
         }
 
         fn send(&mut self, buf: &[u8]) -> Result<(), ConnectionError> {
             let io = self.io.as_mut().ok_or(ConnectionError::IoNotInitialized)?;
+            let buf = match self.compression.as_mut() {
+                Some(compression) => compression.compress(buf)?,
+                None => buf,
+            };
             self.client.send(buf, io)?;
             Ok(())
         }
 
+        fn send_on_stream(
+            &mut self,
+            buf: &[u8],
+            channel_id: ChannelId,
+        ) -> Result<(), ConnectionError> {
+            let io = self.io.as_mut().ok_or(ConnectionError::IoNotInitialized)?;
+            let buf = match self.compression.as_mut() {
+                Some(compression) => compression.compress(buf)?,
+                None => buf,
+            };
+            self.client.send_on_stream(buf, channel_id, io)?;
+            Ok(())
+        }
+
+        fn transport_capabilities(&self) -> TransportCapabilities {
+            self.io
+                .as_ref()
+                .map_or_else(TransportCapabilities::default, |io| {
+                    payload_capabilities(io.capabilities(&self.client.server_addr()))
+                })
+        }
+
         fn id(&self) -> id::ClientId {
             id::ClientId::Netcode(self.client.id())
         }
@@ -25,4 +25,6 @@ pub enum Error {
     Io(#[from] std::io::Error),
     #[error(transparent)]
     Transport(#[from] crate::transport::error::Error),
+    #[error("the protocol has {0} messages and components with legacy versions, but at most {max} are supported", max = crate::protocol::version::MAX_VERSIONED_TYPES)]
+    TooManyVersionedTypes(usize),
 }
@@ -127,6 +127,8 @@ pub use error::{Error, Result};
 pub use server::{connection::Server, Callback, ClientId, NetcodeServer, ServerConfig};
 pub use token::{ConnectToken, ConnectTokenBuilder, InvalidTokenError};
 
+pub(crate) use packet::decrypt_packet;
+
 mod bytes;
 mod client;
 mod crypto;
@@ -138,7 +140,9 @@ mod token;
 mod utils;
 
 pub(crate) const MAC_BYTES: usize = 16;
-pub(crate) const MAX_PKT_BUF_SIZE: usize = 1300;
+/// Netcode packets can use the full UDP payload, so that path MTU discovery can grow the
+/// lightyear packets above [`MAX_PACKET_SIZE`]
+pub(crate) const MAX_PKT_BUF_SIZE: usize = crate::transport::MTU;
 pub(crate) const CONNECTION_TIMEOUT_SEC: i32 = 15;
 pub(crate) const PACKET_SEND_RATE_SEC: f64 = 1.0 / 10.0;
 
@@ -148,7 +152,27 @@ pub const PRIVATE_KEY_BYTES: usize = 32;
 pub const USER_DATA_BYTES: usize = 256;
 /// The size of the connect token in bytes.
 pub const CONNECT_TOKEN_BYTES: usize = 2048;
-/// The maximum size of a packet in bytes.
+/// The maximum size of a packet in bytes, unless a bigger size is discovered with path MTU discovery.
 pub const MAX_PACKET_SIZE: usize = 1200;
+/// The maximum size of the payload of a payload packet, which also contains a prefix byte,
+/// a sequence number (up to 8 bytes) and a MAC.
+pub(crate) const MAX_PAYLOAD_SIZE: usize = MAX_PKT_BUF_SIZE - 1 - 8 - MAC_BYTES;
+/// Netcode packets sent on a reliable stream of the transport are not limited by the MTU.
+/// Some room is left for the middlewares that can grow the packets (for example compression).
+pub(crate) const MAX_STREAM_PKT_BUF_SIZE: usize = crate::transport::MAX_STREAM_PACKET_SIZE - 1024;
+/// The maximum size of the payload of a payload packet sent on a reliable stream of the transport
+pub(crate) const MAX_STREAM_PAYLOAD_SIZE: usize = MAX_STREAM_PKT_BUF_SIZE - 1 - 8 - MAC_BYTES;
+
+/// Capabilities of the transport in terms of the payloads carried by the netcode packets
+pub(crate) fn payload_capabilities(
+    capabilities: crate::transport::TransportCapabilities,
+) -> crate::transport::TransportCapabilities {
+    crate::transport::TransportCapabilities {
+        max_datagram_size: capabilities
+            .max_datagram_size
+            .map(|size| size.saturating_sub(MAX_PKT_BUF_SIZE - MAX_PAYLOAD_SIZE)),
+        ..capabilities
+    }
+}
 /// The version of the netcode protocol implemented by this crate.
 pub const NETCODE_VERSION: &[u8; 13] = b"NETCODE 1.02\0";
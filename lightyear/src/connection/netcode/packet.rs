@@ -9,6 +9,8 @@ use tracing::debug;
 
 use crate::connection::netcode::ClientId;
 use crate::connection::server::DeniedReason;
+use crate::protocol::fingerprint::{ProtocolMismatch, MAX_MISMATCH_DIGESTS};
+use crate::protocol::version::{TypeVersion, VersionedKind, MAX_VERSIONED_TYPES};
 
 use super::{
     bytes::Bytes,
@@ -16,7 +18,7 @@ use super::{
     error::Error as NetcodeError,
     replay::ReplayProtection,
     token::{ChallengeToken, ConnectTokenPrivate},
-    MAC_BYTES, MAX_PKT_BUF_SIZE, NETCODE_VERSION,
+    MAC_BYTES, MAX_PKT_BUF_SIZE, MAX_STREAM_PKT_BUF_SIZE, NETCODE_VERSION,
 };
 
 #[derive(thiserror::Error, Debug)]
@@ -78,24 +80,32 @@ where
 pub struct RequestPacket {
     pub version_info: [u8; NETCODE_VERSION.len()],
     pub protocol_id: u64,
+    /// Hash of the client's [`ProtocolFingerprint`](crate::protocol::fingerprint::ProtocolFingerprint)
+    pub protocol_hash: u64,
     pub expire_timestamp: u64,
     pub token_nonce: XNonce,
     pub token_data: Box<[u8; ConnectTokenPrivate::SIZE]>,
+    /// Versions used by the client for the messages and components that have legacy versions
+    pub type_versions: Vec<TypeVersion>,
 }
 
 impl RequestPacket {
     pub fn create(
         protocol_id: u64,
+        protocol_hash: u64,
         expire_timestamp: u64,
         token_nonce: XNonce,
         token_data: [u8; ConnectTokenPrivate::SIZE],
+        type_versions: Vec<TypeVersion>,
     ) -> Packet<'static> {
         Packet::Request(RequestPacket {
             version_info: *NETCODE_VERSION,
             protocol_id,
+            protocol_hash,
             expire_timestamp,
             token_nonce,
             token_data: Box::new(token_data),
+            type_versions,
         })
     }
     pub fn validate(&self, protocol_id: u64, current_timestamp: u64) -> Result<(), Error> {
@@ -133,9 +143,20 @@ impl Bytes for RequestPacket {
     fn write_to(&self, writer: &mut impl WriteBytesExt) -> Result<(), Self::Error> {
         writer.write_all(&self.version_info)?;
         writer.write_u64::<LittleEndian>(self.protocol_id)?;
+        writer.write_u64::<LittleEndian>(self.protocol_hash)?;
         writer.write_u64::<LittleEndian>(self.expire_timestamp)?;
         writer.write_all(&self.token_nonce)?;
         writer.write_all(&self.token_data[..])?;
+        if self.type_versions.len() > MAX_VERSIONED_TYPES {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "too many versioned types",
+            ));
+        }
+        writer.write_u8(self.type_versions.len() as u8)?;
+        for type_version in &self.type_versions {
+            type_version.write_to(writer)?;
+        }
         Ok(())
     }
 
@@ -143,18 +164,64 @@ impl Bytes for RequestPacket {
         let mut version_info = [0; NETCODE_VERSION.len()];
         reader.read_exact(&mut version_info)?;
         let protocol_id = reader.read_u64::<LittleEndian>()?;
+        let protocol_hash = reader.read_u64::<LittleEndian>()?;
         let expire_timestamp = reader.read_u64::<LittleEndian>()?;
         let mut nonce = [0; size_of::<XNonce>()];
         reader.read_exact(&mut nonce)?;
         let token_nonce = XNonce::from_slice(&nonce).to_owned();
         let mut token_data = [0; ConnectTokenPrivate::SIZE];
         reader.read_exact(&mut token_data)?;
+        let num_versions = reader.read_u8()? as usize;
+        if num_versions > MAX_VERSIONED_TYPES {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "too many versioned types",
+            ));
+        }
+        let type_versions = (0..num_versions)
+            .map(|_| TypeVersion::read_from(reader))
+            .collect::<Result<Vec<_>, _>>()?;
         Ok(Self {
             version_info,
             protocol_id,
+            protocol_hash,
             expire_timestamp,
             token_nonce,
             token_data: Box::new(token_data),
+            type_versions,
+        })
+    }
+}
+
+impl Bytes for TypeVersion {
+    type Error = io::Error;
+    fn write_to(&self, writer: &mut impl WriteBytesExt) -> Result<(), Self::Error> {
+        writer.write_u8(match self.kind {
+            VersionedKind::Message => 0,
+            VersionedKind::Component => 1,
+        })?;
+        writer.write_u16::<LittleEndian>(self.net_id)?;
+        writer.write_u8(self.version)?;
+        Ok(())
+    }
+
+    fn read_from(reader: &mut impl ReadBytesExt) -> Result<Self, Self::Error> {
+        let kind = match reader.read_u8()? {
+            0 => VersionedKind::Message,
+            1 => VersionedKind::Component,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "invalid versioned type kind",
+                ))
+            }
+        };
+        let net_id = reader.read_u16::<LittleEndian>()?;
+        let version = reader.read_u8()?;
+        Ok(Self {
+            kind,
+            net_id,
+            version,
         })
     }
 }
@@ -210,6 +277,20 @@ impl Bytes for DeniedReason {
                     ));
                 }
             }
+            DeniedReason::ProtocolMismatch(mismatch) => {
+                writer.write_u8(7)?;
+                // only the digests are sent, the client will find the entry that differs
+                writer.write_u16::<LittleEndian>(mismatch.server_num_entries)?;
+                let num_digests = mismatch.server_digests.len().min(MAX_MISMATCH_DIGESTS);
+                writer.write_u16::<LittleEndian>(num_digests as u16)?;
+                for digest in &mismatch.server_digests[..num_digests] {
+                    writer.write_u32::<LittleEndian>(*digest)?;
+                }
+            }
+            DeniedReason::UnsupportedVersion(type_version) => {
+                writer.write_u8(8)?;
+                type_version.write_to(writer)?;
+            }
         }
         Ok(())
     }
@@ -235,6 +316,27 @@ impl Bytes for DeniedReason {
             let reason_str = String::from_utf8(string_buf)
                 .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid denied reason"))?;
             Ok(DeniedReason::Custom(reason_str))
+        } else if variant == 7 {
+            let server_num_entries = reader.read_u16::<LittleEndian>()?;
+            let num_digests = reader.read_u16::<LittleEndian>()? as usize;
+            if num_digests > MAX_MISMATCH_DIGESTS {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "too many protocol digests",
+                ));
+            }
+            let server_digests = (0..num_digests)
+                .map(|_| reader.read_u32::<LittleEndian>())
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(DeniedReason::ProtocolMismatch(ProtocolMismatch {
+                server_num_entries,
+                server_digests,
+                entry: None,
+            }))
+        } else if variant == 8 {
+            Ok(DeniedReason::UnsupportedVersion(TypeVersion::read_from(
+                reader,
+            )?))
         } else {
             Err(io::Error::new(
                 io::ErrorKind::InvalidData,
@@ -486,7 +588,7 @@ impl<'p> Packet<'p> {
         if buf_len < 1 {
             return Err(Error::TooSmall.into());
         }
-        if buf_len > MAX_PKT_BUF_SIZE {
+        if buf_len > MAX_STREAM_PKT_BUF_SIZE {
             return Err(Error::TooLarge.into());
         }
         let mut cursor = std::io::Cursor::new(&mut buf[..]);
@@ -507,10 +609,20 @@ impl<'p> Packet<'p> {
             return Err(Error::TooSmall.into());
         }
         let sequence = cursor.read_sequence(sequence_len)?;
+        // packets sent on a reliable stream are not limited by the MTU, and the transport already
+        // protects them against replays, so they skip the replay protection (which would drop
+        // the ones that were delayed by retransmissions)
+        let on_stream = is_stream_sequence(sequence);
+        if !on_stream && buf_len > MAX_PKT_BUF_SIZE {
+            return Err(Error::TooLarge.into());
+        }
 
         // Replay protection
         if let Some(replay_protection) = replay_protection.as_ref() {
-            if pkt_kind >= Packet::KEEP_ALIVE && replay_protection.is_already_received(sequence) {
+            if pkt_kind >= Packet::KEEP_ALIVE
+                && !on_stream
+                && replay_protection.is_already_received(sequence)
+            {
                 return Err(Error::AlreadyReceived(sequence).into());
             }
         }
@@ -527,7 +639,7 @@ impl<'p> Packet<'p> {
         cursor.set_position(decryption_start as u64);
 
         if let Some(replay_protection) = replay_protection {
-            if pkt_kind >= Packet::KEEP_ALIVE {
+            if pkt_kind >= Packet::KEEP_ALIVE && !on_stream {
                 replay_protection.advance_sequence(sequence);
             }
         }
@@ -551,6 +663,38 @@ impl<'p> Packet<'p> {
     }
 }
 
+/// Decrypt a packet with the key of the peer that sent it, for debugging tools such as the packet capture.
+///
+/// Returns the kind of the packet, and its content for payload packets
+pub(crate) fn decrypt_packet(
+    buf: &mut [u8],
+    protocol_id: u64,
+    key: Key,
+) -> Result<(String, Option<Vec<u8>>), NetcodeError> {
+    // connection requests are encrypted with the private key of the server
+    if buf.first() == Some(&Packet::REQUEST) {
+        return Ok(("connection request".to_string(), None));
+    }
+    let packet = Packet::read(buf, protocol_id, 0, key, None, u8::MAX)?;
+    let payload = match &packet {
+        Packet::Payload(PayloadPacket { buf }) => Some(buf.to_vec()),
+        _ => None,
+    };
+    Ok((packet.to_string(), payload))
+}
+
+/// The packets sent on the reliable streams of the transport use their own sequence numbers,
+/// which have this bit set, so that they never reuse the nonce of a datagram.
+///
+/// (the server already uses the sequences starting at `1 << 63` for the packets sent before a
+/// client is connected)
+pub(crate) const STREAM_SEQUENCE_BIT: u64 = 1 << 62;
+
+/// Returns true if the sequence number is the one of a packet sent on a reliable stream
+pub(crate) fn is_stream_sequence(sequence: u64) -> bool {
+    sequence & STREAM_SEQUENCE_BIT != 0
+}
+
 pub fn sequence_len(sequence: u64) -> u8 {
     std::cmp::max(8 - sequence.leading_zeros() as u8 / 8, 1)
 }
@@ -615,9 +759,15 @@ mod tests {
         let packet = Packet::Request(RequestPacket {
             version_info: *NETCODE_VERSION,
             protocol_id,
+            protocol_hash: 0xabcd,
             expire_timestamp,
             token_nonce: nonce,
             token_data: Box::new(token_data),
+            type_versions: vec![TypeVersion {
+                kind: VersionedKind::Component,
+                net_id: 3,
+                version: 1,
+            }],
         });
 
         let mut buf = [0u8; MAX_PACKET_SIZE];
@@ -641,6 +791,15 @@ mod tests {
 
         assert_eq!(req_pkt.version_info, *NETCODE_VERSION);
         assert_eq!(req_pkt.protocol_id, protocol_id);
+        assert_eq!(req_pkt.protocol_hash, 0xabcd);
+        assert_eq!(
+            req_pkt.type_versions,
+            vec![TypeVersion {
+                kind: VersionedKind::Component,
+                net_id: 3,
+                version: 1,
+            }]
+        );
         assert_eq!(req_pkt.expire_timestamp, expire_timestamp);
         assert_eq!(req_pkt.token_nonce, nonce);
 
@@ -722,6 +881,43 @@ mod tests {
         assert_eq!(denied_pkt.reason, DeniedReason::ServerFull);
     }
 
+    #[test]
+    fn denied_packet_protocol_mismatch() {
+        let packet_key = generate_key();
+        let protocol_id = 0x1234_5678_9abc_def0;
+        let sequence = 0u64;
+        let mut replay_protection = ReplayProtection::new();
+
+        let mismatch = ProtocolMismatch {
+            server_num_entries: 3,
+            server_digests: vec![1, 2, 3],
+            entry: None,
+        };
+        let packet = Packet::Denied(DeniedPacket {
+            reason: DeniedReason::ProtocolMismatch(mismatch.clone()),
+        });
+
+        let mut buf = [0u8; MAX_PKT_BUF_SIZE];
+        let size = packet
+            .write(&mut buf, sequence, &packet_key, protocol_id)
+            .unwrap();
+
+        let packet = Packet::read(
+            &mut buf[..size],
+            protocol_id,
+            0,
+            packet_key,
+            Some(&mut replay_protection),
+            0xff,
+        )
+        .unwrap();
+
+        let Packet::Denied(denied_pkt) = packet else {
+            panic!("wrong packet type");
+        };
+        assert_eq!(denied_pkt.reason, DeniedReason::ProtocolMismatch(mismatch));
+    }
+
     #[test]
     pub fn challenge_packet() {
         let token = [0u8; ChallengeToken::SIZE];
@@ -847,4 +1043,73 @@ mod tests {
 
         assert_eq!(data_pkt.buf.len(), 100);
     }
+
+    #[test]
+    pub fn stream_payload_packet() {
+        let packet_key = generate_key();
+        let protocol_id = 0x1234_5678_9abc_def0;
+        let mut replay_protection = ReplayProtection::new();
+        let write = |payload: &[u8], sequence: u64| {
+            let mut buf = vec![0u8; MAX_STREAM_PKT_BUF_SIZE];
+            let size = Packet::Payload(PayloadPacket { buf: payload })
+                .write(&mut buf, sequence, &packet_key, protocol_id)
+                .unwrap();
+            buf.truncate(size);
+            buf
+        };
+        let small = vec![0u8; 100];
+        // a packet sent on a stream can be bigger than the MTU
+        let big = vec![0u8; 10 * MAX_PKT_BUF_SIZE];
+
+        // many datagrams are received while the packet on the stream is delayed by retransmissions
+        Packet::read(
+            &mut write(&small, 1000),
+            protocol_id,
+            0,
+            packet_key,
+            Some(&mut replay_protection),
+            0xff,
+        )
+        .unwrap();
+        assert!(matches!(
+            Packet::read(
+                &mut write(&small, 0),
+                protocol_id,
+                0,
+                packet_key,
+                Some(&mut replay_protection),
+                0xff,
+            ),
+            Err(NetcodeError::Packet(Error::AlreadyReceived(0)))
+        ));
+
+        // the packet sent on the stream is not dropped by the replay protection
+        let mut buf = write(&big, STREAM_SEQUENCE_BIT);
+        let packet = Packet::read(
+            &mut buf,
+            protocol_id,
+            0,
+            packet_key,
+            Some(&mut replay_protection),
+            0xff,
+        )
+        .unwrap();
+        let Packet::Payload(data_pkt) = packet else {
+            panic!("wrong packet type");
+        };
+        assert_eq!(data_pkt.buf.len(), big.len());
+
+        // datagrams are still limited by the MTU
+        assert!(matches!(
+            Packet::read(
+                &mut write(&big, 1001),
+                protocol_id,
+                0,
+                packet_key,
+                Some(&mut replay_protection),
+                0xff,
+            ),
+            Err(NetcodeError::Packet(Error::TooLarge))
+        ));
+    }
 }
@@ -15,9 +15,13 @@ use crate::connection::server::{
     ConnectionRequestHandler, DefaultConnectionRequestHandler, DeniedReason, IoConfig, NetServer,
 };
 use crate::packet::packet_builder::RecvPayload;
+use crate::protocol::channel::ChannelId;
+use crate::protocol::fingerprint::ProtocolFingerprint;
+use crate::protocol::version::PeerVersions;
 use crate::server::config::NetcodeConfig;
 use crate::server::io::{Io, ServerIoEvent, ServerNetworkEventSender};
-use crate::transport::{PacketReceiver, PacketSender};
+use crate::transport::middleware::compression::PayloadCompression;
+use crate::transport::{PacketReceiver, PacketSender, TransportCapabilities};
 
 use super::{
     bytes::Bytes,
@@ -25,11 +29,13 @@ use super::{
     error::{Error, Result},
     packet::{
         ChallengePacket, DeniedPacket, DisconnectPacket, KeepAlivePacket, Packet, PayloadPacket,
-        RequestPacket, ResponsePacket,
+        RequestPacket, ResponsePacket, STREAM_SEQUENCE_BIT,
     },
+    payload_capabilities,
     replay::ReplayProtection,
     token::{ChallengeToken, ConnectToken, ConnectTokenBuilder, ConnectTokenPrivate},
-    MAC_BYTES, MAX_PACKET_SIZE, MAX_PKT_BUF_SIZE, PACKET_SEND_RATE_SEC,
+    MAC_BYTES, MAX_PAYLOAD_SIZE, MAX_PKT_BUF_SIZE, MAX_STREAM_PAYLOAD_SIZE,
+    MAX_STREAM_PKT_BUF_SIZE, PACKET_SEND_RATE_SEC,
 };
 
 pub const MAX_CLIENTS: usize = 256;
@@ -93,6 +99,8 @@ struct Connection {
     send_key: Key,
     receive_key: Key,
     sequence: u64,
+    /// Sequence of the packets sent on the reliable streams of the transport
+    stream_sequence: u64,
 }
 
 impl Connection {
@@ -124,6 +132,12 @@ struct ConnectionCache {
     // we are not using a free-list here to not allocate memory up-front, since `ReplayProtection` is biggish (~2kb)
     replay_protection: HashMap<ClientId, ReplayProtection>,
 
+    // versions negotiated with each connected client for the versioned messages and components
+    peer_versions: HashMap<ClientId, PeerVersions>,
+
+    // versions negotiated during the connection request, until the client is connected
+    pending_versions: HashMap<ClientId, PeerVersions>,
+
     // packet queue for all clients
     packet_queue: VecDeque<(RecvPayload, ClientId)>,
 
@@ -137,6 +151,8 @@ impl ConnectionCache {
             clients: HashMap::with_capacity(MAX_CLIENTS),
             client_id_map: HashMap::with_capacity(MAX_CLIENTS),
             replay_protection: HashMap::with_capacity(MAX_CLIENTS),
+            peer_versions: HashMap::new(),
+            pending_versions: HashMap::new(),
             packet_queue: VecDeque::with_capacity(MAX_CLIENTS * 2),
             time: server_time,
         }
@@ -148,7 +164,13 @@ impl ConnectionCache {
         timeout: i32,
         send_key: Key,
         receive_key: Key,
+        peer_versions: PeerVersions,
     ) {
+        if peer_versions.is_empty() {
+            self.pending_versions.remove(&client_id);
+        } else {
+            self.pending_versions.insert(client_id, peer_versions);
+        }
         if let Some((_, ref mut existing)) = self.find_by_addr(&addr) {
             existing.client_id = client_id;
             existing.timeout = timeout;
@@ -169,6 +191,7 @@ impl ConnectionCache {
             send_key,
             receive_key,
             sequence: 0,
+            stream_sequence: STREAM_SEQUENCE_BIT,
         };
         self.clients.insert(client_id, conn);
         self.replay_protection
@@ -185,8 +208,30 @@ impl ConnectionCache {
         }
         self.client_id_map.remove(&conn.addr);
         self.replay_protection.remove(&client_id);
+        self.peer_versions.remove(&client_id);
+        self.clients.remove(&client_id);
+    }
+    /// Remove a client that sent a connection request but never completed the handshake
+    fn remove_pending(&mut self, client_id: ClientId) {
+        let Some(conn) = self.clients.get(&client_id) else {
+            return;
+        };
+        if conn.is_connected() {
+            return;
+        }
+        if self.client_id_map.get(&conn.addr) == Some(&client_id) {
+            self.client_id_map.remove(&conn.addr);
+        }
+        self.replay_protection.remove(&client_id);
+        self.pending_versions.remove(&client_id);
         self.clients.remove(&client_id);
     }
+    /// The client completed the handshake: keep the versions negotiated during the connection request
+    fn on_connected(&mut self, client_id: ClientId) {
+        if let Some(peer_versions) = self.pending_versions.remove(&client_id) {
+            self.peer_versions.insert(client_id, peer_versions);
+        }
+    }
 
     fn ids(&self) -> Vec<ClientId> {
         self.clients.keys().cloned().collect()
@@ -383,6 +428,7 @@ pub struct NetcodeServer<Ctx = ()> {
     protocol_id: u64,
     conn_cache: ConnectionCache,
     token_entries: TokenEntries,
+    protocol_fingerprint: Option<ProtocolFingerprint>,
     cfg: ServerConfig<Ctx>,
 }
 
@@ -401,6 +447,7 @@ impl NetcodeServer {
             challenge_key: crypto::generate_key(),
             conn_cache: ConnectionCache::new(0.0),
             token_entries: TokenEntries::new(),
+            protocol_fingerprint: None,
             cfg: ServerConfig::default(),
         };
         // info!("server started on {}", server.io.local_addr());
@@ -435,6 +482,7 @@ impl<Ctx> NetcodeServer<Ctx> {
             challenge_key: crypto::generate_key(),
             conn_cache: ConnectionCache::new(0.0),
             token_entries: TokenEntries::new(),
+            protocol_fingerprint: None,
             cfg,
         };
         // info!("server started on {}", server.addr());
@@ -443,6 +491,20 @@ impl<Ctx> NetcodeServer<Ctx> {
 }
 
 impl<Ctx> NetcodeServer<Ctx> {
+    /// Set the fingerprint of the protocol.
+    ///
+    /// Connection requests from clients whose protocol hash doesn't match will be denied
+    /// with [`DeniedReason::ProtocolMismatch`].
+    pub(crate) fn set_protocol_fingerprint(&mut self, fingerprint: ProtocolFingerprint) {
+        self.protocol_fingerprint = Some(fingerprint);
+    }
+
+    /// Versions of the versioned messages and components that were negotiated with the client,
+    /// if the client uses older versions than the server
+    pub(crate) fn peer_versions(&self, client_id: ClientId) -> Option<&PeerVersions> {
+        self.conn_cache.peer_versions.get(&client_id)
+    }
+
     const ALLOWED_PACKETS: u8 = 1 << Packet::REQUEST
         | 1 << Packet::RESPONSE
         | 1 << Packet::KEEP_ALIVE
@@ -536,20 +598,48 @@ impl<Ctx> NetcodeServer<Ctx> {
         id: ClientId,
         sender: &mut impl PacketSender,
     ) -> Result<()> {
-        let mut buf = [0u8; MAX_PKT_BUF_SIZE];
+        self.send_to_client_on_stream(packet, id, None, sender)
+    }
+    /// Send the packet to the client as a datagram, or on the reliable stream of the channel `stream`
+    fn send_to_client_on_stream(
+        &mut self,
+        packet: Packet,
+        id: ClientId,
+        stream: Option<ChannelId>,
+        sender: &mut impl PacketSender,
+    ) -> Result<()> {
         let conn = &mut self
             .conn_cache
             .clients
             .get_mut(&id)
             .expect("invalid client id");
-        let size = packet.write(&mut buf, conn.sequence, &conn.send_key, self.protocol_id)?;
-        sender
-            .send(&buf[..size], &conn.addr)
-            // .inspect_err(|e| error!("ERROR SENDING: {:?}", e))
-            .map_err(Error::from)?;
+        match stream {
+            Some(channel_id) => {
+                let mut buf = vec![0u8; MAX_STREAM_PKT_BUF_SIZE];
+                let size = packet.write(
+                    &mut buf,
+                    conn.stream_sequence,
+                    &conn.send_key,
+                    self.protocol_id,
+                )?;
+                sender
+                    .send_on_stream(&buf[..size], &conn.addr, channel_id)
+                    .map_err(Error::from)?;
+                conn.stream_sequence += 1;
+            }
+            None => {
+                let mut buf = [0u8; MAX_PKT_BUF_SIZE];
+                let size =
+                    packet.write(&mut buf, conn.sequence, &conn.send_key, self.protocol_id)?;
+                sender
+                    .send(&buf[..size], &conn.addr)
+                    // .inspect_err(|e| error!("ERROR SENDING: {:?}", e))
+                    .map_err(Error::from)?;
+                conn.sequence += 1;
+            }
+        }
         conn.last_access_time = self.time;
         conn.last_send_time = self.time;
-        conn.sequence += 1;
         Ok(())
     }
 
@@ -616,6 +706,32 @@ impl<Ctx> NetcodeServer<Ctx> {
             )?;
             return Ok(());
         };
+        let mut peer_versions = PeerVersions::default();
+        if let Some(fingerprint) = &self.protocol_fingerprint {
+            if packet.protocol_hash != fingerprint.hash() {
+                debug!("server denied connection request. the client's protocol does not match");
+                self.send_to_addr(
+                    DeniedPacket::create(DeniedReason::ProtocolMismatch(fingerprint.mismatch())),
+                    from_addr,
+                    token.server_to_client_key,
+                    sender,
+                )?;
+                return Ok(());
+            }
+            match PeerVersions::negotiate(fingerprint.versions(), &packet.type_versions) {
+                Ok(versions) => peer_versions = versions,
+                Err(type_version) => {
+                    debug!("server denied connection request. the client uses a more recent version of {type_version:?}");
+                    self.send_to_addr(
+                        DeniedPacket::create(DeniedReason::UnsupportedVersion(type_version)),
+                        from_addr,
+                        token.server_to_client_key,
+                        sender,
+                    )?;
+                    return Ok(());
+                }
+            }
+        }
         if let Some(denied_reason) = self
             .cfg
             .connection_request_handler
@@ -636,6 +752,7 @@ impl<Ctx> NetcodeServer<Ctx> {
             token.timeout_seconds,
             token.server_to_client_key,
             token.client_to_server_key,
+            peer_versions,
         );
         let Ok(challenge_token_encrypted) = ChallengeToken {
             client_id: token.client_id,
@@ -699,6 +816,7 @@ impl<Ctx> NetcodeServer<Ctx> {
         client.connect();
         client.last_send_time = self.time;
         client.last_receive_time = self.time;
+        self.conn_cache.on_connected(id);
         debug!(
             "server accepted client {} with id {}",
             id, challenge_token.client_id
@@ -713,6 +831,13 @@ impl<Ctx> NetcodeServer<Ctx> {
                 continue;
             };
             if !client.is_connected() {
+                // drop the handshakes that were never completed
+                if client.timeout.is_positive()
+                    && client.last_access_time + (client.timeout as f64) < self.time
+                {
+                    debug!("server dropped pending connection of client {id}");
+                    self.conn_cache.remove_pending(id);
+                }
                 continue;
             }
             let addr = client.addr;
@@ -869,15 +994,48 @@ impl<Ctx> NetcodeServer<Ctx> {
     }
     /// Sends a packet to a client.
     ///
-    /// The provided buffer must be smaller than [`MAX_PACKET_SIZE`].
+    /// The provided buffer must not be bigger than `MAX_PAYLOAD_SIZE` (1447 bytes).
     #[cfg_attr(feature = "trace", instrument(level = Level::INFO, skip_all))]
     pub fn send(&mut self, buf: &[u8], client_id: ClientId, io: &mut Io) -> Result<()> {
-        if buf.len() > MAX_PACKET_SIZE {
-            return Err(Error::SizeMismatch(MAX_PACKET_SIZE, buf.len()));
-        }
+        self.send_payload(buf, client_id, None, io)
+    }
+
+    /// Sends a packet to a client on the reliable stream of the transport dedicated to the
+    /// channel `channel_id`, or as a datagram if the transport has no streams.
+    ///
+    /// The provided buffer must not be bigger than `MAX_STREAM_PAYLOAD_SIZE` (64487 bytes) if the
+    /// transport has streams, or `MAX_PAYLOAD_SIZE` (1447 bytes) otherwise.
+    pub fn send_on_stream(
+        &mut self,
+        buf: &[u8],
+        client_id: ClientId,
+        channel_id: ChannelId,
+        io: &mut Io,
+    ) -> Result<()> {
+        self.send_payload(buf, client_id, Some(channel_id), io)
+    }
+
+    fn send_payload(
+        &mut self,
+        buf: &[u8],
+        client_id: ClientId,
+        stream: Option<ChannelId>,
+        io: &mut Io,
+    ) -> Result<()> {
         let Some(conn) = self.conn_cache.clients.get_mut(&client_id) else {
             return Err(Error::ClientNotFound);
         };
+        // only use the stream sequences if the packet is really sent on a stream, since they
+        // are not protected against replays
+        let stream = stream.filter(|_| io.capabilities(&conn.addr).reliable_streams);
+        let max_size = if stream.is_some() {
+            MAX_STREAM_PAYLOAD_SIZE
+        } else {
+            MAX_PAYLOAD_SIZE
+        };
+        if buf.len() > max_size {
+            return Err(Error::SizeMismatch(max_size, buf.len()));
+        }
         if !conn.is_connected() {
             // since there is no way to obtain a client index of clients that are not connected,
             // there is no straight-forward way for a user to send a packet to a non-connected client.
@@ -889,12 +1047,12 @@ impl<Ctx> NetcodeServer<Ctx> {
             self.send_to_client(KeepAlivePacket::create(client_id), client_id, io)?;
         }
         let packet = PayloadPacket::create(buf);
-        self.send_to_client(packet, client_id, io)
+        self.send_to_client_on_stream(packet, client_id, stream, io)
     }
 
     /// Sends a packet to all connected clients.
     ///
-    /// The provided buffer must be smaller than [`MAX_PACKET_SIZE`].
+    /// The provided buffer must not be bigger than `MAX_PAYLOAD_SIZE` (1447 bytes).
     pub fn send_all(&mut self, buf: &[u8], io: &mut Io) -> Result<()> {
         for id in self.conn_cache.ids() {
             match self.send(buf, id, io) {
@@ -1036,13 +1194,19 @@ pub(crate) mod connection {
     #[derive(Resource)]
     pub struct Server {
         pub(crate) server: NetcodeServer<NetcodeServerContext>,
-        io_config: IoConfig,
+        pub(crate) io_config: IoConfig,
         io: Option<Io>,
+        /// Compression of the payloads, created from the `io_config` when starting
+        compression: Option<PayloadCompression>,
     }
 
     impl NetServer for Server {
         fn start(&mut self) -> Result<(), ConnectionError> {
             let io_config = self.io_config.clone();
+            self.compression = Some(PayloadCompression::new(
+                &io_config.compression,
+                io_config.recorder.clone(),
+            )?);
             let io = io_config.start()?;
             self.server
                 .cfg
@@ -1102,9 +1266,15 @@ pub(crate) mod connection {
         }
 
         fn recv(&mut self) -> Option<(RecvPayload, id::ClientId)> {
-            self.server
-                .recv()
-                .map(|(packet, id)| (packet, id::ClientId::Netcode(id)))
+            let (payload, id) = self.server.recv()?;
+            let payload = match self.compression.as_mut() {
+                Some(compression) => compression
+                    .decompress(payload)
+                    .inspect_err(|e| error!("error decompressing payload: {:?}", e))
+                    .ok()?,
+                None => payload,
+            };
+            Some((payload, id::ClientId::Netcode(id)))
         }
 
         fn send(&mut self, buf: &[u8], client_id: id::ClientId) -> Result<(), ConnectionError> {
@@ -1112,10 +1282,42 @@ pub(crate) mod connection {
             let id::ClientId::Netcode(client_id) = client_id else {
                 return Err(ConnectionError::InvalidConnectionType);
             };
+            let buf = match self.compression.as_mut() {
+                Some(compression) => compression.compress(buf)?,
+                None => buf,
+            };
             self.server.send(buf, client_id, io)?;
             Ok(())
         }
 
+        fn send_on_stream(
+            &mut self,
+            buf: &[u8],
+            client_id: id::ClientId,
+            channel_id: ChannelId,
+        ) -> Result<(), ConnectionError> {
+            let io = self.io.as_mut().ok_or(ConnectionError::IoNotInitialized)?;
+            let id::ClientId::Netcode(client_id) = client_id else {
+                return Err(ConnectionError::InvalidConnectionType);
+            };
+            let buf = match self.compression.as_mut() {
+                Some(compression) => compression.compress(buf)?,
+                None => buf,
+            };
+            self.server.send_on_stream(buf, client_id, channel_id, io)?;
+            Ok(())
+        }
+
+        fn transport_capabilities(&self, client_id: id::ClientId) -> TransportCapabilities {
+            let id::ClientId::Netcode(client_id) = client_id else {
+                return TransportCapabilities::default();
+            };
+            match (self.io.as_ref(), self.server.client_addr(client_id)) {
+                (Some(io), Some(addr)) => payload_capabilities(io.capabilities(&addr)),
+                _ => TransportCapabilities::default(),
+            }
+        }
+
         fn new_connections(&self) -> Vec<id::ClientId> {
             self.server.cfg.context.connections.clone()
         }
@@ -1163,6 +1365,7 @@ pub(crate) mod connection {
                 server,
                 io_config,
                 io: None,
+                compression: None,
             }
         }
 
@@ -14,9 +14,13 @@ use crate::packet::packet_builder::RecvPayload;
 use crate::prelude::server::ServerTransport;
 #[cfg(all(feature = "steam", not(target_family = "wasm")))]
 use crate::prelude::LinkConditionerConfig;
+use crate::protocol::channel::ChannelId;
+use crate::protocol::fingerprint::{ProtocolFingerprint, ProtocolMismatch};
+use crate::protocol::version::{PeerVersions, TypeVersion};
 use crate::server::config::NetcodeConfig;
 use crate::server::io::Io;
 use crate::transport::config::SharedIoConfig;
+use crate::transport::TransportCapabilities;
 
 /// Reasons for denying a connection request
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
@@ -28,6 +32,10 @@ pub enum DeniedReason {
     TokenAlreadyUsed,
     InvalidToken,
     Custom(String),
+    /// The client was built with a different protocol (channels, messages, components) than the server
+    ProtocolMismatch(ProtocolMismatch),
+    /// The client uses a more recent version of a message or component than the server
+    UnsupportedVersion(TypeVersion),
 }
 
 /// Trait for handling connection requests from clients.
@@ -76,6 +84,24 @@ pub trait NetServer: Send + Sync {
     /// Send a packet to one of the connected clients
     fn send(&mut self, buf: &[u8], client_id: ClientId) -> Result<(), ConnectionError>;
 
+    /// Send a packet to one of the connected clients on the reliable stream of the transport
+    /// dedicated to the channel `channel_id`.
+    ///
+    /// Connections whose transport has no streams send it like any other packet.
+    fn send_on_stream(
+        &mut self,
+        buf: &[u8],
+        client_id: ClientId,
+        _channel_id: ChannelId,
+    ) -> Result<(), ConnectionError> {
+        self.send(buf, client_id)
+    }
+
+    /// Capabilities of the transport to a client, in terms of the packets passed to [`NetServer::send`]
+    fn transport_capabilities(&self, _client_id: ClientId) -> TransportCapabilities {
+        TransportCapabilities::default()
+    }
+
     fn new_connections(&self) -> Vec<ClientId>;
 
     fn new_disconnections(&self) -> Vec<ClientId>;
@@ -92,6 +118,19 @@ pub enum ServerConnection {
     Steam(super::steam::server::Server),
 }
 
+impl ServerConnection {
+    /// Versions of the versioned messages and components that were negotiated with the client
+    /// during the handshake
+    pub(crate) fn peer_versions(&self, client_id: ClientId) -> PeerVersions {
+        match (self, client_id) {
+            (ServerConnection::Netcode(server), ClientId::Netcode(id)) => {
+                server.server.peer_versions(id).cloned().unwrap_or_default()
+            }
+            _ => PeerVersions::default(),
+        }
+    }
+}
+
 pub type IoConfig = SharedIoConfig<ServerTransport>;
 
 /// Configuration for the server connection
@@ -195,6 +234,20 @@ impl ServerConnections {
         }
     }
 
+    /// Set the [`ProtocolFingerprint`] that clients must match to be able to connect
+    pub(crate) fn set_protocol_fingerprint(&mut self, fingerprint: &ProtocolFingerprint) {
+        for server in &mut self.servers {
+            #[allow(irrefutable_let_patterns)]
+            if let ServerConnection::Netcode(server) = server {
+                server.server.set_protocol_fingerprint(
+                    fingerprint
+                        .clone()
+                        .with_compression(&server.io_config.compression),
+                );
+            }
+        }
+    }
+
     /// Start listening for client connections on all internal servers
     pub fn start(&mut self) -> Result<(), ConnectionError> {
         for server in &mut self.servers {
@@ -16,15 +16,19 @@ pub(crate) fn get_networking_options(
         // TODO: float options are not useable, see https://github.com/Noxime/steamworks-rs/pull/168
         // options.push(NetworkingConfigEntry::new_float(
         //     NetworkingConfigValue::FakePacketLossRecv,
-        //     conditioner.incoming_loss * 100.0,
+        //     conditioner.incoming.loss * 100.0,
         // ));
         options.push(NetworkingConfigEntry::new_int32(
             NetworkingConfigValue::FakePacketLagRecv,
-            conditioner.incoming_latency.as_millis() as i32,
+            conditioner.incoming.latency.as_millis() as i32,
         ));
         options.push(NetworkingConfigEntry::new_int32(
             NetworkingConfigValue::FakePacketReorderTime,
-            conditioner.incoming_jitter.as_millis() as i32,
+            conditioner.incoming.jitter.as_millis() as i32,
+        ));
+        options.push(NetworkingConfigEntry::new_int32(
+            NetworkingConfigValue::FakePacketLagSend,
+            conditioner.outgoing.latency.as_millis() as i32,
         ));
         // TODO: float options are not useable, see https://github.com/Noxime/steamworks-rs/pull/168
         // options.push(NetworkingConfigEntry::new_float(
@@ -175,31 +175,38 @@ fn component_inserted(query: Query<Entity, (With<Replicated>, Added<MyComponent>
 
 // re-exports
 #[doc(hidden)]
-pub(crate) mod _internal {
+pub mod _internal {
     pub use paste::paste;
+    pub use serde;
 }
 
 /// Prelude containing commonly used types
 pub mod prelude {
-    pub use lightyear_macros::Channel;
+    pub use lightyear_macros::{Channel, Diffable};
     pub use serde::{Deserialize, Serialize};
 
     pub use crate::channel::builder::{
         Channel, ChannelBuilder, ChannelContainer, ChannelDirection, ChannelMode, ChannelSettings,
-        InputChannel, ReliableSettings,
+        InputChannel, RedundancySettings, ReliableSettings, StreamId,
     };
+    pub use crate::channel::senders::SendOptions;
     pub use crate::client::prediction::prespawn::PreSpawnedPlayerObject;
     pub use crate::connection::id::ClientId;
     pub use crate::connection::netcode::{generate_key, ConnectToken, Key};
     #[cfg(feature = "leafwing")]
     pub use crate::inputs::leafwing::{input_message::InputMessage, LeafwingUserAction};
     pub use crate::inputs::native::UserAction;
+    pub use crate::packet::congestion::CongestionControlConfig;
     pub use crate::packet::error::PacketError;
     pub use crate::packet::message::Message;
+    pub use crate::packet::mtu::MtuDiscoveryConfig;
     pub use crate::protocol::channel::{AppChannelExt, ChannelKind, ChannelRegistry};
-    pub use crate::protocol::component::{AppComponentExt, ComponentRegistry, Linear};
+    pub use crate::protocol::component::{
+        AppComponentExt, ComponentRegistry, Linear, WorldComponentExt,
+    };
     pub use crate::protocol::message::{AppMessageExt, MessageRegistry};
     pub use crate::protocol::serialize::AppSerializeExt;
+    pub use crate::protocol::NetIdMode;
     pub use crate::shared::config::{Mode, SharedConfig};
     #[cfg(feature = "leafwing")]
     pub use crate::shared::input::leafwing::LeafwingInputPlugin;
@@ -209,9 +216,11 @@ pub mod prelude {
     pub use crate::shared::replication::authority::HasAuthority;
     pub use crate::shared::replication::components::{
         DeltaCompression, DisabledComponent, NetworkRelevanceMode, OverrideTargetComponent,
-        PrePredicted, ReplicateHierarchy, ReplicateOnceComponent, Replicated, Replicating,
-        ReplicationGroup, ReplicationTarget, ShouldBePredicted, TargetEntity,
+        PrePredicted, RelevanceLost, ReplicateHierarchy, ReplicateOnceComponent, Replicated,
+        Replicating, ReplicationFrequency, ReplicationGroup, ReplicationTarget, ShouldBePredicted,
+        TargetEntity,
     };
+    pub use crate::shared::replication::delta::Diffable;
     pub use crate::shared::replication::entity_map::RemoteEntityMap;
     pub use crate::shared::replication::hierarchy::ParentSync;
     pub use crate::shared::replication::network_target::NetworkTarget;
@@ -220,13 +229,22 @@ pub mod prelude {
     pub use crate::shared::replication::resources::{
         ReplicateResourceExt, ReplicateResourceMetadata, StopReplicateResourceExt,
     };
+    pub use crate::shared::rpc::{RequestError, RequestHandle, RequestId};
     pub use crate::shared::run_conditions::*;
     pub use crate::shared::sets::{FixedUpdateSet, MainSet};
     pub use crate::shared::tick_manager::TickManager;
     pub use crate::shared::tick_manager::{Tick, TickConfig};
     pub use crate::shared::time_manager::TimeManager;
+    pub use crate::transport::middleware::capture::{
+        Capture, CaptureRecord, CaptureRecordKind, PacketCapture,
+    };
     pub use crate::transport::middleware::compression::CompressionConfig;
-    pub use crate::transport::middleware::conditioner::LinkConditionerConfig;
+    #[cfg(feature = "zstd")]
+    pub use crate::transport::middleware::compression::ZstdDictionary;
+    pub use crate::transport::middleware::conditioner::{
+        BandwidthLimit, LinkConditionerConfig, LinkConditionerProfile, LinkConditions, PacketLoss,
+    };
+    pub use crate::transport::middleware::recorder::PacketRecorder;
 
     mod rename {
         pub use crate::client::events::ComponentInsertEvent as ClientComponentInsertEvent;
@@ -237,6 +255,10 @@ pub mod prelude {
         pub use crate::client::events::EntityDespawnEvent as ClientEntityDespawnEvent;
         pub use crate::client::events::EntitySpawnEvent as ClientEntitySpawnEvent;
         pub use crate::client::events::MessageEvent as ClientMessageEvent;
+        pub use crate::client::events::MessageExpired as ClientMessageExpired;
+        pub use crate::client::events::RemoteTrigger as ClientRemoteTrigger;
+        pub use crate::client::events::RequestEvent as ClientRequestEvent;
+        pub use crate::client::events::ResponseEvent as ClientResponseEvent;
 
         pub use crate::client::connection::ConnectionManager as ClientConnectionManager;
 
@@ -248,6 +270,10 @@ pub mod prelude {
         pub use crate::server::events::EntityDespawnEvent as ServerEntityDespawnEvent;
         pub use crate::server::events::EntitySpawnEvent as ServerEntitySpawnEvent;
         pub use crate::server::events::MessageEvent as ServerMessageEvent;
+        pub use crate::server::events::MessageExpired as ServerMessageExpired;
+        pub use crate::server::events::RemoteTrigger as ServerRemoteTrigger;
+        pub use crate::server::events::RequestEvent as ServerRequestEvent;
+        pub use crate::server::events::ResponseEvent as ServerResponseEvent;
 
         pub use crate::server::connection::ConnectionManager as ServerConnectionManager;
     }
@@ -263,6 +289,7 @@ pub mod prelude {
         pub use crate::client::events::{
             ComponentInsertEvent, ComponentRemoveEvent, ComponentUpdateEvent, ConnectEvent,
             DisconnectEvent, EntityDespawnEvent, EntitySpawnEvent, InputEvent, MessageEvent,
+            MessageExpired, RemoteTrigger, RequestEvent, ResponseEvent,
         };
         #[cfg(feature = "leafwing")]
         pub use crate::client::input::leafwing::LeafwingInputConfig;
@@ -293,8 +320,12 @@ pub mod prelude {
         };
         #[cfg(all(feature = "steam", not(target_family = "wasm")))]
         pub use crate::connection::steam::client::{SocketConfig, SteamConfig};
+        #[cfg(all(feature = "quic", not(target_family = "wasm")))]
+        pub use crate::transport::quic::CertificateDer;
     }
     pub mod server {
+        #[cfg(all(feature = "quic", not(target_family = "wasm")))]
+        pub use crate::transport::quic::QuicIdentity;
         #[cfg(all(feature = "webtransport", not(target_family = "wasm")))]
         pub use wtransport::tls::Identity;
 
@@ -310,13 +341,20 @@ pub mod prelude {
         pub use crate::server::events::{
             ComponentInsertEvent, ComponentRemoveEvent, ComponentUpdateEvent, ConnectEvent,
             DisconnectEvent, EntityDespawnEvent, EntitySpawnEvent, InputEvent, MessageEvent,
+            MessageExpired, RemoteTrigger, RequestEvent, ResponseEvent,
         };
         pub use crate::server::io::config::ServerTransport;
         pub use crate::server::io::Io;
         pub use crate::server::networking::{NetworkingState, ServerCommands};
         pub use crate::server::plugin::ServerPlugins;
+        pub use crate::server::priority::AppReplicationPriorityExt;
+        pub use crate::server::relevance::graceful::GracefulRelevanceLoss;
         pub use crate::server::relevance::immediate::RelevanceManager;
         pub use crate::server::relevance::room::{RoomId, RoomManager};
+        pub use crate::server::relevance::spatial::{
+            SpatialPosition, SpatialRelevanceConfig, SpatialRelevanceManager,
+            SpatialRelevancePlugin, SpatialViewer,
+        };
         pub use crate::server::replication::commands::AuthorityCommandExt;
         pub use crate::server::replication::commands::DespawnReplicationCommandExt;
         pub use crate::server::replication::{
@@ -0,0 +1,361 @@
+//! Congestion control
+//!
+//! Estimates the bandwidth available on a connection, in the style of LEDBAT: the controller keeps track
+//! of the minimum RTT of the connection (the RTT of the path when no queue builds up), and considers
+//! that the difference between the current RTT and this base RTT is the time spent by our packets in
+//! the queues of the network.
+//!
+//! Once per adjustment interval (one RTT, and at least [`MIN_ADJUST_INTERVAL`]) the estimate is updated:
+//! - if more than [`CongestionControlConfig::loss_threshold`] of the packets were lost, the estimate
+//!   is multiplied by [`LOSS_DECREASE`]
+//! - if the queuing delay is above [`CongestionControlConfig::target_delay`], the estimate decreases
+//!   proportionally to the excess delay
+//! - if the queuing delay is below the target and most of the budget was used, the estimate increases
+//!   proportionally to how far we are below the target
+//!
+//! The estimate drives the budget of the [`PriorityManager`](super::priority_manager::PriorityManager):
+//! each frame adds `bandwidth * delta` bytes to the budget, and the messages with the lowest priority
+//! are not sent once the budget is used.
+use bevy::prelude::Reflect;
+use bevy::utils::Duration;
+use tracing::trace;
+
+use crate::shared::time_manager::WrappedTime;
+use crate::transport::MTU;
+
+/// Minimum duration between two updates of the bandwidth estimate
+const MIN_ADJUST_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Factor applied to the bandwidth estimate when the packet loss is above the threshold
+const LOSS_DECREASE: f32 = 0.75;
+
+/// Maximum decrease of the bandwidth estimate in one interval when the queuing delay is above the target
+const DELAY_DECREASE: f32 = 0.25;
+
+/// Maximum increase of the bandwidth estimate in one interval when the queuing delay is below the target
+const GAIN: f32 = 0.1;
+
+/// The estimate only increases if we sent at least this ratio of the estimate during the interval.
+/// Otherwise the application doesn't need more bandwidth, and we don't know if the path can carry more.
+const APP_LIMITED_RATIO: f32 = 0.5;
+
+/// The base RTT is the minimum RTT over the current and the previous windows, so that it can adapt
+/// if the network path changes
+const BASE_RTT_WINDOW: Duration = Duration::from_secs(30);
+
+/// Maximum number of bytes that can accumulate in the budget, as a duration of the bandwidth estimate
+const MAX_BURST_DURATION: f32 = 0.1;
+
+/// The budget can always hold a few packets, so that any message can be sent
+const MIN_BURST_BYTES: f32 = 2.0 * MTU as f32;
+
+/// Configuration of the congestion control of a connection
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct CongestionControlConfig {
+    /// If true, the bandwidth used by the connection adapts to the network conditions.
+    ///
+    /// The static bandwidth cap of the [`PacketConfig`](crate::prelude::client::PacketConfig) is
+    /// not used when congestion control is enabled.
+    pub enabled: bool,
+    /// Estimate of the bandwidth (in bytes per second) at the start of the connection
+    pub initial_bandwidth: u32,
+    /// The bandwidth estimate (in bytes per second) never goes below this value
+    pub min_bandwidth: u32,
+    /// The bandwidth estimate (in bytes per second) never goes above this value
+    pub max_bandwidth: u32,
+    /// Queuing delay (RTT above the minimum RTT of the connection) that the controller aims for
+    pub target_delay: Duration,
+    /// Ratio of lost packets above which the bandwidth estimate is reduced
+    pub loss_threshold: f32,
+}
+
+impl Default for CongestionControlConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            initial_bandwidth: 56_000,
+            min_bandwidth: 8_000,
+            max_bandwidth: 1_000_000,
+            target_delay: Duration::from_millis(25),
+            loss_threshold: 0.05,
+        }
+    }
+}
+
+impl CongestionControlConfig {
+    pub fn enable(mut self) -> Self {
+        self.enabled = true;
+        self
+    }
+
+    pub fn with_initial_bandwidth(mut self, initial_bandwidth: u32) -> Self {
+        self.initial_bandwidth = initial_bandwidth;
+        self
+    }
+
+    pub fn with_bandwidth_range(mut self, min_bandwidth: u32, max_bandwidth: u32) -> Self {
+        self.min_bandwidth = min_bandwidth;
+        self.max_bandwidth = max_bandwidth;
+        self
+    }
+
+    pub fn with_target_delay(mut self, target_delay: Duration) -> Self {
+        self.target_delay = target_delay;
+        self
+    }
+}
+
+/// Statistics accumulated since the start of the current adjustment interval
+#[derive(Debug, Default)]
+struct IntervalStats {
+    start: Option<WrappedTime>,
+    bytes_sent: u32,
+    packets_acked: u32,
+    packets_lost: u32,
+}
+
+/// Estimates the bandwidth available on a connection, and keeps track of the number of bytes
+/// that can be sent on this frame
+#[derive(Debug)]
+pub(crate) struct CongestionController {
+    config: CongestionControlConfig,
+    /// Bandwidth estimate, in bytes per second
+    bandwidth: f32,
+    /// Number of bytes that can still be sent. Can be negative if we sent more than the budget
+    budget: f32,
+    interval: IntervalStats,
+    /// Minimum RTT in the current window
+    min_rtt: Option<Duration>,
+    /// Minimum RTT in the previous window
+    previous_min_rtt: Option<Duration>,
+    min_rtt_window_start: Option<WrappedTime>,
+}
+
+impl CongestionController {
+    pub(crate) fn new(config: CongestionControlConfig) -> Self {
+        Self {
+            config,
+            bandwidth: config
+                .initial_bandwidth
+                .clamp(config.min_bandwidth, config.max_bandwidth) as f32,
+            budget: 0.0,
+            interval: IntervalStats::default(),
+            min_rtt: None,
+            previous_min_rtt: None,
+            min_rtt_window_start: None,
+        }
+    }
+
+    /// Current estimate of the bandwidth, in bytes per second
+    pub(crate) fn bandwidth(&self) -> u32 {
+        self.bandwidth as u32
+    }
+
+    /// Minimum RTT of the connection, when no queue builds up on the network path
+    fn base_rtt(&self) -> Option<Duration> {
+        match (self.min_rtt, self.previous_min_rtt) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// Refill the budget for the new frame, and update the bandwidth estimate if the adjustment
+    /// interval is over
+    pub(crate) fn update(&mut self, current_time: WrappedTime, delta: Duration, rtt: Duration) {
+        let burst = (self.bandwidth * MAX_BURST_DURATION).max(MIN_BURST_BYTES);
+        self.budget = (self.budget + self.bandwidth * delta.as_secs_f32()).min(burst);
+
+        // the RTT is zero until we receive the first pongs
+        if rtt > Duration::ZERO {
+            let window_start = *self.min_rtt_window_start.get_or_insert(current_time);
+            if current_time - window_start > chrono::Duration::from_std(BASE_RTT_WINDOW).unwrap() {
+                self.previous_min_rtt = self.min_rtt.take();
+                self.min_rtt_window_start = Some(current_time);
+            }
+            self.min_rtt = Some(self.min_rtt.map_or(rtt, |min_rtt| min_rtt.min(rtt)));
+        }
+
+        let interval_start = *self.interval.start.get_or_insert(current_time);
+        let elapsed = (current_time - interval_start).to_std().unwrap_or_default();
+        if elapsed >= rtt.max(MIN_ADJUST_INTERVAL) {
+            self.adjust(elapsed, rtt);
+            self.interval = IntervalStats {
+                start: Some(current_time),
+                ..Default::default()
+            };
+        }
+    }
+
+    fn adjust(&mut self, elapsed: Duration, rtt: Duration) {
+        let packets = self.interval.packets_acked + self.interval.packets_lost;
+        let loss = if packets > 0 {
+            self.interval.packets_lost as f32 / packets as f32
+        } else {
+            0.0
+        };
+        let factor = if loss > self.config.loss_threshold {
+            LOSS_DECREASE
+        } else if let Some(base_rtt) = self.base_rtt().filter(|_| rtt > Duration::ZERO) {
+            let queuing_delay = rtt.saturating_sub(base_rtt).as_secs_f32();
+            let target = self.config.target_delay.as_secs_f32();
+            let off_target = ((target - queuing_delay) / target).max(-1.0);
+            let app_limited = (self.interval.bytes_sent as f32)
+                < APP_LIMITED_RATIO * self.bandwidth * elapsed.as_secs_f32();
+            if off_target < 0.0 {
+                1.0 + off_target * DELAY_DECREASE
+            } else if !app_limited {
+                1.0 + off_target * GAIN
+            } else {
+                1.0
+            }
+        } else {
+            1.0
+        };
+        self.bandwidth = (self.bandwidth * factor).clamp(
+            self.config.min_bandwidth as f32,
+            self.config.max_bandwidth as f32,
+        );
+        trace!(?loss, ?rtt, base_rtt = ?self.base_rtt(), bandwidth = ?self.bandwidth, "updated bandwidth estimate");
+        #[cfg(feature = "metrics")]
+        metrics::gauge!("congestion.bandwidth").set(self.bandwidth as f64);
+    }
+
+    /// Returns true if `bytes` fit in the budget of the current frame
+    pub(crate) fn can_send(&self, bytes: u32) -> bool {
+        bytes as f32 <= self.budget
+    }
+
+    /// Use some of the budget
+    pub(crate) fn on_bytes_sent(&mut self, bytes: u32) {
+        self.budget -= bytes as f32;
+        self.interval.bytes_sent += bytes;
+    }
+
+    pub(crate) fn on_packets_acked(&mut self, num_packets: usize) {
+        self.interval.packets_acked += num_packets as u32;
+    }
+
+    pub(crate) fn on_packets_lost(&mut self, num_packets: usize) {
+        self.interval.packets_lost += num_packets as u32;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::client::{ClientConfig, ConnectionManager};
+    use crate::prelude::ClientId;
+    use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
+
+    const FRAME: Duration = Duration::from_millis(10);
+
+    /// Simulate a link with a bottleneck: the queuing delay grows when we send more than `capacity`
+    /// bytes per second, and the packets are dropped once the queue holds 200ms of data.
+    /// Returns the final bandwidth estimate
+    fn simulate(
+        controller: &mut CongestionController,
+        time: &mut WrappedTime,
+        capacity: f32,
+        seconds: u32,
+    ) -> u32 {
+        let base_rtt = 0.05;
+        let max_queue = capacity * 0.2;
+        let mut queue = 0.0f32;
+        for _ in 0..(seconds * 100) {
+            *time += FRAME;
+            let rtt = Duration::from_secs_f32(base_rtt + queue / capacity);
+            controller.update(*time, FRAME, rtt);
+            // the application always has data to send
+            while controller.can_send(500) {
+                controller.on_bytes_sent(500);
+                if queue + 500.0 > max_queue {
+                    controller.on_packets_lost(1);
+                } else {
+                    controller.on_packets_acked(1);
+                    queue += 500.0;
+                }
+            }
+            queue = (queue - capacity * FRAME.as_secs_f32()).max(0.0);
+        }
+        controller.bandwidth()
+    }
+
+    #[test]
+    fn test_bandwidth_converges_to_capacity() {
+        let config = CongestionControlConfig::default().enable();
+        // the bandwidth grows up to the capacity of the link
+        let mut controller = CongestionController::new(config);
+        let mut time = WrappedTime::default();
+        let bandwidth = simulate(&mut controller, &mut time, 200_000.0, 30);
+        assert!((150_000..260_000).contains(&bandwidth), "{bandwidth}");
+
+        // the link gets congested: the bandwidth drops
+        let bandwidth = simulate(&mut controller, &mut time, 40_000.0, 30);
+        assert!((20_000..60_000).contains(&bandwidth), "{bandwidth}");
+    }
+
+    #[test]
+    fn test_packet_loss_reduces_bandwidth() {
+        let config = CongestionControlConfig::default().enable();
+        let mut controller = CongestionController::new(config);
+        let mut time = WrappedTime::default();
+        time += FRAME;
+        controller.update(time, FRAME, Duration::from_millis(50));
+        controller.on_packets_acked(8);
+        controller.on_packets_lost(2);
+        time += MIN_ADJUST_INTERVAL;
+        controller.update(time, FRAME, Duration::from_millis(50));
+        assert_eq!(
+            controller.bandwidth(),
+            (config.initial_bandwidth as f32 * LOSS_DECREASE) as u32
+        );
+    }
+
+    #[test]
+    fn test_app_limited() {
+        let config = CongestionControlConfig::default().enable();
+        let mut controller = CongestionController::new(config);
+        let mut time = WrappedTime::default();
+        // we don't send anything, so the estimate doesn't grow
+        for _ in 0..1000 {
+            time += FRAME;
+            controller.update(time, FRAME, Duration::from_millis(50));
+        }
+        assert_eq!(controller.bandwidth(), config.initial_bandwidth);
+        // the budget cannot grow indefinitely
+        assert!(!controller.can_send(100_000));
+    }
+
+    /// The estimate is exposed on the connections where congestion control is enabled
+    #[test]
+    fn test_congestion_control_connection() {
+        let mut stepper = BevyStepper::default();
+        stepper.stop();
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<ClientConfig>()
+            .packet
+            .congestion_control = CongestionControlConfig::default().enable();
+        stepper.start();
+        for _ in 0..20 {
+            stepper.frame_step();
+        }
+        let bandwidth = stepper
+            .client_app
+            .world()
+            .resource::<ConnectionManager>()
+            .bandwidth_estimate()
+            .unwrap();
+        assert!((8_000..=1_000_000).contains(&bandwidth), "{bandwidth}");
+
+        let server_bandwidth = stepper
+            .server_app
+            .world()
+            .resource::<crate::server::connection::ConnectionManager>()
+            .connection(ClientId::Netcode(TEST_CLIENT_ID))
+            .unwrap()
+            .bandwidth_estimate();
+        assert_eq!(server_bandwidth, None);
+    }
+}
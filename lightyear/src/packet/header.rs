@@ -81,6 +81,14 @@ impl PacketHeader {
     pub fn get_packet_type(&self) -> PacketType {
         self.packet_type
     }
+
+    /// Returns true if the header acks the packet `packet_id` from the receiver's perspective
+    pub(crate) fn acks(&self, packet_id: PacketId) -> bool {
+        let diff = self.last_ack_packet_id - packet_id;
+        diff == 0
+            || (1..=ACK_BITFIELD_SIZE as i16).contains(&diff)
+                && self.get_bitfield_bit(diff as u8 - 1)
+    }
 }
 
 // we can only send acks for the last 32 packets ids before the last received packet
@@ -122,6 +130,8 @@ pub struct PacketHeaderManager {
     /// The default is 1.5; i.e. after 1.5 times the round trip time, we consider a packet lost if
     /// we haven't received an ACK for it.
     nack_rtt_multiple: f32,
+    /// Duration after which a packet that hasn't been acked is considered lost
+    nack_duration: chrono::Duration,
 }
 
 impl PacketHeaderManager {
@@ -138,6 +148,7 @@ impl PacketHeaderManager {
             // ack_notification_receiver,
             current_time: WrappedTime::default(),
             nack_rtt_multiple,
+            nack_duration: chrono::TimeDelta::milliseconds(MIN_NACK_MILLIS),
         }
     }
 
@@ -155,6 +166,7 @@ impl PacketHeaderManager {
             .expect("duration should be valid")
             .min(chrono::TimeDelta::seconds(MAX_NACK_SECONDS))
             .max(chrono::TimeDelta::milliseconds(MIN_NACK_MILLIS));
+        self.nack_duration = nack_duration;
         // clear sent packets that haven't received any ack for a while
         let mut lost_packets = vec![];
         self.sent_packets_not_acked.retain(|packet_id, time_sent| {
@@ -175,6 +187,11 @@ impl PacketHeaderManager {
     //     &self.ack_notification_receiver
     // }
 
+    /// Duration after which a packet that hasn't been acked is considered lost
+    pub(crate) fn nack_duration(&self) -> chrono::Duration {
+        self.nack_duration
+    }
+
     /// Return the packet id of the next packet to be sent
     pub fn next_packet_id(&self) -> PacketId {
         self.next_packet_id
@@ -259,6 +276,26 @@ impl PacketHeaderManager {
         self.increment_next_packet_id();
         outgoing_header
     }
+
+    /// Prepare the header of a MTU probe packet.
+    ///
+    /// Probes are not tracked by the ack system and don't count in the packet stats, since
+    /// losing a probe that is too big doesn't mean that the network is lossy.
+    pub(crate) fn prepare_send_probe_header(&mut self) -> PacketHeader {
+        let last_ack_packet_id = match self.recv_buffer.last_recv_packet_id {
+            Some(id) => id,
+            None => PacketId(u16::MAX),
+        };
+        let outgoing_header = PacketHeader {
+            packet_type: PacketType::MtuProbe,
+            packet_id: self.next_packet_id,
+            last_ack_packet_id,
+            ack_bitfield: self.recv_buffer.get_bitfield(),
+            tick: Tick(0),
+        };
+        self.increment_next_packet_id();
+        outgoing_header
+    }
 }
 
 /// Data structure to keep track of the ids of the received packets
@@ -404,6 +441,22 @@ mod tests {
         assert_eq!(recv_buffer.get_bitfield(), 1 << (32 - 1));
     }
 
+    #[test]
+    fn test_header_acks() {
+        let header = PacketHeader {
+            packet_type: PacketType::Data,
+            packet_id: PacketId(0),
+            last_ack_packet_id: PacketId(1),
+            ack_bitfield: 0b101,
+            tick: Tick(0),
+        };
+        assert!(header.acks(PacketId(1)));
+        assert!(header.acks(PacketId(0)));
+        assert!(!header.acks(PacketId(u16::MAX)));
+        assert!(header.acks(PacketId(u16::MAX - 1)));
+        assert!(!header.acks(PacketId(2)));
+    }
+
     #[test]
     fn test_serde_header() -> Result<(), SerializationError> {
         let header = PacketHeader {
@@ -232,6 +232,49 @@ impl FragmentData {
     }
 }
 
+/// Header written in front of the messages sent on a channel with multiple ordering streams
+///
+/// The message ids are shared by all the streams of the channel (they are used for acks and resends),
+/// so each message points to the previous message of its stream: the receiver only returns a message
+/// once the previous message of its stream has been returned.
+/// The receiver doesn't need to keep any state for a stream that has no message waiting.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct StreamHeader {
+    /// Id of the previous message of the stream.
+    ///
+    /// None if the sender knows that the receiver already received all the previous messages of the stream.
+    pub(crate) previous: Option<MessageId>,
+}
+
+impl ToBytes for StreamHeader {
+    fn len(&self) -> usize {
+        self.previous.map_or(1, |_| 3)
+    }
+
+    fn to_bytes<T: WriteBytesExt>(&self, buffer: &mut T) -> Result<(), SerializationError> {
+        match self.previous {
+            None => buffer.write_u8(0)?,
+            Some(previous) => {
+                buffer.write_u8(1)?;
+                buffer.write_u16::<NetworkEndian>(previous.0)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn from_bytes(buffer: &mut Reader) -> Result<Self, SerializationError>
+    where
+        Self: Sized,
+    {
+        let previous = match buffer.read_u8()? {
+            0 => None,
+            1 => Some(MessageId(buffer.read_u16::<NetworkEndian>()?)),
+            _ => return Err(SerializationError::InvalidValue),
+        };
+        Ok(Self { previous })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -280,4 +323,19 @@ mod tests {
         let decoded = FragmentData::from_bytes(&mut reader).unwrap();
         assert_eq!(decoded, data);
     }
+
+    #[test]
+    fn test_to_bytes_stream_header() {
+        for previous in [None, Some(MessageId(0)), Some(MessageId(65535))] {
+            let header = StreamHeader { previous };
+            let mut writer = vec![];
+            header.to_bytes(&mut writer).unwrap();
+
+            assert_eq!(writer.len(), header.len());
+
+            let mut reader = writer.into();
+            let decoded = StreamHeader::from_bytes(&mut reader).unwrap();
+            assert_eq!(decoded, header);
+        }
+    }
 }
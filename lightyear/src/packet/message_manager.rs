@@ -1,14 +1,14 @@
 use byteorder::ReadBytesExt;
 use bytes::Bytes;
 use crossbeam_channel::{Receiver, Sender};
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use tracing::trace;
 #[cfg(feature = "trace")]
 use tracing::{instrument, Level};
 
 use crate::channel::builder::ChannelContainer;
 use crate::channel::receivers::ChannelReceive;
-use crate::channel::senders::ChannelSend;
+use crate::channel::senders::{ChannelSend, ChannelSender, SendOptions};
 #[cfg(feature = "trace")]
 use crate::channel::stats::send::ChannelSendStats;
 use crate::packet::error::PacketError;
@@ -16,7 +16,8 @@ use crate::packet::header::PacketHeader;
 use crate::packet::message::{
     FragmentData, MessageAck, MessageId, ReceiveMessage, SendMessage, SingleData,
 };
-use crate::packet::packet::PacketId;
+use crate::packet::mtu::{MtuDiscovery, MtuDiscoveryConfig};
+use crate::packet::packet::{PacketId, MAX_STREAM_PACKET_SIZE, STREAM_FRAGMENT_SIZE};
 use crate::packet::packet_builder::{PacketBuilder, Payload, RecvPayload};
 use crate::packet::packet_type::PacketType;
 use crate::packet::priority_manager::{PriorityConfig, PriorityManager};
@@ -28,6 +29,7 @@ use crate::shared::ping::manager::PingManager;
 use crate::shared::tick_manager::Tick;
 use crate::shared::tick_manager::TickManager;
 use crate::shared::time_manager::TimeManager;
+use crate::transport::TransportCapabilities;
 #[cfg(test)]
 use crate::utils::captures::Captures;
 
@@ -50,6 +52,14 @@ pub struct MessageManager {
     /// reliable senders can stop trying to send a message that has already been received
     packet_to_message_ack_map: HashMap<PacketId, Vec<(ChannelKind, MessageAck)>>,
     nack_senders: Vec<Sender<MessageId>>,
+    mtu_discovery: MtuDiscovery,
+    /// Channels and ids of the messages that expired before being acked
+    expired_messages: Vec<(ChannelKind, MessageId)>,
+    /// True if the transport has reliable streams, so that the packets of the channels that use
+    /// them are built separately
+    transport_streams: bool,
+    /// Packets to send on the reliable stream of their channel
+    stream_payloads: Vec<(ChannelId, Payload)>,
 }
 
 impl MessageManager {
@@ -57,14 +67,72 @@ impl MessageManager {
         channel_registry: &ChannelRegistry,
         nack_rtt_multiple: f32,
         priority_config: PriorityConfig,
+        mtu_config: MtuDiscoveryConfig,
     ) -> Self {
-        Self {
+        let mut message_manager = Self {
             packet_manager: PacketBuilder::new(nack_rtt_multiple),
             priority_manager: PriorityManager::new(priority_config),
             channels: channel_registry.channels(),
             channel_registry: channel_registry.clone(),
             packet_to_message_ack_map: HashMap::new(),
             nack_senders: vec![],
+            mtu_discovery: MtuDiscovery::new(mtu_config),
+            expired_messages: vec![],
+            transport_streams: false,
+            stream_payloads: vec![],
+        };
+        message_manager.set_max_packet_size(message_manager.mtu_discovery.mtu());
+        message_manager
+    }
+
+    /// Maximum size of the packets sent on this connection, as found by the path MTU discovery.
+    ///
+    /// It doesn't include the size of the netcode/transport headers.
+    pub fn mtu(&self) -> usize {
+        self.mtu_discovery.mtu()
+    }
+
+    /// Current estimate of the bandwidth available on this connection (in bytes per second),
+    /// if congestion control is enabled
+    pub fn bandwidth_estimate(&self) -> Option<u32> {
+        self.priority_manager
+            .congestion_controller
+            .as_ref()
+            .map(|controller| controller.bandwidth())
+    }
+
+    /// Adapt the packets to the capabilities of the transport of this connection
+    pub(crate) fn set_transport_capabilities(&mut self, capabilities: TransportCapabilities) {
+        let streams_changed = self.transport_streams != capabilities.reliable_streams;
+        self.transport_streams = capabilities.reliable_streams;
+        if let Some(mtu) = self
+            .mtu_discovery
+            .set_transport_limit(capabilities.max_datagram_size)
+        {
+            self.set_max_packet_size(mtu);
+        } else if streams_changed {
+            self.set_max_packet_size(self.mtu_discovery.mtu());
+        }
+    }
+
+    /// Returns the packets to send on the reliable stream of their channel, built since the last call
+    pub(crate) fn take_stream_payloads(&mut self) -> Vec<(ChannelId, Payload)> {
+        std::mem::take(&mut self.stream_payloads)
+    }
+
+    /// Update the maximum size of the packets, and the size of the fragments of big messages
+    ///
+    /// The messages of the channels that use the reliable streams of the transport are only
+    /// fragmented if they don't fit in a stream packet.
+    fn set_max_packet_size(&mut self, max_packet_size: usize) {
+        self.packet_manager.set_max_packet_size(max_packet_size);
+        let fragment_size = self.packet_manager.fragment_size();
+        for channel in self.channels.values_mut() {
+            if self.transport_streams && channel.setting.mode.uses_transport_stream() {
+                channel.sender.set_fragment_size(STREAM_FRAGMENT_SIZE);
+            } else {
+                channel.sender.set_fragment_size(fragment_size);
+            }
         }
     }
 
@@ -86,9 +154,22 @@ impl MessageManager {
             .packet_manager
             .header_manager
             .update(time_manager, ping_manager);
+        self.mtu_discovery.update(
+            time_manager.current_time(),
+            self.packet_manager.header_manager.nack_duration(),
+        );
+        if let Some(controller) = &mut self.priority_manager.congestion_controller {
+            controller.on_packets_lost(lost_packets.len());
+            controller.update(
+                time_manager.current_time(),
+                time_manager.delta(),
+                ping_manager.rtt(),
+            );
+        }
         // notify that some messages have been lost
         for lost_packet in lost_packets {
             if let Some(message_map) = self.packet_to_message_ack_map.remove(&lost_packet) {
+                self.notify_packet_outcome(&message_map, true);
                 for (channel_kind, message_ack) in message_map {
                     let channel = self
                         .channels
@@ -105,11 +186,42 @@ impl MessageManager {
                 }
             }
         }
-        for channel in self.channels.values_mut() {
+        for (channel_kind, channel) in self.channels.iter_mut() {
             channel
                 .sender
                 .update(time_manager, ping_manager, tick_manager);
             channel.receiver.update(time_manager, tick_manager);
+            for message_id in channel.sender.drain_expired() {
+                trace!(?message_id, ?channel_kind, "message expired");
+                self.expired_messages.push((*channel_kind, message_id));
+            }
+        }
+    }
+
+    /// Notify each channel that had messages in a packet that the packet was acked or lost
+    fn notify_packet_outcome(&mut self, message_acks: &[(ChannelKind, MessageAck)], lost: bool) {
+        let channel_kinds: HashSet<ChannelKind> = message_acks
+            .iter()
+            .map(|(channel_kind, _)| *channel_kind)
+            .collect();
+        for channel_kind in channel_kinds {
+            if let Some(channel) = self.channels.get_mut(&channel_kind) {
+                channel.sender.receive_packet_outcome(lost);
+            }
+        }
+    }
+
+    /// Returns the channels and ids of the messages that expired before being acked since the last call
+    pub(crate) fn drain_expired_messages(&mut self) -> Vec<(ChannelKind, MessageId)> {
+        std::mem::take(&mut self.expired_messages)
+    }
+
+    /// Returns the id that the next message buffered on the channel will be assigned,
+    /// if the channel is reliable (only reliable messages can expire)
+    pub(crate) fn next_reliable_message_id(&self, channel_kind: &ChannelKind) -> Option<MessageId> {
+        match &self.channels.get(channel_kind)?.sender {
+            ChannelSender::Reliable(sender) => Some(sender.next_message_id()),
+            _ => None,
         }
     }
 
@@ -145,7 +257,28 @@ impl MessageManager {
             .channels
             .get_mut(&channel_kind)
             .ok_or(PacketError::ChannelNotFound)?;
-        Ok(channel.sender.buffer_send(message, priority)?)
+        Ok(channel
+            .sender
+            .buffer_send(message, priority, SendOptions::default())?)
+    }
+
+    /// Buffer a message to be sent on this connection, with options that override the settings
+    /// of the channel (time-to-live, ordering stream)
+    ///
+    /// Returns the message id associated with the message, if there is one
+    pub(crate) fn buffer_send_with_options(
+        &mut self,
+        message: Bytes,
+        channel_kind: ChannelKind,
+        options: SendOptions,
+    ) -> Result<Option<MessageId>, PacketError> {
+        let channel = self
+            .channels
+            .get_mut(&channel_kind)
+            .ok_or(PacketError::ChannelNotFound)?;
+        Ok(channel
+            .sender
+            .buffer_send(message, DEFAULT_MESSAGE_PRIORITY, options)?)
     }
 
     /// Prepare buckets from the internal send buffers, and return the bytes to send
@@ -154,6 +287,16 @@ impl MessageManager {
     //  maybe be generic over a Context ?
     #[cfg_attr(feature = "trace", instrument(level = Level::INFO, skip_all))]
     pub fn send_packets(&mut self, current_tick: Tick) -> Result<Vec<Payload>, PacketError> {
+        let mut bytes = Vec::new();
+        // Step 0. Send a MTU probe if needed
+        if let Some(size) = self.mtu_discovery.probe_to_send() {
+            let (packet_id, payload) =
+                self.packet_manager.build_probe_packet(size, current_tick)?;
+            trace!(?packet_id, ?size, "sending MTU probe");
+            self.mtu_discovery.probe_sent(packet_id, size);
+            bytes.push(payload);
+        }
+
         // Step 1. Get the list of packets to send from all channels
         // for each channel, prepare packets using the buffered messages that are ready to be sent
         // TODO: iterate through the channels in order of channel priority? (with accumulation)
@@ -174,7 +317,8 @@ impl MessageManager {
         }
         // return early if there are no messages to send
         if !has_data_to_send {
-            return Ok(vec![]);
+            self.use_congestion_budget(&bytes);
+            return Ok(bytes);
         }
 
         // priority manager: get the list of messages we can send according to the rate limiter
@@ -215,6 +359,14 @@ impl MessageManager {
             }
         }
 
+        // the channels that use the reliable streams of the transport get their own packets
+        let num_stream_payloads = self.stream_payloads.len();
+        let (single_data, fragment_data) = if self.transport_streams {
+            self.build_stream_packets(current_tick, single_data, fragment_data)?
+        } else {
+            (single_data, fragment_data)
+        };
+
         let packets =
             self.packet_manager
                 .build_packets(current_tick, single_data, fragment_data)?;
@@ -222,7 +374,6 @@ impl MessageManager {
         //     trace!(?packet, "packet to send");
         // }
 
-        let mut bytes = Vec::new();
         for mut packet in packets {
             trace!(packet_id = ?packet.packet_id, num_messages = ?packet.num_messages(), "sending packet");
             // TODO: should we update this to include fragment info as well?
@@ -259,7 +410,15 @@ impl MessageManager {
 
         // adjust the real amount of bytes that we sent through the limiter (to account for the actual packet size)
         if self.priority_manager.config.enabled {
-            let total_bytes_sent = bytes.iter().map(|b| b.len() as u32).sum::<u32>();
+            let total_bytes_sent = bytes
+                .iter()
+                .chain(
+                    self.stream_payloads[num_stream_payloads..]
+                        .iter()
+                        .map(|(_, payload)| payload),
+                )
+                .map(|b| b.len() as u32)
+                .sum::<u32>();
             if let Ok(remaining_bytes_to_add) =
                 (total_bytes_sent - num_bytes_added_to_limiter).try_into()
             {
@@ -269,10 +428,105 @@ impl MessageManager {
                     .check_n(remaining_bytes_to_add);
             }
         }
+        self.use_congestion_budget(&bytes);
+        if let Some(controller) = &mut self.priority_manager.congestion_controller {
+            controller.on_bytes_sent(
+                self.stream_payloads[num_stream_payloads..]
+                    .iter()
+                    .map(|(_, payload)| payload.len() as u32)
+                    .sum::<u32>(),
+            );
+        }
 
         Ok(bytes)
     }
 
+    /// Build the packets of the channels that use a reliable stream of the transport.
+    ///
+    /// Each packet only contains the messages of one channel, and the messages are acked as soon
+    /// as the packet is built since the stream guarantees that they will be delivered.
+    /// The packets are not limited by the MTU, so big messages are not fragmented.
+    ///
+    /// Returns the data of the other channels.
+    #[allow(clippy::type_complexity)]
+    fn build_stream_packets(
+        &mut self,
+        current_tick: Tick,
+        single_data: Vec<(ChannelId, VecDeque<SingleData>)>,
+        fragment_data: Vec<(ChannelId, VecDeque<FragmentData>)>,
+    ) -> Result<
+        (
+            Vec<(ChannelId, VecDeque<SingleData>)>,
+            Vec<(ChannelId, VecDeque<FragmentData>)>,
+        ),
+        PacketError,
+    > {
+        let uses_stream = |channel_id: ChannelId| {
+            self.channel_registry
+                .get_kind_from_net_id(channel_id)
+                .and_then(|kind| self.channels.get(kind))
+                .is_some_and(|channel| channel.setting.mode.uses_transport_stream())
+        };
+        let mut stream_data: HashMap<ChannelId, (VecDeque<SingleData>, VecDeque<FragmentData>)> =
+            HashMap::new();
+        let single_data = single_data
+            .into_iter()
+            .filter_map(|(channel_id, data)| {
+                if !uses_stream(channel_id) {
+                    return Some((channel_id, data));
+                }
+                stream_data.entry(channel_id).or_default().0 = data;
+                None
+            })
+            .collect();
+        let fragment_data = fragment_data
+            .into_iter()
+            .filter_map(|(channel_id, data)| {
+                if !uses_stream(channel_id) {
+                    return Some((channel_id, data));
+                }
+                stream_data.entry(channel_id).or_default().1 = data;
+                None
+            })
+            .collect();
+
+        for (channel_id, (single, fragment)) in stream_data {
+            self.packet_manager
+                .set_max_packet_size(MAX_STREAM_PACKET_SIZE);
+            let packets = self.packet_manager.build_packets(
+                current_tick,
+                vec![(channel_id, single)],
+                vec![(channel_id, fragment)],
+            );
+            self.packet_manager
+                .set_max_packet_size(self.mtu_discovery.mtu());
+            let packets = packets?;
+            let channel_kind = self
+                .channel_registry
+                .get_kind_from_net_id(channel_id)
+                .ok_or(PacketError::ChannelNotFound)?;
+            let channel = self
+                .channels
+                .get_mut(channel_kind)
+                .ok_or(PacketError::ChannelNotFound)?;
+            for mut packet in packets {
+                trace!(packet_id = ?packet.packet_id, ?channel_id, "sending packet on the transport stream");
+                for (_, message_ack) in std::mem::take(&mut packet.message_acks) {
+                    channel.sender.receive_ack(&message_ack);
+                }
+                self.stream_payloads.push((channel_id, packet.payload));
+            }
+        }
+        Ok((single_data, fragment_data))
+    }
+
+    /// Use the budget of the congestion controller for the packets we are about to send
+    fn use_congestion_budget(&mut self, packets: &[Payload]) {
+        if let Some(controller) = &mut self.priority_manager.congestion_controller {
+            controller.on_bytes_sent(packets.iter().map(|b| b.len() as u32).sum::<u32>());
+        }
+    }
+
     /// Process packet received over the network as raw bytes
     /// Update the acks, and put the messages from the packets in internal buffers
     /// Returns the tick of the packet
@@ -298,11 +552,18 @@ impl MessageManager {
             .packet_manager
             .header_manager
             .process_recv_packet_header(&header);
+        if let Some(mtu) = self.mtu_discovery.process_recv_packet_header(&header) {
+            self.set_max_packet_size(mtu);
+        }
+        if let Some(controller) = &mut self.priority_manager.congestion_controller {
+            controller.on_packets_acked(acked_packets.len());
+        }
 
         // Step 3. Update the list of messages that have been acked
         for acked_packet in acked_packets {
             trace!("Acked packet {:?}", acked_packet);
             if let Some(message_acks) = self.packet_to_message_ack_map.remove(&acked_packet) {
+                self.notify_packet_outcome(&message_acks, false);
                 for (channel_kind, message_ack) in message_acks {
                     let channel_name = self
                         .channel_registry
@@ -322,6 +583,11 @@ impl MessageManager {
             }
         }
 
+        // MTU probes don't contain any message, they only need to be acked
+        if header.get_packet_type() == PacketType::MtuProbe {
+            return Ok(tick);
+        }
+
         // Step 4. Parse the payload into messages, put them in the internal buffers for each channel
         // we read directly from the packet and don't create intermediary datastructures to avoid allocations
         // TODO: maybe do this in a helper function?
@@ -435,6 +701,9 @@ mod tests {
 
     use bevy::prelude::default;
 
+    use bevy::utils::Duration;
+
+    use crate::connection::netcode::MAX_PACKET_SIZE;
     use crate::packet::message::MessageId;
     use crate::packet::packet::FRAGMENT_SIZE;
     use crate::packet::priority_manager::PriorityConfig;
@@ -456,10 +725,18 @@ mod tests {
         });
 
         // Create message managers
-        let client_message_manager =
-            MessageManager::new(&channel_registry, 1.5, PriorityConfig::default());
-        let server_message_manager =
-            MessageManager::new(&channel_registry, 1.5, PriorityConfig::default());
+        let client_message_manager = MessageManager::new(
+            &channel_registry,
+            1.5,
+            PriorityConfig::default(),
+            MtuDiscoveryConfig::default(),
+        );
+        let server_message_manager = MessageManager::new(
+            &channel_registry,
+            1.5,
+            PriorityConfig::default(),
+            MtuDiscoveryConfig::default(),
+        );
         (client_message_manager, server_message_manager)
     }
 
@@ -639,6 +916,181 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_message_manager_mtu_discovery() -> Result<(), PacketError> {
+        let (_, mut server_message_manager) = setup();
+        let mut client_message_manager = MessageManager::new(
+            &server_message_manager.channel_registry,
+            1.5,
+            PriorityConfig::default(),
+            MtuDiscoveryConfig::default().enable(),
+        );
+        let mut time_manager = TimeManager::default();
+        let ping_manager = PingManager::new(PingConfig::default());
+        let tick_manager = TickManager::from_config(TickConfig::new(Duration::from_millis(10)));
+        for _ in 0..2 {
+            time_manager.update(Duration::from_millis(600));
+            client_message_manager.update(&time_manager, &ping_manager, &tick_manager);
+        }
+
+        // the client sends a probe padded to a bigger size than the default MTU
+        assert_eq!(client_message_manager.mtu(), MAX_PACKET_SIZE);
+        let payloads = client_message_manager.send_packets(Tick(0))?;
+        assert_eq!(payloads.len(), 1);
+        let probe_size = payloads[0].len();
+        assert!(probe_size > MAX_PACKET_SIZE);
+        server_message_manager.recv_packet(payloads[0].clone().into())?;
+
+        // the server acks the probe
+        server_message_manager.buffer_send(vec![1].into(), Channel1::kind())?;
+        for payload in server_message_manager.send_packets(Tick(0))? {
+            client_message_manager.recv_packet(payload.into())?;
+        }
+        assert_eq!(client_message_manager.mtu(), probe_size);
+
+        // a message that used to be fragmented now fits in a single packet
+        let message = Bytes::from(vec![2; FRAGMENT_SIZE + 50]);
+        client_message_manager.buffer_send(message.clone(), Channel1::kind())?;
+        let payloads = client_message_manager.send_packets(Tick(0))?;
+        assert_eq!(payloads.len(), 1);
+        server_message_manager.recv_packet(payloads[0].clone().into())?;
+        let data = MessageManager::collect_messages(server_message_manager.read_messages());
+        assert_eq!(
+            data.get(&Channel1::kind()).unwrap(),
+            &vec![(Tick(0), message)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_message_manager_transport_streams() -> Result<(), PacketError> {
+        let mut channel_registry = ChannelRegistry::default();
+        channel_registry.add_channel::<Channel1>(ChannelSettings {
+            mode: ChannelMode::UnorderedUnreliable,
+            ..default()
+        });
+        channel_registry.add_channel::<Channel2>(ChannelSettings {
+            mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+            ..default()
+        });
+        let new_manager = || {
+            MessageManager::new(
+                &channel_registry,
+                1.5,
+                PriorityConfig::default(),
+                MtuDiscoveryConfig::default(),
+            )
+        };
+        let mut client_message_manager = new_manager();
+        let mut server_message_manager = new_manager();
+        client_message_manager.set_transport_capabilities(TransportCapabilities {
+            reliable_streams: true,
+            max_datagram_size: Some(1100),
+        });
+        // the packets are capped to the size of the datagrams of the transport
+        assert!(client_message_manager.mtu() < 1100);
+
+        // the messages of the reliable channel are sent in their own packets
+        let reliable_message = Bytes::from("reliable");
+        client_message_manager.buffer_send(Bytes::from("unreliable"), Channel1::kind())?;
+        client_message_manager.buffer_send(reliable_message.clone(), Channel2::kind())?;
+        let payloads = client_message_manager.send_packets(Tick(0))?;
+        assert_eq!(payloads.len(), 1);
+        let stream_payloads = client_message_manager.take_stream_payloads();
+        assert_eq!(stream_payloads.len(), 1);
+        let (channel_id, stream_payload) = stream_payloads[0].clone();
+        assert_eq!(
+            channel_id,
+            *channel_registry
+                .get_net_from_kind(&Channel2::kind())
+                .unwrap()
+        );
+        server_message_manager.recv_packet(stream_payload.into())?;
+        let data = MessageManager::collect_messages(server_message_manager.read_messages());
+        assert_eq!(
+            data.get(&Channel2::kind()).unwrap(),
+            &vec![(Tick(0), reliable_message)]
+        );
+
+        // the reliable message is acked as soon as it is sent on the stream, so it is never resent
+        let mut time_manager = TimeManager::default();
+        let ping_manager = PingManager::new(PingConfig::default());
+        let tick_manager = TickManager::from_config(TickConfig::new(Duration::from_millis(10)));
+        time_manager.update(Duration::from_secs(1));
+        client_message_manager.update(&time_manager, &ping_manager, &tick_manager);
+        assert!(client_message_manager.send_packets(Tick(1))?.is_empty());
+        assert!(client_message_manager.take_stream_payloads().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_message_manager_transport_streams_big_message() -> Result<(), PacketError> {
+        let mut channel_registry = ChannelRegistry::default();
+        channel_registry.add_channel::<Channel1>(ChannelSettings {
+            mode: ChannelMode::UnorderedUnreliable,
+            ..default()
+        });
+        channel_registry.add_channel::<Channel2>(ChannelSettings {
+            mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+            ..default()
+        });
+        let new_manager = || {
+            MessageManager::new(
+                &channel_registry,
+                1.5,
+                PriorityConfig::default(),
+                MtuDiscoveryConfig::default(),
+            )
+        };
+        let mut client_message_manager = new_manager();
+        let mut server_message_manager = new_manager();
+        client_message_manager.set_transport_capabilities(TransportCapabilities {
+            reliable_streams: true,
+            max_datagram_size: None,
+        });
+
+        // a message bigger than the MTU is sent on the stream without being fragmented
+        let message = Bytes::from(vec![1; STREAM_FRAGMENT_SIZE]);
+        client_message_manager.buffer_send(message.clone(), Channel2::kind())?;
+        assert!(client_message_manager.send_packets(Tick(0))?.is_empty());
+        let stream_payloads = client_message_manager.take_stream_payloads();
+        assert_eq!(stream_payloads.len(), 1);
+        let (_, stream_payload) = stream_payloads[0].clone();
+        assert!(stream_payload.len() <= MAX_STREAM_PACKET_SIZE);
+        server_message_manager.recv_packet(stream_payload.into())?;
+        let data = MessageManager::collect_messages(server_message_manager.read_messages());
+        assert_eq!(
+            data.get(&Channel2::kind()).unwrap(),
+            &vec![(Tick(0), message)]
+        );
+
+        // messages that don't fit in a stream packet are still fragmented
+        let message = Bytes::from(vec![2; STREAM_FRAGMENT_SIZE + 1]);
+        client_message_manager.buffer_send(message.clone(), Channel2::kind())?;
+        assert!(client_message_manager.send_packets(Tick(1))?.is_empty());
+        let stream_payloads = client_message_manager.take_stream_payloads();
+        assert_eq!(stream_payloads.len(), 2);
+        for (_, stream_payload) in stream_payloads {
+            assert!(stream_payload.len() <= MAX_STREAM_PACKET_SIZE);
+            server_message_manager.recv_packet(stream_payload.into())?;
+        }
+        let data = MessageManager::collect_messages(server_message_manager.read_messages());
+        assert_eq!(
+            data.get(&Channel2::kind()).unwrap(),
+            &vec![(Tick(1), message)]
+        );
+
+        // the messages of the other channels are still fragmented to fit in the datagrams
+        client_message_manager
+            .buffer_send(Bytes::from(vec![3; 2 * FRAGMENT_SIZE]), Channel1::kind())?;
+        let payloads = client_message_manager.send_packets(Tick(2))?;
+        assert!(payloads.len() > 1);
+        assert!(payloads
+            .iter()
+            .all(|payload| payload.len() <= MAX_PACKET_SIZE));
+        Ok(())
+    }
+
     #[test]
     fn test_notify_ack() -> Result<(), PacketError> {
         let (mut client_message_manager, mut server_message_manager) = setup();
@@ -686,4 +1138,48 @@ mod tests {
         assert_eq!(update_acks_tracker.try_recv().unwrap(), message_id);
         Ok(())
     }
+
+    /// A message sent on a redundant channel is received even if the first packet is lost
+    #[test]
+    fn test_message_manager_redundant_channel() -> Result<(), PacketError> {
+        let mut channel_registry = ChannelRegistry::default();
+        channel_registry.add_channel::<Channel1>(ChannelSettings {
+            mode: ChannelMode::UnorderedUnreliableRedundant(RedundancySettings {
+                redundancy: 1,
+                adaptive: false,
+            }),
+            ..default()
+        });
+        let mut client_message_manager = MessageManager::new(
+            &channel_registry,
+            1.5,
+            PriorityConfig::default(),
+            MtuDiscoveryConfig::default(),
+        );
+        let mut server_message_manager = MessageManager::new(
+            &channel_registry,
+            1.5,
+            PriorityConfig::default(),
+            MtuDiscoveryConfig::default(),
+        );
+
+        let message: Bytes = vec![0, 1].into();
+        client_message_manager.buffer_send(message.clone(), Channel1::kind())?;
+        // the first packet is lost
+        let payloads = client_message_manager.send_packets(Tick(0))?;
+        assert_eq!(payloads.len(), 1);
+
+        // the copy of the message is sent in the next packet
+        for tick in 1..3 {
+            for payload in client_message_manager.send_packets(Tick(tick))? {
+                server_message_manager.recv_packet(payload.into())?;
+            }
+        }
+        let data = MessageManager::collect_messages(server_message_manager.read_messages());
+        assert_eq!(
+            data.get(&Channel1::kind()).unwrap(),
+            &vec![(Tick(1), message)]
+        );
+        Ok(())
+    }
 }
@@ -3,8 +3,8 @@ Module to group messages into packets
 
 # Packet
 This module defines the concept of a [`Packet`] which is a byte array that will be sent over the network.
-A [`Packet`] has a maximum size that depends on the transport (around 1400 bytes for UDP), and is
-composed of a header and a payload.
+A [`Packet`] has a maximum size that depends on the network path (1200 bytes by default; it can
+grow with path MTU discovery), and is composed of a header and a payload.
 
 The header will compute important information such as the packet sequence number, the packet type, etc.
 as well as information to handle the ack system.
@@ -19,6 +19,9 @@ Packets that are over the maximum packet size will be fragmented into multiple [
 [`FragmentData`]: message::FragmentData
 */
 
+/// Adapts the bandwidth used by a connection to the network conditions
+pub(crate) mod congestion;
+
 /// Manages the [`PacketHeader`](header::PacketHeader) which includes important packet information
 pub(crate) mod header;
 
@@ -27,12 +30,15 @@ pub(crate) mod message;
 /// Manages sending and receiving [`Packets`](packet::Packet) over the network
 pub mod message_manager;
 
+/// Discovers the maximum size of the packets that can be sent to the remote peer
+pub(crate) mod mtu;
+
 pub mod packet;
 
 pub(crate) mod error;
 /// Manages building a single [`Packet`](packet::Packet) from multiple [`Messages`](message::Message)
 pub(crate) mod packet_builder;
 /// Defines the [`PacketType`](packet_type::PacketType) enum
-mod packet_type;
+pub(crate) mod packet_type;
 pub(crate) mod priority_manager;
 pub(crate) mod stats_manager;
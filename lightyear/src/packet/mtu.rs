@@ -0,0 +1,353 @@
+//! Path MTU discovery
+//!
+//! Every connection starts by sending packets of at most [`MtuDiscoveryConfig::min_packet_size`] bytes,
+//! which should go through any network path (including VPNs and tunnels).
+//!
+//! When the discovery is enabled, the sender regularly sends a probe packet padded to a bigger size.
+//! If the remote peer acks the probe, the path can carry packets of that size and the maximum packet
+//! size of the connection grows. If the probe is lost [`MAX_PROBE_ATTEMPTS`] times in a row, the size
+//! is considered too big. The probed sizes follow a binary search between the minimum and maximum packet sizes.
+//!
+//! The sizes are the sizes of the lightyear packets; they don't include the netcode, UDP and IP headers
+//! (25 + 8 + 20 bytes for netcode over UDP over IPv4).
+use bevy::prelude::Reflect;
+use bevy::utils::Duration;
+use tracing::debug;
+
+use crate::connection::netcode::{MAX_PACKET_SIZE, MAX_PAYLOAD_SIZE};
+use crate::packet::header::PacketHeader;
+use crate::packet::packet::PacketId;
+use crate::shared::time_manager::WrappedTime;
+use crate::transport::middleware::compression::PAYLOAD_COMPRESSION_OVERHEAD;
+
+/// Number of times a probe of a given size can be lost before we consider that the size is too big
+const MAX_PROBE_ATTEMPTS: u8 = 3;
+
+/// The discovery stops once the difference between the biggest size that was acked and the smallest
+/// size that was lost is at most this number of bytes
+const SEARCH_PRECISION: usize = 16;
+
+/// Configuration of the path MTU discovery of a connection
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct MtuDiscoveryConfig {
+    /// If false, the packets are never bigger than `min_packet_size`
+    pub enabled: bool,
+    /// Maximum size of the packets sent before any probe is acked
+    pub min_packet_size: usize,
+    /// Biggest packet size that will be probed.
+    ///
+    /// It leaves some room for the compression overhead below the UDP payload size
+    /// (1472 bytes on Ethernet), and cannot be bigger than what a netcode packet can carry.
+    pub max_packet_size: usize,
+    /// Interval between two probes
+    pub probe_interval: Duration,
+}
+
+impl Default for MtuDiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_packet_size: MAX_PACKET_SIZE,
+            max_packet_size: 1400,
+            probe_interval: Duration::from_millis(500),
+        }
+    }
+}
+
+impl MtuDiscoveryConfig {
+    pub fn enable(mut self) -> Self {
+        self.enabled = true;
+        self
+    }
+
+    pub fn with_min_packet_size(mut self, min_packet_size: usize) -> Self {
+        self.min_packet_size = min_packet_size;
+        self
+    }
+
+    pub fn with_max_packet_size(mut self, max_packet_size: usize) -> Self {
+        self.max_packet_size = max_packet_size;
+        self
+    }
+
+    pub fn with_probe_interval(mut self, probe_interval: Duration) -> Self {
+        self.probe_interval = probe_interval;
+        self
+    }
+}
+
+/// A probe that has been sent and that hasn't been acked or lost yet
+#[derive(Debug)]
+struct Probe {
+    packet_id: PacketId,
+    size: usize,
+    sent_time: WrappedTime,
+}
+
+/// Discovers the maximum packet size that can be sent to the remote peer
+#[derive(Debug)]
+pub(crate) struct MtuDiscovery {
+    config: MtuDiscoveryConfig,
+    /// Biggest packet size that is known to reach the remote peer
+    mtu: usize,
+    /// Smallest packet size that is known to not reach the remote peer
+    upper_bound: usize,
+    /// Maximum packet size allowed by the transport, if it limits it
+    transport_limit: Option<usize>,
+    probe: Option<Probe>,
+    /// Number of probes of the current size that were lost
+    lost_probes: u8,
+    next_probe_time: Option<WrappedTime>,
+    current_time: WrappedTime,
+}
+
+impl MtuDiscovery {
+    pub(crate) fn new(config: MtuDiscoveryConfig) -> Self {
+        let max_packet_size = config
+            .max_packet_size
+            .min(MAX_PAYLOAD_SIZE - PAYLOAD_COMPRESSION_OVERHEAD)
+            .max(config.min_packet_size);
+        Self {
+            config,
+            mtu: config.min_packet_size,
+            upper_bound: max_packet_size + 1,
+            transport_limit: None,
+            probe: None,
+            lost_probes: 0,
+            next_probe_time: None,
+            current_time: WrappedTime::default(),
+        }
+    }
+
+    /// Biggest packet size that is known to reach the remote peer
+    pub(crate) fn mtu(&self) -> usize {
+        self.transport_limit
+            .map_or(self.mtu, |limit| self.mtu.min(limit))
+    }
+
+    /// Smallest packet size that is known to not reach the remote peer, or to not be accepted by the transport
+    fn upper_bound(&self) -> usize {
+        self.transport_limit
+            .map_or(self.upper_bound, |limit| self.upper_bound.min(limit + 1))
+    }
+
+    /// Limit the packets to the size of the datagrams that the transport can currently send
+    /// (`max_payload_size` is the maximum size of the netcode payloads).
+    ///
+    /// Returns the new MTU if it changed
+    pub(crate) fn set_transport_limit(&mut self, max_payload_size: Option<usize>) -> Option<usize> {
+        let mtu = self.mtu();
+        self.transport_limit =
+            max_payload_size.map(|size| size.saturating_sub(PAYLOAD_COMPRESSION_OVERHEAD));
+        (self.mtu() != mtu).then(|| self.mtu())
+    }
+
+    fn is_complete(&self) -> bool {
+        !self.config.enabled || self.upper_bound().saturating_sub(self.mtu()) <= SEARCH_PRECISION
+    }
+
+    /// Check if the probe in flight is lost
+    pub(crate) fn update(&mut self, current_time: WrappedTime, nack_duration: chrono::Duration) {
+        self.current_time = current_time;
+        if self.is_complete() {
+            return;
+        }
+        // wait for one interval before sending the first probe, so that the RTT estimate is available
+        let next_probe_time = self
+            .next_probe_time
+            .get_or_insert(current_time + self.config.probe_interval);
+        if self
+            .probe
+            .as_ref()
+            .is_some_and(|probe| current_time - probe.sent_time > nack_duration)
+        {
+            let probe = self.probe.take().unwrap();
+            self.lost_probes += 1;
+            if self.lost_probes >= MAX_PROBE_ATTEMPTS {
+                debug!(size = ?probe.size, "MTU probe lost too many times, the size is too big");
+                self.upper_bound = probe.size;
+                self.lost_probes = 0;
+            }
+            *next_probe_time = current_time + self.config.probe_interval;
+        }
+    }
+
+    /// Returns the size of the next probe to send, if it's time to send one
+    pub(crate) fn probe_to_send(&self) -> Option<usize> {
+        if self.is_complete()
+            || self.probe.is_some()
+            || !self
+                .next_probe_time
+                .is_some_and(|next_probe_time| self.current_time >= next_probe_time)
+        {
+            return None;
+        }
+        Some((self.mtu() + self.upper_bound()) / 2)
+    }
+
+    /// Keep track of the probe that was just sent
+    pub(crate) fn probe_sent(&mut self, packet_id: PacketId, size: usize) {
+        self.probe = Some(Probe {
+            packet_id,
+            size,
+            sent_time: self.current_time,
+        });
+    }
+
+    /// Check if the header of a received packet acks the probe in flight.
+    ///
+    /// Returns the new MTU if it increased
+    pub(crate) fn process_recv_packet_header(&mut self, header: &PacketHeader) -> Option<usize> {
+        if !self
+            .probe
+            .as_ref()
+            .is_some_and(|probe| header.acks(probe.packet_id))
+        {
+            return None;
+        }
+        let probe = self.probe.take().unwrap();
+        debug!(size = ?probe.size, "MTU probe acked");
+        self.mtu = probe.size;
+        self.lost_probes = 0;
+        self.next_probe_time = Some(self.current_time + self.config.probe_interval);
+        #[cfg(feature = "metrics")]
+        metrics::gauge!("mtu").set(self.mtu() as f64);
+        Some(self.mtu())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::diagnostics::ClientDiagnosticsPlugin;
+    use crate::packet::header::PacketHeaderManager;
+    use crate::packet::packet_type::PacketType;
+    use crate::prelude::client::{ClientConfig, ConnectionManager};
+    use crate::prelude::ClientId;
+    use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
+    use bevy::diagnostic::DiagnosticsStore;
+
+    /// Run the discovery on a path that drops the packets bigger than `path_mtu`
+    fn discover(path_mtu: usize) -> usize {
+        let config = MtuDiscoveryConfig::default().enable();
+        let mut discovery = MtuDiscovery::new(config);
+        let mut sender = PacketHeaderManager::new(1.5);
+        let mut receiver = PacketHeaderManager::new(1.5);
+        let nack_duration = chrono::Duration::milliseconds(100);
+        let mut time = WrappedTime::default();
+        for _ in 0..1000 {
+            time += Duration::from_millis(50);
+            discovery.update(time, nack_duration);
+            if let Some(size) = discovery.probe_to_send() {
+                let header = sender.prepare_send_probe_header();
+                discovery.probe_sent(header.packet_id, size);
+                if size <= path_mtu {
+                    receiver.process_recv_packet_header(&header);
+                }
+            }
+            // the receiver acks the packets it received
+            let header = receiver.prepare_send_packet_header(PacketType::Data);
+            discovery.process_recv_packet_header(&header);
+        }
+        discovery.mtu()
+    }
+
+    #[test]
+    fn test_mtu_discovery() {
+        // the whole range of sizes goes through
+        let mtu = discover(MAX_PAYLOAD_SIZE);
+        assert!(mtu <= 1400 && 1400 - mtu <= SEARCH_PRECISION, "{mtu}");
+
+        // a tunnel limits the packet size
+        let mtu = discover(1320);
+        assert!(mtu <= 1320 && 1320 - mtu <= SEARCH_PRECISION, "{mtu}");
+
+        // nothing bigger than the minimum size goes through
+        assert_eq!(discover(MAX_PACKET_SIZE), MAX_PACKET_SIZE);
+    }
+
+    #[test]
+    fn test_mtu_discovery_leaves_room_for_compression() {
+        let config = MtuDiscoveryConfig::default()
+            .enable()
+            .with_max_packet_size(usize::MAX);
+        let discovery = MtuDiscovery::new(config);
+        // a compressed payload can be one byte bigger than the packet, and must still fit in netcode
+        assert_eq!(
+            discovery.upper_bound - 1 + PAYLOAD_COMPRESSION_OVERHEAD,
+            MAX_PAYLOAD_SIZE
+        );
+    }
+
+    #[test]
+    fn test_mtu_discovery_transport_limit() {
+        let mut discovery = MtuDiscovery::new(MtuDiscoveryConfig::default().enable());
+        assert_eq!(discovery.mtu(), MAX_PACKET_SIZE);
+
+        // the transport can only send small datagrams
+        let limit = Some(1100 + PAYLOAD_COMPRESSION_OVERHEAD);
+        assert_eq!(discovery.set_transport_limit(limit), Some(1100));
+        assert_eq!(discovery.mtu(), 1100);
+        assert!(discovery.is_complete());
+
+        // the transport can send bigger datagrams, but the discovery doesn't probe above them
+        let limit = Some(1300 + PAYLOAD_COMPRESSION_OVERHEAD);
+        assert_eq!(discovery.set_transport_limit(limit), Some(MAX_PACKET_SIZE));
+        assert_eq!(discovery.upper_bound(), 1301);
+        assert_eq!(discovery.set_transport_limit(limit), None);
+    }
+
+    /// The probes go through netcode and the io, so the connection can send bigger packets
+    #[test]
+    fn test_mtu_discovery_connection() {
+        let mut stepper = BevyStepper::default();
+        stepper.stop();
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<ClientConfig>()
+            .packet
+            .mtu_discovery = MtuDiscoveryConfig::default()
+            .enable()
+            .with_probe_interval(Duration::from_millis(10));
+        stepper.start();
+        for _ in 0..100 {
+            stepper.frame_step();
+        }
+        let mtu = stepper
+            .client_app
+            .world()
+            .resource::<ConnectionManager>()
+            .mtu();
+        assert!(mtu <= 1400 && 1400 - mtu <= SEARCH_PRECISION, "{mtu}");
+        // the MTU is also part of the connection diagnostics
+        let diagnostic = stepper
+            .client_app
+            .world()
+            .resource::<DiagnosticsStore>()
+            .get(&ClientDiagnosticsPlugin::MTU)
+            .and_then(|diagnostic| diagnostic.value());
+        assert_eq!(diagnostic, Some(mtu as f64));
+
+        // the discovery is only enabled on the client
+        let server_mtu = stepper
+            .server_app
+            .world()
+            .resource::<crate::server::connection::ConnectionManager>()
+            .connection(ClientId::Netcode(TEST_CLIENT_ID))
+            .unwrap()
+            .mtu();
+        assert_eq!(server_mtu, MAX_PACKET_SIZE);
+    }
+
+    #[test]
+    fn test_mtu_discovery_disabled() {
+        let mut discovery = MtuDiscovery::new(MtuDiscoveryConfig::default());
+        discovery.update(
+            WrappedTime::default() + Duration::from_secs(10),
+            chrono::Duration::milliseconds(100),
+        );
+        assert_eq!(discovery.probe_to_send(), None);
+        assert_eq!(discovery.mtu(), MAX_PACKET_SIZE);
+    }
+}
@@ -1,9 +1,10 @@
 /// Defines the [`Packet`] struct
-use crate::connection::netcode::MAX_PACKET_SIZE;
+use crate::connection::netcode::{MAX_PACKET_SIZE, MAX_STREAM_PAYLOAD_SIZE};
 use crate::packet::message::MessageAck;
 use crate::packet::packet_builder::Payload;
 use crate::protocol::channel::ChannelId;
 use crate::serialize::ToBytes;
+use crate::transport::middleware::compression::PAYLOAD_COMPRESSION_OVERHEAD;
 use crate::utils::wrapping_id::wrapping_id;
 
 cfg_if::cfg_if!(
@@ -24,13 +25,33 @@ wrapping_id!(PacketId);
 /// Number of bytes to write the header
 const HEADER_BYTES: usize = 11;
 
-/// The maximum number of bytes for a message before it is fragmented
-/// MAX_PACKET_SIZE - HEADER_BYTES - 1 (channel_net_id) - 6 (message_id/fragment_id/num_fragments) - 2 (num bytes in fragment)
+/// Number of bytes of a fragment packet that are not fragment data:
+/// HEADER_BYTES + 1 (channel_net_id) + 6 (message_id/fragment_id/num_fragments) + 2 (num bytes in fragment)
 #[cfg(feature = "big_messages")]
-pub(crate) const FRAGMENT_SIZE: usize = MAX_PACKET_SIZE - HEADER_BYTES - 9;
+const FRAGMENT_OVERHEAD: usize = HEADER_BYTES + 9;
 
 #[cfg(not(feature = "big_messages"))]
-pub(crate) const FRAGMENT_SIZE: usize = MAX_PACKET_SIZE - HEADER_BYTES - 7;
+const FRAGMENT_OVERHEAD: usize = HEADER_BYTES + 7;
+
+/// The maximum number of bytes for a message before it is fragmented, for packets of
+/// at most MAX_PACKET_SIZE bytes
+pub(crate) const FRAGMENT_SIZE: usize = fragment_size(MAX_PACKET_SIZE);
+
+/// Maximum size of the packets sent on a reliable stream of the transport, which are not limited
+/// by the MTU
+pub(crate) const MAX_STREAM_PACKET_SIZE: usize =
+    MAX_STREAM_PAYLOAD_SIZE - PAYLOAD_COMPRESSION_OVERHEAD;
+
+/// The maximum number of bytes for a message before it is fragmented, for the packets sent on a
+/// reliable stream of the transport (with some room for the length of such big messages, which
+/// takes more bytes)
+pub(crate) const STREAM_FRAGMENT_SIZE: usize = fragment_size(MAX_STREAM_PACKET_SIZE) - 2;
+
+/// The maximum number of bytes for a message before it is fragmented, for packets of
+/// at most `max_packet_size` bytes
+pub(crate) const fn fragment_size(max_packet_size: usize) -> usize {
+    max_packet_size - FRAGMENT_OVERHEAD
+}
 
 /// Data structure that will help us write the packet
 #[derive(Debug)]
@@ -41,12 +62,14 @@ pub(crate) struct Packet {
     pub(crate) packet_id: PacketId,
     // How many bytes we know we are going to have to write in the packet, but haven't written yet
     pub(crate) prewritten_size: usize,
+    /// Maximum number of bytes of the packet
+    pub(crate) max_size: usize,
 }
 
 impl Packet {
     /// Check that we can still fit some data in the buffer
     pub(crate) fn can_fit(&self, size: usize) -> bool {
-        self.payload.len() + size + self.prewritten_size <= MAX_PACKET_SIZE
+        self.payload.len() + size + self.prewritten_size <= self.max_size
     }
 
     /// Check if we can write a channel_id + the number of messages in the packet.
@@ -2,7 +2,7 @@
 use crate::connection::netcode::MAX_PACKET_SIZE;
 use crate::packet::header::PacketHeaderManager;
 use crate::packet::message::{FragmentData, MessageAck, SingleData};
-use crate::packet::packet::{Packet, FRAGMENT_SIZE};
+use crate::packet::packet::{fragment_size, Packet, PacketId};
 use crate::packet::packet_type::PacketType;
 use crate::prelude::Tick;
 use crate::protocol::channel::ChannelId;
@@ -31,6 +31,8 @@ pub type RecvPayload = Bytes;
 pub(crate) struct PacketBuilder {
     pub(crate) header_manager: PacketHeaderManager,
     current_packet: Option<Packet>,
+    /// Maximum number of bytes of the packets we build
+    max_packet_size: usize,
     // Pre-allocated buffer to encode/decode without allocation.
     // TODO: should this be associated with Packet?
     // cursor: Vec<u8>,
@@ -45,6 +47,7 @@ impl PacketBuilder {
         Self {
             header_manager: PacketHeaderManager::new(nack_rtt_multiple),
             current_packet: None,
+            max_packet_size: MAX_PACKET_SIZE,
             // cursor: Vec::with_capacity(PACKET_BUFFER_CAPACITY),
             // acks: Vec::new(),
 
@@ -55,9 +58,19 @@ impl PacketBuilder {
         }
     }
 
+    /// Update the maximum number of bytes of the packets we build
+    pub(crate) fn set_max_packet_size(&mut self, max_packet_size: usize) {
+        self.max_packet_size = max_packet_size;
+    }
+
+    /// The maximum number of bytes for a message before it is fragmented
+    pub(crate) fn fragment_size(&self) -> usize {
+        fragment_size(self.max_packet_size)
+    }
+
     // TODO: get the vec from a pool of preallocated buffers
     fn get_new_buffer(&self) -> Payload {
-        Vec::with_capacity(MAX_PACKET_SIZE)
+        Vec::with_capacity(self.max_packet_size)
     }
 
     /// Start building new packet, we start with an empty packet
@@ -80,6 +93,7 @@ impl PacketBuilder {
             message_acks: vec![],
             packet_id: header.packet_id,
             prewritten_size: 0,
+            max_size: self.max_packet_size,
         });
         Ok(())
     }
@@ -112,6 +126,7 @@ impl PacketBuilder {
             )],
             packet_id: header.packet_id,
             prewritten_size: 0,
+            max_size: self.max_packet_size,
         });
         Ok(())
 
@@ -137,6 +152,20 @@ impl PacketBuilder {
         // }
     }
 
+    /// Build a MTU probe packet: a header followed by zeros up to `size` bytes
+    pub(crate) fn build_probe_packet(
+        &mut self,
+        size: usize,
+        current_tick: Tick,
+    ) -> Result<(PacketId, Payload), SerializationError> {
+        let mut cursor = Vec::with_capacity(size);
+        let mut header = self.header_manager.prepare_send_probe_header();
+        header.tick = current_tick;
+        header.to_bytes(&mut cursor)?;
+        cursor.resize(size, 0);
+        Ok((header.packet_id, cursor))
+    }
+
     pub fn finish_packet(&mut self) -> Packet {
         let mut packet = self.current_packet.take().unwrap();
         packet.payload.shrink_to_fit();
@@ -172,7 +201,7 @@ impl PacketBuilder {
         // try to fill the packet with fragment messages first
         for (channel_id, mut fragment_messages) in fragment_data.into_iter() {
             while let Some(fragment_data) = fragment_messages.pop_front() {
-                debug_assert!(fragment_data.bytes.len() <= FRAGMENT_SIZE);
+                debug_assert!(fragment_data.bytes.len() <= self.fragment_size());
                 self.build_new_fragment_packet(channel_id, &fragment_data, current_tick)?;
                 if !fragment_data.is_last_fragment() {
                     // big fragment, write packet immediately
@@ -427,6 +456,7 @@ mod tests {
 
     use crate::channel::senders::fragment_sender::FragmentSender;
     use crate::packet::message::MessageId;
+    use crate::packet::packet::FRAGMENT_SIZE;
     use crate::prelude::*;
 
     use super::*;
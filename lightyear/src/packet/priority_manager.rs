@@ -9,6 +9,7 @@ use tracing::{debug, error, trace};
 #[cfg(feature = "trace")]
 use tracing::{instrument, Level};
 
+use crate::packet::congestion::{CongestionControlConfig, CongestionController};
 use crate::packet::message::{FragmentData, MessageData, MessageId, SendMessage, SingleData};
 use crate::prelude::{ChannelRegistry, Tick};
 use crate::protocol::channel::ChannelId;
@@ -29,6 +30,8 @@ pub struct PriorityConfig {
     pub bandwidth_quota: Quota,
     /// If false, there is no bandwidth cap and all messages are sent as soon as possible
     pub enabled: bool,
+    /// If enabled, the bandwidth cap adapts to the network conditions instead of using `bandwidth_quota`
+    pub congestion_control: CongestionControlConfig,
 }
 
 // this is mostly for testing
@@ -38,6 +41,7 @@ impl Default for PriorityConfig {
             // 56 KB/s bandwidth cap
             bandwidth_quota: Quota::per_second(nonzero!(56000u32)),
             enabled: false,
+            congestion_control: CongestionControlConfig::default(),
         }
     }
 }
@@ -47,6 +51,7 @@ impl From<crate::client::config::PacketConfig> for PriorityConfig {
         Self {
             bandwidth_quota: value.send_bandwidth_cap,
             enabled: value.bandwidth_cap_enabled,
+            congestion_control: value.congestion_control,
         }
     }
 }
@@ -56,6 +61,7 @@ impl From<crate::server::config::PacketConfig> for PriorityConfig {
         Self {
             bandwidth_quota: value.per_client_send_bandwidth_cap,
             enabled: value.bandwidth_cap_enabled,
+            congestion_control: value.congestion_control,
         }
     }
 }
@@ -65,6 +71,8 @@ pub(crate) struct PriorityManager {
     pub(crate) config: PriorityConfig,
     // TODO: can I do without this limiter?
     pub(crate) limiter: DefaultDirectRateLimiter,
+    /// Adaptive bandwidth cap, used instead of the `limiter` if congestion control is enabled
+    pub(crate) congestion_controller: Option<CongestionController>,
     // // Internal buffer of data that we want to send
     // // Reuse allocation across frames
     // data_to_send: BTreeMap<ChannelId, (VecDeque<SendMessage>, VecDeque<SendMessage>)>,
@@ -79,6 +87,10 @@ impl PriorityManager {
         Self {
             config: config.clone(),
             limiter: DefaultDirectRateLimiter::direct(config.bandwidth_quota),
+            congestion_controller: config
+                .congestion_control
+                .enabled
+                .then(|| CongestionController::new(config.congestion_control)),
             // data_to_send: BTreeMap::new(),
             // buffered_data: Vec::new(),
             replication_update_senders: Vec::new(),
@@ -110,7 +122,7 @@ impl PriorityManager {
     ) {
         // if the bandwidth quota is disabled, just pass all messages through
         // As an optimization: no need to send the tick of the message, it is the same as the header tick
-        if !self.config.enabled {
+        if !self.config.enabled && self.congestion_controller.is_none() {
             let mut single_data = vec![];
             let mut fragment_data = vec![];
             for (net_id, (single, fragment)) in data {
@@ -186,10 +198,22 @@ impl PriorityManager {
             // we don't use the exact size of the message, but the size of the bytes
             // we will adjust for this later
             let message_bytes = buffered_message.data.len() as u32;
-            let nonzero_message_bytes = NonZeroU32::try_from(message_bytes).unwrap();
-            let Ok(result) = self.limiter.check_n(nonzero_message_bytes) else {
-                error!("the bandwidth does not have enough capacity for a message of this size!");
-                break;
+            let result = if let Some(controller) = &self.congestion_controller {
+                // the budget is only used once the packets are built
+                if controller.can_send(bytes_used + message_bytes) {
+                    Ok(())
+                } else {
+                    Err(())
+                }
+            } else {
+                let nonzero_message_bytes = NonZeroU32::try_from(message_bytes).unwrap();
+                let Ok(result) = self.limiter.check_n(nonzero_message_bytes) else {
+                    error!(
+                        "the bandwidth does not have enough capacity for a message of this size!"
+                    );
+                    break;
+                };
+                result.map_err(|_| ())
             };
 
             // above BYPASS_QUOTA_PRIORITY, we still send the message
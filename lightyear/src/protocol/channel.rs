@@ -5,7 +5,8 @@ use std::any::TypeId;
 use std::collections::HashMap;
 
 use crate::channel::builder::{
-    AuthorityChannel, Channel, ChannelBuilder, ChannelSettings, PongChannel,
+    AuthorityChannel, Channel, ChannelBuilder, ChannelSettings, PongChannel, ResourceAckChannel,
+    RpcChannel,
 };
 use crate::channel::builder::{
     ChannelContainer, EntityActionsChannel, EntityUpdatesChannel, InputChannel, PingChannel,
@@ -83,6 +84,7 @@ impl ChannelRegistry {
             // directly on the replication_sender
             send_frequency: Duration::default(),
             priority: 1.0,
+            message_ttl: None,
         });
         registry.add_channel::<EntityActionsChannel>(ChannelSettings {
             mode: ChannelMode::UnorderedReliable(ReliableSettings::default()),
@@ -93,30 +95,47 @@ impl ChannelRegistry {
             send_frequency: Duration::default(),
             // we want to send the entity actions as soon as possible
             priority: 10.0,
+            message_ttl: None,
         });
         registry.add_channel::<PingChannel>(ChannelSettings {
             mode: ChannelMode::SequencedUnreliable,
             send_frequency: Duration::default(),
             // we always want to include the ping in the packet
             priority: f32::INFINITY,
+            message_ttl: None,
         });
         registry.add_channel::<PongChannel>(ChannelSettings {
             mode: ChannelMode::SequencedUnreliable,
             send_frequency: Duration::default(),
             // we always want to include the pong in the packet
             priority: f32::INFINITY,
+            message_ttl: None,
         });
         registry.add_channel::<InputChannel>(ChannelSettings {
             mode: ChannelMode::UnorderedUnreliable,
             send_frequency: input_send_interval,
             // we always want to include the inputs in the packet
             priority: f32::INFINITY,
+            message_ttl: None,
         });
         registry.add_channel::<AuthorityChannel>(ChannelSettings {
             mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
             send_frequency: Duration::default(),
             // we want to send the authority transfers as soon as possible
             priority: 10.0,
+            message_ttl: None,
+        });
+        registry.add_channel::<RpcChannel>(ChannelSettings {
+            mode: ChannelMode::UnorderedReliable(ReliableSettings::default()),
+            send_frequency: Duration::default(),
+            priority: 10.0,
+            message_ttl: None,
+        });
+        registry.add_channel::<ResourceAckChannel>(ChannelSettings {
+            mode: ChannelMode::UnorderedUnreliable,
+            send_frequency: Duration::default(),
+            priority: 10.0,
+            message_ttl: None,
         });
         registry
     }
@@ -5,8 +5,12 @@ use std::fmt::Debug;
 use std::hash::Hash;
 use std::ops::{Add, Mul};
 
-use bevy::prelude::{App, Component, EntityWorldMut, Mut, Resource, TypePath, World};
+use bevy::prelude::{
+    App, AppTypeRegistry, Component, EntityWorldMut, Mut, ReflectComponent, Resource, TypePath,
+    World,
+};
 use bevy::ptr::Ptr;
+use bevy::reflect::TypeRegistry;
 use bevy::utils::HashMap;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
@@ -23,7 +27,8 @@ use crate::prelude::client::SyncComponent;
 use crate::prelude::server::ServerConfig;
 use crate::prelude::{ChannelDirection, Message, Tick};
 use crate::protocol::delta::ErasedDeltaFns;
-use crate::protocol::registry::{NetId, TypeKind, TypeMapper};
+use crate::protocol::registry::{NetId, NetIdCollision, TypeKind, TypeMapper};
+use crate::protocol::schema::{ComponentSchema, TypeLayout};
 use crate::protocol::serialize::{ErasedSerializeFns, SerializeFns};
 use crate::serialize::reader::Reader;
 use crate::serialize::SerializationError;
@@ -47,6 +52,14 @@ pub enum ComponentError {
     DeltaCompressionError(String),
     #[error("component error: {0}")]
     SerializationError(#[from] SerializationError),
+    #[error("component {0} is already registered in the protocol")]
+    AlreadyRegistered(String),
+    #[error("type {0} is not registered in the TypeRegistry")]
+    NotInTypeRegistry(String),
+    #[error("type {0} is missing the {1} type data")]
+    MissingReflectData(String, &'static str),
+    #[error(transparent)]
+    NetIdCollision(#[from] NetIdCollision),
 }
 
 /// A [`Resource`] that will keep track of all the [`Components`](Component) that can be replicated.
@@ -143,18 +156,47 @@ pub struct ComponentRegistry {
     prediction_map: HashMap<ComponentKind, PredictionMetadata>,
     serialize_fns_map: HashMap<ComponentKind, ErasedSerializeFns>,
     delta_fns_map: HashMap<ComponentKind, ErasedDeltaFns>,
+    direction_map: HashMap<ComponentKind, ChannelDirection>,
     pub(crate) kind_map: TypeMapper<ComponentKind>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ReplicationMetadata {
     pub component_id: ComponentId,
-    pub delta_compression_id: ComponentId,
-    pub replicate_once_id: ComponentId,
-    pub override_target_id: ComponentId,
-    pub disabled_id: ComponentId,
+    /// The ids of the marker components that modify how the component is replicated.
+    ///
+    /// They are `None` when the markers can't exist for this component, for example for
+    /// components registered at runtime through reflection.
+    pub delta_compression_id: Option<ComponentId>,
+    pub replicate_once_id: Option<ComponentId>,
+    pub override_target_id: Option<ComponentId>,
+    pub frequency_id: Option<ComponentId>,
+    pub disabled_id: Option<ComponentId>,
     pub write: RawWriteFn,
     pub remove: Option<RawRemoveFn>,
+    /// Used to insert/update/remove components that were registered through reflection
+    pub(crate) reflect: Option<ReflectComponentFns>,
+}
+
+/// [`ReflectComponent`] of a component that was registered at runtime through reflection
+#[derive(Clone)]
+pub(crate) struct ReflectComponentFns {
+    type_id: TypeId,
+    reflect: ReflectComponent,
+}
+
+impl Debug for ReflectComponentFns {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReflectComponentFns")
+            .field("type_id", &self.type_id)
+            .finish()
+    }
+}
+
+impl PartialEq for ReflectComponentFns {
+    fn eq(&self, other: &Self) -> bool {
+        self.type_id == other.type_id
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -244,7 +286,12 @@ impl ComponentRegistry {
 
     /// Check that the protocol is correct:
     /// - emits warnings for every component that has prediction/interpolation metadata but wasn't registered
+    /// - panics if two components have the same [`NetId`], after the explicit net ids were assigned
+    /// - panics if a component with legacy versions uses delta compression
     pub fn check(&self) {
+        if let Some(collision) = self.kind_map.collisions().first() {
+            panic!("{collision}");
+        }
         for component_kind in self.prediction_map.keys() {
             if !self.serialize_fns_map.contains_key(component_kind) {
                 panic!(
@@ -267,6 +314,66 @@ impl ComponentRegistry {
                 panic!("The Component {name:?} was registered for interpolation with ComponentSyncMode::FULL but no interpolation function was provided!");
             }
         }
+        for component_kind in self.delta_fns_map.keys() {
+            if self
+                .serialize_fns_map
+                .get(component_kind)
+                .is_some_and(|fns| !fns.legacy.is_empty())
+            {
+                let name = self.name(*component_kind);
+                panic!("The Component {name:?} has legacy versions, which are not supported with delta compression");
+            }
+        }
+    }
+
+    pub(crate) fn set_direction<C: 'static>(&mut self, direction: ChannelDirection) {
+        self.direction_map
+            .insert(ComponentKind::of::<C>(), direction);
+    }
+
+    /// Describe the component registered with this [`ComponentNetId`]: type name and direction
+    pub(crate) fn describe(&self, net_id: ComponentNetId) -> Option<String> {
+        let kind = self.kind_map.kind(net_id)?;
+        // use the name of the oldest version so that all versions of the component are equivalent
+        let type_name = self.serialize_fns_map.get(kind)?.root_type_name();
+        let direction = self
+            .direction_map
+            .get(kind)
+            .map_or("Internal".to_string(), |direction| format!("{direction:?}"));
+        Some(format!("{type_name} ({direction})"))
+    }
+
+    /// Describe the component registered with this [`ComponentNetId`] for the [`ProtocolSchema`](crate::protocol::schema::ProtocolSchema)
+    pub(crate) fn schema(
+        &self,
+        net_id: ComponentNetId,
+        type_registry: Option<&TypeRegistry>,
+    ) -> Option<ComponentSchema> {
+        let kind = self.kind_map.kind(net_id)?;
+        let serialize_fns = self.serialize_fns_map.get(kind)?;
+        Some(ComponentSchema {
+            net_id,
+            type_path: serialize_fns.type_name.to_string(),
+            direction: self
+                .direction_map
+                .get(kind)
+                .map(|direction| format!("{direction:?}")),
+            prediction: self
+                .prediction_map
+                .get(kind)
+                .map(|metadata| format!("{:?}", metadata.prediction_mode)),
+            interpolation: self
+                .interpolation_map
+                .get(kind)
+                .map(|metadata| format!("{:?}", metadata.interpolation_mode)),
+            delta_compression: self.delta_fns_map.contains_key(kind),
+            legacy_versions: serialize_fns
+                .legacy
+                .iter()
+                .map(|legacy| legacy.type_name.to_string())
+                .collect(),
+            layout: type_registry.and_then(|registry| TypeLayout::new(registry, kind.0)),
+        })
     }
 
     pub(crate) fn register_component<C: Message + Serialize + DeserializeOwned>(&mut self) {
@@ -289,10 +396,12 @@ impl ComponentRegistry {
 
 mod serialize {
     use super::*;
+    use crate::protocol::version::{self, PeerVersions, TypeVersion, VersionedKind};
     use crate::serialize::reader::Reader;
     use crate::serialize::writer::Writer;
     use crate::serialize::ToBytes;
     use crate::shared::replication::entity_map::SendEntityMap;
+    use bytes::Bytes;
 
     impl ComponentRegistry {
         pub(crate) fn try_add_map_entities<C: Clone + MapEntities + 'static>(&mut self) {
@@ -313,6 +422,75 @@ mod serialize {
             erased_fns.add_map_entities::<C>();
         }
 
+        pub(crate) fn add_legacy<
+            C: Message,
+            V: Message + Serialize + DeserializeOwned + for<'a> From<&'a C>,
+        >(
+            &mut self,
+            migrate: fn(V) -> C,
+        ) {
+            let kind = ComponentKind::of::<C>();
+            let erased_fns = self.serialize_fns_map.get_mut(&kind).unwrap_or_else(|| {
+                panic!(
+                    "Component {} is not part of the protocol",
+                    std::any::type_name::<C>()
+                )
+            });
+            let previous_name = erased_fns.root_type_name();
+            erased_fns.add_legacy::<C, V>(migrate);
+            self.kind_map
+                .rename(kind, previous_name, std::any::type_name::<V>());
+        }
+
+        /// The current version of every component that has legacy versions
+        pub(crate) fn versions(&self) -> Vec<TypeVersion> {
+            self.kind_map
+                .net_ids()
+                .into_iter()
+                .filter_map(|net_id| {
+                    let kind = self.kind_map.kind(net_id)?;
+                    let version = self.serialize_fns_map.get(kind)?.current_version();
+                    (version > 0).then_some(TypeVersion {
+                        kind: VersionedKind::Component,
+                        net_id,
+                        version,
+                    })
+                })
+                .collect()
+        }
+
+        /// Convert a serialized component from the current version to the version used by the peer
+        pub(crate) fn downgrade(
+            &self,
+            component: Bytes,
+            peer_versions: &PeerVersions,
+        ) -> Result<Bytes, SerializationError> {
+            version::convert(
+                component,
+                VersionedKind::Component,
+                &self.kind_map,
+                &self.serialize_fns_map,
+                peer_versions,
+                false,
+            )
+        }
+
+        /// Convert a serialized component from the version used by the peer to the current version
+        pub(crate) fn upgrade(
+            &self,
+            component: Bytes,
+            peer_versions: &PeerVersions,
+        ) -> Result<Bytes, SerializationError> {
+            version::convert(
+                component,
+                VersionedKind::Component,
+                &self.kind_map,
+                &self.serialize_fns_map,
+                peer_versions,
+                true,
+            )
+        }
+
         /// Returns true if we have a registered `map_entities` function for this component type
         pub(crate) fn is_map_entities<C: 'static>(&self) -> bool {
             let kind = ComponentKind::of::<C>();
@@ -562,6 +740,7 @@ mod replication {
     use super::*;
     use crate::prelude::{
         DeltaCompression, DisabledComponent, OverrideTargetComponent, ReplicateOnceComponent,
+        ReplicationFrequency,
     };
     use crate::serialize::reader::Reader;
     use crate::serialize::ToBytes;
@@ -576,12 +755,18 @@ mod replication {
                 kind,
                 ReplicationMetadata {
                     component_id: world.register_component::<C>(),
-                    delta_compression_id: world.register_component::<DeltaCompression<C>>(),
-                    replicate_once_id: world.register_component::<ReplicateOnceComponent<C>>(),
-                    override_target_id: world.register_component::<OverrideTargetComponent<C>>(),
-                    disabled_id: world.register_component::<DisabledComponent<C>>(),
+                    delta_compression_id: Some(world.register_component::<DeltaCompression<C>>()),
+                    replicate_once_id: Some(
+                        world.register_component::<ReplicateOnceComponent<C>>(),
+                    ),
+                    override_target_id: Some(
+                        world.register_component::<OverrideTargetComponent<C>>(),
+                    ),
+                    frequency_id: Some(world.register_component::<ReplicationFrequency<C>>()),
+                    disabled_id: Some(world.register_component::<DisabledComponent<C>>()),
                     write,
                     remove: Some(remove),
+                    reflect: None,
                 },
             );
         }
@@ -652,6 +837,10 @@ mod replication {
                 .replication_map
                 .get(kind)
                 .expect("the component is not part of the protocol");
+            if let Some(reflect) = &replication_metadata.reflect {
+                reflect.reflect.remove(entity_world_mut);
+                return;
+            }
             let f = replication_metadata
                 .remove
                 .expect("the component does not have a remove function");
@@ -692,14 +881,16 @@ mod delta {
             self.replication_map.insert(
                 delta_kind,
                 ReplicationMetadata {
-                    // NOTE: we set these to 0 because they are never used for the DeltaMessage component
+                    // NOTE: we set this to 0 because it is never used for the DeltaMessage component
                     component_id: ComponentId::new(0),
-                    delta_compression_id: ComponentId::new(0),
-                    replicate_once_id: ComponentId::new(0),
-                    override_target_id: ComponentId::new(0),
-                    disabled_id: ComponentId::new(0),
+                    delta_compression_id: None,
+                    replicate_once_id: None,
+                    override_target_id: None,
+                    frequency_id: None,
+                    disabled_id: None,
                     write,
                     remove: None,
+                    reflect: None,
                 },
             );
         }
@@ -858,6 +1049,176 @@ mod delta {
     }
 }
 
+mod reflect {
+    use super::*;
+    use crate::protocol::serialize::ReflectSerializeFns;
+    use bevy::reflect::{ReflectDeserialize, ReflectFromPtr, ReflectSerialize, TypeRegistration};
+
+    impl ComponentRegistry {
+        /// Register a component that is only known at runtime, using the reflection data
+        /// stored in the [`AppTypeRegistry`]
+        pub(crate) fn register_reflect_component(
+            &mut self,
+            world: &mut World,
+            type_path: &str,
+            direction: ChannelDirection,
+        ) -> Result<(ComponentNetId, ComponentId), ComponentError> {
+            let type_registry = world.resource::<AppTypeRegistry>().clone();
+            let type_registry = type_registry.read();
+            let registration = type_registry
+                .get_with_type_path(type_path)
+                .ok_or_else(|| ComponentError::NotInTypeRegistry(type_path.to_string()))?;
+            let reflect_component = reflect_data::<ReflectComponent>(registration)?;
+            let serialize_fns = ReflectSerializeFns {
+                from_ptr: reflect_data::<ReflectFromPtr>(registration)?,
+                serialize: reflect_data::<ReflectSerialize>(registration)?,
+                deserialize: reflect_data::<ReflectDeserialize>(registration)?,
+            };
+
+            let type_id = registration.type_id();
+            let type_name = registration.type_info().type_path();
+            let kind = ComponentKind(type_id);
+            if self.kind_map.net_id(&kind).is_some() {
+                return Err(ComponentError::AlreadyRegistered(type_name.to_string()));
+            }
+            self.kind_map.try_add_erased(type_id, type_name)?;
+            self.serialize_fns_map.insert(
+                kind,
+                ErasedSerializeFns::new_reflect(type_id, type_name, serialize_fns),
+            );
+            let component_id = reflect_component.register_component(world);
+            let write: RawWriteFn = Self::write_reflect;
+            self.replication_map.insert(
+                kind,
+                ReplicationMetadata {
+                    component_id,
+                    // the marker components (DisabledComponent<C>, etc.) are generic over the component type,
+                    // so they can't be used with components that are only known at runtime
+                    delta_compression_id: None,
+                    replicate_once_id: None,
+                    override_target_id: None,
+                    frequency_id: None,
+                    disabled_id: None,
+                    write,
+                    remove: None,
+                    reflect: Some(ReflectComponentFns {
+                        type_id,
+                        reflect: reflect_component,
+                    }),
+                },
+            );
+            self.direction_map.insert(kind, direction);
+            let net_id = *self.kind_map.net_id(&kind).unwrap();
+            Ok((net_id, component_id))
+        }
+
+        /// Return the [`ComponentNetId`] of a component that was registered through reflection
+        pub(crate) fn reflect_net_id(&self, component_id: ComponentId) -> Option<ComponentNetId> {
+            self.replication_map
+                .iter()
+                .find(|(_, metadata)| {
+                    metadata.reflect.is_some() && metadata.component_id == component_id
+                })
+                .and_then(|(kind, _)| self.kind_map.net_id(kind).copied())
+        }
+
+        pub(crate) fn write_reflect(
+            &self,
+            reader: &mut Reader,
+            net_id: ComponentNetId,
+            tick: Tick,
+            entity_world_mut: &mut EntityWorldMut,
+            _entity_map: &mut ReceiveEntityMap,
+            events: &mut ConnectionEvents,
+        ) -> Result<(), ComponentError> {
+            let kind = self
+                .kind_map
+                .kind(net_id)
+                .ok_or(ComponentError::NotRegistered)?;
+            trace!("Writing component {} to entity", self.name(*kind));
+            let component = self
+                .serialize_fns_map
+                .get(kind)
+                .ok_or(ComponentError::MissingSerializationFns)?
+                .reflect_deserialize(reader)?;
+            let reflect = &self
+                .replication_map
+                .get(kind)
+                .and_then(|metadata| metadata.reflect.as_ref())
+                .ok_or(ComponentError::MissingReplicationFns)?
+                .reflect;
+            let entity = entity_world_mut.id();
+            let equal = reflect.reflect(&*entity_world_mut).map(|c| {
+                c.reflect_partial_eq(component.as_partial_reflect())
+                    .unwrap_or(false)
+            });
+            match equal {
+                // only apply the update if the component is different, to not trigger change detection
+                Some(true) => {}
+                Some(false) => {
+                    events.push_update_component(entity, net_id, tick);
+                    reflect.apply(&mut *entity_world_mut, component.as_partial_reflect());
+                }
+                None => {
+                    events.push_insert_component(entity, net_id, tick);
+                    let type_registry = entity_world_mut
+                        .world()
+                        .resource::<AppTypeRegistry>()
+                        .clone();
+                    reflect.insert(
+                        entity_world_mut,
+                        component.as_partial_reflect(),
+                        &type_registry.read(),
+                    );
+                }
+            }
+            Ok(())
+        }
+    }
+
+    fn reflect_data<T: Clone + bevy::reflect::TypeData>(
+        registration: &TypeRegistration,
+    ) -> Result<T, ComponentError> {
+        registration.data::<T>().cloned().ok_or_else(|| {
+            ComponentError::MissingReflectData(
+                registration.type_info().type_path().to_string(),
+                std::any::type_name::<T>(),
+            )
+        })
+    }
+}
+
+fn register_reflect_component_send(
+    world: &mut World,
+    component_id: ComponentId,
+    direction: ChannelDirection,
+) {
+    let is_client = world.get_resource::<ClientConfig>().is_some();
+    let is_server = world.get_resource::<ServerConfig>().is_some();
+    match direction {
+        ChannelDirection::ClientToServer => {
+            if is_client {
+                crate::client::replication::send::register_replicate_reflect_component_send(
+                    world,
+                    component_id,
+                );
+            }
+        }
+        ChannelDirection::ServerToClient => {
+            if is_server {
+                crate::server::replication::send::register_replicate_reflect_component_send(
+                    world,
+                    component_id,
+                );
+            }
+        }
+        ChannelDirection::Bidirectional => {
+            register_reflect_component_send(world, component_id, ChannelDirection::ServerToClient);
+            register_reflect_component_send(world, component_id, ChannelDirection::ClientToServer);
+        }
+    }
+}
+
 fn register_component_send<C: Component>(app: &mut App, direction: ChannelDirection) {
     let is_client = app.world().get_resource::<ClientConfig>().is_some();
     let is_server = app.world().get_resource::<ServerConfig>().is_some();
@@ -911,6 +1272,16 @@ pub trait AppComponentExt {
         serialize_fns: SerializeFns<C>,
     ) -> ComponentRegistration<'_, C>;
 
+    /// Registers a component that is only known at runtime (for example a component added by a mod),
+    /// using its reflection data from the [`AppTypeRegistry`].
+    ///
+    /// See [`WorldComponentExt::register_reflect_component`]
+    fn register_reflect_component(
+        &mut self,
+        type_path: &str,
+        direction: ChannelDirection,
+    ) -> Result<ComponentNetId, ComponentError>;
+
     /// Enable rollbacks for a component even if the component is not networked
     fn add_rollback<C: Component + PartialEq + Clone>(&mut self);
 
@@ -970,6 +1341,38 @@ impl<C> ComponentRegistration<'_, C> {
         self
     }
 
+    /// Assign an explicit [`ComponentNetId`] to the component, instead of the one derived from the
+    /// registration order (or from the type name, with [`NetIdMode::Hash`](crate::protocol::NetIdMode::Hash))
+    pub fn with_net_id(self, net_id: ComponentNetId) -> Self
+    where
+        C: 'static,
+    {
+        let mut registry = self.app.world_mut().resource_mut::<ComponentRegistry>();
+        registry
+            .kind_map
+            .set_net_id(ComponentKind::of::<C>(), net_id);
+        self
+    }
+
+    /// Register `V` as a legacy version of the component, so that peers built with the older version
+    /// of the protocol can still connect.
+    ///
+    /// Received legacy components are converted to `C` with `migrate`, and components are converted
+    /// back to `V` with `From<&C>` before being replicated to those peers.
+    /// Each call registers a version that is older than the previous ones, so legacy versions
+    /// should be added from the most recent to the oldest.
+    ///
+    /// Components with legacy versions cannot use delta compression.
+    pub fn with_legacy<V>(self, migrate: fn(V) -> C) -> Self
+    where
+        C: Message,
+        V: Message + Serialize + DeserializeOwned + for<'a> From<&'a C>,
+    {
+        let mut registry = self.app.world_mut().resource_mut::<ComponentRegistry>();
+        registry.add_legacy::<C, V>(migrate);
+        self
+    }
+
     /// Enable prediction systems for this component.
     /// You can specify the prediction [`ComponentSyncMode`]
     pub fn add_prediction(self, prediction_mode: ComponentSyncMode) -> Self
@@ -1070,6 +1473,7 @@ impl AppComponentExt for App {
                     registry.register_component::<C>();
                 }
                 registry.set_replication_fns::<C>(world);
+                registry.set_direction::<C>(direction);
                 debug!("register component {}", std::any::type_name::<C>());
             });
         register_component_send::<C>(self, direction);
@@ -1090,6 +1494,7 @@ impl AppComponentExt for App {
                     registry.register_component_custom_serde::<C>(serialize_fns);
                 }
                 registry.set_replication_fns::<C>(world);
+                registry.set_direction::<C>(direction);
                 debug!("register component {}", std::any::type_name::<C>());
             });
         register_component_send::<C>(self, direction);
@@ -1099,6 +1504,15 @@ impl AppComponentExt for App {
         }
     }
 
+    fn register_reflect_component(
+        &mut self,
+        type_path: &str,
+        direction: ChannelDirection,
+    ) -> Result<ComponentNetId, ComponentError> {
+        self.world_mut()
+            .register_reflect_component(type_path, direction)
+    }
+
     // TODO: move this away from protocol? since it doesn't even use the registry at all
     //  maybe put this in the PredictionPlugin?
     fn add_rollback<C: Component + PartialEq + Clone>(&mut self) {
@@ -1196,6 +1610,49 @@ impl AppComponentExt for App {
     }
 }
 
+/// Register components in the [`ComponentRegistry`] from the [`World`], after the [`App`] was built
+pub trait WorldComponentExt {
+    /// Registers a component that is only known at runtime (for example a component added by a mod),
+    /// using its reflection data from the [`AppTypeRegistry`].
+    ///
+    /// The type must be registered in the [`AppTypeRegistry`] with the `Component`, `Serialize` and
+    /// `Deserialize` type data (i.e. `#[reflect(Component, Serialize, Deserialize)]`).
+    /// The client and the server must register the same components, in the same order if
+    /// [`NetIdMode::Sequential`](crate::protocol::registry::NetIdMode::Sequential) is used;
+    /// components should be registered before the client connects.
+    /// Returns [`ComponentError::NetIdCollision`] if the net id of the component is already used.
+    ///
+    /// Entity mapping, prediction, interpolation, delta compression and the per-component
+    /// replication settings (`DisabledComponent`, etc.) are not supported for these components.
+    fn register_reflect_component(
+        &mut self,
+        type_path: &str,
+        direction: ChannelDirection,
+    ) -> Result<ComponentNetId, ComponentError>;
+}
+
+impl WorldComponentExt for World {
+    fn register_reflect_component(
+        &mut self,
+        type_path: &str,
+        direction: ChannelDirection,
+    ) -> Result<ComponentNetId, ComponentError> {
+        let (net_id, component_id) =
+            self.resource_scope(|world, mut registry: Mut<ComponentRegistry>| {
+                registry.register_reflect_component(world, type_path, direction)
+            })?;
+        debug!("register reflected component {type_path}");
+        register_reflect_component_send(self, component_id, direction);
+        // the client's connection uses its own copy of the registry
+        if self.contains_resource::<crate::client::connection::ConnectionManager>() {
+            let registry = self.resource::<ComponentRegistry>().clone();
+            self.resource_mut::<crate::client::connection::ConnectionManager>()
+                .component_registry = registry;
+        }
+        Ok(net_id)
+    }
+}
+
 /// [`ComponentKind`] is an internal wrapper around the type of the component
 #[derive(Debug, Eq, Hash, Copy, Clone, PartialEq)]
 pub struct ComponentKind(pub(crate) TypeId);
@@ -1217,8 +1674,13 @@ impl From<TypeId> for ComponentKind {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::prelude::{client, server};
+    use crate::protocol::NetIdMode;
     use crate::serialize::writer::Writer;
     use crate::tests::protocol::*;
+    use crate::tests::stepper::BevyStepper;
+    use bevy::prelude::{Reflect, ReflectDeserialize, ReflectSerialize};
+    use serde::Deserialize;
 
     #[test]
     fn test_custom_serde() {
@@ -1241,4 +1703,150 @@ mod tests {
             .unwrap();
         assert_eq!(component, read);
     }
+
+    #[test]
+    #[should_panic(expected = "have the same net id 0")]
+    fn test_net_id_collision() {
+        let mut registry = ComponentRegistry::default();
+        registry.register_component::<ComponentSyncModeFull>();
+        registry.register_component::<ComponentSyncModeOnce>();
+        registry
+            .kind_map
+            .set_net_id(ComponentKind::of::<ComponentSyncModeOnce>(), 0);
+        registry.check();
+    }
+
+    #[test]
+    fn test_net_id_collision_resolved() {
+        let mut registry = ComponentRegistry::default();
+        registry.register_component::<ComponentSyncModeFull>();
+        registry.register_component::<ComponentSyncModeOnce>();
+        registry
+            .kind_map
+            .set_net_id(ComponentKind::of::<ComponentSyncModeOnce>(), 0);
+        // moving the other component to another net id resolves the collision before the check
+        registry
+            .kind_map
+            .set_net_id(ComponentKind::of::<ComponentSyncModeFull>(), 5);
+        registry.check();
+        assert_eq!(
+            registry
+                .kind_map
+                .net_id(&ComponentKind::of::<ComponentSyncModeOnce>()),
+            Some(&0)
+        );
+    }
+
+    impl From<&ComponentSyncModeFull> for ComponentSyncModeOnce {
+        fn from(component: &ComponentSyncModeFull) -> Self {
+            ComponentSyncModeOnce(component.0)
+        }
+    }
+
+    #[test]
+    fn test_legacy_net_id_hash() {
+        let mut registry = ComponentRegistry::default();
+        registry.kind_map.mode = NetIdMode::Hash;
+        registry.register_component::<ComponentSyncModeFull>();
+        registry.add_legacy::<ComponentSyncModeFull, ComponentSyncModeOnce>(|legacy| {
+            ComponentSyncModeFull(legacy.0)
+        });
+
+        // the net id is computed from the oldest version, so that it matches the net id
+        // used by peers that only know about the legacy version
+        let mut legacy_registry = ComponentRegistry::default();
+        legacy_registry.kind_map.mode = NetIdMode::Hash;
+        legacy_registry.register_component::<ComponentSyncModeOnce>();
+        let net_id = *registry
+            .kind_map
+            .net_id(&ComponentKind::of::<ComponentSyncModeFull>())
+            .unwrap();
+        assert_eq!(
+            Some(&net_id),
+            legacy_registry
+                .kind_map
+                .net_id(&ComponentKind::of::<ComponentSyncModeOnce>())
+        );
+        assert_eq!(registry.describe(net_id), legacy_registry.describe(net_id));
+    }
+
+    #[derive(Component, Reflect, Serialize, Deserialize, Debug, PartialEq, Clone)]
+    #[reflect(Component, Serialize, Deserialize)]
+    struct ReflectedComponent(f32);
+
+    #[test]
+    fn test_reflect_component_replication() {
+        let mut stepper = BevyStepper::default();
+        for app in [&mut stepper.server_app, &mut stepper.client_app] {
+            app.register_type::<ReflectedComponent>();
+            app.world_mut()
+                .register_reflect_component(
+                    ReflectedComponent::type_path(),
+                    ChannelDirection::ServerToClient,
+                )
+                .unwrap();
+        }
+        assert!(matches!(
+            stepper.server_app.world_mut().register_reflect_component(
+                ReflectedComponent::type_path(),
+                ChannelDirection::ServerToClient,
+            ),
+            Err(ComponentError::AlreadyRegistered(_))
+        ));
+
+        // insert
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((server::Replicate::default(), ReflectedComponent(1.0)))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        let client_entity = stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client");
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get::<ReflectedComponent>(client_entity),
+            Some(&ReflectedComponent(1.0))
+        );
+
+        // update
+        stepper
+            .server_app
+            .world_mut()
+            .get_mut::<ReflectedComponent>(server_entity)
+            .unwrap()
+            .0 = 2.0;
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get::<ReflectedComponent>(client_entity),
+            Some(&ReflectedComponent(2.0))
+        );
+
+        // remove
+        stepper
+            .server_app
+            .world_mut()
+            .entity_mut(server_entity)
+            .remove::<ReflectedComponent>();
+        stepper.frame_step();
+        stepper.frame_step();
+        assert!(stepper
+            .client_app
+            .world()
+            .get::<ReflectedComponent>(client_entity)
+            .is_none());
+    }
 }
@@ -0,0 +1,293 @@
+//! Compute a deterministic fingerprint of the protocol, so that a client and a server
+//! built with different protocols can detect it during the connection handshake.
+use std::hash::Hasher;
+
+use serde::{Deserialize, Serialize};
+
+use crate::channel::builder::ChannelMode;
+use crate::protocol::channel::ChannelRegistry;
+use crate::protocol::component::ComponentRegistry;
+use crate::protocol::message::MessageRegistry;
+use crate::protocol::version::TypeVersion;
+use crate::transport::middleware::compression::CompressionConfig;
+
+/// Maximum number of entry digests that the server sends back to the client when
+/// denying a connection because of a protocol mismatch (so that the denied packet fits in the MTU)
+pub(crate) const MAX_MISMATCH_DIGESTS: usize = 256;
+
+/// One entry of the protocol: a registered channel, message or component
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProtocolEntry {
+    /// Human-readable description of the entry (kind, net id, type name, mode/direction)
+    pub description: String,
+    /// Short digest of the description, used to find which entry differs between two protocols
+    pub(crate) digest: u32,
+}
+
+impl ProtocolEntry {
+    fn new(description: String) -> Self {
+        let digest = seahash::hash(description.as_bytes()) as u32;
+        Self {
+            description,
+            digest,
+        }
+    }
+}
+
+/// Deterministic description of every type registered in the [`ChannelRegistry`], [`MessageRegistry`]
+/// and [`ComponentRegistry`].
+///
+/// The client sends the hash of its fingerprint in the connection request; the server denies the
+/// connection with [`DeniedReason::ProtocolMismatch`](crate::connection::server::DeniedReason::ProtocolMismatch)
+/// if it doesn't match its own.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProtocolFingerprint {
+    entries: Vec<ProtocolEntry>,
+    hash: u64,
+    /// Current version of the messages and components that have legacy versions.
+    /// They are not part of the hash, so that peers using older versions can connect.
+    versions: Vec<TypeVersion>,
+}
+
+impl ProtocolFingerprint {
+    pub fn new(
+        component_registry: &ComponentRegistry,
+        message_registry: &MessageRegistry,
+        channel_registry: &ChannelRegistry,
+    ) -> Self {
+        let mut entries = vec![];
+        for net_id in channel_registry.kind_map.net_ids() {
+            let Some(kind) = channel_registry.kind_map.kind(net_id) else {
+                continue;
+            };
+            let name = channel_registry.name(kind).unwrap_or_default();
+            let mode = channel_registry
+                .get_builder_from_kind(kind)
+                .map_or("unknown", |builder| mode_name(&builder.settings.mode));
+            entries.push(ProtocolEntry::new(format!(
+                "channel #{net_id} {name} ({mode})"
+            )));
+        }
+        for net_id in message_registry.kind_map.net_ids() {
+            let Some(description) = message_registry.describe(net_id) else {
+                continue;
+            };
+            entries.push(ProtocolEntry::new(format!(
+                "message #{net_id} {description}"
+            )));
+        }
+        for net_id in component_registry.kind_map.net_ids() {
+            let Some(description) = component_registry.describe(net_id) else {
+                continue;
+            };
+            entries.push(ProtocolEntry::new(format!(
+                "component #{net_id} {description}"
+            )));
+        }
+        let mut versions = message_registry.versions();
+        versions.extend(component_registry.versions());
+        Self {
+            hash: hash_entries(&entries),
+            entries,
+            versions,
+        }
+    }
+
+    /// Add the compression dictionary used by the io to the fingerprint, so that peers that
+    /// use different dictionaries cannot connect
+    #[cfg_attr(not(feature = "zstd"), allow(unused_mut, unused_variables))]
+    pub(crate) fn with_compression(mut self, compression: &CompressionConfig) -> Self {
+        #[cfg(feature = "zstd")]
+        if let CompressionConfig::ZstdDictionary { dictionary, .. } = compression {
+            self.entries.push(ProtocolEntry::new(format!(
+                "compression zstd dictionary {:016x}",
+                dictionary.fingerprint()
+            )));
+            self.hash = hash_entries(&self.entries);
+        }
+        self
+    }
+
+    /// Hash of the whole protocol
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Current version of the messages and components that have legacy versions
+    pub fn versions(&self) -> &[TypeVersion] {
+        &self.versions
+    }
+
+    /// All the entries of the protocol, in a deterministic order
+    pub fn entries(&self) -> &[ProtocolEntry] {
+        &self.entries
+    }
+
+    /// Build the [`ProtocolMismatch`] that the server sends to a client whose protocol differs
+    pub(crate) fn mismatch(&self) -> ProtocolMismatch {
+        ProtocolMismatch {
+            server_num_entries: self.entries.len() as u16,
+            server_digests: self
+                .entries
+                .iter()
+                .take(MAX_MISMATCH_DIGESTS)
+                .map(|entry| entry.digest)
+                .collect(),
+            entry: None,
+        }
+    }
+
+    /// Describe the first entry of this protocol that differs from the server's protocol
+    pub(crate) fn first_difference(&self, mismatch: &ProtocolMismatch) -> String {
+        for (i, server_digest) in mismatch.server_digests.iter().enumerate() {
+            let Some(entry) = self.entries.get(i) else {
+                return format!(
+                    "the server registered {} entries but the client only registered {}",
+                    mismatch.server_num_entries,
+                    self.entries.len()
+                );
+            };
+            if entry.digest != *server_digest {
+                return format!("{} (differs from the server)", entry.description);
+            }
+        }
+        let compared = mismatch.server_digests.len();
+        if compared < mismatch.server_num_entries as usize {
+            return format!("an entry after the first {compared} entries");
+        }
+        match self.entries.get(compared) {
+            Some(entry) => format!("{} (not registered on the server)", entry.description),
+            None => "unknown entry".to_string(),
+        }
+    }
+}
+
+fn hash_entries(entries: &[ProtocolEntry]) -> u64 {
+    let mut hasher = seahash::SeaHasher::new();
+    for entry in entries {
+        hasher.write(entry.description.as_bytes());
+        hasher.write_u8(b'\n');
+    }
+    hasher.finish()
+}
+
+pub(crate) fn mode_name(mode: &ChannelMode) -> &'static str {
+    match mode {
+        ChannelMode::UnorderedUnreliableWithAcks => "UnorderedUnreliableWithAcks",
+        ChannelMode::UnorderedUnreliable => "UnorderedUnreliable",
+        ChannelMode::UnorderedUnreliableRedundant(_) => "UnorderedUnreliableRedundant",
+        ChannelMode::SequencedUnreliable => "SequencedUnreliable",
+        ChannelMode::UnorderedReliable(_) => "UnorderedReliable",
+        ChannelMode::SequencedReliable(_) => "SequencedReliable",
+        ChannelMode::OrderedReliable(_) => "OrderedReliable",
+        ChannelMode::OrderedReliableStreams(_) => "OrderedReliableStreams",
+    }
+}
+
+/// Information sent by the server when the client's protocol doesn't match its own
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProtocolMismatch {
+    /// Total number of entries in the server's protocol
+    pub(crate) server_num_entries: u16,
+    /// Digests of the first [`MAX_MISMATCH_DIGESTS`] entries of the server's protocol
+    pub(crate) server_digests: Vec<u32>,
+    /// Description of the first entry that differs between the client and the server protocols.
+    ///
+    /// This is not sent over the network: the client fills it in by comparing the server's digests
+    /// with its own protocol.
+    pub entry: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::{ChannelDirection, ChannelSettings};
+    use crate::protocol::message::MessageType;
+    use crate::tests::protocol::*;
+    use bevy::utils::Duration;
+
+    fn registries() -> (ComponentRegistry, MessageRegistry, ChannelRegistry) {
+        let mut message_registry = MessageRegistry::default();
+        message_registry.add_message::<StringMessage>(MessageType::Normal);
+        message_registry.set_direction::<StringMessage>(ChannelDirection::Bidirectional);
+        let mut component_registry = ComponentRegistry::default();
+        component_registry.register_component::<ComponentSyncModeFull>();
+        component_registry.set_direction::<ComponentSyncModeFull>(ChannelDirection::ServerToClient);
+        (
+            component_registry,
+            message_registry,
+            ChannelRegistry::new(Duration::default()),
+        )
+    }
+
+    #[test]
+    fn test_fingerprint_is_deterministic() {
+        let (components, messages, channels) = registries();
+        let a = ProtocolFingerprint::new(&components, &messages, &channels);
+        let b = ProtocolFingerprint::new(&components, &messages, &channels);
+        assert_eq!(a.hash(), b.hash());
+        assert_eq!(
+            a.entries().last().unwrap().description,
+            format!(
+                "component #0 {} (ServerToClient)",
+                std::any::type_name::<ComponentSyncModeFull>()
+            )
+        );
+    }
+
+    #[test]
+    fn test_fingerprint_mismatch() {
+        let (components, messages, channels) = registries();
+        let server = ProtocolFingerprint::new(&components, &messages, &channels);
+
+        // the client registers an additional channel
+        let (components, messages, mut channels) = registries();
+        channels.add_channel::<Channel1>(ChannelSettings::default());
+        let client = ProtocolFingerprint::new(&components, &messages, &channels);
+        assert_ne!(server.hash(), client.hash());
+        let difference = client.first_difference(&server.mismatch());
+        assert!(difference.contains("Channel1"));
+
+        // the client registers the message with a different direction
+        let (components, _, channels) = registries();
+        let mut messages = MessageRegistry::default();
+        messages.add_message::<StringMessage>(MessageType::Normal);
+        messages.set_direction::<StringMessage>(ChannelDirection::ClientToServer);
+        let client = ProtocolFingerprint::new(&components, &messages, &channels);
+        let difference = client.first_difference(&server.mismatch());
+        assert!(difference.starts_with("message #0"));
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_fingerprint_dictionary_mismatch() {
+        use crate::transport::middleware::compression::ZstdDictionary;
+        let dictionary = |bytes: &[u8]| CompressionConfig::ZstdDictionary {
+            level: 3,
+            dictionary: ZstdDictionary::new(bytes.to_vec()),
+        };
+        let (components, messages, channels) = registries();
+        let fingerprint = |bytes: &[u8]| {
+            ProtocolFingerprint::new(&components, &messages, &channels)
+                .with_compression(&dictionary(bytes))
+        };
+        let mut header = 0xEC30A437_u32.to_le_bytes().to_vec();
+        header.extend(1u32.to_le_bytes());
+        let server = fingerprint(&header);
+        header[4] = 2;
+        let client = fingerprint(&header);
+        assert_ne!(server.hash(), client.hash());
+        let difference = client.first_difference(&server.mismatch());
+        assert!(difference.starts_with("compression zstd dictionary"));
+
+        // raw content dictionaries don't have an id, but are still told apart
+        assert_ne!(
+            fingerprint(b"first dictionary").hash(),
+            fingerprint(b"second dictionary").hash()
+        );
+        assert_eq!(
+            fingerprint(b"first dictionary").hash(),
+            fingerprint(b"first dictionary").hash()
+        );
+    }
+}
@@ -5,8 +5,10 @@ use std::fmt::Debug;
 use crate::client::config::ClientConfig;
 use crate::client::message::add_client_receive_message_from_server;
 use crate::prelude::{client, server};
-use bevy::prelude::{App, Resource, TypePath};
+use bevy::prelude::{App, Event, Resource, TypePath};
+use bevy::reflect::TypeRegistry;
 use bevy::utils::HashMap;
+use bytes::Bytes;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use tracing::{debug, error};
@@ -15,13 +17,18 @@ use crate::packet::message::Message;
 use crate::prelude::server::ServerConfig;
 use crate::prelude::ChannelDirection;
 use crate::protocol::registry::{NetId, TypeKind, TypeMapper};
+use crate::protocol::schema::{MessageSchema, TypeLayout};
 use crate::protocol::serialize::{ErasedSerializeFns, SerializeFns};
+use crate::protocol::version::{self, PeerVersions, TypeVersion, VersionedKind};
 use crate::serialize::reader::Reader;
 use crate::serialize::writer::Writer;
-use crate::serialize::ToBytes;
+use crate::serialize::{SerializationError, ToBytes};
 use crate::server::message::add_server_receive_message_from_client;
+use crate::shared::replication::delta::Diffable;
 use crate::shared::replication::entity_map::{ReceiveEntityMap, SendEntityMap};
-use crate::shared::replication::resources::DespawnResource;
+use crate::shared::replication::resources::{DespawnResource, ResourceDelta, ResourceDeltaAck};
+use crate::shared::rpc::{RpcRequest, RpcResponse};
+use crate::shared::trigger::{receive_map_trigger_entities, TriggerMessage};
 
 #[derive(thiserror::Error, Debug)]
 pub enum MessageError {
@@ -99,6 +106,7 @@ pub(crate) enum MessageType {
 pub struct MessageRegistry {
     typed_map: HashMap<MessageKind, MessageType>,
     serialize_fns_map: HashMap<MessageKind, ErasedSerializeFns>,
+    direction_map: HashMap<MessageKind, ChannelDirection>,
     pub(crate) kind_map: TypeMapper<MessageKind>,
 }
 
@@ -123,6 +131,54 @@ fn register_message_send<M: Message>(app: &mut App, direction: ChannelDirection)
     }
 }
 
+fn register_request_send<Req: Message, Resp: Message>(app: &mut App, direction: ChannelDirection) {
+    let is_client = app.world().get_resource::<ClientConfig>().is_some();
+    let is_server = app.world().get_resource::<ServerConfig>().is_some();
+    match direction {
+        ChannelDirection::ClientToServer => {
+            if is_client {
+                crate::client::rpc::add_client_receive_response::<Req, Resp>(app);
+            }
+            if is_server {
+                crate::server::rpc::add_server_receive_request::<Req>(app);
+            }
+        }
+        ChannelDirection::ServerToClient => {
+            if is_server {
+                crate::server::rpc::add_server_receive_response::<Req, Resp>(app);
+            }
+            if is_client {
+                crate::client::rpc::add_client_receive_request::<Req>(app);
+            }
+        }
+        ChannelDirection::Bidirectional => {
+            register_request_send::<Req, Resp>(app, ChannelDirection::ClientToServer);
+            register_request_send::<Req, Resp>(app, ChannelDirection::ServerToClient);
+        }
+    }
+}
+
+fn register_trigger_send<E: Event + Message>(app: &mut App, direction: ChannelDirection) {
+    let is_client = app.world().get_resource::<ClientConfig>().is_some();
+    let is_server = app.world().get_resource::<ServerConfig>().is_some();
+    match direction {
+        ChannelDirection::ClientToServer => {
+            if is_server {
+                crate::server::trigger::add_server_receive_trigger::<E>(app);
+            }
+        }
+        ChannelDirection::ServerToClient => {
+            if is_client {
+                crate::client::trigger::add_client_receive_trigger::<E>(app);
+            }
+        }
+        ChannelDirection::Bidirectional => {
+            register_trigger_send::<E>(app, ChannelDirection::ClientToServer);
+            register_trigger_send::<E>(app, ChannelDirection::ServerToClient);
+        }
+    }
+}
+
 fn register_resource_send<R: Resource + Message>(app: &mut App, direction: ChannelDirection) {
     let is_client = app.world().get_resource::<ClientConfig>().is_some();
     let is_server = app.world().get_resource::<ServerConfig>().is_some();
@@ -182,6 +238,58 @@ fn register_resource_send<R: Resource + Message>(app: &mut App, direction: Chann
     }
 }
 
+/// Add the systems to replicate a delta-compressed resource.
+///
+/// Only the updates from the server to the clients are delta-compressed.
+fn register_resource_delta_send<R: Resource + Message + Diffable>(
+    app: &mut App,
+    direction: ChannelDirection,
+) {
+    let is_client = app.world().get_resource::<ClientConfig>().is_some();
+    let is_server = app.world().get_resource::<ServerConfig>().is_some();
+    match direction {
+        ChannelDirection::ClientToServer => {}
+        ChannelDirection::ServerToClient | ChannelDirection::Bidirectional => {
+            if is_server {
+                crate::shared::replication::resources::send::add_resource_delta_send_systems::<R>(
+                    app,
+                );
+            }
+            if is_client {
+                crate::shared::replication::resources::receive::add_resource_delta_receive_systems::<
+                    R,
+                >(app, matches!(direction, ChannelDirection::Bidirectional));
+            }
+        }
+    }
+}
+
+pub struct ResourceRegistration<'a, R> {
+    app: &'a mut App,
+    direction: ChannelDirection,
+    _marker: std::marker::PhantomData<R>,
+}
+
+impl<R> ResourceRegistration<'_, R> {
+    /// Enable delta compression when replicating this resource from the server to the clients.
+    ///
+    /// Each client receives the delta between the current value and the last value that it acked.
+    /// Clients that haven't acked any value yet (for example newly connected clients) receive the full state.
+    /// The updates sent from the clients to the server are not delta-compressed.
+    pub fn add_delta_compression(self) -> Self
+    where
+        R: Resource + Message + Diffable,
+        R::Delta: Serialize + DeserializeOwned,
+    {
+        self.app
+            .register_message::<ResourceDelta<R>>(ChannelDirection::ServerToClient);
+        self.app
+            .register_message::<ResourceDeltaAck<R>>(ChannelDirection::ClientToServer);
+        register_resource_delta_send::<R>(self.app, self.direction);
+        self
+    }
+}
+
 pub struct MessageRegistration<'a, M> {
     app: &'a mut App,
     _marker: std::marker::PhantomData<M>,
@@ -198,6 +306,34 @@ impl<M> MessageRegistration<'_, M> {
         registry.add_map_entities::<M>();
         self
     }
+
+    /// Assign an explicit [`NetId`] to the message, instead of the one derived from the
+    /// registration order (or from the type name, with [`NetIdMode::Hash`](crate::protocol::NetIdMode::Hash))
+    pub fn with_net_id(self, net_id: NetId) -> Self
+    where
+        M: 'static,
+    {
+        let mut registry = self.app.world_mut().resource_mut::<MessageRegistry>();
+        registry.kind_map.set_net_id(MessageKind::of::<M>(), net_id);
+        self
+    }
+
+    /// Register `V` as a legacy version of the message, so that peers built with the older version
+    /// of the protocol can still connect.
+    ///
+    /// Received legacy messages are converted to `M` with `migrate`, and messages are converted
+    /// back to `V` with `From<&M>` before being sent to those peers.
+    /// Each call registers a version that is older than the previous ones, so legacy versions
+    /// should be added from the most recent to the oldest.
+    pub fn with_legacy<V>(self, migrate: fn(V) -> M) -> Self
+    where
+        M: Message,
+        V: Message + Serialize + DeserializeOwned + for<'a> From<&'a M>,
+    {
+        let mut registry = self.app.world_mut().resource_mut::<MessageRegistry>();
+        registry.add_legacy::<M, V>(migrate);
+        self
+    }
 }
 
 pub(crate) trait AppMessageInternalExt {
@@ -228,6 +364,7 @@ impl AppMessageInternalExt for App {
         if !registry.is_registered::<M>() {
             registry.add_message::<M>(message_type);
         }
+        registry.set_direction::<M>(direction);
         debug!("register message {}", std::any::type_name::<M>());
         register_message_send::<M>(self, direction);
         MessageRegistration {
@@ -246,6 +383,7 @@ impl AppMessageInternalExt for App {
         if !registry.is_registered::<M>() {
             registry.add_message_custom_serde::<M>(message_type, serialize_fns);
         }
+        registry.set_direction::<M>(direction);
         debug!("register message {}", std::any::type_name::<M>());
         register_message_send::<M>(self, direction);
         MessageRegistration {
@@ -279,7 +417,7 @@ pub trait AppMessageExt {
     fn register_resource<R: Resource + Message + Serialize + DeserializeOwned>(
         &mut self,
         direction: ChannelDirection,
-    );
+    ) -> ResourceRegistration<'_, R>;
 
     /// Registers the resource in the Registry
     ///
@@ -289,6 +427,25 @@ pub trait AppMessageExt {
         &mut self,
         direction: ChannelDirection,
         serialize_fns: SerializeFns<R>,
+    ) -> ResourceRegistration<'_, R>;
+
+    /// Registers a request type and the type of its response.
+    ///
+    /// `direction` is the direction of the requests: the responses are sent in the opposite direction.
+    /// Requests can then be sent with `send_request` and answered with `respond` on the `ConnectionManager`.
+    fn register_request<Req, Resp>(&mut self, direction: ChannelDirection)
+    where
+        Req: Message + Serialize + DeserializeOwned,
+        Resp: Message + Serialize + DeserializeOwned;
+
+    /// Registers an [`Event`] that can be triggered on the remote peer.
+    ///
+    /// The event can then be sent with `send_trigger` on the `ConnectionManager`; the target entities
+    /// are mapped to the remote peer's entities, and the event is triggered there as a
+    /// [`RemoteTrigger`](crate::shared::trigger::RemoteTrigger).
+    fn register_trigger<E: Event + Message + Clone + Serialize + DeserializeOwned>(
+        &mut self,
+        direction: ChannelDirection,
     );
 }
 
@@ -312,10 +469,15 @@ impl AppMessageExt for App {
     fn register_resource<R: Resource + Message + Serialize + DeserializeOwned>(
         &mut self,
         direction: ChannelDirection,
-    ) {
+    ) -> ResourceRegistration<'_, R> {
         self.register_message::<R>(direction);
         self.register_message::<DespawnResource<R>>(direction);
-        register_resource_send::<R>(self, direction)
+        register_resource_send::<R>(self, direction);
+        ResourceRegistration {
+            app: self,
+            direction,
+            _marker: std::marker::PhantomData,
+        }
     }
 
     /// Register a resource to be automatically replicated over the network
@@ -323,10 +485,41 @@ impl AppMessageExt for App {
         &mut self,
         direction: ChannelDirection,
         serialize_fns: SerializeFns<R>,
-    ) {
+    ) -> ResourceRegistration<'_, R> {
         self.register_message_custom_serde::<R>(direction, serialize_fns);
         self.register_message::<DespawnResource<R>>(direction);
-        register_resource_send::<R>(self, direction)
+        register_resource_send::<R>(self, direction);
+        ResourceRegistration {
+            app: self,
+            direction,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    fn register_request<Req, Resp>(&mut self, direction: ChannelDirection)
+    where
+        Req: Message + Serialize + DeserializeOwned,
+        Resp: Message + Serialize + DeserializeOwned,
+    {
+        let response_direction = match direction {
+            ChannelDirection::ClientToServer => ChannelDirection::ServerToClient,
+            ChannelDirection::ServerToClient => ChannelDirection::ClientToServer,
+            ChannelDirection::Bidirectional => ChannelDirection::Bidirectional,
+        };
+        self.register_message::<RpcRequest<Req>>(direction);
+        self.register_message::<RpcResponse<Req, Resp>>(response_direction);
+        register_request_send::<Req, Resp>(self, direction)
+    }
+
+    fn register_trigger<E: Event + Message + Clone + Serialize + DeserializeOwned>(
+        &mut self,
+        direction: ChannelDirection,
+    ) {
+        self.register_message::<TriggerMessage<E>>(direction);
+        self.world_mut()
+            .resource_mut::<MessageRegistry>()
+            .add_trigger_map_entities::<E>();
+        register_trigger_send::<E>(self, direction)
     }
 }
 
@@ -342,6 +535,14 @@ impl MessageRegistry {
         self.kind_map.net_id(&MessageKind::of::<M>()).is_some()
     }
 
+    /// Check that the protocol is correct:
+    /// - panics if two messages have the same [`NetId`], after the explicit net ids were assigned
+    pub fn check(&self) {
+        if let Some(collision) = self.kind_map.collisions().first() {
+            panic!("{collision}");
+        }
+    }
+
     pub(crate) fn add_message<M: Message + Serialize + DeserializeOwned>(
         &mut self,
         message_type: MessageType,
@@ -365,6 +566,119 @@ impl MessageRegistry {
         self.typed_map.insert(message_kind, message_type);
     }
 
+    pub(crate) fn set_direction<M: 'static>(&mut self, direction: ChannelDirection) {
+        self.direction_map.insert(MessageKind::of::<M>(), direction);
+    }
+
+    /// Describe the message registered with this [`NetId`]: type name, direction and message type
+    pub(crate) fn describe(&self, net_id: NetId) -> Option<String> {
+        let kind = self.kind_map.kind(net_id)?;
+        // use the name of the oldest version so that all versions of the message are equivalent
+        let type_name = self.serialize_fns_map.get(kind)?.root_type_name();
+        let direction = self
+            .direction_map
+            .get(kind)
+            .map_or("Internal".to_string(), |direction| format!("{direction:?}"));
+        Some(format!(
+            "{type_name} ({direction}, {:?})",
+            self.message_type(net_id)
+        ))
+    }
+
+    /// Describe the message registered with this [`NetId`] for the [`ProtocolSchema`](crate::protocol::schema::ProtocolSchema)
+    pub(crate) fn schema(
+        &self,
+        net_id: NetId,
+        type_registry: Option<&TypeRegistry>,
+    ) -> Option<MessageSchema> {
+        let kind = self.kind_map.kind(net_id)?;
+        let serialize_fns = self.serialize_fns_map.get(kind)?;
+        Some(MessageSchema {
+            net_id,
+            type_path: serialize_fns.type_name.to_string(),
+            direction: self
+                .direction_map
+                .get(kind)
+                .map(|direction| format!("{direction:?}")),
+            message_type: format!("{:?}", self.message_type(net_id)),
+            legacy_versions: serialize_fns
+                .legacy
+                .iter()
+                .map(|legacy| legacy.type_name.to_string())
+                .collect(),
+            layout: type_registry.and_then(|registry| TypeLayout::new(registry, kind.0)),
+        })
+    }
+
+    pub(crate) fn add_legacy<
+        M: Message,
+        V: Message + Serialize + DeserializeOwned + for<'a> From<&'a M>,
+    >(
+        &mut self,
+        migrate: fn(V) -> M,
+    ) {
+        let kind = MessageKind::of::<M>();
+        let erased_fns = self.serialize_fns_map.get_mut(&kind).unwrap_or_else(|| {
+            panic!(
+                "Message {} is not part of the protocol",
+                std::any::type_name::<M>()
+            )
+        });
+        let previous_name = erased_fns.root_type_name();
+        erased_fns.add_legacy::<M, V>(migrate);
+        self.kind_map
+            .rename(kind, previous_name, std::any::type_name::<V>());
+    }
+
+    /// The current version of every message that has legacy versions
+    pub(crate) fn versions(&self) -> Vec<TypeVersion> {
+        self.kind_map
+            .net_ids()
+            .into_iter()
+            .filter_map(|net_id| {
+                let kind = self.kind_map.kind(net_id)?;
+                let version = self.serialize_fns_map.get(kind)?.current_version();
+                (version > 0).then_some(TypeVersion {
+                    kind: VersionedKind::Message,
+                    net_id,
+                    version,
+                })
+            })
+            .collect()
+    }
+
+    /// Convert a serialized message from the current version to the version used by the peer
+    pub(crate) fn downgrade(
+        &self,
+        message: Bytes,
+        peer_versions: &PeerVersions,
+    ) -> Result<Bytes, SerializationError> {
+        version::convert(
+            message,
+            VersionedKind::Message,
+            &self.kind_map,
+            &self.serialize_fns_map,
+            peer_versions,
+            false,
+        )
+    }
+
+    /// Convert a serialized message from the version used by the peer to the current version
+    pub(crate) fn upgrade(
+        &self,
+        message: Bytes,
+        peer_versions: &PeerVersions,
+    ) -> Result<Bytes, SerializationError> {
+        version::convert(
+            message,
+            VersionedKind::Message,
+            &self.kind_map,
+            &self.serialize_fns_map,
+            peer_versions,
+            true,
+        )
+    }
+
     pub(crate) fn try_add_map_entities<M: Clone + MapEntities + 'static>(&mut self) {
         let kind = MessageKind::of::<M>();
         if let Some(erased_fns) = self.serialize_fns_map.get_mut(&kind) {
@@ -381,6 +695,17 @@ impl MessageRegistry {
         erased_fns.add_map_entities::<M>();
     }
 
+    /// Map the target entities of a [`TriggerMessage`] like any other message, except that
+    /// on the receive side the targets that can't be mapped are replaced with [`Entity::PLACEHOLDER`](bevy::prelude::Entity::PLACEHOLDER)
+    pub(crate) fn add_trigger_map_entities<E: Message + Clone>(&mut self) {
+        self.add_map_entities::<TriggerMessage<E>>();
+        let erased_fns = self
+            .serialize_fns_map
+            .get_mut(&MessageKind::of::<TriggerMessage<E>>())
+            .expect("the message is not part of the protocol");
+        erased_fns.receive_map_entities = Some(receive_map_trigger_entities::<E>);
+    }
+
     /// Returns true if we have a registered `map_entities` function for this message type
     pub(crate) fn is_map_entities<M: 'static>(&self) -> bool {
         let kind = MessageKind::of::<M>();
@@ -455,6 +780,7 @@ mod tests {
         deserialize_resource2, serialize_resource2, ComponentMapEntities, Resource1, Resource2,
     };
     use bevy::prelude::Entity;
+    use serde::Deserialize;
 
     #[test]
     fn test_serde() {
@@ -518,4 +844,83 @@ mod tests {
             .unwrap();
         assert_eq!(message, read);
     }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+    struct MessageV1(f32);
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+    struct MessageV2 {
+        value: f32,
+        name: String,
+    }
+
+    impl From<&MessageV2> for MessageV1 {
+        fn from(message: &MessageV2) -> Self {
+            MessageV1(message.value)
+        }
+    }
+
+    #[test]
+    fn test_legacy_versions() {
+        let mut registry = MessageRegistry::default();
+        registry.add_message::<MessageV2>(MessageType::Normal);
+        registry.add_legacy::<MessageV2, MessageV1>(|legacy| MessageV2 {
+            value: legacy.0,
+            name: String::new(),
+        });
+        assert_eq!(
+            registry.versions(),
+            vec![TypeVersion {
+                kind: VersionedKind::Message,
+                net_id: 0,
+                version: 1,
+            }]
+        );
+        // the message is described with the name of its oldest version
+        assert!(registry.describe(0).unwrap().contains("MessageV1"));
+
+        // a peer that only knows about the legacy version
+        let mut legacy_registry = MessageRegistry::default();
+        legacy_registry.add_message::<MessageV1>(MessageType::Normal);
+        let peer_versions = PeerVersions::negotiate(&registry.versions(), &[]).unwrap();
+
+        let message = MessageV2 {
+            value: 1.0,
+            name: "a".to_string(),
+        };
+        let mut writer = Writer::default();
+        registry.serialize(&message, &mut writer, None).unwrap();
+        let data = writer.to_bytes();
+        // peers that use the current version receive the bytes as is
+        assert_eq!(
+            registry
+                .downgrade(data.clone(), &PeerVersions::default())
+                .unwrap(),
+            data
+        );
+
+        let legacy_data = registry.downgrade(data, &peer_versions).unwrap();
+        let read = legacy_registry
+            .deserialize::<MessageV1>(
+                &mut Reader::from(legacy_data.clone()),
+                &mut ReceiveEntityMap::default(),
+            )
+            .unwrap();
+        assert_eq!(read, MessageV1(1.0));
+
+        let upgraded_data = registry.upgrade(legacy_data, &peer_versions).unwrap();
+        let read = registry
+            .deserialize::<MessageV2>(
+                &mut Reader::from(upgraded_data),
+                &mut ReceiveEntityMap::default(),
+            )
+            .unwrap();
+        assert_eq!(
+            read,
+            MessageV2 {
+                value: 1.0,
+                name: String::new(),
+            }
+        );
+    }
 }
@@ -20,10 +20,18 @@ pub(crate) mod component;
 pub(crate) mod message;
 
 pub(crate) mod delta;
+
+/// Deterministic fingerprint of the protocol, checked during the connection handshake
+pub mod fingerprint;
 /// Provides a mapping from a type to a unique identifier that can be serialized
 pub(crate) mod registry;
+/// Machine-readable description of the protocol
+pub mod schema;
+pub use registry::{NetIdCollision, NetIdMode};
 pub(crate) mod serialize;
 pub use serialize::SerializeFns;
+/// Versions of the messages and components that have legacy versions
+pub mod version;
 
 /// Data that can be used in an Event
 /// Same as `Event`, but we implement it automatically for all compatible types
@@ -1,6 +1,7 @@
 use crate::serialize::reader::Reader;
 use crate::serialize::varint::{varint_len, VarIntReadExt, VarIntWriteExt};
 use crate::serialize::{SerializationError, ToBytes};
+use bevy::reflect::Reflect;
 use bevy::utils::HashMap;
 use byteorder::WriteBytesExt;
 use std::any::TypeId;
@@ -29,12 +30,44 @@ impl ToBytes for NetId {
 
 pub trait TypeKind: From<TypeId> + Copy + PartialEq + Eq + Hash {}
 
+/// How the [`NetId`] of a registered type is chosen
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+pub enum NetIdMode {
+    /// Net ids are assigned from a counter, in registration order.
+    ///
+    /// The types must be registered in the same order on the client and the server.
+    #[default]
+    Sequential,
+    /// Net ids are computed from a hash of the type name, so they don't depend on the registration order.
+    ///
+    /// Collisions are detected when the protocol is checked; a type can be given an explicit net id
+    /// with `with_net_id` to resolve them.
+    /// Note that type names are only stable between builds using the same compiler version.
+    Hash,
+}
+
+/// Error returned when a type is assigned a [`NetId`] that is already used by another type
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("the types {existing:?} and {type_name:?} have the same net id {net_id}. Use `with_net_id` to assign a different net id to one of them")]
+pub struct NetIdCollision {
+    pub net_id: u16,
+    /// The type that already uses the net id
+    pub existing: String,
+    /// The type that could not be assigned the net id
+    pub type_name: String,
+}
+
 /// Struct to map a type to an id that can be serialized over the network
 #[derive(Clone, Debug, PartialEq)]
 pub struct TypeMapper<K: TypeKind> {
+    pub(crate) mode: NetIdMode,
     pub(crate) next_net_id: NetId,
     pub(crate) kind_map: HashMap<K, NetId>,
     pub(crate) id_map: HashMap<NetId, K>,
+    /// Types that could not be assigned their net id because it was already used by another type
+    collisions: Vec<(NetId, K)>,
+    /// Name of each registered type, used to report collisions
+    names: HashMap<K, String>,
 }
 
 impl<K: TypeKind> Default for TypeMapper<K> {
@@ -46,25 +79,123 @@ impl<K: TypeKind> Default for TypeMapper<K> {
 impl<K: TypeKind> TypeMapper<K> {
     pub fn new() -> Self {
         Self {
+            mode: NetIdMode::default(),
             next_net_id: 0,
             kind_map: HashMap::new(),
             id_map: HashMap::new(),
+            collisions: Vec::new(),
+            names: HashMap::new(),
         }
     }
 
-    /// Register a new type
+    /// Register a new type.
+    ///
+    /// If its net id is already used by another type, the collision is recorded and
+    /// reported by [`collisions`](Self::collisions), so that it can still be resolved with an explicit net id.
     pub fn add<T: 'static>(&mut self) -> K {
-        let kind = K::from(TypeId::of::<T>());
+        self.add_erased(TypeId::of::<T>(), std::any::type_name::<T>())
+    }
+
+    /// Register a new type from its [`TypeId`] and name, for types that are not known at compile time
+    pub(crate) fn add_erased(&mut self, type_id: TypeId, type_name: &str) -> K {
+        let kind = K::from(type_id);
         if self.kind_map.contains_key(&kind) {
-            panic!("Type {:?} already registered", std::any::type_name::<T>());
+            panic!("Type {:?} already registered", type_name);
         }
-        let net_id = self.next_net_id;
-        self.kind_map.insert(kind, net_id);
-        self.id_map.insert(net_id, kind);
-        self.next_net_id += 1;
+        let net_id = match self.mode {
+            NetIdMode::Sequential => {
+                // skip the ids that were explicitly assigned to other types
+                while self.id_map.contains_key(&self.next_net_id) {
+                    self.next_net_id += 1;
+                }
+                let net_id = self.next_net_id;
+                self.next_net_id += 1;
+                net_id
+            }
+            NetIdMode::Hash => hash_net_id(type_name),
+        };
+        self.names.insert(kind, type_name.to_string());
+        self.insert(kind, net_id);
         kind
     }
 
+    /// Register a new type from its [`TypeId`] and name, failing right away if its net id is already used.
+    ///
+    /// Used for the types registered after the protocol was checked.
+    pub(crate) fn try_add_erased(
+        &mut self,
+        type_id: TypeId,
+        type_name: &str,
+    ) -> Result<K, NetIdCollision> {
+        let kind = self.add_erased(type_id, type_name);
+        if let Some(collision) = self
+            .collisions()
+            .into_iter()
+            .find(|c| c.type_name == type_name)
+        {
+            self.kind_map.remove(&kind);
+            self.names.remove(&kind);
+            self.collisions.retain(|(_, k)| *k != kind);
+            return Err(collision);
+        }
+        Ok(kind)
+    }
+
+    /// Assign an explicit net id to a type that was already registered
+    pub(crate) fn set_net_id(&mut self, kind: K, net_id: NetId) {
+        if let Some(previous) = self.kind_map.remove(&kind) {
+            if self.id_map.get(&previous) == Some(&kind) {
+                self.id_map.remove(&previous);
+                // a type that collided with this one can now use the net id
+                if let Some(index) = self.collisions.iter().position(|(id, _)| *id == previous) {
+                    let (_, other) = self.collisions.remove(index);
+                    self.id_map.insert(previous, other);
+                }
+            }
+        }
+        self.collisions.retain(|(_, k)| *k != kind);
+        self.insert(kind, net_id);
+    }
+
+    fn insert(&mut self, kind: K, net_id: NetId) {
+        self.kind_map.insert(kind, net_id);
+        if self.id_map.contains_key(&net_id) {
+            self.collisions.push((net_id, kind));
+        } else {
+            self.id_map.insert(net_id, kind);
+        }
+    }
+
+    /// In [`NetIdMode::Hash`], compute the net id of a type from another name, unless
+    /// the net id was assigned explicitly
+    pub(crate) fn rename(&mut self, kind: K, previous_name: &str, name: &str) {
+        if self.mode == NetIdMode::Hash
+            && self.kind_map.get(&kind) == Some(&hash_net_id(previous_name))
+        {
+            self.set_net_id(kind, hash_net_id(name));
+        }
+    }
+
+    /// The types that could not be assigned their net id because another type already uses it
+    pub(crate) fn collisions(&self) -> Vec<NetIdCollision> {
+        let name = |kind: &K| self.names.get(kind).cloned().unwrap_or_default();
+        self.collisions
+            .iter()
+            .map(|(net_id, kind)| NetIdCollision {
+                net_id: *net_id,
+                existing: self.id_map.get(net_id).map(name).unwrap_or_default(),
+                type_name: name(kind),
+            })
+            .collect()
+    }
+
+    /// All the net ids that are assigned, in increasing order
+    pub(crate) fn net_ids(&self) -> Vec<NetId> {
+        let mut net_ids: Vec<NetId> = self.id_map.keys().copied().collect();
+        net_ids.sort_unstable();
+        net_ids
+    }
+
     pub fn kind(&self, net_id: NetId) -> Option<&K> {
         self.id_map.get(&net_id)
     }
@@ -78,3 +209,108 @@ impl<K: TypeKind> TypeMapper<K> {
         self.kind_map.len()
     }
 }
+
+fn hash_net_id(type_name: &str) -> NetId {
+    seahash::hash(type_name.as_bytes()) as NetId
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::message::MessageKind;
+
+    struct A;
+    struct B;
+    struct C;
+
+    #[test]
+    fn test_sequential_skips_explicit_ids() {
+        let mut mapper = TypeMapper::<MessageKind>::new();
+        let a = mapper.add::<A>();
+        let b = mapper.add::<B>();
+        mapper.set_net_id(b, 1);
+        // B keeps its net id, so C gets the next free one
+        let c = mapper.add::<C>();
+        mapper.set_net_id(a, 10);
+        assert_eq!(mapper.net_id(&a), Some(&10));
+        assert_eq!(mapper.net_id(&c), Some(&2));
+        assert_eq!(mapper.kind(0), None);
+        assert_eq!(mapper.net_ids(), vec![1, 2, 10]);
+        assert!(mapper.collisions().is_empty());
+    }
+
+    #[test]
+    fn test_hash_mode_independent_of_order() {
+        let mut first = TypeMapper::<MessageKind>::new();
+        first.mode = NetIdMode::Hash;
+        let a = first.add::<A>();
+        let b = first.add::<B>();
+        let mut second = TypeMapper::<MessageKind>::new();
+        second.mode = NetIdMode::Hash;
+        second.add::<B>();
+        second.add::<A>();
+        assert_eq!(first.net_id(&a), second.net_id(&a));
+        assert_eq!(first.net_id(&b), second.net_id(&b));
+    }
+
+    #[test]
+    fn test_collision() {
+        let mut mapper = TypeMapper::<MessageKind>::new();
+        let a = mapper.add::<A>();
+        let b = mapper.add::<B>();
+        mapper.set_net_id(b, 0);
+        assert_eq!(
+            mapper.collisions(),
+            vec![NetIdCollision {
+                net_id: 0,
+                existing: std::any::type_name::<A>().to_string(),
+                type_name: std::any::type_name::<B>().to_string(),
+            }]
+        );
+        // the net id stays assigned to the first type
+        assert_eq!(mapper.kind(0), Some(&a));
+        assert_eq!(mapper.kind(1), None);
+        // resolving the collision
+        mapper.set_net_id(b, 5);
+        assert!(mapper.collisions().is_empty());
+        assert_eq!(mapper.kind(5), Some(&b));
+    }
+
+    #[test]
+    fn test_hash_collision_on_registration() {
+        let mut mapper = TypeMapper::<MessageKind>::new();
+        let a = mapper.add::<A>();
+        mapper.mode = NetIdMode::Hash;
+        // B's hashed net id is already used
+        let b_net_id = hash_net_id(std::any::type_name::<B>());
+        mapper.set_net_id(a, b_net_id);
+        // the collision is recorded instead of failing the registration
+        let b = mapper.add::<B>();
+        let collisions = mapper.collisions();
+        assert_eq!(collisions.len(), 1);
+        assert!(collisions[0]
+            .to_string()
+            .contains(std::any::type_name::<A>()));
+        assert!(collisions[0]
+            .to_string()
+            .contains(std::any::type_name::<B>()));
+        // giving the other type an explicit net id resolves it
+        mapper.set_net_id(a, 0);
+        assert!(mapper.collisions().is_empty());
+        assert_eq!(mapper.kind(b_net_id), Some(&b));
+        assert_eq!(mapper.kind(0), Some(&a));
+    }
+
+    #[test]
+    fn test_try_add_collision() {
+        let mut mapper = TypeMapper::<MessageKind>::new();
+        let a = mapper.add::<A>();
+        mapper.mode = NetIdMode::Hash;
+        mapper.set_net_id(a, hash_net_id(std::any::type_name::<B>()));
+        assert!(mapper
+            .try_add_erased(TypeId::of::<B>(), std::any::type_name::<B>())
+            .is_err());
+        assert_eq!(mapper.net_id(&MessageKind::of::<B>()), None);
+        assert!(mapper.collisions().is_empty());
+    }
+}
@@ -0,0 +1,354 @@
+//! Export the protocol as a machine-readable schema, so that tools or clients that are not written
+//! in Rust can talk to a lightyear peer, and so that protocols can be compared between releases.
+//!
+//! ```rust,ignore
+//! let schema = ProtocolSchema::from_world(app.world());
+//! std::fs::write("protocol.json", schema.to_json()?)?;
+//! ```
+//!
+//! Types are serialized with bincode (unless a custom serialization was registered). When the type is
+//! registered in the [`AppTypeRegistry`], the schema contains its field layout.
+use std::any::TypeId;
+
+use bevy::prelude::{AppTypeRegistry, World};
+use bevy::reflect::{TypeInfo, TypeRegistry, VariantInfo};
+use serde::Serialize;
+
+use crate::protocol::channel::ChannelRegistry;
+use crate::protocol::component::ComponentRegistry;
+use crate::protocol::fingerprint::mode_name;
+use crate::protocol::message::MessageRegistry;
+use crate::protocol::registry::NetId;
+
+/// Description of every type registered in the [`ChannelRegistry`], [`MessageRegistry`]
+/// and [`ComponentRegistry`], ordered by net id.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProtocolSchema {
+    pub channels: Vec<ChannelSchema>,
+    pub messages: Vec<MessageSchema>,
+    /// The input messages registered by the input plugins
+    pub inputs: Vec<MessageSchema>,
+    pub components: Vec<ComponentSchema>,
+}
+
+/// Description of a registered channel
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChannelSchema {
+    pub net_id: NetId,
+    pub type_path: String,
+    pub mode: String,
+    /// How often messages are sent on this channel, in milliseconds (0 means every frame)
+    pub send_frequency_ms: u128,
+    pub priority: f32,
+}
+
+/// Description of a registered message
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MessageSchema {
+    pub net_id: NetId,
+    pub type_path: String,
+    /// `None` for internal messages
+    pub direction: Option<String>,
+    pub message_type: String,
+    /// Type paths of the legacy versions of the message, from the oldest to the most recent
+    pub legacy_versions: Vec<String>,
+    pub layout: Option<TypeLayout>,
+}
+
+/// Description of a registered component
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ComponentSchema {
+    pub net_id: NetId,
+    pub type_path: String,
+    /// `None` for internal components
+    pub direction: Option<String>,
+    pub prediction: Option<String>,
+    pub interpolation: Option<String>,
+    pub delta_compression: bool,
+    /// Type paths of the legacy versions of the component, from the oldest to the most recent
+    pub legacy_versions: Vec<String>,
+    pub layout: Option<TypeLayout>,
+}
+
+/// Field layout of a type, taken from its `bevy_reflect` type info
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TypeLayout {
+    Struct {
+        fields: Vec<FieldLayout>,
+    },
+    TupleStruct {
+        fields: Vec<String>,
+    },
+    Tuple {
+        fields: Vec<String>,
+    },
+    List {
+        item: String,
+    },
+    Array {
+        item: String,
+        capacity: usize,
+    },
+    Map {
+        key: String,
+        value: String,
+    },
+    Set {
+        value: String,
+    },
+    Enum {
+        variants: Vec<VariantLayout>,
+    },
+    /// The type doesn't expose its fields
+    Opaque,
+}
+
+/// Named field of a struct
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldLayout {
+    pub name: String,
+    pub type_path: String,
+}
+
+/// Variant of an enum, in declaration order
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum VariantLayout {
+    Unit {
+        name: String,
+    },
+    Tuple {
+        name: String,
+        fields: Vec<String>,
+    },
+    Struct {
+        name: String,
+        fields: Vec<FieldLayout>,
+    },
+}
+
+impl TypeLayout {
+    /// Layout of the type, if it is registered in the [`TypeRegistry`]
+    pub(crate) fn new(type_registry: &TypeRegistry, type_id: TypeId) -> Option<Self> {
+        let layout = match type_registry.get_type_info(type_id)? {
+            TypeInfo::Struct(info) => TypeLayout::Struct {
+                fields: info
+                    .iter()
+                    .map(|field| FieldLayout {
+                        name: field.name().to_string(),
+                        type_path: field.type_path().to_string(),
+                    })
+                    .collect(),
+            },
+            TypeInfo::TupleStruct(info) => TypeLayout::TupleStruct {
+                fields: info
+                    .iter()
+                    .map(|field| field.type_path().to_string())
+                    .collect(),
+            },
+            TypeInfo::Tuple(info) => TypeLayout::Tuple {
+                fields: info
+                    .iter()
+                    .map(|field| field.type_path().to_string())
+                    .collect(),
+            },
+            TypeInfo::List(info) => TypeLayout::List {
+                item: info.item_ty().path().to_string(),
+            },
+            TypeInfo::Array(info) => TypeLayout::Array {
+                item: info.item_ty().path().to_string(),
+                capacity: info.capacity(),
+            },
+            TypeInfo::Map(info) => TypeLayout::Map {
+                key: info.key_ty().path().to_string(),
+                value: info.value_ty().path().to_string(),
+            },
+            TypeInfo::Set(info) => TypeLayout::Set {
+                value: info.value_ty().path().to_string(),
+            },
+            TypeInfo::Enum(info) => TypeLayout::Enum {
+                variants: info
+                    .iter()
+                    .map(|variant| match variant {
+                        VariantInfo::Unit(variant) => VariantLayout::Unit {
+                            name: variant.name().to_string(),
+                        },
+                        VariantInfo::Tuple(variant) => VariantLayout::Tuple {
+                            name: variant.name().to_string(),
+                            fields: variant
+                                .iter()
+                                .map(|field| field.type_path().to_string())
+                                .collect(),
+                        },
+                        VariantInfo::Struct(variant) => VariantLayout::Struct {
+                            name: variant.name().to_string(),
+                            fields: variant
+                                .iter()
+                                .map(|field| FieldLayout {
+                                    name: field.name().to_string(),
+                                    type_path: field.type_path().to_string(),
+                                })
+                                .collect(),
+                        },
+                    })
+                    .collect(),
+            },
+            TypeInfo::Opaque(_) => TypeLayout::Opaque,
+        };
+        Some(layout)
+    }
+}
+
+impl ProtocolSchema {
+    /// Describe the protocol. The field layouts are only available for the types registered in
+    /// the `type_registry`
+    pub fn new(
+        component_registry: &ComponentRegistry,
+        message_registry: &MessageRegistry,
+        channel_registry: &ChannelRegistry,
+        type_registry: Option<&TypeRegistry>,
+    ) -> Self {
+        let channels = channel_registry
+            .kind_map
+            .net_ids()
+            .into_iter()
+            .filter_map(|net_id| {
+                let kind = channel_registry.kind_map.kind(net_id)?;
+                let settings = &channel_registry.get_builder_from_kind(kind)?.settings;
+                Some(ChannelSchema {
+                    net_id,
+                    type_path: channel_registry.name(kind).unwrap_or_default().to_string(),
+                    mode: mode_name(&settings.mode).to_string(),
+                    send_frequency_ms: settings.send_frequency.as_millis(),
+                    priority: settings.priority,
+                })
+            })
+            .collect();
+        let (inputs, messages) = message_registry
+            .kind_map
+            .net_ids()
+            .into_iter()
+            .filter_map(|net_id| message_registry.schema(net_id, type_registry))
+            .partition(|message| message.message_type != "Normal");
+        let components = component_registry
+            .kind_map
+            .net_ids()
+            .into_iter()
+            .filter_map(|net_id| component_registry.schema(net_id, type_registry))
+            .collect();
+        Self {
+            channels,
+            messages,
+            inputs,
+            components,
+        }
+    }
+
+    /// Describe the protocol of the registries stored in the [`World`], using the
+    /// [`AppTypeRegistry`] for the field layouts
+    pub fn from_world(world: &World) -> Self {
+        let type_registry = world.get_resource::<AppTypeRegistry>().map(|r| r.read());
+        Self::new(
+            world.resource::<ComponentRegistry>(),
+            world.resource::<MessageRegistry>(),
+            world.resource::<ChannelRegistry>(),
+            type_registry.as_deref(),
+        )
+    }
+
+    /// Serialize the schema as pretty-printed JSON
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::ChannelDirection;
+    use crate::protocol::message::MessageType;
+    use crate::tests::protocol::*;
+    use bevy::reflect::Reflect;
+    use bevy::utils::Duration;
+
+    #[derive(Reflect)]
+    enum Shape {
+        Empty,
+        Circle(f32),
+        Rect { width: f32, height: f32 },
+    }
+
+    #[test]
+    fn test_schema() {
+        let mut message_registry = MessageRegistry::default();
+        message_registry.add_message::<StringMessage>(MessageType::Normal);
+        message_registry.set_direction::<StringMessage>(ChannelDirection::Bidirectional);
+        let mut component_registry = ComponentRegistry::default();
+        component_registry.register_component::<ComponentSyncModeFull>();
+        component_registry.set_direction::<ComponentSyncModeFull>(ChannelDirection::ServerToClient);
+        component_registry.set_prediction_mode::<ComponentSyncModeFull>(
+            crate::client::components::ComponentSyncMode::Full,
+        );
+        let channel_registry = ChannelRegistry::new(Duration::default());
+        let mut type_registry = TypeRegistry::default();
+        type_registry.register::<Shape>();
+
+        let schema = ProtocolSchema::new(
+            &component_registry,
+            &message_registry,
+            &channel_registry,
+            Some(&type_registry),
+        );
+        assert!(schema.inputs.is_empty());
+        assert_eq!(
+            schema.messages,
+            vec![MessageSchema {
+                net_id: 0,
+                type_path: std::any::type_name::<StringMessage>().to_string(),
+                direction: Some("Bidirectional".to_string()),
+                message_type: "Normal".to_string(),
+                legacy_versions: vec![],
+                layout: None,
+            }]
+        );
+        let component = &schema.components[0];
+        assert_eq!(component.direction, Some("ServerToClient".to_string()));
+        assert_eq!(component.prediction, Some("Full".to_string()));
+        assert_eq!(component.interpolation, None);
+        assert!(!component.delta_compression);
+        assert!(schema
+            .channels
+            .iter()
+            .any(|channel| channel.mode == "UnorderedUnreliableWithAcks"));
+
+        assert_eq!(
+            TypeLayout::new(&type_registry, TypeId::of::<Shape>()),
+            Some(TypeLayout::Enum {
+                variants: vec![
+                    VariantLayout::Unit {
+                        name: "Empty".to_string()
+                    },
+                    VariantLayout::Tuple {
+                        name: "Circle".to_string(),
+                        fields: vec!["f32".to_string()]
+                    },
+                    VariantLayout::Struct {
+                        name: "Rect".to_string(),
+                        fields: vec![
+                            FieldLayout {
+                                name: "width".to_string(),
+                                type_path: "f32".to_string()
+                            },
+                            FieldLayout {
+                                name: "height".to_string(),
+                                type_path: "f32".to_string()
+                            },
+                        ]
+                    },
+                ]
+            })
+        );
+    }
+}
@@ -1,12 +1,16 @@
 use crate::prelude::{ComponentRegistry, Message, MessageRegistry};
+use crate::protocol::version::Version;
+use crate::serialize::bits::{BitReader, BitSerialize, BitWriter};
 use crate::serialize::{reader::Reader, writer::Writer, SerializationError};
 use crate::shared::replication::entity_map::{EntityMap, ReceiveEntityMap, SendEntityMap};
 use bevy::app::App;
 use bevy::ecs::entity::MapEntities;
 use bevy::ptr::{Ptr, PtrMut};
+use bevy::reflect::{Reflect, ReflectDeserialize, ReflectFromPtr, ReflectSerialize};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::any::TypeId;
+use std::fmt::{Debug, Formatter};
 
 /// Stores function pointers related to serialization and deserialization
 #[derive(Clone, Debug, PartialEq)]
@@ -14,13 +18,63 @@ pub struct ErasedSerializeFns {
     pub(crate) type_id: TypeId,
     pub(crate) type_name: &'static str,
     // TODO: maybe use `Vec<MaybeUninit<u8>>` instead of unsafe fn(), like bevy?
-    pub serialize: unsafe fn(),
+    /// Typed serialize function; None for the types registered through reflection
+    pub serialize: Option<unsafe fn()>,
     pub erased_serialize: ErasedSerializeFn,
-    pub deserialize: unsafe fn(),
+    /// Typed deserialize function; None for the types registered through reflection
+    pub deserialize: Option<unsafe fn()>,
     pub erased_clone: Option<unsafe fn()>,
     pub map_entities: Option<ErasedMapEntitiesFn>,
     pub send_map_entities: Option<ErasedSendMapEntitiesFn>,
     pub receive_map_entities: Option<ErasedReceiveMapEntitiesFn>,
+    /// Legacy versions of the type, from the oldest to the most recent
+    pub(crate) legacy: Vec<ErasedLegacyFns>,
+    /// Reflection data used to serialize types that are only known at runtime
+    pub(crate) reflect: Option<ReflectSerializeFns>,
+}
+
+/// Reflection data of a type registered at runtime from the `TypeRegistry`.
+///
+/// The type is serialized with bincode through its [`ReflectSerialize`] and [`ReflectDeserialize`]
+/// implementations, so both peers need to register the same type.
+#[derive(Clone)]
+pub(crate) struct ReflectSerializeFns {
+    pub(crate) from_ptr: ReflectFromPtr,
+    pub(crate) serialize: ReflectSerialize,
+    pub(crate) deserialize: ReflectDeserialize,
+}
+
+impl Debug for ReflectSerializeFns {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReflectSerializeFns")
+            .field("type_id", &self.from_ptr.type_id())
+            .finish()
+    }
+}
+
+impl PartialEq for ReflectSerializeFns {
+    fn eq(&self, other: &Self) -> bool {
+        self.from_ptr.type_id() == other.from_ptr.type_id()
+    }
+}
+
+/// Stores function pointers used to read and write a legacy version of a type,
+/// registered with `with_legacy`
+#[derive(Clone, Debug)]
+pub struct ErasedLegacyFns {
+    /// [`TypeId`] of the legacy type, used to compare the legacy versions
+    type_id: TypeId,
+    pub(crate) type_name: &'static str,
+    read: unsafe fn(),
+    write: unsafe fn(),
+    migrate: unsafe fn(),
+    transcode: ErasedTranscodeFn,
+}
+
+impl PartialEq for ErasedLegacyFns {
+    fn eq(&self, other: &Self) -> bool {
+        self.type_id == other.type_id
+    }
 }
 
 /// Controls how a type (resources/components/messages) is serialized and deserialized
@@ -36,6 +90,17 @@ pub struct SerializeFns<M> {
     pub serialize_map_entities: Option<SerializeMapEntitiesFn<M>>,
 }
 
+impl<M: Message + BitSerialize> SerializeFns<M> {
+    /// Serialize the type at the bit level using its [`BitSerialize`] implementation
+    pub fn bit_packed() -> Self {
+        Self {
+            serialize: bit_serialize::<M>,
+            deserialize: bit_deserialize::<M>,
+            serialize_map_entities: None,
+        }
+    }
+}
+
 type ErasedSerializeFn = unsafe fn(
     erased_serialize_fn: &ErasedSerializeFns,
     message: Ptr,
@@ -50,6 +115,20 @@ type DeserializeFn<M> = fn(reader: &mut Reader) -> Result<M, SerializationError>
 
 type CloneFn<M> = fn(&M) -> M;
 
+/// Deserialize the legacy version and migrate it to the current version with the (erased) migrate function
+type LegacyReadFn<M> =
+    fn(reader: &mut Reader, migrate: unsafe fn()) -> Result<M, SerializationError>;
+/// Convert the current version to the legacy version and serialize it
+type LegacyWriteFn<M> = fn(message: &M, writer: &mut Writer) -> Result<(), SerializationError>;
+
+pub(crate) type ErasedTranscodeFn = unsafe fn(
+    erased_serialize_fn: &ErasedSerializeFns,
+    reader: &mut Reader,
+    writer: &mut Writer,
+    from: Version,
+    to: Version,
+) -> Result<(), SerializationError>;
+
 type SerializeMapEntitiesFn<M> = fn(
     &M,
     &mut Writer,
@@ -75,7 +154,7 @@ unsafe fn erased_serialize_fn<M: Message>(
     writer: &mut Writer,
     entity_map: Option<&mut SendEntityMap>,
 ) -> Result<(), SerializationError> {
-    let typed_serialize_fns = erased_serialize_fn.typed::<M>();
+    let typed_serialize_fns = erased_serialize_fn.typed::<M>()?;
     if let Some(map_entities) = erased_serialize_fn.send_map_entities {
         let serialize_map_entities = typed_serialize_fns.serialize_map_entities.unwrap();
         serialize_map_entities(
@@ -93,6 +172,39 @@ unsafe fn erased_serialize_fn<M: Message>(
     }
 }
 
+/// Serialize a type registered through reflection, using its [`ReflectSerialize`] implementation
+///
+/// SAFETY: the Ptr must point to a value of the type that the ErasedSerializeFns were created for
+unsafe fn reflect_serialize_fn(
+    erased_serialize_fn: &ErasedSerializeFns,
+    message: Ptr,
+    writer: &mut Writer,
+    _entity_map: Option<&mut SendEntityMap>,
+) -> Result<(), SerializationError> {
+    let reflect = erased_serialize_fn
+        .reflect
+        .as_ref()
+        .expect("the type was not registered through reflection");
+    let serializable = reflect
+        .serialize
+        .get_serializable(reflect.from_ptr.as_reflect(message));
+    let _ =
+        bincode::serde::encode_into_std_write(&*serializable, writer, bincode::config::standard())?;
+    Ok(())
+}
+
+/// Adapter to read from a [`Reader`] with a bincode decoder
+struct BincodeReader<'a>(&'a mut Reader);
+
+impl bincode::de::read::Reader for BincodeReader<'_> {
+    fn read(&mut self, bytes: &mut [u8]) -> Result<(), bincode::error::DecodeError> {
+        std::io::Read::read_exact(self.0, bytes).map_err(|inner| bincode::error::DecodeError::Io {
+            inner,
+            additional: bytes.len(),
+        })
+    }
+}
+
 /// Default serialize function using bincode
 fn default_serialize<M: Message + Serialize>(
     message: &M,
@@ -110,6 +222,54 @@ fn default_deserialize<M: Message + DeserializeOwned>(
     Ok(data)
 }
 
+/// Serialize function using the [`BitSerialize`] implementation
+fn bit_serialize<M: Message + BitSerialize>(
+    message: &M,
+    buffer: &mut Writer,
+) -> Result<(), SerializationError> {
+    let mut writer = BitWriter::new(buffer);
+    message.bit_serialize(&mut writer)?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Deserialize function using the [`BitSerialize`] implementation
+fn bit_deserialize<M: Message + BitSerialize>(
+    buffer: &mut Reader,
+) -> Result<M, SerializationError> {
+    M::bit_deserialize(&mut BitReader::new(buffer))
+}
+
+/// Read a legacy version `V` of the type and migrate it to the current version `M`
+fn legacy_read<M, V: Message + DeserializeOwned>(
+    reader: &mut Reader,
+    migrate: unsafe fn(),
+) -> Result<M, SerializationError> {
+    // SAFETY: the migrate function was created for the types V and M
+    let migrate: fn(V) -> M = unsafe { std::mem::transmute(migrate) };
+    Ok(migrate(default_deserialize::<V>(reader)?))
+}
+
+/// Convert the current version `M` of the type to the legacy version `V` and serialize it
+fn legacy_write<M, V: Message + Serialize + for<'a> From<&'a M>>(
+    message: &M,
+    writer: &mut Writer,
+) -> Result<(), SerializationError> {
+    default_serialize::<V>(&V::from(message), writer)
+}
+
+/// SAFETY: the ErasedSerializeFns must be created for the type M
+unsafe fn transcode<M: 'static>(
+    erased_serialize_fn: &ErasedSerializeFns,
+    reader: &mut Reader,
+    writer: &mut Writer,
+    from: Version,
+    to: Version,
+) -> Result<(), SerializationError> {
+    let message = erased_serialize_fn.read_version::<M>(reader, from)?;
+    erased_serialize_fn.write_version::<M>(&message, writer, to)
+}
+
 pub(crate) fn serialize_map_entities<M>(
     message: &M,
     writer: &mut Writer,
@@ -167,12 +327,14 @@ impl ErasedSerializeFns {
             type_id: TypeId::of::<M>(),
             type_name: std::any::type_name::<M>(),
             erased_serialize: erased_serialize_fn::<M>,
-            serialize: unsafe { std::mem::transmute(serialize_fns.serialize) },
-            deserialize: unsafe { std::mem::transmute(serialize_fns.deserialize) },
+            serialize: Some(unsafe { std::mem::transmute(serialize_fns.serialize) }),
+            deserialize: Some(unsafe { std::mem::transmute(serialize_fns.deserialize) }),
             erased_clone: None,
             map_entities: None,
             send_map_entities: None,
             receive_map_entities: None,
+            legacy: Vec::new(),
+            reflect: None,
         }
     }
 
@@ -181,16 +343,61 @@ impl ErasedSerializeFns {
             type_id: TypeId::of::<M>(),
             type_name: std::any::type_name::<M>(),
             erased_serialize: erased_serialize_fn::<M>,
-            serialize: unsafe { std::mem::transmute(serialize_fns.serialize) },
-            deserialize: unsafe { std::mem::transmute(serialize_fns.deserialize) },
+            serialize: Some(unsafe { std::mem::transmute(serialize_fns.serialize) }),
+            deserialize: Some(unsafe { std::mem::transmute(serialize_fns.deserialize) }),
             erased_clone: None,
             map_entities: None,
             send_map_entities: None,
             receive_map_entities: None,
+            legacy: Vec::new(),
+            reflect: None,
         }
     }
 
-    pub(crate) unsafe fn typed<M: 'static>(&self) -> SerializeFns<M> {
+    /// Create the serialization functions of a type that is only known at runtime, from its reflection data
+    pub(crate) fn new_reflect(
+        type_id: TypeId,
+        type_name: &'static str,
+        reflect: ReflectSerializeFns,
+    ) -> Self {
+        Self {
+            type_id,
+            type_name,
+            erased_serialize: reflect_serialize_fn,
+            // the type is only ever (de)serialized through the erased functions
+            serialize: None,
+            deserialize: None,
+            erased_clone: None,
+            map_entities: None,
+            send_map_entities: None,
+            receive_map_entities: None,
+            legacy: Vec::new(),
+            reflect: Some(reflect),
+        }
+    }
+
+    /// Deserialize a value of a type that was registered through reflection
+    pub(crate) fn reflect_deserialize(
+        &self,
+        reader: &mut Reader,
+    ) -> Result<Box<dyn Reflect>, SerializationError> {
+        let reflect = self
+            .reflect
+            .as_ref()
+            .expect("the type was not registered through reflection");
+        let mut decoder = bincode::serde::OwnedSerdeDecoder::from_reader(
+            BincodeReader(reader),
+            bincode::config::standard(),
+        );
+        Ok(reflect.deserialize.deserialize(decoder.as_deserializer())?)
+    }
+
+    /// Get the typed serialization functions.
+    ///
+    /// Returns an error for the types registered through reflection, which don't have any.
+    ///
+    /// SAFETY: the ErasedSerializeFns must be created for the type M
+    pub(crate) unsafe fn typed<M: 'static>(&self) -> Result<SerializeFns<M>, SerializationError> {
         debug_assert_eq!(
             self.type_id,
             TypeId::of::<M>(),
@@ -206,11 +413,14 @@ impl ErasedSerializeFns {
             ErasedSendMapEntitiesFn,
             SerializeFn<M>,
         ) -> Result<(), SerializationError> = serialize_map_entities::<M>;
-        SerializeFns {
-            serialize: unsafe { std::mem::transmute(self.serialize) },
-            deserialize: unsafe { std::mem::transmute(self.deserialize) },
+        let (Some(serialize), Some(deserialize)) = (self.serialize, self.deserialize) else {
+            return Err(SerializationError::NotTyped(self.type_name));
+        };
+        Ok(SerializeFns {
+            serialize: unsafe { std::mem::transmute(serialize) },
+            deserialize: unsafe { std::mem::transmute(deserialize) },
             serialize_map_entities: Some(unsafe { std::mem::transmute(serialize_map_entities) }),
-        }
+        })
     }
 
     // We need to be able to clone the data, because when serialize we:
@@ -228,6 +438,98 @@ impl ErasedSerializeFns {
         self.erased_clone = Some(unsafe { std::mem::transmute(clone_fn) });
     }
 
+    /// Add a legacy version `V` of the type `M`, older than all the legacy versions added so far
+    pub(crate) fn add_legacy<
+        M: Message,
+        V: Message + Serialize + DeserializeOwned + for<'a> From<&'a M>,
+    >(
+        &mut self,
+        migrate: fn(V) -> M,
+    ) {
+        let read: LegacyReadFn<M> = legacy_read::<M, V>;
+        let write: LegacyWriteFn<M> = legacy_write::<M, V>;
+        self.legacy.insert(
+            0,
+            ErasedLegacyFns {
+                type_id: TypeId::of::<V>(),
+                type_name: std::any::type_name::<V>(),
+                read: unsafe { std::mem::transmute(read) },
+                write: unsafe { std::mem::transmute(write) },
+                migrate: unsafe { std::mem::transmute(migrate) },
+                transcode: transcode::<M>,
+            },
+        );
+    }
+
+    /// The current version of the type: every legacy version is a previous version
+    pub(crate) fn current_version(&self) -> Version {
+        self.legacy.len() as Version
+    }
+
+    /// Name of the oldest version of the type.
+    ///
+    /// This is the name used to describe the type in the protocol, so that peers built
+    /// with different versions of the type still have the same protocol.
+    pub(crate) fn root_type_name(&self) -> &'static str {
+        self.legacy
+            .first()
+            .map_or(self.type_name, |legacy| legacy.type_name)
+    }
+
+    /// Deserialize a value that was serialized in the given version, and migrate it to the current version
+    ///
+    /// SAFETY: the ErasedSerializeFns must be created for the type M
+    unsafe fn read_version<M: 'static>(
+        &self,
+        reader: &mut Reader,
+        version: Version,
+    ) -> Result<M, SerializationError> {
+        match self.legacy.get(version as usize) {
+            Some(legacy) => {
+                let read: LegacyReadFn<M> = std::mem::transmute(legacy.read);
+                read(reader, legacy.migrate)
+            }
+            None => (self.typed::<M>()?.deserialize)(reader),
+        }
+    }
+
+    /// Serialize a value in the given version
+    ///
+    /// SAFETY: the ErasedSerializeFns must be created for the type M
+    unsafe fn write_version<M: 'static>(
+        &self,
+        message: &M,
+        writer: &mut Writer,
+        version: Version,
+    ) -> Result<(), SerializationError> {
+        match self.legacy.get(version as usize) {
+            Some(legacy) => {
+                let write: LegacyWriteFn<M> = std::mem::transmute(legacy.write);
+                write(message, writer)
+            }
+            None => (self.typed::<M>()?.serialize)(message, writer),
+        }
+    }
+
+    /// Convert a serialized value from one version to another.
+    ///
+    /// No entity mapping is done: the entities are kept as they were serialized.
+    pub(crate) fn transcode(
+        &self,
+        reader: &mut Reader,
+        writer: &mut Writer,
+        from: Version,
+        to: Version,
+    ) -> Result<(), SerializationError> {
+        let transcode = self
+            .legacy
+            .first()
+            .expect("the type does not have any legacy versions")
+            .transcode;
+        // SAFETY: the transcode function was created for the type of these ErasedSerializeFns
+        unsafe { transcode(self, reader, writer, from, to) }
+    }
+
     pub(crate) fn map_entities<M: 'static>(&self, message: &mut M, entity_map: &mut EntityMap) {
         let ptr = PtrMut::from(message);
         if let Some(map_entities_fn) = self.map_entities {
@@ -247,7 +549,7 @@ impl ErasedSerializeFns {
         writer: &mut Writer,
         entity_map: Option<&mut SendEntityMap>,
     ) -> Result<(), SerializationError> {
-        let fns = unsafe { self.typed::<M>() };
+        let fns = unsafe { self.typed::<M>()? };
         if let Some(map_entities) = self.send_map_entities {
             let serialize_map_entities = fns.serialize_map_entities.unwrap();
             serialize_map_entities(
@@ -271,7 +573,7 @@ impl ErasedSerializeFns {
         reader: &mut Reader,
         entity_map: &mut ReceiveEntityMap,
     ) -> Result<M, SerializationError> {
-        let fns = unsafe { self.typed::<M>() };
+        let fns = unsafe { self.typed::<M>()? };
         let mut message = (fns.deserialize)(reader)?;
         if let Some(map_entities) = self.receive_map_entities {
             map_entities(PtrMut::from(&mut message), entity_map);
@@ -301,13 +603,18 @@ impl AppSerializeExt for App {
 
 #[cfg(test)]
 mod tests {
-    use crate::protocol::serialize::{erased_serialize_fn, ErasedSerializeFns};
+    use crate::protocol::serialize::{
+        erased_serialize_fn, ErasedSerializeFns, ReflectSerializeFns,
+    };
     use crate::serialize::reader::Reader;
     use crate::serialize::writer::Writer;
+    use crate::serialize::SerializationError;
     use crate::shared::replication::authority::AuthorityChange;
     use crate::shared::replication::entity_map::{ReceiveEntityMap, SendEntityMap};
     use bevy::prelude::Entity;
     use bevy::ptr::Ptr;
+    use bevy::reflect::{ReflectDeserialize, ReflectFromPtr, ReflectSerialize};
+    use std::any::TypeId;
 
     #[test]
     fn test_erased_serde() {
@@ -372,4 +679,39 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_reflect_serde_has_no_typed_fns() {
+        let type_registry = bevy::reflect::TypeRegistry::new();
+        let registration = type_registry.get(TypeId::of::<u32>()).unwrap();
+        let registry = ErasedSerializeFns::new_reflect(
+            TypeId::of::<u32>(),
+            std::any::type_name::<u32>(),
+            ReflectSerializeFns {
+                from_ptr: registration.data::<ReflectFromPtr>().unwrap().clone(),
+                serialize: registration.data::<ReflectSerialize>().unwrap().clone(),
+                deserialize: registration.data::<ReflectDeserialize>().unwrap().clone(),
+            },
+        );
+
+        // the reflected type is serialized through the erased functions
+        let mut writer = Writer::default();
+        unsafe { (registry.erased_serialize)(&registry, Ptr::from(&3u32), &mut writer, None) }
+            .unwrap();
+        let mut reader = Reader::from(writer.to_bytes());
+        let value = registry.reflect_deserialize(&mut reader).unwrap();
+        assert_eq!(value.downcast_ref::<u32>(), Some(&3));
+
+        // but the typed functions return an error instead of being called
+        let mut writer = Writer::default();
+        assert!(matches!(
+            unsafe { registry.serialize::<u32>(&3, &mut writer, None) },
+            Err(SerializationError::NotTyped(_))
+        ));
+        let mut reader = Reader::from(vec![3]);
+        assert!(matches!(
+            unsafe { registry.deserialize::<u32>(&mut reader, &mut ReceiveEntityMap::default()) },
+            Err(SerializationError::NotTyped(_))
+        ));
+    }
 }
@@ -0,0 +1,163 @@
+//! Versions of the messages and components that are registered with legacy versions.
+//!
+//! A message or component registered with `with_legacy` has several versions: the legacy types,
+//! from the oldest (version 0) to the newest, and then the current type.
+//! The client announces the version it uses for each of these types in its connection request;
+//! the server then converts the payloads it sends to (and receives from) that client.
+use bevy::utils::HashMap;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::registry::{NetId, TypeKind, TypeMapper};
+use crate::protocol::serialize::ErasedSerializeFns;
+use crate::serialize::reader::Reader;
+use crate::serialize::writer::Writer;
+use crate::serialize::{SerializationError, ToBytes};
+
+/// Maximum number of versioned types that a client can announce in its connection request
+/// (so that the connection request packet fits in the MTU)
+pub(crate) const MAX_VERSIONED_TYPES: usize = 24;
+
+/// Version of a message or component. 0 is the oldest legacy version.
+pub type Version = u8;
+
+/// Whether a versioned type is a message or a component
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum VersionedKind {
+    Message,
+    Component,
+}
+
+/// The version used for a message or component
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypeVersion {
+    pub kind: VersionedKind,
+    pub net_id: NetId,
+    pub version: Version,
+}
+
+/// Versions that a remote peer uses for the versioned messages and components of the protocol.
+///
+/// Only the types for which the peer uses an older version than ours are stored; all the other types
+/// are exchanged in their current version.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PeerVersions {
+    versions: HashMap<(VersionedKind, NetId), Version>,
+}
+
+impl PeerVersions {
+    /// Negotiate the versions to use with a peer, from our own versioned types and the versions
+    /// announced by the peer.
+    ///
+    /// A versioned type that the peer doesn't announce is assumed to be in its oldest version.
+    /// Returns the first type for which the peer uses a version that we don't know about.
+    pub(crate) fn negotiate(
+        local: &[TypeVersion],
+        remote: &[TypeVersion],
+    ) -> Result<Self, TypeVersion> {
+        let find = |versions: &[TypeVersion], kind: VersionedKind, net_id: NetId| {
+            versions
+                .iter()
+                .find(|v| v.kind == kind && v.net_id == net_id)
+                .map_or(0, |v| v.version)
+        };
+        if let Some(unsupported) = remote
+            .iter()
+            .find(|r| r.version > find(local, r.kind, r.net_id))
+        {
+            return Err(*unsupported);
+        }
+        let versions = local
+            .iter()
+            .filter_map(|l| {
+                let version = find(remote, l.kind, l.net_id);
+                (version < l.version).then_some(((l.kind, l.net_id), version))
+            })
+            .collect();
+        Ok(Self { versions })
+    }
+
+    /// The version used by the peer for this type, if it is older than the current version
+    pub(crate) fn get(&self, kind: VersionedKind, net_id: NetId) -> Option<Version> {
+        self.versions.get(&(kind, net_id)).copied()
+    }
+
+    /// Returns true if the peer uses the current version of every type
+    pub fn is_empty(&self) -> bool {
+        self.versions.is_empty()
+    }
+}
+
+/// Convert serialized bytes (the net id followed by the value) between the current version of
+/// the type and the version used by the peer.
+///
+/// If `upgrade` is true, the bytes are converted from the peer's version to the current version,
+/// otherwise from the current version to the peer's version.
+/// The bytes are returned unchanged if the peer uses the current version of the type.
+pub(crate) fn convert<K: TypeKind>(
+    bytes: Bytes,
+    kind: VersionedKind,
+    kind_map: &TypeMapper<K>,
+    serialize_fns_map: &HashMap<K, ErasedSerializeFns>,
+    peer_versions: &PeerVersions,
+    upgrade: bool,
+) -> Result<Bytes, SerializationError> {
+    if peer_versions.is_empty() {
+        return Ok(bytes);
+    }
+    let mut reader = Reader::from(bytes.clone());
+    let net_id = NetId::from_bytes(&mut reader)?;
+    let Some(peer_version) = peer_versions.get(kind, net_id) else {
+        return Ok(bytes);
+    };
+    let Some(erased_fns) = kind_map.kind(net_id).and_then(|k| serialize_fns_map.get(k)) else {
+        return Ok(bytes);
+    };
+    let current = erased_fns.current_version();
+    let (from, to) = if upgrade {
+        (peer_version, current)
+    } else {
+        (current, peer_version)
+    };
+    let mut writer = Writer::default();
+    net_id.to_bytes(&mut writer)?;
+    erased_fns.transcode(&mut reader, &mut writer, from, to)?;
+    Ok(writer.split())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(net_id: NetId, version: Version) -> TypeVersion {
+        TypeVersion {
+            kind: VersionedKind::Message,
+            net_id,
+            version,
+        }
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let local = [message(0, 2), message(3, 1)];
+        // the peer doesn't know about the versions of message 3
+        let versions = PeerVersions::negotiate(&local, &[message(0, 1)]).unwrap();
+        assert_eq!(versions.get(VersionedKind::Message, 0), Some(1));
+        assert_eq!(versions.get(VersionedKind::Message, 3), Some(0));
+        assert_eq!(versions.get(VersionedKind::Component, 0), None);
+
+        // same protocol
+        let versions = PeerVersions::negotiate(&local, &local).unwrap();
+        assert!(versions.is_empty());
+
+        // the peer is more recent than us
+        assert_eq!(
+            PeerVersions::negotiate(&local, &[message(3, 2)]),
+            Err(message(3, 2))
+        );
+        assert_eq!(
+            PeerVersions::negotiate(&local, &[message(1, 1)]),
+            Err(message(1, 1))
+        );
+    }
+}
@@ -0,0 +1,490 @@
+//! Bit-level serialization, to pack values that don't need a full number of bytes.
+//!
+//! This is mostly useful for quantized floats, vectors and rotations, which take most of the bandwidth
+//! when replicating transforms.
+//!
+//! ```rust
+//! use bevy::prelude::{Quat, Vec3};
+//! use lightyear::serialize::bits::*;
+//! use lightyear::serialize::SerializationError;
+//! use std::io::{Read, Write};
+//!
+//! #[derive(Clone, Debug, PartialEq)]
+//! struct PlayerTransform {
+//!     translation: Vec3,
+//!     rotation: Quat,
+//! }
+//!
+//! // positions between -1000.0 and 1000.0, with a precision of 1cm
+//! const TRANSLATION: QuantizedVec3 = QuantizedVec3::new(-1000.0, 1000.0, 0.01);
+//! const ROTATION: SmallestThreeQuat = SmallestThreeQuat::new(10);
+//!
+//! impl BitSerialize for PlayerTransform {
+//!     fn bit_serialize<W: Write>(&self, writer: &mut BitWriter<W>) -> Result<(), SerializationError> {
+//!         TRANSLATION.encode(self.translation, writer)?;
+//!         ROTATION.encode(self.rotation, writer)
+//!     }
+//!
+//!     fn bit_deserialize<R: Read>(reader: &mut BitReader<R>) -> Result<Self, SerializationError> {
+//!         Ok(Self {
+//!             translation: TRANSLATION.decode(reader)?,
+//!             rotation: ROTATION.decode(reader)?,
+//!         })
+//!     }
+//! }
+//! ```
+//! The type can then be registered with [`SerializeFns::bit_packed`](crate::protocol::SerializeFns::bit_packed),
+//! for example with `app.register_component_custom_serde::<PlayerTransform>(direction, SerializeFns::bit_packed())`.
+use bevy::math::{Quat, Vec2, Vec3};
+use byteorder::{ReadBytesExt, WriteBytesExt};
+use std::io::{Read, Write};
+
+use crate::serialize::SerializationError;
+
+/// Maximum number of bits that can be written or read in a single call
+pub const MAX_BITS: u32 = 32;
+
+/// Writes values bit by bit into an underlying [`Write`].
+///
+/// Bits are accumulated in a scratch buffer and flushed byte by byte; [`BitWriter::finish`] must be called
+/// to write the last partial byte.
+pub struct BitWriter<W: Write> {
+    inner: W,
+    scratch: u64,
+    scratch_bits: u32,
+}
+
+impl<W: Write> BitWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            scratch: 0,
+            scratch_bits: 0,
+        }
+    }
+
+    /// Write the `num_bits` lowest bits of `value`
+    pub fn write_bits(&mut self, value: u32, num_bits: u32) -> Result<(), SerializationError> {
+        debug_assert!(num_bits <= MAX_BITS);
+        if num_bits == 0 {
+            return Ok(());
+        }
+        let mask = (1u64 << num_bits) - 1;
+        self.scratch |= (value as u64 & mask) << self.scratch_bits;
+        self.scratch_bits += num_bits;
+        while self.scratch_bits >= 8 {
+            self.inner.write_u8(self.scratch as u8)?;
+            self.scratch >>= 8;
+            self.scratch_bits -= 8;
+        }
+        Ok(())
+    }
+
+    pub fn write_bool(&mut self, value: bool) -> Result<(), SerializationError> {
+        self.write_bits(value as u32, 1)
+    }
+
+    /// Write the remaining bits (padded with zeroes to a full byte) and return the inner writer
+    pub fn finish(mut self) -> Result<W, SerializationError> {
+        if self.scratch_bits > 0 {
+            self.inner.write_u8(self.scratch as u8)?;
+        }
+        Ok(self.inner)
+    }
+}
+
+/// Reads values bit by bit from an underlying [`Read`].
+///
+/// Bytes are only read from the inner reader when needed, so the reader consumes exactly the bytes
+/// that were written by the matching [`BitWriter`].
+pub struct BitReader<R: Read> {
+    inner: R,
+    scratch: u64,
+    scratch_bits: u32,
+}
+
+impl<R: Read> BitReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            scratch: 0,
+            scratch_bits: 0,
+        }
+    }
+
+    /// Read `num_bits` bits
+    pub fn read_bits(&mut self, num_bits: u32) -> Result<u32, SerializationError> {
+        debug_assert!(num_bits <= MAX_BITS);
+        if num_bits == 0 {
+            return Ok(0);
+        }
+        while self.scratch_bits < num_bits {
+            self.scratch |= (self.inner.read_u8()? as u64) << self.scratch_bits;
+            self.scratch_bits += 8;
+        }
+        let value = self.scratch & ((1u64 << num_bits) - 1);
+        self.scratch >>= num_bits;
+        self.scratch_bits -= num_bits;
+        Ok(value as u32)
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, SerializationError> {
+        Ok(self.read_bits(1)? != 0)
+    }
+}
+
+/// A type that can be serialized at the bit level.
+///
+/// Types implementing this trait can be registered as messages or components with
+/// [`SerializeFns::bit_packed`](crate::protocol::SerializeFns::bit_packed).
+pub trait BitSerialize: Sized {
+    fn bit_serialize<W: Write>(&self, writer: &mut BitWriter<W>) -> Result<(), SerializationError>;
+
+    fn bit_deserialize<R: Read>(reader: &mut BitReader<R>) -> Result<Self, SerializationError>;
+}
+
+impl BitSerialize for bool {
+    fn bit_serialize<W: Write>(&self, writer: &mut BitWriter<W>) -> Result<(), SerializationError> {
+        writer.write_bool(*self)
+    }
+
+    fn bit_deserialize<R: Read>(reader: &mut BitReader<R>) -> Result<Self, SerializationError> {
+        reader.read_bool()
+    }
+}
+
+macro_rules! impl_bit_serialize_uint {
+    ($($ty:ty),*) => {
+        $(
+            impl BitSerialize for $ty {
+                fn bit_serialize<W: Write>(&self, writer: &mut BitWriter<W>) -> Result<(), SerializationError> {
+                    writer.write_bits(*self as u32, <$ty>::BITS)
+                }
+
+                fn bit_deserialize<R: Read>(reader: &mut BitReader<R>) -> Result<Self, SerializationError> {
+                    Ok(reader.read_bits(<$ty>::BITS)? as $ty)
+                }
+            }
+        )*
+    };
+}
+
+impl_bit_serialize_uint!(u8, u16, u32);
+
+/// Quantizes a `f32` in the range `[min, max]` with a given precision.
+///
+/// Values outside of the range are clamped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuantizedF32 {
+    pub min: f32,
+    pub max: f32,
+    /// Maximum difference between the encoded value and the decoded value will be `precision / 2`
+    pub precision: f32,
+}
+
+impl QuantizedF32 {
+    pub const fn new(min: f32, max: f32, precision: f32) -> Self {
+        Self {
+            min,
+            max,
+            precision,
+        }
+    }
+
+    /// Number of quantization steps between `min` and `max`
+    fn steps(&self) -> u32 {
+        ((self.max - self.min) / self.precision)
+            .ceil()
+            .clamp(1.0, u32::MAX as f32) as u32
+    }
+
+    /// Number of bits used to encode a value
+    pub fn bits(&self) -> u32 {
+        u32::BITS - self.steps().leading_zeros()
+    }
+
+    pub fn encode<W: Write>(
+        &self,
+        value: f32,
+        writer: &mut BitWriter<W>,
+    ) -> Result<(), SerializationError> {
+        let steps = self.steps();
+        let normalized = ((value - self.min) / (self.max - self.min)).clamp(0.0, 1.0);
+        let quantized = (normalized as f64 * steps as f64).round() as u32;
+        writer.write_bits(quantized, self.bits())
+    }
+
+    pub fn decode<R: Read>(&self, reader: &mut BitReader<R>) -> Result<f32, SerializationError> {
+        let steps = self.steps();
+        let quantized = reader.read_bits(self.bits())?;
+        if quantized > steps {
+            return Err(SerializationError::InvalidValue);
+        }
+        Ok(self.min + (quantized as f64 / steps as f64) as f32 * (self.max - self.min))
+    }
+}
+
+/// Quantizes each axis of a [`Vec2`] with the same [`QuantizedF32`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuantizedVec2(pub QuantizedF32);
+
+impl QuantizedVec2 {
+    pub const fn new(min: f32, max: f32, precision: f32) -> Self {
+        Self(QuantizedF32::new(min, max, precision))
+    }
+
+    pub fn encode<W: Write>(
+        &self,
+        value: Vec2,
+        writer: &mut BitWriter<W>,
+    ) -> Result<(), SerializationError> {
+        self.0.encode(value.x, writer)?;
+        self.0.encode(value.y, writer)
+    }
+
+    pub fn decode<R: Read>(&self, reader: &mut BitReader<R>) -> Result<Vec2, SerializationError> {
+        Ok(Vec2::new(self.0.decode(reader)?, self.0.decode(reader)?))
+    }
+}
+
+/// Quantizes each axis of a [`Vec3`] with the same [`QuantizedF32`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuantizedVec3(pub QuantizedF32);
+
+impl QuantizedVec3 {
+    pub const fn new(min: f32, max: f32, precision: f32) -> Self {
+        Self(QuantizedF32::new(min, max, precision))
+    }
+
+    pub fn encode<W: Write>(
+        &self,
+        value: Vec3,
+        writer: &mut BitWriter<W>,
+    ) -> Result<(), SerializationError> {
+        self.0.encode(value.x, writer)?;
+        self.0.encode(value.y, writer)?;
+        self.0.encode(value.z, writer)
+    }
+
+    pub fn decode<R: Read>(&self, reader: &mut BitReader<R>) -> Result<Vec3, SerializationError> {
+        Ok(Vec3::new(
+            self.0.decode(reader)?,
+            self.0.decode(reader)?,
+            self.0.decode(reader)?,
+        ))
+    }
+}
+
+/// Encodes a unit [`Quat`] with the 'smallest three' method: we write the index of the largest component
+/// (2 bits), and the 3 other components quantized with `bits` bits each.
+/// The largest component is recomputed from the other three since the quaternion is normalized.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SmallestThreeQuat {
+    /// Number of bits used for each of the 3 smallest components
+    bits: u32,
+}
+
+impl SmallestThreeQuat {
+    /// The 3 smallest components of a normalized quaternion are in the range `[-1/sqrt(2), 1/sqrt(2)]`
+    const RANGE: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+    /// Create a codec that uses `bits` bits for each of the 3 smallest components.
+    ///
+    /// # Panics
+    /// Panics if `bits` is 0 or greater than [`MAX_BITS`]. When used in a `const`, the check happens at compile time.
+    pub const fn new(bits: u32) -> Self {
+        assert!(
+            bits > 0 && bits <= MAX_BITS,
+            "SmallestThreeQuat needs between 1 and 32 bits per component"
+        );
+        Self { bits }
+    }
+
+    /// Number of bits used for each of the 3 smallest components
+    pub const fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    fn max_quantized(&self) -> u32 {
+        ((1u64 << self.bits) - 1) as u32
+    }
+
+    pub fn encode<W: Write>(
+        &self,
+        value: Quat,
+        writer: &mut BitWriter<W>,
+    ) -> Result<(), SerializationError> {
+        let mut components = value.normalize().to_array();
+        let (largest, _) = components
+            .iter()
+            .enumerate()
+            .fold((0, -1.0), |(index, max), (i, c)| {
+                if c.abs() > max {
+                    (i, c.abs())
+                } else {
+                    (index, max)
+                }
+            });
+        // q and -q represent the same rotation, so we can make sure that the largest component is positive
+        if components[largest] < 0.0 {
+            components.iter_mut().for_each(|c| *c = -*c);
+        }
+        writer.write_bits(largest as u32, 2)?;
+        let max_quantized = self.max_quantized() as f32;
+        for (_, c) in components.iter().enumerate().filter(|(i, _)| *i != largest) {
+            let normalized = ((c + Self::RANGE) / (2.0 * Self::RANGE)).clamp(0.0, 1.0);
+            writer.write_bits((normalized * max_quantized).round() as u32, self.bits)?;
+        }
+        Ok(())
+    }
+
+    pub fn decode<R: Read>(&self, reader: &mut BitReader<R>) -> Result<Quat, SerializationError> {
+        let largest = reader.read_bits(2)? as usize;
+        let max_quantized = self.max_quantized() as f32;
+        let mut components = [0.0; 4];
+        let mut sum_squares = 0.0;
+        for (i, c) in components.iter_mut().enumerate() {
+            if i == largest {
+                continue;
+            }
+            let normalized = reader.read_bits(self.bits)? as f32 / max_quantized;
+            *c = normalized * 2.0 * Self::RANGE - Self::RANGE;
+            sum_squares += *c * *c;
+        }
+        components[largest] = (1.0 - sum_squares).max(0.0).sqrt();
+        Ok(Quat::from_array(components).normalize())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::serialize::SerializeFns;
+    use crate::serialize::reader::Reader;
+    use crate::serialize::writer::Writer;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Transform {
+        translation: Vec3,
+        rotation: Quat,
+        flag: bool,
+    }
+
+    const TRANSLATION: QuantizedVec3 = QuantizedVec3::new(-100.0, 100.0, 0.01);
+    const ROTATION: SmallestThreeQuat = SmallestThreeQuat::new(10);
+
+    impl BitSerialize for Transform {
+        fn bit_serialize<W: Write>(
+            &self,
+            writer: &mut BitWriter<W>,
+        ) -> Result<(), SerializationError> {
+            TRANSLATION.encode(self.translation, writer)?;
+            ROTATION.encode(self.rotation, writer)?;
+            self.flag.bit_serialize(writer)
+        }
+
+        fn bit_deserialize<R: Read>(reader: &mut BitReader<R>) -> Result<Self, SerializationError> {
+            Ok(Self {
+                translation: TRANSLATION.decode(reader)?,
+                rotation: ROTATION.decode(reader)?,
+                flag: bool::bit_deserialize(reader)?,
+            })
+        }
+    }
+
+    #[test]
+    fn test_write_read_bits() {
+        let mut writer = BitWriter::new(vec![]);
+        writer.write_bits(5, 3).unwrap();
+        writer.write_bool(true).unwrap();
+        writer.write_bits(u32::MAX, 32).unwrap();
+        writer.write_bits(2, 2).unwrap();
+        let bytes = writer.finish().unwrap();
+        // 38 bits fit in 5 bytes
+        assert_eq!(bytes.len(), 5);
+
+        let mut reader = BitReader::new(bytes.as_slice());
+        assert_eq!(reader.read_bits(3).unwrap(), 5);
+        assert!(reader.read_bool().unwrap());
+        assert_eq!(reader.read_bits(32).unwrap(), u32::MAX);
+        assert_eq!(reader.read_bits(2).unwrap(), 2);
+        assert!(reader.read_bits(8).is_err());
+    }
+
+    #[test]
+    fn test_quantized_f32() {
+        let codec = QuantizedF32::new(-10.0, 10.0, 0.01);
+        // 2000 steps
+        assert_eq!(codec.bits(), 11);
+        let mut writer = BitWriter::new(vec![]);
+        for value in [-10.0, -3.3, 0.0, 7.777, 10.0, 20.0] {
+            codec.encode(value, &mut writer).unwrap();
+        }
+        let bytes = writer.finish().unwrap();
+        let mut reader = BitReader::new(bytes.as_slice());
+        for value in [-10.0, -3.3, 0.0, 7.777, 10.0] {
+            let decoded = codec.decode(&mut reader).unwrap();
+            assert!((decoded - value).abs() <= 0.005 + f32::EPSILON);
+        }
+        // out of range values are clamped
+        assert_eq!(codec.decode(&mut reader).unwrap(), 10.0);
+    }
+
+    #[test]
+    fn test_smallest_three_quat() {
+        let mut writer = BitWriter::new(vec![]);
+        let rotations = [
+            Quat::IDENTITY,
+            Quat::from_rotation_y(2.5),
+            Quat::from_euler(bevy::math::EulerRot::XYZ, 0.3, -1.2, 2.0),
+            -Quat::from_rotation_x(1.0),
+        ];
+        for rotation in rotations {
+            ROTATION.encode(rotation, &mut writer).unwrap();
+        }
+        let bytes = writer.finish().unwrap();
+        // 32 bits per rotation
+        assert_eq!(bytes.len(), 16);
+        let mut reader = BitReader::new(bytes.as_slice());
+        for rotation in rotations {
+            let decoded = ROTATION.decode(&mut reader).unwrap();
+            assert!(decoded.angle_between(rotation) < 0.01);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_smallest_three_quat_zero_bits() {
+        SmallestThreeQuat::new(0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_smallest_three_quat_too_many_bits() {
+        SmallestThreeQuat::new(MAX_BITS + 1);
+    }
+
+    #[test]
+    fn test_bit_packed_serialize_fns() {
+        let fns = SerializeFns::<Transform>::bit_packed();
+        let transform = Transform {
+            translation: Vec3::new(1.0, -50.0, 99.99),
+            rotation: Quat::from_rotation_z(0.5),
+            flag: true,
+        };
+        let mut writer = Writer::default();
+        (fns.serialize)(&transform, &mut writer).unwrap();
+        let bytes = writer.to_bytes();
+        // 3 * 15 bits + 32 bits + 1 bit
+        assert_eq!(bytes.len(), 10);
+
+        let mut reader = Reader::from(bytes);
+        let decoded = (fns.deserialize)(&mut reader).unwrap();
+        assert!(decoded
+            .translation
+            .abs_diff_eq(transform.translation, 0.005));
+        assert!(decoded.rotation.angle_between(transform.rotation) < 0.01);
+        assert!(decoded.flag);
+        assert!(!reader.has_remaining());
+    }
+}
@@ -7,6 +7,7 @@ use byteorder::{ReadBytesExt, WriteBytesExt};
 use bytes::Bytes;
 use std::hash::{BuildHasher, Hash};
 
+pub mod bits;
 pub mod reader;
 pub(crate) mod varint;
 pub mod writer;
@@ -30,6 +31,10 @@ pub enum SerializationError {
     BincodeDecode(#[from] bincode::error::DecodeError),
     #[error("The message is too big ({0} bytes) to be sent. We can split a message only up to 256 fragments.")]
     MessageTooBig(usize),
+    #[error(
+        "the type {0} was registered through reflection and has no typed serialization functions"
+    )]
+    NotTyped(&'static str),
 }
 
 #[allow(clippy::len_without_is_empty)]
@@ -47,7 +47,7 @@ pub trait VarIntWriteExt: WriteBytesExt {
                 self.write_u32::<NetworkEndian>(val)?;
             }
             8 => {
-                let val = value | 0xc0_0000_0000_0000;
+                let val = value | 0xc000_0000_0000_0000;
                 self.write_u64::<NetworkEndian>(val)?;
             }
             _ => return Err(std::io::Error::other("value is too large for varint").into()),
@@ -144,4 +144,34 @@ mod tests {
         let read_val = reader.read_varint().unwrap();
         assert_eq!(val, read_val);
     }
+
+    #[test]
+    fn test_varint_len_8() {
+        let mut writer = vec![];
+
+        let val = u32::MAX as u64;
+        writer.write_varint(val).unwrap();
+        assert_eq!(writer.len(), 8);
+
+        let mut reader = Cursor::new(writer);
+        let read_val = reader.read_varint().unwrap();
+        assert_eq!(val, read_val);
+    }
+
+    /// The 8-byte length tag must be written in the 2 most significant bits of the first byte,
+    /// otherwise the value is read back as a 1-byte varint
+    #[test]
+    fn test_varint_len_8_tag() {
+        let mut writer = vec![];
+
+        let val = 4_611_686_018_427_387_903;
+        writer.write_varint(val).unwrap();
+        writer.write_varint(1).unwrap();
+        assert_eq!(writer.len(), 9);
+        assert_eq!(writer[0] >> 6, 3);
+
+        let mut reader = Cursor::new(writer);
+        assert_eq!(reader.read_varint().unwrap(), val);
+        assert_eq!(reader.read_varint().unwrap(), 1);
+    }
 }
@@ -8,6 +8,8 @@ use crate::connection::netcode::{Key, PRIVATE_KEY_BYTES};
 use crate::connection::server::{
     ConnectionRequestHandler, DefaultConnectionRequestHandler, NetConfig,
 };
+use crate::packet::congestion::CongestionControlConfig;
+use crate::packet::mtu::MtuDiscoveryConfig;
 use crate::prelude::ReplicationConfig;
 use crate::shared::config::SharedConfig;
 use crate::shared::ping::manager::PingConfig;
@@ -67,6 +69,12 @@ pub struct PacketConfig {
     pub per_client_send_bandwidth_cap: Quota,
     /// If false, there is no bandwidth cap and all messages are sent as soon as possible
     pub bandwidth_cap_enabled: bool,
+    /// Configuration of the path MTU discovery, which lets the packets grow above the default
+    /// maximum packet size if the network path allows it
+    pub mtu_discovery: MtuDiscoveryConfig,
+    /// Configuration of the congestion control, which adapts the bandwidth cap to the network
+    /// conditions of each connection
+    pub congestion_control: CongestionControlConfig,
 }
 
 impl Default for PacketConfig {
@@ -76,6 +84,8 @@ impl Default for PacketConfig {
             // 56 KB/s bandwidth cap
             per_client_send_bandwidth_cap: Quota::per_second(nonzero!(56000u32)),
             bandwidth_cap_enabled: false,
+            mtu_discovery: MtuDiscoveryConfig::default(),
+            congestion_control: CongestionControlConfig::default(),
         }
     }
 }
@@ -96,6 +106,16 @@ impl PacketConfig {
         self.bandwidth_cap_enabled = true;
         self
     }
+
+    pub fn enable_mtu_discovery(mut self) -> Self {
+        self.mtu_discovery.enabled = true;
+        self
+    }
+
+    pub fn enable_congestion_control(mut self) -> Self {
+        self.congestion_control.enabled = true;
+        self
+    }
 }
 
 /// Configuration for the server plugin.
@@ -11,14 +11,15 @@ use tracing::{debug, info, info_span, trace, trace_span};
 use tracing::{instrument, Level};
 
 use crate::channel::builder::{
-    EntityActionsChannel, EntityUpdatesChannel, PingChannel, PongChannel,
+    EntityActionsChannel, EntityUpdatesChannel, PingChannel, PongChannel, StreamId,
 };
 
 use crate::channel::receivers::ChannelReceive;
-use crate::channel::senders::ChannelSend;
+use crate::channel::senders::{ChannelSend, SendOptions};
 use crate::client::message::ClientMessage;
 use crate::connection::id::ClientId;
 use crate::connection::netcode::MAX_PACKET_SIZE;
+use crate::packet::message::MessageId;
 use crate::packet::message_manager::MessageManager;
 use crate::packet::packet_builder::{Payload, RecvPayload};
 use crate::prelude::server::{DisconnectEvent, RoomId, RoomManager};
@@ -32,6 +33,7 @@ use crate::protocol::component::{
 };
 use crate::protocol::message::{MessageError, MessageRegistry, MessageType};
 use crate::protocol::registry::NetId;
+use crate::protocol::version::PeerVersions;
 use crate::serialize::reader::Reader;
 use crate::serialize::writer::Writer;
 use crate::serialize::{SerializationError, ToBytes};
@@ -47,9 +49,10 @@ use crate::shared::replication::components::ReplicationGroupId;
 use crate::shared::replication::delta::DeltaManager;
 use crate::shared::replication::network_target::NetworkTarget;
 use crate::shared::replication::receive::ReplicationReceiver;
-use crate::shared::replication::send::ReplicationSender;
+use crate::shared::replication::send::{ComponentFrequency, ReplicationSender};
 use crate::shared::replication::{EntityActionsMessage, EntityUpdatesMessage, ReplicationPeer};
 use crate::shared::replication::{ReplicationReceive, ReplicationSend};
+use crate::shared::rpc::RpcManager;
 use crate::shared::sets::ServerMarker;
 use crate::shared::tick_manager::Tick;
 use crate::shared::tick_manager::TickManager;
@@ -69,6 +72,8 @@ pub struct ConnectionManager {
     // (we want to keep track of them because we need to replicate the entire world state to them)
     pub(crate) new_clients: Vec<ClientId>,
     pub(crate) writer: Writer,
+    /// Requests sent to clients that are waiting for a response
+    pub(crate) rpc: RpcManager<ClientId>,
 
     // CONFIG
     replication_config: ReplicationConfig,
@@ -105,6 +110,7 @@ impl ConnectionManager {
             delta_manager: DeltaManager::default(),
             new_clients: vec![],
             writer: Writer::with_capacity(MAX_PACKET_SIZE),
+            rpc: RpcManager::default(),
             replication_config,
             packet_config,
             ping_config,
@@ -169,6 +175,100 @@ impl ConnectionManager {
         self.send_message_to_target::<C, M>(message, NetworkTarget::Single(client_id))
     }
 
+    /// Queues up a message to be sent to all clients matching the specific [`NetworkTarget`]
+    ///
+    /// The message is dropped if it cannot be sent before `ttl` elapses (this overrides the
+    /// [`message_ttl`](crate::prelude::ChannelSettings::message_ttl) of the channel)
+    pub fn send_message_to_target_with_ttl<C: Channel, M: Message>(
+        &mut self,
+        message: &mut M,
+        target: NetworkTarget,
+        ttl: Duration,
+    ) -> Result<(), ServerError> {
+        self.erased_send_message_with_options(
+            message,
+            ChannelKind::of::<C>(),
+            target,
+            SendOptions::with_ttl(ttl),
+        )
+    }
+
+    /// Queues up a message to be sent to a client
+    ///
+    /// The message is dropped if it cannot be sent before `ttl` elapses (this overrides the
+    /// [`message_ttl`](crate::prelude::ChannelSettings::message_ttl) of the channel)
+    ///
+    /// Returns the [`MessageId`] of the message if the channel is reliable, so that it can be matched
+    /// with a [`MessageExpired`](crate::prelude::server::MessageExpired) event.
+    pub fn send_message_with_ttl<C: Channel, M: Message>(
+        &mut self,
+        client_id: ClientId,
+        message: &mut M,
+        ttl: Duration,
+    ) -> Result<Option<MessageId>, ServerError> {
+        let connection = self.connection(client_id)?;
+        // messages to the local client are not buffered in the MessageManager
+        let message_id = if connection.is_local_client() {
+            None
+        } else {
+            connection
+                .message_manager
+                .next_reliable_message_id(&ChannelKind::of::<C>())
+        };
+        self.send_message_to_target_with_ttl::<C, M>(
+            message,
+            NetworkTarget::Single(client_id),
+            ttl,
+        )?;
+        Ok(message_id)
+    }
+
+    /// Queues up a message to be sent to all clients matching the specific [`NetworkTarget`], with
+    /// [`SendOptions`] that override the settings of the channel for this message
+    pub fn send_message_to_target_with_options<C: Channel, M: Message>(
+        &mut self,
+        message: &mut M,
+        target: NetworkTarget,
+        options: SendOptions,
+    ) -> Result<(), ServerError> {
+        self.erased_send_message_with_options(message, ChannelKind::of::<C>(), target, options)
+    }
+
+    /// Queues up a message to be sent to all clients matching the specific [`NetworkTarget`], on a stream
+    /// of a [`ChannelMode::OrderedReliableStreams`](crate::prelude::ChannelMode::OrderedReliableStreams) channel
+    ///
+    /// The message is only ordered with the other messages of the same stream.
+    pub fn send_message_to_target_on_stream<C: Channel, M: Message>(
+        &mut self,
+        message: &mut M,
+        target: NetworkTarget,
+        stream_id: StreamId,
+    ) -> Result<(), ServerError> {
+        self.erased_send_message_with_options(
+            message,
+            ChannelKind::of::<C>(),
+            target,
+            SendOptions::with_stream(stream_id),
+        )
+    }
+
+    /// Queues up a message to be sent to a client, on a stream of a
+    /// [`ChannelMode::OrderedReliableStreams`](crate::prelude::ChannelMode::OrderedReliableStreams) channel
+    ///
+    /// The message is only ordered with the other messages of the same stream.
+    pub fn send_message_to_stream<C: Channel, M: Message>(
+        &mut self,
+        client_id: ClientId,
+        message: &mut M,
+        stream_id: StreamId,
+    ) -> Result<(), ServerError> {
+        self.send_message_to_target_on_stream::<C, M>(
+            message,
+            NetworkTarget::Single(client_id),
+            stream_id,
+        )
+    }
+
     /// Update the priority of a `ReplicationGroup` that is replicated to a given client
     pub fn update_priority(
         &mut self,
@@ -259,13 +359,18 @@ impl ConnectionManager {
     }
 
     /// Add a new [`Connection`] to the list of connections with the given [`ClientId`]
-    pub(crate) fn add(&mut self, client_id: ClientId, client_entity: Entity) {
+    pub(crate) fn add(
+        &mut self,
+        client_id: ClientId,
+        client_entity: Entity,
+        peer_versions: PeerVersions,
+    ) {
         if let Entry::Vacant(e) = self.connections.entry(client_id) {
             #[cfg(feature = "metrics")]
             metrics::gauge!("connected_clients").increment(1.0);
 
             info!("New connection from id: {}", client_id);
-            let connection = Connection::new(
+            let mut connection = Connection::new(
                 client_id,
                 client_entity,
                 &self.channel_registry,
@@ -273,6 +378,7 @@ impl ConnectionManager {
                 self.packet_config,
                 self.ping_config,
             );
+            connection.peer_versions = peer_versions;
             self.events.add_connect_event(ConnectEvent {
                 client_id,
                 entity: client_entity,
@@ -303,6 +409,7 @@ impl ConnectionManager {
         message: Bytes,
         channel: ChannelKind,
         target: NetworkTarget,
+        options: SendOptions,
     ) -> Result<(), ServerError> {
         self.connections
             .iter_mut()
@@ -314,7 +421,10 @@ impl ConnectionManager {
                     c.local_messages_to_send.push(message.clone())
                 } else {
                     // NOTE: this clone is O(1), it just increments the reference count
-                    c.buffer_message(message.clone(), channel)?;
+                    let message = self
+                        .message_registry
+                        .downgrade(message.clone(), &c.peer_versions)?;
+                    c.buffer_message(message, channel, options)?;
                 }
                 Ok::<(), ServerError>(())
             })
@@ -328,6 +438,7 @@ impl ConnectionManager {
         message: &M,
         channel: ChannelKind,
         target: NetworkTarget,
+        options: SendOptions,
     ) -> Result<(), ServerError> {
         self.connections
             .iter_mut()
@@ -344,7 +455,10 @@ impl ConnectionManager {
                 if c.is_local_client() {
                     c.local_messages_to_send.push(message_bytes);
                 } else {
-                    c.buffer_message(message_bytes, channel)?;
+                    let message_bytes = self
+                        .message_registry
+                        .downgrade(message_bytes, &c.peer_versions)?;
+                    c.buffer_message(message_bytes, channel, options)?;
                 }
                 Ok::<(), ServerError>(())
             })
@@ -360,14 +474,26 @@ impl ConnectionManager {
         message: &M,
         channel_kind: ChannelKind,
         target: NetworkTarget,
+    ) -> Result<(), ServerError> {
+        self.erased_send_message_with_options(message, channel_kind, target, SendOptions::default())
+    }
+
+    /// Serialize the message and buffer it to be sent in each `Connection`, with options
+    /// (time-to-live, ordering stream) that override the settings of the channel
+    fn erased_send_message_with_options<M: Message>(
+        &mut self,
+        message: &M,
+        channel_kind: ChannelKind,
+        target: NetworkTarget,
+        options: SendOptions,
     ) -> Result<(), ServerError> {
         if self.message_registry.is_map_entities::<M>() {
-            self.buffer_map_entities_message(message, channel_kind, target)?;
+            self.buffer_map_entities_message(message, channel_kind, target, options)?;
         } else {
             self.message_registry
                 .serialize(message, &mut self.writer, None)?;
             let message_bytes = self.writer.split();
-            self.buffer_message_bytes(message_bytes, channel_kind, target)?;
+            self.buffer_message_bytes(message_bytes, channel_kind, target, options)?;
         }
         Ok(())
     }
@@ -420,7 +546,7 @@ impl ConnectionManager {
                 Ok::<(), ServerError>(())
             })?;
         for (message, target, channel_kind) in messages_to_rebroadcast {
-            self.buffer_message_bytes(message, channel_kind, target)?;
+            self.buffer_message_bytes(message, channel_kind, target, SendOptions::default())?;
         }
         Ok(())
     }
@@ -476,6 +602,11 @@ pub struct Connection {
     is_local_client: bool,
     /// Messages to send to the local client (we don't buffer them in the MessageManager because there is no io)
     pub(crate) local_messages_to_send: Vec<Bytes>,
+    /// Versions used by the client for the messages and components that have legacy versions
+    pub(crate) peer_versions: PeerVersions,
+    /// Entities that lost relevance for this client but are kept by the client until the end of their
+    /// grace period, with their replication group and the tick at which the grace period ends
+    pub(crate) relevance_lost: EntityHashMap<Entity, (ReplicationGroupId, Tick)>,
 }
 
 impl Connection {
@@ -487,12 +618,14 @@ impl Connection {
         packet_config: PacketConfig,
         ping_config: PingConfig,
     ) -> Self {
-        let bandwidth_cap_enabled = packet_config.bandwidth_cap_enabled;
+        let bandwidth_cap_enabled =
+            packet_config.bandwidth_cap_enabled || packet_config.congestion_control.enabled;
         // create the message manager and the channels
         let mut message_manager = MessageManager::new(
             channel_registry,
             packet_config.nack_rtt_multiple,
             packet_config.into(),
+            packet_config.mtu_discovery,
         );
         // get notified about acks/nacks for replication-update messages
         let entity_updates_sender = &mut message_manager
@@ -529,6 +662,8 @@ impl Connection {
             messages_to_rebroadcast: vec![],
             is_local_client: false,
             local_messages_to_send: vec![],
+            peer_versions: PeerVersions::default(),
+            relevance_lost: EntityHashMap::default(),
         }
     }
 
@@ -552,6 +687,17 @@ impl Connection {
         self.ping_manager.jitter()
     }
 
+    /// Return the maximum size of the packets sent to this client, as found by the path MTU discovery
+    pub fn mtu(&self) -> usize {
+        self.message_manager.mtu()
+    }
+
+    /// Return the current estimate of the bandwidth available to send packets to this client
+    /// (in bytes per second), if congestion control is enabled
+    pub fn bandwidth_estimate(&self) -> Option<u32> {
+        self.message_manager.bandwidth_estimate()
+    }
+
     pub(crate) fn update(
         &mut self,
         world_tick: BevyTick,
@@ -571,6 +717,7 @@ impl Connection {
         &mut self,
         message: Bytes,
         channel: ChannelKind,
+        options: SendOptions,
     ) -> Result<(), ServerError> {
         // TODO: i know channel names never change so i should be able to get them as static
         // TODO: just have a channel registry enum as well?
@@ -580,7 +727,8 @@ impl Connection {
             .name(&channel)
             .ok_or::<ServerError>(MessageError::NotRegistered.into())?;
         // message.emit_send_logs(&channel_name);
-        self.message_manager.buffer_send(message, channel)?;
+        self.message_manager
+            .buffer_send_with_options(message, channel, options)?;
         Ok(())
     }
 
@@ -698,12 +846,18 @@ impl Connection {
                         self.ping_manager
                             .process_pong(&pong, time_manager.current_time());
                     } else if channel_kind == &ChannelKind::of::<EntityActionsChannel>() {
-                        let actions = EntityActionsMessage::from_bytes(&mut reader)?;
+                        let mut actions = EntityActionsMessage::from_bytes(&mut reader)?;
+                        if !self.peer_versions.is_empty() {
+                            actions.upgrade(component_registry, &self.peer_versions)?;
+                        }
                         trace!(?tick, ?actions, "received replication actions message");
                         // buffer the replication message
                         self.replication_receiver.recv_actions(actions, tick);
                     } else if channel_kind == &ChannelKind::of::<EntityUpdatesChannel>() {
-                        let updates = EntityUpdatesMessage::from_bytes(&mut reader)?;
+                        let mut updates = EntityUpdatesMessage::from_bytes(&mut reader)?;
+                        if !self.peer_versions.is_empty() {
+                            updates.upgrade(component_registry, &self.peer_versions)?;
+                        }
                         trace!(?tick, ?updates, "received replication updates message");
                         // buffer the replication message
                         self.replication_receiver.recv_updates(updates, tick);
@@ -725,7 +879,9 @@ impl Connection {
                         //  I don't think so... maybe the sender should map_entities themselves?
                         //  or it matters for input messages?
                         // TODO: avoid clone with Arc<[u8]>?
-                        let data = (reader.consume(), target, *channel_kind);
+                        let message =
+                            message_registry.upgrade(reader.consume(), &self.peer_versions)?;
+                        let data = (message, target, *channel_kind);
                         match message_registry.message_type(net_id) {
                             #[cfg(feature = "leafwing")]
                             MessageType::LeafwingInput => self
@@ -785,7 +941,8 @@ impl Connection {
         //  I don't think so... maybe the sender should map_entities themselves?
         //  or it matters for input messages?
         // TODO: avoid clone with Arc<[u8]>?
-        let data = (reader.consume(), target, channel_kind);
+        let message = message_registry.upgrade(reader.consume(), &self.peer_versions)?;
+        let data = (message, target, channel_kind);
         match message_registry.message_type(net_id) {
             #[cfg(feature = "leafwing")]
             MessageType::LeafwingInput => self
@@ -824,6 +981,32 @@ impl Connection {
 }
 
 impl ConnectionManager {
+    /// The entity lost relevance for the clients in `target`, which keep the entity until the
+    /// `grace_end` tick
+    pub(crate) fn prepare_entity_relevance_lost(
+        &mut self,
+        entity: Entity,
+        group_id: ReplicationGroupId,
+        target: NetworkTarget,
+        grace_end: Tick,
+    ) -> Result<(), ServerError> {
+        self.connected_targets(target).try_for_each(|client_id| {
+            let connection = self.connection_mut(client_id)?;
+            // convert the entity to a network entity (possibly mapped)
+            let remote_entity = connection
+                .replication_receiver
+                .remote_entity_map
+                .to_remote(entity);
+            connection
+                .replication_sender
+                .prepare_entity_relevance_lost(remote_entity, group_id);
+            connection
+                .relevance_lost
+                .insert(entity, (group_id, grace_end));
+            Ok(())
+        })
+    }
+
     pub(crate) fn prepare_entity_despawn(
         &mut self,
         mut entity: Entity,
@@ -1024,9 +1207,12 @@ impl ConnectionManager {
                 //     .entry(group)
                 //     .or_default()
                 //     .update_collect_changes_since_this_tick(system_current_tick);
-                self.connection_mut(client_id)?
+                let connection = self.connection_mut(client_id)?;
+                let raw_data = component_registry
+                    .downgrade(raw_data.clone().unwrap(), &connection.peer_versions)?;
+                connection
                     .replication_sender
-                    .prepare_component_insert(entity, group_id, raw_data.clone().unwrap());
+                    .prepare_component_insert(entity, group_id, raw_data);
                 Ok(())
             })
     }
@@ -1044,28 +1230,34 @@ impl ConnectionManager {
         system_current_tick: BevyTick,
         tick: Tick,
         delta_compression: bool,
+        frequency: Option<ComponentFrequency>,
     ) -> Result<(), ServerError> {
         let mut num_targets = 0;
         let mut existing_bytes: Option<Bytes> = None;
         self.connected_targets(target).try_for_each(|client_id| {
             let connection = self.connections.get_mut(&client_id).ok_or(ServerError::ClientIdNotFound(client_id))?;
-            let send_tick = connection
-                .replication_sender
-                .group_channels
-                .entry(group_id)
-                .or_default()
-                .send_tick;
+            // use the network entity
+            let remote_entity = connection
+                .replication_receiver
+                .remote_entity_map
+                .to_remote(entity);
             // send the update for all changes newer than the last send_tick for the group
+            // (or for the component, if it is sent at its own frequency)
             debug!(
                 ?kind,
                 change_tick = ?component_change_tick,
-                ?send_tick,
                 "prepare entity update changed check (we want the component-change-tick to be higher than send_tick)"
             );
 
-            if send_tick.map_or(true, |tick| {
-                component_change_tick.is_newer_than(tick, system_current_tick)
-            }) {
+            if connection.replication_sender.should_send_component_update(
+                remote_entity,
+                group_id,
+                kind,
+                frequency,
+                component_change_tick,
+                system_current_tick,
+                tick,
+            ) {
                 num_targets += 1;
                 trace!(
                     ?entity,
@@ -1086,13 +1278,11 @@ impl ConnectionManager {
                         // we re-serialize every time if there is entity mapping
                         existing_bytes = Some(self.writer.split());
                     }
-                    let raw_data = existing_bytes.clone().unwrap();
-                    // use the network entity
-                    let entity = connection
-                        .replication_receiver
-                        .remote_entity_map
-                        .to_remote(entity);
-                    connection.replication_sender.prepare_component_update(entity, group_id, raw_data);
+                    let raw_data = registry.downgrade(existing_bytes.clone().unwrap(), &connection.peer_versions)?;
+                    connection.replication_sender.prepare_component_update(remote_entity, group_id, raw_data);
+                }
+                if let Some(frequency) = frequency {
+                    connection.replication_sender.prepare_frequency_component_update(remote_entity, group_id, kind, frequency);
                 }
             }
             Ok::<(), ServerError>(())
@@ -26,13 +26,15 @@ impl Plugin for ServerEventsPlugin {
             // EVENTS
             .add_event::<ConnectEvent>()
             .add_event::<DisconnectEvent>()
+            .add_event::<MessageExpired>()
             // PLUGIN
             .add_plugins(EventsPlugin::<ConnectionManager>::default())
             // SYSTEMS
             .add_systems(
                 PreUpdate,
                 // TODO: check if this should be between Receive and EmitEvents
-                emit_connect_events.in_set(InternalMainSet::<ServerMarker>::EmitEvents),
+                (emit_connect_events, emit_message_expired_events)
+                    .in_set(InternalMainSet::<ServerMarker>::EmitEvents),
             );
     }
 }
@@ -73,6 +75,22 @@ fn emit_connect_events(
     }
 }
 
+/// Emit a [`MessageExpired`] event for each message sent to a client that expired before being acked
+fn emit_message_expired_events(
+    mut connection_manager: ResMut<ConnectionManager>,
+    mut events: EventWriter<MessageExpired>,
+) {
+    for (client_id, connection) in connection_manager.connections.iter_mut() {
+        events.send_batch(
+            connection
+                .message_manager
+                .drain_expired_messages()
+                .into_iter()
+                .map(|(channel, message_id)| MessageExpired::new(channel, message_id, *client_id)),
+        );
+    }
+}
+
 #[derive(Debug)]
 pub struct ServerEvents {
     pub connections: Vec<ConnectEvent>,
@@ -281,6 +299,14 @@ pub type ComponentRemoveEvent<C> =
 
 /// Bevy [`Event`] emitted on the server on the frame where a (non-replication) message is received
 pub type MessageEvent<M> = crate::shared::events::components::MessageEvent<M, ClientId>;
+/// Bevy [`Event`] emitted on the server when a message sent to a client expired before being acked
+pub type MessageExpired = crate::shared::events::components::MessageExpired<ClientId>;
+/// Bevy [`Event`] emitted on the server on the frame where a request from a client is received
+pub type RequestEvent<Req> = crate::shared::rpc::RequestEvent<Req, ClientId>;
+/// Bevy [`Event`] emitted on the server on the frame where a request sent to a client completes
+pub type ResponseEvent<Req, Resp> = crate::shared::rpc::ResponseEvent<Req, Resp, ClientId>;
+/// Bevy [`Event`] triggered on the server when a trigger is received from a client
+pub type RemoteTrigger<E> = crate::shared::trigger::RemoteTrigger<E, ClientId>;
 
 #[cfg(test)]
 mod tests {
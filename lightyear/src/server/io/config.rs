@@ -5,21 +5,29 @@ use crate::transport::channels::Channels;
 use crate::transport::config::SharedIoConfig;
 use crate::transport::dummy::DummyIo;
 use crate::transport::io::IoStats;
+use crate::transport::middleware::capture::Capture;
 #[cfg(feature = "zstd")]
 use crate::transport::middleware::compression::zstd::compression::ZstdCompressor;
 #[cfg(feature = "zstd")]
 use crate::transport::middleware::compression::zstd::decompression::ZstdDecompressor;
-use crate::transport::middleware::conditioner::LinkConditioner;
-use crate::transport::middleware::PacketReceiverWrapper;
+use crate::transport::middleware::conditioner::{LinkConditioner, LinkConditionerHandle};
+use crate::transport::middleware::{PacketReceiverWrapper, PacketSenderWrapper};
+#[cfg(all(feature = "quic", not(target_family = "wasm")))]
+use crate::transport::quic::{server::QuicServerSocketBuilder, QuicIdentity};
+use crate::transport::replay::ReplayBuilder;
 use crate::transport::udp::UdpSocketBuilder;
+#[cfg(unix)]
+use crate::transport::unix::UnixSocketServerBuilder;
 #[cfg(all(feature = "websocket", not(target_family = "wasm")))]
 use crate::transport::websocket::server::WebSocketServerSocketBuilder;
 #[cfg(all(feature = "webtransport", not(target_family = "wasm")))]
 use crate::transport::webtransport::server::WebTransportServerSocketBuilder;
-use crate::transport::BoxedReceiver;
 use crate::transport::Transport;
+use crate::transport::{BoxedReceiver, BoxedSender};
 use bevy::prelude::TypePath;
 use std::net::IpAddr;
+#[cfg(unix)]
+use std::path::PathBuf;
 #[cfg(all(feature = "webtransport", not(target_family = "wasm")))]
 use wtransport::Identity;
 
@@ -34,9 +42,21 @@ pub enum ServerTransport {
         /// Certificate that will be used for authentication
         certificate: Identity,
     },
+    /// Use a native QUIC connection as a transport layer
+    #[cfg(all(feature = "quic", not(target_family = "wasm")))]
+    QuicServer {
+        server_addr: SocketAddr,
+        /// Certificate that will be used for authentication
+        identity: QuicIdentity,
+    },
     /// Use [`WebSocket`](https://developer.mozilla.org/en-US/docs/Web/API/WebSocket) as a transport
     #[cfg(all(feature = "websocket", not(target_family = "wasm")))]
     WebSocketServer { server_addr: SocketAddr },
+    /// Use a Unix datagram socket bound to this path, for clients running on the same machine.
+    ///
+    /// Each client is identified by a virtual [`SocketAddr`] assigned by the server.
+    #[cfg(unix)]
+    UnixSocket(PathBuf),
     /// Use a crossbeam_channel as a transport. This is useful for testing.
     /// This is server-only: each tuple corresponds to a different client.
     Channels {
@@ -46,11 +66,17 @@ pub enum ServerTransport {
             Sender<Vec<u8>>,
         )>,
     },
+    /// Replay the packets received in a [`Capture`], at their original timing and from the
+    /// addresses of the clients that sent them. The packets sent are dropped.
+    ///
+    /// The netcode server cannot accept the replayed connections: the challenge tokens of the
+    /// capture are encrypted with a key generated by the captured server.
+    Replay(Capture),
     /// Dummy transport if the connection handles its own io (for example steam sockets)
     Dummy,
 }
 
-/// We provide a manual implementation because wtranport's `Identity` does not implement Clone
+/// We provide a manual implementation because wtranport's `Identity` and `QuicIdentity` do not implement Clone
 impl Clone for ServerTransport {
     #[inline]
     fn clone(&self) -> ServerTransport {
@@ -66,15 +92,28 @@ impl Clone for ServerTransport {
                 server_addr: Clone::clone(__self_0),
                 certificate: __self_1.clone_identity(),
             },
+            #[cfg(all(feature = "quic", not(target_family = "wasm")))]
+            ServerTransport::QuicServer {
+                server_addr: __self_0,
+                identity: __self_1,
+            } => ServerTransport::QuicServer {
+                server_addr: Clone::clone(__self_0),
+                identity: __self_1.clone_identity(),
+            },
             #[cfg(all(feature = "websocket", not(target_family = "wasm")))]
             ServerTransport::WebSocketServer {
                 server_addr: __self_0,
             } => ServerTransport::WebSocketServer {
                 server_addr: Clone::clone(__self_0),
             },
+            #[cfg(unix)]
+            ServerTransport::UnixSocket(__self_0) => {
+                ServerTransport::UnixSocket(Clone::clone(__self_0))
+            }
             ServerTransport::Channels { channels: __self_0 } => ServerTransport::Channels {
                 channels: Clone::clone(__self_0),
             },
+            ServerTransport::Replay(__self_0) => ServerTransport::Replay(Clone::clone(__self_0)),
             ServerTransport::Dummy => ServerTransport::Dummy,
         }
     }
@@ -94,15 +133,30 @@ impl ServerTransport {
                 server_addr,
                 certificate,
             }),
+            #[cfg(all(feature = "quic", not(target_family = "wasm")))]
+            ServerTransport::QuicServer {
+                server_addr,
+                identity,
+            } => ServerTransportBuilderEnum::QuicServer(QuicServerSocketBuilder {
+                server_addr,
+                identity,
+            }),
             #[cfg(all(feature = "websocket", not(target_family = "wasm")))]
             ServerTransport::WebSocketServer { server_addr } => {
                 ServerTransportBuilderEnum::WebSocketServer(WebSocketServerSocketBuilder {
                     server_addr,
                 })
             }
+            #[cfg(unix)]
+            ServerTransport::UnixSocket(server_path) => {
+                ServerTransportBuilderEnum::UnixSocket(UnixSocketServerBuilder { server_path })
+            }
             ServerTransport::Channels { channels } => {
                 ServerTransportBuilderEnum::Channels(Channels::new(channels))
             }
+            ServerTransport::Replay(capture) => {
+                ServerTransportBuilderEnum::Replay(ReplayBuilder { capture })
+            }
             ServerTransport::Dummy => ServerTransportBuilderEnum::Dummy(DummyIo),
         }
     }
@@ -118,28 +172,33 @@ impl SharedIoConfig<ServerTransport> {
     pub fn start(self) -> Result<Io> {
         let (transport, state, io_rx, network_tx) = self.transport.build().start()?;
         let local_addr = transport.local_addr();
-        #[allow(unused_mut)]
-        let (mut sender, receiver) = transport.split();
-        #[allow(unused_mut)]
-        let mut receiver: BoxedReceiver = if let Some(conditioner_config) = self.conditioner {
-            let conditioner = LinkConditioner::new(conditioner_config);
-            Box::new(conditioner.wrap(receiver))
-        } else {
-            Box::new(receiver)
-        };
+        let (sender, receiver) = transport.split();
+        let conditioner = self.conditioner.map(LinkConditionerHandle::new);
+        let (mut sender, mut receiver): (BoxedSender, BoxedReceiver) =
+            if let Some(conditioner) = &conditioner {
+                let outgoing = LinkConditioner::outgoing(conditioner.clone());
+                let incoming = LinkConditioner::incoming(conditioner.clone());
+                (
+                    Box::new(PacketSenderWrapper::wrap(outgoing, sender)),
+                    Box::new(PacketReceiverWrapper::wrap(incoming, receiver)),
+                )
+            } else {
+                (sender, receiver)
+            };
         match self.compression {
             CompressionConfig::None => {}
             #[cfg(feature = "zstd")]
             CompressionConfig::Zstd { level } => {
-                use crate::transport::middleware::PacketSenderWrapper;
                 let compressor = ZstdCompressor::new(level);
                 sender = Box::new(compressor.wrap(sender));
                 let decompressor = ZstdDecompressor::new();
                 receiver = Box::new(decompressor.wrap(receiver));
             }
+            // the dictionary compression is applied to the netcode payloads, before encryption
+            #[cfg(feature = "zstd")]
+            CompressionConfig::ZstdDictionary { .. } => {}
             #[cfg(feature = "lz4")]
             CompressionConfig::Lz4 => {
-                use crate::transport::middleware::PacketSenderWrapper;
                 let compressor =
                     crate::transport::middleware::compression::lz4::Compressor::default();
                 sender = Box::new(compressor.wrap(sender));
@@ -148,6 +207,11 @@ impl SharedIoConfig<ServerTransport> {
                 receiver = Box::new(decompressor.wrap(receiver));
             }
         }
+        // the capture is applied last so that it records the packets as seen by netcode
+        if let Some(capture) = self.capture {
+            sender = Box::new(PacketSenderWrapper::wrap(capture.clone(), sender));
+            receiver = Box::new(PacketReceiverWrapper::wrap(capture, receiver));
+        }
         Ok(BaseIo {
             local_addr,
             sender,
@@ -158,6 +222,7 @@ impl SharedIoConfig<ServerTransport> {
                 event_sender: network_tx,
                 event_receiver: io_rx,
             },
+            conditioner,
         })
     }
 }
@@ -3,7 +3,12 @@ use crate::transport::channels::Channels;
 use crate::transport::dummy::DummyIo;
 use crate::transport::error::Result;
 use crate::transport::io::IoState;
+#[cfg(all(feature = "quic", not(target_family = "wasm")))]
+use crate::transport::quic::server::{QuicServerSocket, QuicServerSocketBuilder};
+use crate::transport::replay::{ReplayBuilder, ReplayTransport};
 use crate::transport::udp::{UdpSocket, UdpSocketBuilder};
+#[cfg(unix)]
+use crate::transport::unix::{UnixSocketServer, UnixSocketServerBuilder};
 #[cfg(all(feature = "websocket", not(target_family = "wasm")))]
 use crate::transport::websocket::server::{WebSocketServerSocket, WebSocketServerSocketBuilder};
 #[cfg(all(feature = "webtransport", not(target_family = "wasm")))]
@@ -30,9 +35,14 @@ pub(crate) enum ServerTransportBuilderEnum {
     UdpSocket(UdpSocketBuilder),
     #[cfg(all(feature = "webtransport", not(target_family = "wasm")))]
     WebTransportServer(WebTransportServerSocketBuilder),
+    #[cfg(all(feature = "quic", not(target_family = "wasm")))]
+    QuicServer(QuicServerSocketBuilder),
     #[cfg(all(feature = "websocket", not(target_family = "wasm")))]
     WebSocketServer(WebSocketServerSocketBuilder),
+    #[cfg(unix)]
+    UnixSocket(UnixSocketServerBuilder),
     Channels(Channels),
+    Replay(ReplayBuilder),
     Dummy(DummyIo),
 }
 
@@ -42,8 +52,13 @@ pub(crate) enum ServerTransportEnum {
     UdpSocket(UdpSocket),
     #[cfg(all(feature = "webtransport", not(target_family = "wasm")))]
     WebTransportServer(WebTransportServerSocket),
+    #[cfg(all(feature = "quic", not(target_family = "wasm")))]
+    QuicServer(QuicServerSocket),
     #[cfg(all(feature = "websocket", not(target_family = "wasm")))]
     WebSocketServer(WebSocketServerSocket),
+    #[cfg(unix)]
+    UnixSocket(UnixSocketServer),
     Channels(Channels),
+    Replay(ReplayTransport),
     Dummy(DummyIo),
 }
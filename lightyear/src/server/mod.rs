@@ -20,8 +20,12 @@ pub mod plugin;
 pub(crate) mod message;
 pub(crate) mod prediction;
 
+pub mod priority;
+
 pub mod clients;
 pub(crate) mod networking;
 pub mod relevance;
 pub mod replication;
+pub(crate) mod rpc;
 pub mod run_conditions;
+pub(crate) mod trigger;
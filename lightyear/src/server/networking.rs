@@ -5,6 +5,7 @@ use crate::prelude::{
     TimeManager,
 };
 use crate::protocol::component::ComponentRegistry;
+use crate::protocol::fingerprint::ProtocolFingerprint;
 use crate::serialize::reader::Reader;
 use crate::server::clients::ControlledEntities;
 use crate::server::config::ServerConfig;
@@ -12,6 +13,8 @@ use crate::server::connection::ConnectionManager;
 use crate::server::error::ServerError;
 use crate::server::io::ServerIoEvent;
 use crate::shared::sets::{InternalMainSet, ServerMarker};
+use crate::transport::middleware::conditioner::LinkConditionerProfile;
+use crate::transport::PacketSender;
 use async_channel::TryRecvError;
 use bevy::ecs::system::{RunSystemOnce, SystemChangeTick};
 use bevy::prelude::*;
@@ -59,10 +62,21 @@ impl Plugin for ServerNetworkingPlugin {
                 PostUpdate,
                 (send, send_host_server.run_if(is_host_server))
                     .in_set(InternalMainSet::<ServerMarker>::Send),
+            )
+            // the link conditioner can hold back packets, so we flush them every frame
+            .add_systems(
+                PostUpdate,
+                (update_link_conditioner, flush_io)
+                    .chain()
+                    .after(InternalMainSet::<ServerMarker>::Send)
+                    .run_if(is_started),
             );
 
         // ON_START
-        app.add_systems(OnEnter(NetworkingState::Started), on_start);
+        app.add_systems(
+            OnEnter(NetworkingState::Started),
+            (on_start, apply_link_conditioner_profile).chain(),
+        );
 
         // ON_STOP
         app.add_systems(OnEnter(NetworkingState::Stopped), on_stop);
@@ -137,7 +151,7 @@ pub(crate) fn receive_packets(
             let client_entity = commands
                 .spawn((ControlledEntities::default(), Name::new("Client")))
                 .id();
-            connection_manager.add(client_id, client_entity);
+            connection_manager.add(client_id, client_entity, netserver.peer_versions(client_id));
         }
 
         // handle disconnections
@@ -266,9 +280,15 @@ pub(crate) fn send(
                 .servers
                 .get_mut(netserver_idx)
                 .ok_or(ServerError::ServerConnectionNotFound)?;
+            connection
+                .message_manager
+                .set_transport_capabilities(netserver.transport_capabilities(*client_id));
             for packet_byte in connection.send_packets(&time_manager, &tick_manager)? {
                 netserver.send(packet_byte.as_slice(), *client_id)?;
             }
+            for (channel_id, packet_byte) in connection.message_manager.take_stream_payloads() {
+                netserver.send_on_stream(packet_byte.as_slice(), *client_id, channel_id)?;
+            }
             Ok(())
         })
         .unwrap_or_else(|e: ServerError| {
@@ -276,6 +296,47 @@ pub(crate) fn send(
         });
 }
 
+/// Apply the [`LinkConditionerProfile`] to the ios when it is inserted, changed or removed
+fn update_link_conditioner(
+    profile: Option<Res<LinkConditionerProfile>>,
+    mut has_profile: Local<bool>,
+    netservers: Res<ServerConnections>,
+) {
+    let changed = profile.as_ref().is_some_and(|p| p.is_changed());
+    let removed = profile.is_none() && *has_profile;
+    *has_profile = profile.is_some();
+    if changed || removed {
+        apply_link_conditioner_profile(profile, netservers);
+    }
+}
+
+/// Start the [`LinkConditionerProfile`] on the ios of the servers
+fn apply_link_conditioner_profile(
+    profile: Option<Res<LinkConditionerProfile>>,
+    netservers: Res<ServerConnections>,
+) {
+    for io in netservers
+        .servers
+        .iter()
+        .filter_map(|netserver| netserver.io())
+    {
+        io.set_conditioner_profile(profile.as_deref().cloned());
+    }
+}
+
+/// Send the packets that were buffered in the ios
+fn flush_io(mut netservers: ResMut<ServerConnections>) {
+    for io in netservers
+        .servers
+        .iter_mut()
+        .filter_map(|netserver| netserver.io_mut())
+    {
+        let _ = io
+            .flush()
+            .inspect_err(|e| error!("Error flushing io: {}", e));
+    }
+}
+
 /// When running in host-server mode, we also need to send messages to the local client.
 /// We do this directly without io.
 pub(crate) fn send_host_server(
@@ -333,7 +394,12 @@ fn rebuild_server_connections(world: &mut World) {
     world.insert_resource(connection_manager);
 
     // rebuild the server connections and insert them
-    let server_connections = ServerConnections::new(server_config.net);
+    let mut server_connections = ServerConnections::new(server_config.net);
+    server_connections.set_protocol_fingerprint(&ProtocolFingerprint::new(
+        world.resource::<ComponentRegistry>(),
+        world.resource::<MessageRegistry>(),
+        world.resource::<ChannelRegistry>(),
+    ));
     world.insert_resource(server_connections);
 }
 
@@ -377,3 +443,67 @@ impl ServerCommands for Commands<'_, '_> {
         self.insert_resource(NextState::Pending(NetworkingState::Stopped));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::server::NetConfig;
+    use crate::prelude::{LinkConditionerConfig, LinkConditions, PacketLoss};
+    use crate::tests::stepper::BevyStepper;
+
+    fn pongs_received(stepper: &BevyStepper) -> u32 {
+        stepper
+            .client_app
+            .world()
+            .resource::<crate::client::connection::ConnectionManager>()
+            .ping_manager
+            .pongs_recv
+    }
+
+    /// Switch the server's link conditions at runtime with a `LinkConditionerProfile`
+    #[test]
+    fn test_link_conditioner_profile() {
+        let mut stepper = BevyStepper::default();
+        stepper.stop();
+        #[allow(irrefutable_let_patterns)]
+        if let NetConfig::Netcode { io, .. } = stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ServerConfig>()
+            .net
+            .first_mut()
+            .unwrap()
+        {
+            io.conditioner = Some(LinkConditionerConfig::default());
+        }
+        stepper.start();
+        stepper.frame_step();
+
+        // drop all the packets sent by the server, so that the client doesn't receive any pongs
+        stepper
+            .server_app
+            .world_mut()
+            .insert_resource(LinkConditionerProfile::constant(LinkConditionerConfig {
+                incoming: LinkConditions::default(),
+                outgoing: LinkConditions::default().with_loss(PacketLoss::Uniform(1.0)),
+            }));
+        // the packets that were sent before the profile was applied can still arrive
+        stepper.frame_step();
+        stepper.frame_step();
+        let received = pongs_received(&stepper);
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+        assert_eq!(pongs_received(&stepper), received);
+
+        // go back to the base conditions
+        stepper
+            .server_app
+            .world_mut()
+            .remove_resource::<LinkConditionerProfile>();
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+        assert!(pongs_received(&stepper) > received);
+    }
+}
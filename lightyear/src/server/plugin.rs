@@ -12,6 +12,7 @@ use crate::server::clients::ClientsMetadataPlugin;
 use bevy::app::PluginGroupBuilder;
 use bevy::prelude::*;
 
+use crate::protocol::NetIdMode;
 use crate::server::events::ServerEventsPlugin;
 use crate::server::networking::ServerNetworkingPlugin;
 use crate::server::relevance::immediate::NetworkRelevancePlugin;
@@ -37,6 +38,7 @@ use super::config::ServerConfig;
 ///   disabled if you don't need server to client replication.
 pub struct ServerPlugins {
     pub config: ServerConfig,
+    net_id_mode: NetIdMode,
 }
 
 impl ServerPlugins {
@@ -47,7 +49,18 @@ impl ServerPlugins {
                 config.shared.server_replication_send_interval, config.replication.send_interval
             );
         }
-        Self { config }
+        Self {
+            config,
+            net_id_mode: NetIdMode::default(),
+        }
+    }
+
+    /// Set how the net ids of the registered messages and components are assigned.
+    ///
+    /// The clients must use the same [`NetIdMode`].
+    pub fn with_net_id_mode(mut self, net_id_mode: NetIdMode) -> Self {
+        self.net_id_mode = net_id_mode;
+        self
     }
 }
 
@@ -58,6 +71,7 @@ impl PluginGroup for ServerPlugins {
         builder
             .add(SetupPlugin {
                 config: self.config,
+                net_id_mode: self.net_id_mode,
             })
             .add(ServerEventsPlugin)
             .add(ServerNetworkingPlugin)
@@ -72,6 +86,7 @@ impl PluginGroup for ServerPlugins {
 /// A plugin that sets up the server by adding the [`ServerConfig`] resource and the [`SharedPlugin`] plugin.
 struct SetupPlugin {
     config: ServerConfig,
+    net_id_mode: NetIdMode,
 }
 
 impl Plugin for SetupPlugin {
@@ -82,10 +97,10 @@ impl Plugin for SetupPlugin {
         // PLUGINS
         // NOTE: SharedPlugin needs to be added after config
         if !app.is_plugin_added::<SharedPlugin>() {
-            app.add_plugins(SharedPlugin {
-                // TODO: move shared config out of server_config?
-                config: self.config.shared,
-            });
+            // TODO: move shared config out of server_config?
+            app.add_plugins(
+                SharedPlugin::new(self.config.shared).with_net_id_mode(self.net_id_mode),
+            );
         }
     }
 }
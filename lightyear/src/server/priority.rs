@@ -0,0 +1,273 @@
+/*! Dynamic per-client replication priority
+
+The priority of a [`ReplicationGroup`] is the same for every client. With a replication priority function,
+you can compute a priority multiplier for each (client, entity) pair, so that the entities that matter the
+most to a client (close to its player, in its crosshair, on its team, etc.) accumulate priority faster
+for that client. When the bandwidth is limited, the messages with the highest priority are sent first.
+
+The functions are evaluated every send interval, for every replicated entity and every client.
+If multiple functions are registered, their results are multiplied. The priority multiplier of a
+[`ReplicationGroup`] is the highest multiplier of its entities.
+
+```rust
+use bevy::prelude::*;
+use lightyear::prelude::*;
+use lightyear::prelude::server::*;
+
+#[derive(Component, PartialEq)]
+struct Team(u8);
+
+/// Team of each client
+#[derive(Resource)]
+struct ClientTeams(bevy::utils::HashMap<ClientId, Team>);
+
+fn add_priority(app: &mut App) {
+    // entities of the client's team are updated twice as fast
+    app.add_replication_priority_fn(|client_id: ClientId, entity: Entity, world: &World| {
+        let client_team = world.resource::<ClientTeams>().0.get(&client_id);
+        if client_team.is_some() && client_team == world.get::<Team>(entity) {
+            2.0
+        } else {
+            1.0
+        }
+    });
+}
+```
+*/
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use tracing::trace;
+
+use crate::connection::id::ClientId;
+use crate::prelude::ReplicationGroup;
+use crate::server::connection::ConnectionManager;
+use crate::shared::replication::components::{Replicating, ReplicationGroupId};
+
+/// Function that returns the priority multiplier used to replicate an entity to a client
+type PriorityFn = Box<dyn Fn(ClientId, Entity, &World) -> f32 + Send + Sync>;
+
+/// Replication priority functions registered with
+/// [`add_replication_priority_fn`](AppReplicationPriorityExt::add_replication_priority_fn)
+#[derive(Resource, Default)]
+pub(crate) struct ReplicationPriorityFns {
+    fns: Vec<PriorityFn>,
+}
+
+pub trait AppReplicationPriorityExt {
+    /// Register a function that returns the priority multiplier used to replicate an entity to a client.
+    ///
+    /// The function must not access the [`ConnectionManager`] resource, which is not in the [`World`]
+    /// while the function runs.
+    fn add_replication_priority_fn(
+        &mut self,
+        priority_fn: impl Fn(ClientId, Entity, &World) -> f32 + Send + Sync + 'static,
+    ) -> &mut Self;
+}
+
+impl AppReplicationPriorityExt for App {
+    fn add_replication_priority_fn(
+        &mut self,
+        priority_fn: impl Fn(ClientId, Entity, &World) -> f32 + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_insert_with(ReplicationPriorityFns::default)
+            .fns
+            .push(Box::new(priority_fn));
+        self
+    }
+}
+
+/// Compute the priority multiplier of each replication group for each client, using the
+/// replication priority functions
+pub(crate) fn update_dynamic_priority(world: &mut World) {
+    world.resource_scope(|world, priority_fns: Mut<ReplicationPriorityFns>| {
+        if priority_fns.fns.is_empty() {
+            return;
+        }
+        let mut query = world.query_filtered::<(Entity, &ReplicationGroup), With<Replicating>>();
+        world.resource_scope(|world, mut manager: Mut<ConnectionManager>| {
+            let mut priorities = HashMap::<ReplicationGroupId, f32>::default();
+            for (client_id, connection) in manager.connections.iter_mut() {
+                let sender = &mut connection.replication_sender;
+                for (entity, group) in query.iter(world) {
+                    let group_id = group.group_id(Some(entity));
+                    // only compute the priority of the groups that are replicated to the client
+                    if !sender.group_channels.contains_key(&group_id) {
+                        continue;
+                    }
+                    let priority: f32 = priority_fns
+                        .fns
+                        .iter()
+                        .map(|priority_fn| priority_fn(*client_id, entity, world))
+                        .product();
+                    priorities
+                        .entry(group_id)
+                        .and_modify(|p| *p = p.max(priority))
+                        .or_insert(priority);
+                }
+                for (group_id, priority) in priorities.drain() {
+                    trace!(?client_id, ?group_id, ?priority, "update dynamic priority");
+                    sender.update_dynamic_priority(group_id, priority);
+                }
+            }
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::utils::Duration;
+    use governor::Quota;
+    use nonzero_ext::nonzero;
+
+    use crate::prelude::server::{PacketConfig, Replicate, ServerConfig};
+    use crate::prelude::*;
+    use crate::tests::protocol::ComponentSyncModeFull;
+    use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
+
+    use super::*;
+
+    #[derive(Component)]
+    struct Important;
+
+    #[test]
+    fn test_dynamic_priority() {
+        let frame_duration = Duration::from_millis(10);
+        let shared_config = SharedConfig {
+            tick: TickConfig::new(frame_duration),
+            ..Default::default()
+        };
+        let mut stepper = BevyStepper::new(
+            shared_config,
+            client::ClientConfig::default(),
+            frame_duration,
+        );
+        stepper
+            .server_app
+            .add_replication_priority_fn(|_, entity, world| {
+                if world.get::<Important>(entity).is_some() {
+                    3.0
+                } else {
+                    1.0
+                }
+            });
+        stepper.init();
+
+        let important = stepper
+            .server_app
+            .world_mut()
+            .spawn((Replicate::default(), Important))
+            .id();
+        let other = stepper
+            .server_app
+            .world_mut()
+            .spawn(Replicate::default())
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+
+        let manager = stepper.server_app.world().resource::<ConnectionManager>();
+        let sender = &manager
+            .connection(ClientId::Netcode(TEST_CLIENT_ID))
+            .unwrap()
+            .replication_sender;
+        let dynamic_priority = |entity: Entity| {
+            sender.group_channels[&ReplicationGroupId(entity.to_bits())].dynamic_priority
+        };
+        assert_eq!(dynamic_priority(important), 3.0);
+        assert_eq!(dynamic_priority(other), 1.0);
+    }
+
+    /// With a bandwidth cap, the updates of the entity with the highest dynamic priority are sent first
+    #[test]
+    fn test_dynamic_priority_bandwidth_cap() {
+        let frame_duration = Duration::from_millis(10);
+        let shared_config = SharedConfig {
+            tick: TickConfig::new(frame_duration),
+            ..Default::default()
+        };
+        let mut stepper = BevyStepper::new(
+            shared_config,
+            client::ClientConfig::default(),
+            frame_duration,
+        );
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ServerConfig>()
+            .packet = PacketConfig::default()
+            .with_send_bandwidth_cap(
+                Quota::per_second(nonzero!(9000u32)).allow_burst(nonzero!(90u32)),
+            )
+            .enable_bandwidth_cap();
+        stepper
+            .server_app
+            .add_replication_priority_fn(|_, entity, world| {
+                if world.get::<Important>(entity).is_some() {
+                    3.0
+                } else {
+                    1.0
+                }
+            });
+        stepper.init();
+
+        // without the priority function, the other entity would be updated first
+        let other = stepper
+            .server_app
+            .world_mut()
+            .spawn((
+                Replicate {
+                    group: ReplicationGroup::new_from_entity().set_priority(2.0),
+                    ..default()
+                },
+                ComponentSyncModeFull(0.0),
+            ))
+            .id();
+        let important = stepper
+            .server_app
+            .world_mut()
+            .spawn((Replicate::default(), ComponentSyncModeFull(0.0), Important))
+            .id();
+        for _ in 0..20 {
+            stepper.frame_step();
+        }
+        let client_value = |stepper: &BevyStepper, server_entity: Entity| {
+            let client_entity = stepper
+                .client_app
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .unwrap();
+            stepper
+                .client_app
+                .world()
+                .get::<ComponentSyncModeFull>(client_entity)
+                .unwrap()
+                .0
+        };
+        assert_eq!(client_value(&stepper, other), 0.0);
+        assert_eq!(client_value(&stepper, important), 0.0);
+
+        // update both entities on the same tick: only one update fits in the bandwidth of a tick
+        for entity in [other, important] {
+            stepper
+                .server_app
+                .world_mut()
+                .get_mut::<ComponentSyncModeFull>(entity)
+                .unwrap()
+                .0 = 1.0;
+        }
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(client_value(&stepper, important), 1.0);
+        assert_eq!(client_value(&stepper, other), 0.0);
+
+        // the other entity gets updated once its accumulated priority catches up
+        for _ in 0..10 {
+            stepper.frame_step();
+        }
+        assert_eq!(client_value(&stepper, other), 1.0);
+    }
+}
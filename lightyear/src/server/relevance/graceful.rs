@@ -0,0 +1,328 @@
+/*! Graceful relevance loss
+
+By default, when an entity loses relevance for a client (with [`RelevanceManager::lose_relevance`](crate::prelude::server::RelevanceManager::lose_relevance)
+or because the client left the entity's room), the entity is despawned on the client right away.
+
+If the entity has the [`GracefulRelevanceLoss`] component, the client instead keeps the entity and marks it with
+the [`RelevanceLost`](crate::prelude::RelevanceLost) component, so that game code can fade it out or keep it as a stale ghost.
+- if the entity becomes relevant again before the end of the grace period, the client keeps using the same entity.
+  The components that were removed on the server in the meantime are removed on the client
+- otherwise, the entity is despawned on the client at the end of the grace period
+
+```rust
+use bevy::prelude::*;
+use bevy::utils::Duration;
+use lightyear::prelude::*;
+use lightyear::prelude::server::*;
+
+fn spawn_enemy(mut commands: Commands) {
+    commands.spawn((
+        Replicate {
+            relevance_mode: NetworkRelevanceMode::InterestManagement,
+            ..default()
+        },
+        GracefulRelevanceLoss::new(Duration::from_secs(2)),
+    ));
+}
+
+/// On the client, fade out the entities that are not relevant anymore
+fn fade_out(query: Query<Entity, Added<RelevanceLost>>) {
+    for entity in query.iter() {
+        // ...
+    }
+}
+```
+*/
+use bevy::prelude::*;
+use bevy::utils::Duration;
+use tracing::{debug, error};
+
+use crate::server::connection::ConnectionManager;
+use crate::shared::replication::network_target::NetworkTarget;
+use crate::shared::tick_manager::TickManager;
+
+/// When the entity loses relevance for a client, the client keeps the entity for `grace_period`
+/// instead of despawning it immediately
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct GracefulRelevanceLoss {
+    /// How long the client keeps the entity after it lost relevance
+    pub grace_period: Duration,
+}
+
+impl GracefulRelevanceLoss {
+    pub fn new(grace_period: Duration) -> Self {
+        Self { grace_period }
+    }
+
+    /// Convert the grace period to a number of ticks
+    pub(crate) fn grace_ticks(&self, tick_duration: Duration) -> i16 {
+        let ticks = (self.grace_period.as_secs_f64() / tick_duration.as_secs_f64()).round();
+        // the tick difference is computed as an i16
+        ticks.min(i16::MAX as f64) as i16
+    }
+}
+
+/// Despawn the entities on the clients for which the grace period of the relevance loss is over
+pub(crate) fn despawn_expired_relevance(
+    tick_manager: Res<TickManager>,
+    mut manager: ResMut<ConnectionManager>,
+) {
+    let tick = tick_manager.tick();
+    let mut expired = vec![];
+    for (client_id, connection) in manager.connections.iter_mut() {
+        connection
+            .relevance_lost
+            .retain(|entity, (group_id, grace_end)| {
+                if tick - *grace_end >= 0 {
+                    expired.push((*client_id, *entity, *group_id));
+                    return false;
+                }
+                true
+            });
+    }
+    for (client_id, entity, group_id) in expired {
+        debug!(
+            ?client_id,
+            ?entity,
+            "grace period of the relevance loss is over"
+        );
+        let _ = manager
+            .prepare_entity_despawn(entity, group_id, NetworkTarget::Single(client_id))
+            .inspect_err(|e| {
+                error!("error sending entity despawn: {:?}", e);
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::server::{RelevanceManager, Replicate};
+    use crate::prelude::*;
+    use crate::tests::protocol::{ComponentSyncModeFull, ComponentSyncModeSimple};
+    use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
+
+    use super::*;
+
+    #[test]
+    fn test_graceful_relevance_loss() {
+        let mut stepper = BevyStepper::default();
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+        let grace_period = stepper.frame_duration * 10;
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((
+                Replicate {
+                    relevance_mode: NetworkRelevanceMode::InterestManagement,
+                    ..default()
+                },
+                GracefulRelevanceLoss::new(grace_period),
+                ComponentSyncModeFull(1.0),
+            ))
+            .id();
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<RelevanceManager>()
+            .gain_relevance(client_id, server_entity);
+        stepper.frame_step();
+        stepper.frame_step();
+        let client_entity = stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client");
+
+        // the client keeps the entity when it loses relevance
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<RelevanceManager>()
+            .lose_relevance(client_id, server_entity);
+        stepper.frame_step();
+        stepper.frame_step();
+        assert!(stepper
+            .client_app
+            .world()
+            .get::<RelevanceLost>(client_entity)
+            .is_some());
+
+        // the same entity is reused when it regains relevance
+        stepper
+            .server_app
+            .world_mut()
+            .get_mut::<ComponentSyncModeFull>(server_entity)
+            .unwrap()
+            .0 = 2.0;
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<RelevanceManager>()
+            .gain_relevance(client_id, server_entity);
+        stepper.frame_step();
+        stepper.frame_step();
+        assert!(stepper
+            .client_app
+            .world()
+            .get::<RelevanceLost>(client_entity)
+            .is_none());
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get::<ComponentSyncModeFull>(client_entity),
+            Some(&ComponentSyncModeFull(2.0))
+        );
+
+        // the entity is despawned at the end of the grace period
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<RelevanceManager>()
+            .lose_relevance(client_id, server_entity);
+        stepper.frame_step();
+        stepper.frame_step();
+        assert!(stepper.client_app.world().get_entity(client_entity).is_ok());
+        for _ in 0..12 {
+            stepper.frame_step();
+        }
+        assert!(stepper
+            .client_app
+            .world()
+            .get_entity(client_entity)
+            .is_err());
+    }
+
+    /// The entity is despawned right away on the clients that kept it when it is despawned on the server
+    #[test]
+    fn test_graceful_relevance_loss_server_despawn() {
+        let mut stepper = BevyStepper::default();
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((
+                Replicate {
+                    relevance_mode: NetworkRelevanceMode::InterestManagement,
+                    ..default()
+                },
+                GracefulRelevanceLoss::new(Duration::from_secs(10)),
+            ))
+            .id();
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<RelevanceManager>()
+            .gain_relevance(client_id, server_entity);
+        stepper.frame_step();
+        stepper.frame_step();
+        let client_entity = stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client");
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<RelevanceManager>()
+            .lose_relevance(client_id, server_entity);
+        stepper.frame_step();
+        stepper.frame_step();
+        assert!(stepper.client_app.world().get_entity(client_entity).is_ok());
+
+        stepper.server_app.world_mut().despawn(server_entity);
+        stepper.frame_step();
+        stepper.frame_step();
+        assert!(stepper
+            .client_app
+            .world()
+            .get_entity(client_entity)
+            .is_err());
+    }
+
+    /// The components removed on the server while the entity was not relevant are removed on the
+    /// client when the entity regains relevance
+    #[test]
+    fn test_graceful_relevance_loss_component_removed() {
+        let mut stepper = BevyStepper::default();
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((
+                Replicate {
+                    relevance_mode: NetworkRelevanceMode::InterestManagement,
+                    ..default()
+                },
+                GracefulRelevanceLoss::new(Duration::from_secs(10)),
+                ComponentSyncModeFull(1.0),
+                ComponentSyncModeSimple(1.0),
+            ))
+            .id();
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<RelevanceManager>()
+            .gain_relevance(client_id, server_entity);
+        stepper.frame_step();
+        stepper.frame_step();
+        let client_entity = stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client");
+        assert!(stepper
+            .client_app
+            .world()
+            .get::<ComponentSyncModeSimple>(client_entity)
+            .is_some());
+
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<RelevanceManager>()
+            .lose_relevance(client_id, server_entity);
+        stepper.frame_step();
+        stepper.frame_step();
+        stepper
+            .server_app
+            .world_mut()
+            .entity_mut(server_entity)
+            .remove::<ComponentSyncModeSimple>();
+        stepper.frame_step();
+        stepper.frame_step();
+        // the client doesn't receive the removal while the entity is not relevant
+        assert!(stepper
+            .client_app
+            .world()
+            .get::<ComponentSyncModeSimple>(client_entity)
+            .is_some());
+
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<RelevanceManager>()
+            .gain_relevance(client_id, server_entity);
+        stepper.frame_step();
+        stepper.frame_step();
+        let client_world = stepper.client_app.world();
+        assert!(client_world.get::<RelevanceLost>(client_entity).is_none());
+        assert!(client_world
+            .get::<ComponentSyncModeSimple>(client_entity)
+            .is_none());
+        assert_eq!(
+            client_world.get::<ComponentSyncModeFull>(client_entity),
+            Some(&ComponentSyncModeFull(1.0))
+        );
+    }
+}
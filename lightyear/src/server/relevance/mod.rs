@@ -1,4 +1,6 @@
 pub mod immediate;
 
 pub mod error;
+pub mod graceful;
 pub mod room;
+pub mod spatial;
@@ -0,0 +1,669 @@
+/*! Spatial network relevance module, where entities are relevant to the clients that are close to them
+
+# Spatial relevance
+
+The [`SpatialRelevancePlugin`] keeps a uniform grid of the entities that use
+[`NetworkRelevanceMode::InterestManagement`], and makes an entity relevant to a client if it is close to the
+[`SpatialViewer`] of that client.
+
+The position of the entities is read from a component that implements [`SpatialPosition`] (for example [`Transform`]).
+
+To avoid entities flickering in and out of relevance at the edge of the view of a client, the relevance
+uses hysteresis: an entity becomes relevant when it gets within [`enter_radius`](SpatialRelevanceConfig::enter_radius)
+of the viewer, and stops being relevant only when it gets further than [`leave_radius`](SpatialRelevanceConfig::leave_radius).
+
+```rust
+use bevy::prelude::*;
+use lightyear::prelude::*;
+use lightyear::prelude::server::*;
+
+fn add_plugin(app: &mut App) {
+    app.add_plugins(SpatialRelevancePlugin::<Transform>::new(SpatialRelevanceConfig {
+        cell_size: 200.0,
+        enter_radius: 150.0,
+        leave_radius: 200.0,
+    }));
+}
+
+fn spawn_player(mut commands: Commands) {
+    // entities close to this player will be relevant to the client
+    commands.spawn((
+        Transform::default(),
+        SpatialViewer { client_id: ClientId::Netcode(0) },
+    ));
+}
+```
+
+## Implementation
+
+The relevance of each client is only computed from the entities in the cells of the grid around its viewer,
+and only the changes of relevance are sent to the [`RelevanceManager`].
+The spatial relevance can be combined with rooms or with the [`RelevanceManager`], but an entity that is
+handled by the spatial relevance will lose its relevance when it gets far from the viewer, even if
+it was made relevant by another mechanism.
+
+*/
+use std::marker::PhantomData;
+
+use bevy::ecs::entity::{EntityHashMap, EntityHashSet};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use tracing::trace;
+
+use crate::connection::id::ClientId;
+use crate::prelude::server::is_started;
+use crate::prelude::NetworkRelevanceMode;
+use crate::server::relevance::immediate::{NetworkRelevanceSet, RelevanceManager};
+use crate::shared::sets::{InternalReplicationSet, ServerMarker};
+
+/// Component that holds the position of an entity for the spatial relevance
+pub trait SpatialPosition: Component {
+    /// Position of the entity in the world
+    fn spatial_position(&self) -> Vec3;
+}
+
+impl SpatialPosition for Transform {
+    fn spatial_position(&self) -> Vec3 {
+        self.translation
+    }
+}
+
+impl SpatialPosition for GlobalTransform {
+    fn spatial_position(&self) -> Vec3 {
+        self.translation()
+    }
+}
+
+/// Marks the entity whose position is the point of view of a client for the spatial relevance
+/// (for example the entity controlled by the client).
+///
+/// Each client should have at most one viewer.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct SpatialViewer {
+    pub client_id: ClientId,
+}
+
+/// Configuration of the [`SpatialRelevancePlugin`]
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct SpatialRelevanceConfig {
+    /// Size of the cells of the grid. It should be close to the `leave_radius`, so that only a few cells
+    /// are checked for each client
+    pub cell_size: f32,
+    /// An entity becomes relevant to a client when it gets within this distance of the client's viewer
+    pub enter_radius: f32,
+    /// An entity stops being relevant to a client when it gets further than this distance from the
+    /// client's viewer. It should be greater than the `enter_radius`
+    pub leave_radius: f32,
+}
+
+impl Default for SpatialRelevanceConfig {
+    fn default() -> Self {
+        Self {
+            cell_size: 100.0,
+            enter_radius: 100.0,
+            leave_radius: 120.0,
+        }
+    }
+}
+
+/// Resource that keeps the grid of entities used for spatial relevance
+#[derive(Resource, Debug)]
+pub struct SpatialRelevanceManager {
+    config: SpatialRelevanceConfig,
+    /// Entities in each cell of the grid
+    cells: HashMap<IVec3, EntityHashSet>,
+    /// Cell and position of each entity
+    entities: EntityHashMap<(IVec3, Vec3)>,
+    /// Entities that are currently relevant to each client
+    relevant: HashMap<ClientId, EntityHashSet>,
+}
+
+impl SpatialRelevanceManager {
+    /// Create a new [`SpatialRelevanceManager`]
+    ///
+    /// # Panics
+    ///
+    /// Panics if `config.cell_size` is not strictly positive.
+    pub fn new(config: SpatialRelevanceConfig) -> Self {
+        assert!(
+            config.cell_size > 0.0,
+            "the spatial relevance cell size must be positive, got {}",
+            config.cell_size
+        );
+        Self {
+            config,
+            cells: HashMap::default(),
+            entities: EntityHashMap::default(),
+            relevant: HashMap::default(),
+        }
+    }
+
+    pub fn config(&self) -> &SpatialRelevanceConfig {
+        &self.config
+    }
+
+    /// Update the radius used to compute the relevance. The new radius are used from the next update.
+    pub fn set_radius(&mut self, enter_radius: f32, leave_radius: f32) {
+        self.config.enter_radius = enter_radius;
+        self.config.leave_radius = leave_radius;
+    }
+
+    /// Entities that are currently relevant to the client because of their distance to its viewer
+    pub fn relevant_entities(&self, client_id: ClientId) -> Option<&EntityHashSet> {
+        self.relevant.get(&client_id)
+    }
+
+    /// Iterate over the entities of the grid that are within `radius` of `position`
+    pub fn entities_in_radius(
+        &self,
+        position: Vec3,
+        radius: f32,
+    ) -> impl Iterator<Item = Entity> + '_ {
+        candidates(&self.cells, self.config.cell_size, position, radius).filter(move |entity| {
+            self.entities[entity].1.distance_squared(position) <= radius * radius
+        })
+    }
+
+    fn cell(&self, position: Vec3) -> IVec3 {
+        cell(self.config.cell_size, position)
+    }
+
+    /// Add an entity to the grid, or update its position
+    fn update_entity(&mut self, entity: Entity, position: Vec3) {
+        let new_cell = self.cell(position);
+        if let Some((cell, old_position)) = self.entities.get_mut(&entity) {
+            *old_position = position;
+            if *cell == new_cell {
+                return;
+            }
+            let old_cell = std::mem::replace(cell, new_cell);
+            if let Some(entities) = self.cells.get_mut(&old_cell) {
+                entities.remove(&entity);
+                if entities.is_empty() {
+                    self.cells.remove(&old_cell);
+                }
+            }
+        } else {
+            self.entities.insert(entity, (new_cell, position));
+        }
+        self.cells.entry(new_cell).or_default().insert(entity);
+    }
+
+    /// Remove an entity from the grid. The entity is not relevant to any client anymore.
+    ///
+    /// If a [`RelevanceManager`] is provided, the entity loses its relevance for the clients it was relevant to.
+    fn remove_entity(
+        &mut self,
+        entity: Entity,
+        mut relevance_manager: Option<&mut RelevanceManager>,
+    ) {
+        let Some((cell, _)) = self.entities.remove(&entity) else {
+            return;
+        };
+        if let Some(entities) = self.cells.get_mut(&cell) {
+            entities.remove(&entity);
+            if entities.is_empty() {
+                self.cells.remove(&cell);
+            }
+        }
+        for (client_id, relevant) in self.relevant.iter_mut() {
+            if relevant.remove(&entity) {
+                if let Some(relevance_manager) = relevance_manager.as_deref_mut() {
+                    relevance_manager.lose_relevance(*client_id, entity);
+                }
+            }
+        }
+    }
+
+    /// Compute the entities that are relevant to a client from the position of its viewer,
+    /// and send the changes of relevance to the [`RelevanceManager`]
+    fn update_viewer(
+        &mut self,
+        client_id: ClientId,
+        position: Vec3,
+        relevance_manager: &mut RelevanceManager,
+    ) {
+        let enter_radius = self.config.enter_radius;
+        let leave_radius = self.config.leave_radius.max(enter_radius);
+        let relevant = self.relevant.entry(client_id).or_default();
+        let mut new_relevant = EntityHashSet::default();
+        for entity in candidates(&self.cells, self.config.cell_size, position, leave_radius) {
+            let distance_squared = self.entities[&entity].1.distance_squared(position);
+            let was_relevant = relevant.contains(&entity);
+            if distance_squared <= enter_radius * enter_radius
+                || (was_relevant && distance_squared <= leave_radius * leave_radius)
+            {
+                new_relevant.insert(entity);
+                if !was_relevant {
+                    trace!(?client_id, ?entity, "entity entered the view of the client");
+                    relevance_manager.gain_relevance(client_id, entity);
+                }
+            }
+        }
+        for entity in relevant.difference(&new_relevant) {
+            trace!(?client_id, ?entity, "entity left the view of the client");
+            relevance_manager.lose_relevance(client_id, *entity);
+        }
+        *relevant = new_relevant;
+    }
+
+    /// Remove the viewer of a client. None of the entities are relevant to the client anymore.
+    fn remove_viewer(&mut self, client_id: ClientId, relevance_manager: &mut RelevanceManager) {
+        if let Some(relevant) = self.relevant.remove(&client_id) {
+            for entity in relevant {
+                relevance_manager.lose_relevance(client_id, entity);
+            }
+        }
+    }
+}
+
+fn cell(cell_size: f32, position: Vec3) -> IVec3 {
+    (position / cell_size).floor().as_ivec3()
+}
+
+/// Iterate over the entities in the cells that intersect the cube of half-size `radius` around `position`
+fn candidates(
+    cells: &HashMap<IVec3, EntityHashSet>,
+    cell_size: f32,
+    position: Vec3,
+    radius: f32,
+) -> impl Iterator<Item = Entity> + '_ {
+    let min = cell(cell_size, position - Vec3::splat(radius));
+    let max = cell(cell_size, position + Vec3::splat(radius));
+    (min.x..=max.x)
+        .flat_map(move |x| (min.y..=max.y).map(move |y| (x, y)))
+        .flat_map(move |(x, y)| (min.z..=max.z).map(move |z| IVec3::new(x, y, z)))
+        .filter_map(|cell| cells.get(&cell))
+        .flat_map(|entities| entities.iter().copied())
+}
+
+/// Plugin that computes the network relevance of entities from their distance to the [`SpatialViewer`] of each client.
+///
+/// The position of the entities is read from the component `P`.
+pub struct SpatialRelevancePlugin<P> {
+    config: SpatialRelevanceConfig,
+    marker: PhantomData<P>,
+}
+
+impl<P> SpatialRelevancePlugin<P> {
+    pub fn new(config: SpatialRelevanceConfig) -> Self {
+        Self {
+            config,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<P> Default for SpatialRelevancePlugin<P> {
+    fn default() -> Self {
+        Self::new(SpatialRelevanceConfig::default())
+    }
+}
+
+/// System sets related to the spatial relevance
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum SpatialRelevanceSet {
+    /// Update the grid with the positions of the entities, and send the changes of relevance
+    /// to the [`RelevanceManager`]
+    UpdateRelevance,
+}
+
+impl<P: SpatialPosition> Plugin for SpatialRelevancePlugin<P> {
+    fn build(&self, app: &mut App) {
+        // RESOURCES
+        app.insert_resource(SpatialRelevanceManager::new(self.config));
+        // SETS
+        app.configure_sets(
+            PostUpdate,
+            (
+                (
+                    // the spatial relevance must be computed before the relevance events are used
+                    SpatialRelevanceSet::UpdateRelevance,
+                    NetworkRelevanceSet::UpdateRelevance,
+                )
+                    .run_if(is_started)
+                    .chain(),
+                // the spatial relevance only needs to be computed every send_interval
+                SpatialRelevanceSet::UpdateRelevance
+                    .in_set(InternalReplicationSet::<ServerMarker>::SendMessages),
+            ),
+        );
+        // SYSTEMS
+        app.add_systems(
+            PostUpdate,
+            systems::update_spatial_relevance::<P>.in_set(SpatialRelevanceSet::UpdateRelevance),
+        );
+        app.add_observer(systems::handle_client_disconnect);
+        app.add_observer(systems::handle_viewer_removed);
+        app.add_observer(systems::handle_position_removed::<P>);
+        app.add_observer(systems::handle_relevance_mode_removed);
+    }
+}
+
+pub(super) mod systems {
+    use super::*;
+    use crate::server::connection::ConnectionManager;
+    use crate::server::events::DisconnectEvent;
+
+    /// Update the positions of the entities in the grid, then the relevance of the entities for each client
+    pub fn update_spatial_relevance<P: SpatialPosition>(
+        mut manager: ResMut<SpatialRelevanceManager>,
+        mut relevance_manager: ResMut<RelevanceManager>,
+        connection_manager: Res<ConnectionManager>,
+        entities: Query<(Entity, Ref<P>, Ref<NetworkRelevanceMode>)>,
+        viewers: Query<(&SpatialViewer, &P)>,
+    ) {
+        for (entity, position, relevance_mode) in entities.iter() {
+            match relevance_mode.as_ref() {
+                NetworkRelevanceMode::InterestManagement => {
+                    if position.is_changed() || relevance_mode.is_changed() {
+                        manager.update_entity(entity, position.spatial_position());
+                    }
+                }
+                NetworkRelevanceMode::All => {
+                    // the entity is now replicated to all clients, so its relevance cache was removed
+                    if relevance_mode.is_changed() {
+                        manager.remove_entity(entity, None);
+                    }
+                }
+            }
+        }
+        for (viewer, position) in viewers.iter() {
+            // the viewer of a client that is not connected (or has disconnected) doesn't see anything
+            if connection_manager.connection(viewer.client_id).is_err() {
+                continue;
+            }
+            manager.update_viewer(
+                viewer.client_id,
+                position.spatial_position(),
+                &mut relevance_manager,
+            );
+        }
+    }
+
+    /// Clear the relevance of a client when it disconnects
+    pub fn handle_client_disconnect(
+        trigger: Trigger<DisconnectEvent>,
+        mut manager: ResMut<SpatialRelevanceManager>,
+    ) {
+        manager.relevant.remove(&trigger.event().client_id);
+    }
+
+    /// Remove the relevance of all the entities for a client if its viewer is removed
+    pub fn handle_viewer_removed(
+        trigger: Trigger<OnRemove, SpatialViewer>,
+        viewers: Query<&SpatialViewer>,
+        mut manager: ResMut<SpatialRelevanceManager>,
+        mut relevance_manager: ResMut<RelevanceManager>,
+    ) {
+        if let Ok(viewer) = viewers.get(trigger.entity()) {
+            manager.remove_viewer(viewer.client_id, &mut relevance_manager);
+        }
+    }
+
+    /// Remove the entities that don't have a position (or were despawned) from the grid
+    pub fn handle_position_removed<P: SpatialPosition>(
+        trigger: Trigger<OnRemove, P>,
+        mut manager: ResMut<SpatialRelevanceManager>,
+        mut relevance_manager: ResMut<RelevanceManager>,
+    ) {
+        manager.remove_entity(trigger.entity(), Some(&mut relevance_manager));
+    }
+
+    /// Remove the entities that are not replicated anymore from the grid
+    pub fn handle_relevance_mode_removed(
+        trigger: Trigger<OnRemove, NetworkRelevanceMode>,
+        mut manager: ResMut<SpatialRelevanceManager>,
+        mut relevance_manager: ResMut<RelevanceManager>,
+    ) {
+        manager.remove_entity(trigger.entity(), Some(&mut relevance_manager));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Events;
+    use bevy::utils::Duration;
+
+    use crate::prelude::client::{self, EntityDespawnEvent};
+    use crate::prelude::server::Replicate;
+    use crate::prelude::*;
+    use crate::server::relevance::immediate::{CachedNetworkRelevance, ClientRelevance};
+    use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
+
+    use super::*;
+
+    #[test]
+    #[should_panic(expected = "cell size must be positive")]
+    fn test_spatial_relevance_invalid_cell_size() {
+        SpatialRelevanceManager::new(SpatialRelevanceConfig {
+            cell_size: 0.0,
+            ..default()
+        });
+    }
+
+    #[test]
+    fn test_spatial_relevance_hysteresis() {
+        let mut manager = SpatialRelevanceManager::new(SpatialRelevanceConfig {
+            cell_size: 10.0,
+            enter_radius: 10.0,
+            leave_radius: 15.0,
+        });
+        let mut relevance_manager = RelevanceManager::default();
+        let client_id = ClientId::Netcode(1);
+        let near = Entity::from_raw(1);
+        let far = Entity::from_raw(2);
+        manager.update_entity(near, Vec3::new(5.0, 0.0, 0.0));
+        manager.update_entity(far, Vec3::new(50.0, 0.0, 0.0));
+
+        manager.update_viewer(client_id, Vec3::ZERO, &mut relevance_manager);
+        assert_eq!(
+            manager.relevant_entities(client_id),
+            Some(&EntityHashSet::from_iter([near]))
+        );
+
+        // the entity is between the enter and the leave radius: it stays relevant
+        manager.update_entity(near, Vec3::new(12.0, 0.0, 0.0));
+        manager.update_viewer(client_id, Vec3::ZERO, &mut relevance_manager);
+        assert_eq!(
+            manager.relevant_entities(client_id),
+            Some(&EntityHashSet::from_iter([near]))
+        );
+
+        // the entity is further than the leave radius: it is not relevant anymore
+        manager.update_entity(near, Vec3::new(16.0, 0.0, 0.0));
+        manager.update_viewer(client_id, Vec3::ZERO, &mut relevance_manager);
+        assert!(manager.relevant_entities(client_id).unwrap().is_empty());
+
+        // the entity is between the enter and the leave radius: it does not become relevant again
+        manager.update_entity(near, Vec3::new(12.0, 0.0, 0.0));
+        manager.update_viewer(client_id, Vec3::ZERO, &mut relevance_manager);
+        assert!(manager.relevant_entities(client_id).unwrap().is_empty());
+
+        // the viewer moves close to the other entity
+        manager.update_viewer(client_id, Vec3::new(45.0, 0.0, 0.0), &mut relevance_manager);
+        assert_eq!(
+            manager.relevant_entities(client_id),
+            Some(&EntityHashSet::from_iter([far]))
+        );
+        assert_eq!(
+            manager
+                .entities_in_radius(Vec3::new(10.0, 0.0, 0.0), 5.0)
+                .collect::<Vec<_>>(),
+            vec![near]
+        );
+
+        // removed entities are not relevant anymore
+        manager.remove_entity(far, Some(&mut relevance_manager));
+        assert!(manager.relevant_entities(client_id).unwrap().is_empty());
+        assert!(manager.cells.values().all(|cell| !cell.contains(&far)));
+    }
+
+    #[test]
+    fn test_spatial_relevance_replication() {
+        let frame_duration = Duration::from_millis(10);
+        let shared_config = SharedConfig {
+            tick: TickConfig::new(frame_duration),
+            ..Default::default()
+        };
+        let mut stepper = BevyStepper::new(
+            shared_config,
+            client::ClientConfig::default(),
+            frame_duration,
+        );
+        stepper
+            .server_app
+            .add_plugins(SpatialRelevancePlugin::<Transform>::new(
+                SpatialRelevanceConfig {
+                    cell_size: 10.0,
+                    enter_radius: 10.0,
+                    leave_radius: 15.0,
+                },
+            ));
+        stepper.init();
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((
+                Transform::from_xyz(5.0, 0.0, 0.0),
+                Replicate {
+                    relevance_mode: NetworkRelevanceMode::InterestManagement,
+                    ..default()
+                },
+            ))
+            .id();
+        let viewer = stepper
+            .server_app
+            .world_mut()
+            .spawn((Transform::default(), SpatialViewer { client_id }))
+            .id();
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .server_app
+                .world()
+                .entity(server_entity)
+                .get::<CachedNetworkRelevance>()
+                .unwrap()
+                .clients_cache,
+            bevy::utils::HashMap::from([(client_id, ClientRelevance::Maintained)])
+        );
+        stepper.frame_step();
+        let client_entity = stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client");
+
+        // the viewer moves away from the entity: the entity is despawned on the client
+        stepper
+            .server_app
+            .world_mut()
+            .entity_mut(viewer)
+            .get_mut::<Transform>()
+            .unwrap()
+            .translation = Vec3::new(30.0, 0.0, 0.0);
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .resource::<Events<EntityDespawnEvent>>()
+                .len(),
+            1
+        );
+        assert!(stepper
+            .client_app
+            .world()
+            .get_entity(client_entity)
+            .is_err());
+
+        // the viewer is removed: the entity does not become relevant again
+        stepper
+            .server_app
+            .world_mut()
+            .entity_mut(viewer)
+            .insert(Transform::default())
+            .remove::<SpatialViewer>();
+        stepper.frame_step();
+        assert!(stepper
+            .server_app
+            .world()
+            .entity(server_entity)
+            .get::<CachedNetworkRelevance>()
+            .unwrap()
+            .clients_cache
+            .is_empty());
+    }
+
+    /// The viewers of clients that are not connected are ignored, and the entities that are
+    /// not replicated anymore are removed from the grid
+    #[test]
+    fn test_spatial_relevance_cleanup() {
+        let frame_duration = Duration::from_millis(10);
+        let shared_config = SharedConfig {
+            tick: TickConfig::new(frame_duration),
+            ..Default::default()
+        };
+        let mut stepper = BevyStepper::new(
+            shared_config,
+            client::ClientConfig::default(),
+            frame_duration,
+        );
+        stepper
+            .server_app
+            .add_plugins(SpatialRelevancePlugin::<Transform>::new(
+                SpatialRelevanceConfig {
+                    cell_size: 10.0,
+                    enter_radius: 10.0,
+                    leave_radius: 15.0,
+                },
+            ));
+        stepper.init();
+        let disconnected_client_id = ClientId::Netcode(TEST_CLIENT_ID + 1);
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((
+                Transform::default(),
+                Replicate {
+                    relevance_mode: NetworkRelevanceMode::InterestManagement,
+                    ..default()
+                },
+            ))
+            .id();
+        stepper.server_app.world_mut().spawn((
+            Transform::default(),
+            SpatialViewer {
+                client_id: disconnected_client_id,
+            },
+        ));
+        stepper.frame_step();
+        let manager = stepper
+            .server_app
+            .world()
+            .resource::<SpatialRelevanceManager>();
+        assert!(manager.entities.contains_key(&server_entity));
+        assert!(manager.relevant_entities(disconnected_client_id).is_none());
+
+        stepper
+            .server_app
+            .world_mut()
+            .entity_mut(server_entity)
+            .remove::<Replicate>();
+        stepper.frame_step();
+        let manager = stepper
+            .server_app
+            .world()
+            .resource::<SpatialRelevanceManager>();
+        assert!(!manager.entities.contains_key(&server_entity));
+        assert!(manager.cells.is_empty());
+    }
+}
@@ -60,18 +60,21 @@ pub(crate) mod send {
     use crate::protocol::component::ComponentKind;
     use crate::server::error::ServerError;
     use crate::server::prediction::handle_pre_predicted;
+    use crate::server::priority::{update_dynamic_priority, ReplicationPriorityFns};
+    use crate::server::relevance::graceful::{despawn_expired_relevance, GracefulRelevanceLoss};
     use crate::server::relevance::immediate::{CachedNetworkRelevance, ClientRelevance};
     use crate::shared::replication::archetypes::{
         get_erased_component, ServerReplicatedArchetypes,
     };
     use crate::shared::replication::authority::{AuthorityPeer, HasAuthority};
     use crate::shared::replication::components::{
-        Cached, Controlled, InitialReplicated, Replicating, ReplicationGroupId, ReplicationTarget,
-        ShouldBeInterpolated,
+        Cached, Controlled, InitialReplicated, Replicating, ReplicationFrequency,
+        ReplicationGroupId, ReplicationTarget, ShouldBeInterpolated,
     };
     use crate::shared::replication::network_target::NetworkTarget;
+    use crate::shared::replication::send::ComponentFrequency;
     use crate::shared::replication::ReplicationSend;
-    use bevy::ecs::component::ComponentTicks;
+    use bevy::ecs::component::{ComponentId, ComponentTicks};
     use bevy::ecs::system::SystemChangeTick;
     use bevy::ptr::Ptr;
 
@@ -91,6 +94,9 @@ pub(crate) mod send {
             app
                 // REFLECTION
                 .register_type::<Replicate>()
+                .register_type::<GracefulRelevanceLoss>()
+                // RESOURCES
+                .init_resource::<ReplicationPriorityFns>()
                 // PLUGIN
                 .add_plugins(ReplicationSendPlugin::<ConnectionManager>::new(
                     self.tick_interval,
@@ -119,11 +125,15 @@ pub(crate) mod send {
                 (
                     // TODO: putting it here means we might miss entities that are spawned and despawned within the send_interval? bug or feature?
                     //  be careful that newly_connected_client is cleared every send_interval, not every frame.
+                    despawn_expired_relevance
+                        .before(replicate)
+                        .in_set(InternalReplicationSet::<ServerMarker>::BufferEntityUpdates),
                     replicate
                         .in_set(InternalReplicationSet::<ServerMarker>::BufferEntityUpdates)
                         .in_set(InternalReplicationSet::<ServerMarker>::BufferComponentUpdates),
                     (
                         handle_replication_target_update,
+                        update_dynamic_priority.before(buffer_replication_messages),
                         buffer_replication_messages,
                     )
                         .in_set(InternalReplicationSet::<ServerMarker>::AfterBuffer),
@@ -430,6 +440,10 @@ pub(crate) mod send {
                 let controlled_by = entity_ref.get::<ControlledBy>();
                 let authority_peer = entity_ref.get::<AuthorityPeer>();
                 let initial_replicated = entity_ref.get::<InitialReplicated>();
+                // tick at which the grace period ends if the entity loses relevance now
+                let relevance_grace_end = entity_ref.get::<GracefulRelevanceLoss>().map(|g| {
+                    tick_manager.tick() + g.grace_ticks(tick_manager.config.tick_duration)
+                });
                 // SAFETY: we know that the entity has the ReplicationTarget component
                 // because the archetype is in replicated_archetypes
                 let replication_target =
@@ -461,6 +475,7 @@ pub(crate) mod send {
                     cached_replication_target,
                     authority_peer,
                     visibility,
+                    relevance_grace_end,
                     &mut sender,
                 );
 
@@ -506,6 +521,17 @@ pub(crate) mod send {
                             // the OverrideTarget<C> component has the same memory layout as NetworkTarget
                             .map(|ptr| unsafe { ptr.deref::<NetworkTarget>() })
                     });
+                    let frequency = replicated_component.frequency.and_then(|id| {
+                        entity_ref
+                            .get_by_id(id)
+                            .ok()
+                            // SAFETY: we know the archetype has the ReplicationFrequency<C> component
+                            // which is repr(C), so it has the same memory layout for any C
+                            .map(|ptr| unsafe {
+                                ptr.deref::<ReplicationFrequency<()>>()
+                                    .to_tick_frequency(tick_manager.config.tick_duration)
+                            })
+                    });
 
                     replicate_component_updates(
                         tick_manager.tick(),
@@ -522,6 +548,7 @@ pub(crate) mod send {
                         replicated_component.delta_compression,
                         replicated_component.replicate_once,
                         override_target,
+                        frequency,
                         &system_ticks,
                         &mut sender,
                     );
@@ -677,6 +704,12 @@ pub(crate) mod send {
                     )?;
                 }
 
+                // if the client kept the entity after it lost relevance, it will reuse it
+                sender
+                    .connection_mut(client_id)?
+                    .relevance_lost
+                    .remove(&entity);
+
                 if let Some(TargetEntity::Preexisting(remote_entity)) = target_entity {
                     sender
                         .connection_mut(client_id)?
@@ -728,7 +761,19 @@ pub(crate) mod send {
                 // TODO: optimize this in cases like All/None/Single/ExceptSingle
                 target.intersection(&NetworkTarget::Only(
                     network_relevance.clients_cache.keys().copied().collect(),
-                ))
+                ));
+                // and to clients that kept the entity after it lost relevance
+                let relevance_lost_clients: Vec<ClientId> = sender
+                    .connections
+                    .iter_mut()
+                    .filter_map(|(client_id, connection)| {
+                        connection
+                            .relevance_lost
+                            .remove(&entity)
+                            .map(|_| *client_id)
+                    })
+                    .collect();
+                target.union(&NetworkTarget::Only(relevance_lost_clients));
             }
             trace!(?entity, ?target, "send entity despawn");
             let _ = sender
@@ -743,6 +788,10 @@ pub(crate) mod send {
     /// Send entity despawn is:
     /// 1) the client lost visibility of the entity
     /// 2) the replication target was updated and the client is no longer in the ReplicationTarget
+    ///
+    /// If the entity has a [`GracefulRelevanceLoss`], the clients that lost visibility of the entity
+    /// keep it until `relevance_grace_end` instead.
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn replicate_entity_despawn(
         entity: Entity,
         group_id: ReplicationGroupId,
@@ -750,6 +799,7 @@ pub(crate) mod send {
         cached_replication_target: Option<&Cached<ReplicationTarget>>,
         authority_peer: Option<&AuthorityPeer>,
         visibility: Option<&CachedNetworkRelevance>,
+        relevance_grace_end: Option<Tick>,
         sender: &mut ConnectionManager,
     ) {
         // 1. send despawn for clients that lost visibility
@@ -774,6 +824,24 @@ pub(crate) mod send {
                 NetworkTarget::None
             }
         };
+        if let Some(grace_end) = relevance_grace_end {
+            let mut relevance_lost_target = std::mem::take(&mut target);
+            if let Some(AuthorityPeer::Client(c)) = authority_peer {
+                relevance_lost_target.exclude(&NetworkTarget::Single(*c));
+            }
+            if !relevance_lost_target.is_empty() {
+                let _ = sender
+                    .prepare_entity_relevance_lost(
+                        entity,
+                        group_id,
+                        relevance_lost_target,
+                        grace_end,
+                    )
+                    .inspect_err(|e| {
+                        error!("error sending entity relevance lost: {:?}", e);
+                    });
+            }
+        }
         // 2. if the replication target changed, find the clients that were removed in the new replication target
         if replication_target.is_changed() && !replication_target.is_added() {
             if let Some(cached_target) = cached_replication_target {
@@ -824,6 +892,7 @@ pub(crate) mod send {
         delta_compression: bool,
         replicate_once: bool,
         override_target: Option<&NetworkTarget>,
+        frequency: Option<ComponentFrequency>,
         system_ticks: &SystemChangeTick,
         sender: &mut ConnectionManager,
     ) {
@@ -959,6 +1028,7 @@ pub(crate) mod send {
                         system_ticks.this_run(),
                         current_tick,
                         delta_compression,
+                        frequency,
                     )
                     .inspect_err(|e| {
                         error!("error sending component update: {:?}", e);
@@ -1005,32 +1075,7 @@ pub(crate) mod send {
                     .map_or(&replication_target.target, |override_target| {
                         &override_target.target
                     });
-                let mut target = match visibility {
-                    Some(visibility) => {
-                        visibility
-                            .clients_cache
-                            .iter()
-                            .filter_map(|(client_id, visibility)| {
-                                if base_target.targets(client_id) {
-                                    // TODO: maybe send no matter the vis?
-                                    if matches!(visibility, ClientRelevance::Maintained) {
-                                        // TODO: USE THE CUSTOM REPLICATE TARGET FOR THIS COMPONENT IF PRESENT!
-                                        return Some(*client_id);
-                                    }
-                                };
-                                None
-                            })
-                            .collect()
-                    }
-                    None => {
-                        trace!("sending component remove!");
-                        // TODO: USE THE CUSTOM REPLICATE TARGET FOR THIS COMPONENT IF PRESENT!
-                        base_target.clone()
-                    }
-                };
-                if let Some(AuthorityPeer::Client(c)) = authority_peer {
-                    target.exclude(&NetworkTarget::Single(*c));
-                }
+                let target = component_remove_target(base_target, authority_peer, visibility);
                 if target.is_empty() {
                     return;
                 }
@@ -1041,6 +1086,85 @@ pub(crate) mod send {
         })
     }
 
+    /// Compute the clients that should receive a component removal for an entity
+    fn component_remove_target(
+        base_target: &NetworkTarget,
+        authority_peer: Option<&AuthorityPeer>,
+        visibility: Option<&CachedNetworkRelevance>,
+    ) -> NetworkTarget {
+        let mut target = match visibility {
+            Some(visibility) => {
+                visibility
+                    .clients_cache
+                    .iter()
+                    .filter_map(|(client_id, visibility)| {
+                        if base_target.targets(client_id) {
+                            // TODO: maybe send no matter the vis?
+                            if matches!(visibility, ClientRelevance::Maintained) {
+                                // TODO: USE THE CUSTOM REPLICATE TARGET FOR THIS COMPONENT IF PRESENT!
+                                return Some(*client_id);
+                            }
+                        };
+                        None
+                    })
+                    .collect()
+            }
+            None => {
+                trace!("sending component remove!");
+                // TODO: USE THE CUSTOM REPLICATE TARGET FOR THIS COMPONENT IF PRESENT!
+                base_target.clone()
+            }
+        };
+        if let Some(AuthorityPeer::Client(c)) = authority_peer {
+            target.exclude(&NetworkTarget::Single(*c));
+        }
+        target
+    }
+
+    /// Send component remove for components that were registered through reflection.
+    ///
+    /// The observer watches the component of the reflected type, which is only known at runtime.
+    pub(crate) fn send_reflect_component_removed(
+        trigger: Trigger<OnRemove>,
+        registry: Res<ComponentRegistry>,
+        // only remove the component for entities that are being actively replicated
+        query: Query<
+            (
+                &ReplicationTarget,
+                &ReplicationGroup,
+                Option<&AuthorityPeer>,
+                Option<&CachedNetworkRelevance>,
+            ),
+            With<Replicating>,
+        >,
+        mut sender: ResMut<ConnectionManager>,
+    ) {
+        let entity = trigger.entity();
+        let Some(kind) = trigger
+            .components()
+            .iter()
+            .find_map(|component_id| registry.reflect_net_id(*component_id))
+        else {
+            return;
+        };
+        if let Ok((replication_target, group, authority_peer, visibility)) = query.get(entity) {
+            let target =
+                component_remove_target(&replication_target.target, authority_peer, visibility);
+            if target.is_empty() {
+                return;
+            }
+            debug!(?entity, ?kind, "Sending RemoveComponent");
+            let _ = sender.prepare_component_remove(entity, kind, group, target);
+        }
+    }
+
+    pub(crate) fn register_replicate_reflect_component_send(
+        world: &mut World,
+        component_id: ComponentId,
+    ) {
+        world.spawn(Observer::new(send_reflect_component_removed).with_component(component_id));
+    }
+
     pub(crate) fn register_replicate_component_send<C: Component>(app: &mut App) {
         app.add_systems(
             PostUpdate,
@@ -2105,6 +2229,70 @@ pub(crate) mod send {
             );
         }
 
+        #[test]
+        fn test_component_update_replication_frequency() {
+            let mut stepper = BevyStepper::default();
+
+            // spawn an entity on server
+            let server_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn((
+                    Replicate::default(),
+                    ComponentSyncModeFull(1.0),
+                    ComponentSyncModeSimple(1.0),
+                    // replicate every 4 ticks
+                    ReplicationFrequency::<ComponentSyncModeSimple>::new(Duration::from_millis(40))
+                        .with_priority(0.5),
+                ))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+            let client_entity = stepper
+                .client_app
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .expect("entity was not replicated to client");
+
+            let update = |stepper: &mut BevyStepper, value: f32| {
+                stepper
+                    .server_app
+                    .world_mut()
+                    .entity_mut(server_entity)
+                    .insert((ComponentSyncModeFull(value), ComponentSyncModeSimple(value)));
+            };
+            let check = |stepper: &BevyStepper, full: f32, simple: f32| {
+                let entity = stepper.client_app.world().entity(client_entity);
+                assert_eq!(
+                    entity.get::<ComponentSyncModeFull>(),
+                    Some(&ComponentSyncModeFull(full))
+                );
+                assert_eq!(
+                    entity.get::<ComponentSyncModeSimple>(),
+                    Some(&ComponentSyncModeSimple(simple))
+                );
+            };
+
+            // the first update of the component is sent right away
+            update(&mut stepper, 2.0);
+            stepper.frame_step();
+            stepper.frame_step();
+            check(&stepper, 2.0, 2.0);
+
+            // the next update is only sent once 4 ticks have passed since the last one
+            update(&mut stepper, 3.0);
+            stepper.frame_step();
+            stepper.frame_step();
+            check(&stepper, 3.0, 2.0);
+            stepper.frame_step();
+            check(&stepper, 3.0, 2.0);
+            stepper.frame_step();
+            check(&stepper, 3.0, 3.0);
+        }
+
         #[test]
         fn test_component_update_delta() {
             let mut stepper = BevyStepper::default();
@@ -2430,12 +2618,12 @@ pub(crate) mod send {
                 .first_mut()
                 .unwrap()
             {
-                io.conditioner = Some(LinkConditionerConfig {
-                    // the server receives client packets after 3 ticks
-                    incoming_latency: Duration::from_millis(30),
-                    incoming_jitter: Default::default(),
-                    incoming_loss: 0.0,
-                })
+                // the server receives client packets after 3 ticks
+                io.conditioner = Some(LinkConditionerConfig::new(
+                    Duration::from_millis(30),
+                    Duration::default(),
+                    0.0,
+                ))
             }
             stepper.start();
 
@@ -2602,12 +2790,12 @@ pub(crate) mod send {
                 .first_mut()
                 .unwrap()
             {
-                io.conditioner = Some(LinkConditionerConfig {
-                    // the server receives client packets after 3 ticks
-                    incoming_latency: Duration::from_millis(30),
-                    incoming_jitter: Default::default(),
-                    incoming_loss: 0.0,
-                })
+                // the server receives client packets after 3 ticks
+                io.conditioner = Some(LinkConditionerConfig::new(
+                    Duration::from_millis(30),
+                    Duration::default(),
+                    0.0,
+                ))
             }
             stepper.start();
 
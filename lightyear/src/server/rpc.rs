@@ -0,0 +1,232 @@
+//! Send requests to clients and respond to the requests received from clients
+use bevy::prelude::{
+    resource_exists, App, EventWriter, Events, IntoSystemConfigs, PreUpdate, Res, ResMut,
+};
+use bevy::utils::Duration;
+
+use crate::channel::builder::RpcChannel;
+use crate::connection::id::ClientId;
+use crate::connection::server::ServerConnections;
+use crate::prelude::server::is_started;
+use crate::prelude::{Channel, Message, TimeManager};
+use crate::server::connection::ConnectionManager;
+use crate::server::error::ServerError;
+use crate::server::events::{MessageEvent, RequestEvent, ResponseEvent};
+use crate::shared::rpc::{RequestHandle, RpcRequest, RpcResponse};
+use crate::shared::sets::{InternalMainSet, ServerMarker};
+
+impl ConnectionManager {
+    /// Send a request to a client using a specific [`Channel`].
+    ///
+    /// The returned [`RequestHandle`] identifies the [`ResponseEvent`] that will be emitted when the
+    /// response is received, or when the request fails because it timed out or because the client
+    /// disconnected.
+    pub fn send_request<C: Channel, Req: Message>(
+        &mut self,
+        client_id: ClientId,
+        request: Req,
+        timeout: Duration,
+    ) -> Result<RequestHandle<Req, ClientId>, ServerError> {
+        let id = self.rpc.next_id();
+        self.send_message::<C, _>(client_id, &mut RpcRequest { id, request })?;
+        Ok(self.rpc.add::<Req>(id, client_id, timeout))
+    }
+
+    /// Respond to a request that was received from a client
+    pub fn respond<Req: Message, Resp: Message>(
+        &mut self,
+        handle: RequestHandle<Req, ClientId>,
+        response: Resp,
+    ) -> Result<(), ServerError> {
+        self.send_message::<RpcChannel, _>(
+            *handle.context(),
+            &mut RpcResponse::<Req, Resp>::new(handle.id(), response),
+        )
+    }
+}
+
+/// Convert the requests received from clients into [`RequestEvent`]s
+fn receive_requests<Req: Message>(
+    mut messages: ResMut<Events<MessageEvent<RpcRequest<Req>>>>,
+    mut events: EventWriter<RequestEvent<Req>>,
+) {
+    events.send_batch(messages.drain().map(|event| {
+        RequestEvent::new(
+            event.message.request,
+            RequestHandle::new(event.message.id, event.context),
+        )
+    }));
+}
+
+/// Emit a [`ResponseEvent`] for each response received from a client, and for each request that
+/// failed.
+///
+/// All the pending requests fail once the server is stopped.
+fn receive_responses<Req: Message, Resp: Message>(
+    server: Option<Res<ServerConnections>>,
+    time_manager: Res<TimeManager>,
+    mut connection_manager: ResMut<ConnectionManager>,
+    mut messages: ResMut<Events<MessageEvent<RpcResponse<Req, Resp>>>>,
+    mut events: EventWriter<ResponseEvent<Req, Resp>>,
+) {
+    for event in messages.drain() {
+        if let Some(handle) = connection_manager
+            .rpc
+            .receive::<Req>(event.message.id, event.context)
+        {
+            events.send(ResponseEvent::new(handle, Ok(event.message.response)));
+        }
+    }
+    let started = is_started(server);
+    let connection_manager = connection_manager.as_mut();
+    let connections = &connection_manager.connections;
+    for (handle, error) in connection_manager
+        .rpc
+        .fail::<Req>(time_manager.delta(), |client_id| {
+            started && connections.contains_key(client_id)
+        })
+    {
+        events.send(ResponseEvent::new(handle, Err(error)));
+    }
+}
+
+/// Register the systems to receive requests of type `Req` from clients
+pub(crate) fn add_server_receive_request<Req: Message>(app: &mut App) {
+    app.add_event::<RequestEvent<Req>>();
+    app.add_systems(
+        PreUpdate,
+        receive_requests::<Req>
+            .after(InternalMainSet::<ServerMarker>::EmitEvents)
+            .run_if(is_started),
+    );
+}
+
+/// Register the systems to receive the responses to the requests of type `Req` sent to clients
+pub(crate) fn add_server_receive_response<Req: Message, Resp: Message>(app: &mut App) {
+    app.add_event::<ResponseEvent<Req, Resp>>();
+    app.add_systems(
+        PreUpdate,
+        receive_responses::<Req, Resp>
+            .after(InternalMainSet::<ServerMarker>::EmitEvents)
+            .run_if(resource_exists::<ConnectionManager>),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::server::ServerCommands;
+    use crate::prelude::{client, RequestError};
+    use crate::tests::protocol::{Channel1, StringMessage};
+    use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::prelude::Commands;
+
+    #[test]
+    fn test_request_response() {
+        let mut stepper = BevyStepper::default();
+
+        // send a request from the client to the server
+        let handle = stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<client::ConnectionManager>()
+            .send_request::<Channel1, _>(StringMessage("a".to_string()), Duration::from_secs(1))
+            .unwrap();
+        stepper.frame_step();
+        stepper.frame_step();
+
+        let requests: Vec<_> = stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<Events<RequestEvent<StringMessage>>>()
+            .drain()
+            .collect();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].request(), &StringMessage("a".to_string()));
+        assert_eq!(requests[0].context(), &ClientId::Netcode(TEST_CLIENT_ID));
+
+        // respond to the request
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ConnectionManager>()
+            .respond(requests[0].handle(), 1usize)
+            .unwrap();
+        stepper.frame_step();
+        stepper.frame_step();
+
+        let responses: Vec<_> = stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<Events<client::ResponseEvent<StringMessage, usize>>>()
+            .drain()
+            .collect();
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0].handle(), handle);
+        assert_eq!(responses[0].response(), Ok(&1));
+    }
+
+    #[test]
+    fn test_request_timeout() {
+        let mut stepper = BevyStepper::default();
+
+        // send a request from the server to the client, that the client never responds to
+        let handle = stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ConnectionManager>()
+            .send_request::<Channel1, _>(
+                ClientId::Netcode(TEST_CLIENT_ID),
+                StringMessage("a".to_string()),
+                Duration::from_millis(50),
+            )
+            .unwrap();
+        // events are only kept for 2 frames, so don't step too far past the timeout
+        for _ in 0..6 {
+            stepper.frame_step();
+        }
+
+        let responses: Vec<_> = stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<Events<ResponseEvent<StringMessage, usize>>>()
+            .drain()
+            .collect();
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0].handle(), handle);
+        assert_eq!(responses[0].response(), Err(RequestError::TimedOut));
+    }
+
+    #[test]
+    fn test_request_fails_on_server_stop() {
+        let mut stepper = BevyStepper::default();
+
+        let handle = stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ConnectionManager>()
+            .send_request::<Channel1, _>(
+                ClientId::Netcode(TEST_CLIENT_ID),
+                StringMessage("a".to_string()),
+                Duration::from_secs(10),
+            )
+            .unwrap();
+        let _ = stepper
+            .server_app
+            .world_mut()
+            .run_system_once(|mut commands: Commands| commands.stop_server());
+        stepper.frame_step();
+        stepper.frame_step();
+
+        let responses: Vec<_> = stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<Events<ResponseEvent<StringMessage, usize>>>()
+            .drain()
+            .collect();
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0].handle(), handle);
+        assert_eq!(responses[0].response(), Err(RequestError::Disconnected));
+    }
+}
@@ -0,0 +1,164 @@
+//! Send triggers to clients and trigger the events received from clients
+use bevy::prelude::{App, Commands, Entity, Event, Events, IntoSystemConfigs, PreUpdate, ResMut};
+use tracing::error;
+
+use crate::prelude::server::is_started;
+use crate::prelude::{Channel, Message, NetworkTarget};
+use crate::server::connection::ConnectionManager;
+use crate::server::error::ServerError;
+use crate::server::events::{MessageEvent, RemoteTrigger};
+use crate::shared::sets::{InternalMainSet, ServerMarker};
+use crate::shared::trigger::TriggerMessage;
+
+impl ConnectionManager {
+    /// Send a trigger to the clients that match the [`NetworkTarget`], using a specific [`Channel`].
+    ///
+    /// The event will be triggered on each client's entities that correspond to `targets`,
+    /// or as a global trigger if `targets` is empty.
+    pub fn send_trigger_to_target<C: Channel, E: Event + Message>(
+        &mut self,
+        trigger: E,
+        targets: impl IntoIterator<Item = Entity>,
+        target: NetworkTarget,
+    ) -> Result<(), ServerError> {
+        self.send_message_to_target::<C, _>(
+            &mut TriggerMessage {
+                trigger,
+                target_entities: targets.into_iter().collect(),
+            },
+            target,
+        )
+    }
+}
+
+/// Trigger a [`RemoteTrigger`] for each trigger received from the clients
+fn read_triggers<E: Event + Message>(
+    mut commands: Commands,
+    mut messages: ResMut<Events<MessageEvent<TriggerMessage<E>>>>,
+) {
+    for event in messages.drain() {
+        if event.message.has_unmapped_targets() {
+            error!(
+                "Skipping trigger {} because some of its target entities could not be mapped",
+                std::any::type_name::<E>()
+            );
+            continue;
+        }
+        let trigger = RemoteTrigger::new(event.message.trigger, event.context);
+        if event.message.target_entities.is_empty() {
+            commands.trigger(trigger);
+        } else {
+            commands.trigger_targets(trigger, event.message.target_entities);
+        }
+    }
+}
+
+/// Register the system to receive triggers of type `E` from clients
+pub(crate) fn add_server_receive_trigger<E: Event + Message>(app: &mut App) {
+    app.add_systems(
+        PreUpdate,
+        read_triggers::<E>
+            .after(InternalMainSet::<ServerMarker>::EmitEvents)
+            .run_if(is_started),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{Resource, Trigger};
+
+    use super::*;
+    use crate::prelude::{client, server::Replicate, ClientId};
+    use crate::tests::protocol::{Channel1, StringTrigger};
+    use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
+
+    #[derive(Resource, Default)]
+    struct Received(Vec<(Entity, StringTrigger, ClientId)>);
+
+    #[test]
+    fn test_client_to_server_trigger() {
+        let mut stepper = BevyStepper::default();
+        stepper.server_app.init_resource::<Received>();
+        stepper.server_app.add_observer(
+            |trigger: Trigger<RemoteTrigger<StringTrigger>>, mut received: ResMut<Received>| {
+                received.0.push((
+                    trigger.entity(),
+                    trigger.event().trigger.clone(),
+                    trigger.event().from,
+                ));
+            },
+        );
+
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn(Replicate::default())
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        let client_entity = stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client");
+
+        // the client triggers the event on its local entity
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<client::ConnectionManager>()
+            .send_trigger::<Channel1, _>(StringTrigger("a".to_string()), [client_entity])
+            .unwrap();
+        stepper.frame_step();
+        stepper.frame_step();
+
+        // the event is triggered on the corresponding server entity
+        assert_eq!(
+            stepper.server_app.world().resource::<Received>().0,
+            vec![(
+                server_entity,
+                StringTrigger("a".to_string()),
+                ClientId::Netcode(TEST_CLIENT_ID)
+            )]
+        );
+    }
+
+    /// A trigger targeting an entity that the server doesn't know about must not be triggered
+    /// on an unrelated server entity
+    #[test]
+    fn test_client_to_server_trigger_unmapped_target() {
+        let mut stepper = BevyStepper::default();
+        stepper.server_app.init_resource::<Received>();
+        stepper.server_app.add_observer(
+            |trigger: Trigger<RemoteTrigger<StringTrigger>>, mut received: ResMut<Received>| {
+                received.0.push((
+                    trigger.entity(),
+                    trigger.event().trigger.clone(),
+                    trigger.event().from,
+                ));
+            },
+        );
+
+        // the client entity is not replicated, so it can't be mapped to a server entity
+        let client_entity = stepper.client_app.world_mut().spawn_empty().id();
+
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<client::ConnectionManager>()
+            .send_trigger::<Channel1, _>(StringTrigger("a".to_string()), [client_entity])
+            .unwrap();
+        stepper.frame_step();
+        stepper.frame_step();
+
+        assert!(stepper
+            .server_app
+            .world()
+            .resource::<Received>()
+            .0
+            .is_empty());
+    }
+}
@@ -4,7 +4,8 @@ use std::marker::PhantomData;
 
 use bevy::prelude::{Component, Entity, Event};
 
-use crate::packet::message::Message;
+use crate::packet::message::{Message, MessageId};
+use crate::protocol::channel::ChannelKind;
 
 /// This event is emitted whenever we receive a message from the remote
 #[derive(Event, Debug)]
@@ -27,6 +28,41 @@ impl<M: Message, Ctx> MessageEvent<M, Ctx> {
     }
 }
 
+/// This event is emitted when a message that we sent on a reliable channel expired before the
+/// remote peer acknowledged it (see [`ChannelSettings::message_ttl`](crate::prelude::ChannelSettings::message_ttl)).
+///
+/// The message is not resent anymore, and the remote peer will not receive it if it hasn't already.
+#[derive(Event, Debug)]
+pub struct MessageExpired<Ctx = ()> {
+    channel: ChannelKind,
+    message_id: MessageId,
+    context: Ctx,
+}
+
+impl<Ctx> MessageExpired<Ctx> {
+    pub fn new(channel: ChannelKind, message_id: MessageId, context: Ctx) -> Self {
+        Self {
+            channel,
+            message_id,
+            context,
+        }
+    }
+
+    /// The channel that the message was sent on
+    pub fn channel(&self) -> ChannelKind {
+        self.channel
+    }
+
+    /// The id of the message that expired, as returned by `send_message_with_ttl`
+    pub fn message_id(&self) -> MessageId {
+        self.message_id
+    }
+
+    pub fn context(&self) -> &Ctx {
+        &self.context
+    }
+}
+
 #[derive(Event)]
 /// Event emitted on server every time we receive an event
 pub struct InputEvent<I: crate::inputs::native::UserAction, Ctx = ()> {
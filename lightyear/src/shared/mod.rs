@@ -12,6 +12,10 @@ pub mod plugin;
 
 pub mod replication;
 
+pub mod rpc;
+
+pub mod trigger;
+
 pub mod sets;
 
 pub mod tick_manager;
@@ -8,9 +8,10 @@ use bevy::utils::Duration;
 use crate::prelude::client::ComponentSyncMode;
 use crate::prelude::{
     AppComponentExt, AppMessageExt, ChannelDirection, ChannelRegistry, ComponentRegistry,
-    LinkConditionerConfig, MessageRegistry, Mode, ParentSync, PingConfig, PrePredicted,
-    PreSpawnedPlayerObject, ShouldBePredicted, TickConfig,
+    LinkConditionerConfig, LinkConditionerProfile, MessageRegistry, Mode, ParentSync, PingConfig,
+    PrePredicted, PreSpawnedPlayerObject, ShouldBePredicted, TickConfig,
 };
+use crate::protocol::NetIdMode;
 use crate::shared::config::SharedConfig;
 use crate::shared::replication::authority::AuthorityChange;
 use crate::shared::replication::components::{Controlled, ShouldBeInterpolated};
@@ -22,6 +23,23 @@ use crate::transport::middleware::compression::CompressionConfig;
 #[derive(Default, Debug)]
 pub struct SharedPlugin {
     pub config: SharedConfig,
+    /// How the net ids of the registered messages and components are assigned
+    pub net_id_mode: NetIdMode,
+}
+
+impl SharedPlugin {
+    pub fn new(config: SharedConfig) -> Self {
+        Self {
+            config,
+            net_id_mode: NetIdMode::default(),
+        }
+    }
+
+    /// Set how the net ids of the registered messages and components are assigned
+    pub fn with_net_id_mode(mut self, net_id_mode: NetIdMode) -> Self {
+        self.net_id_mode = net_id_mode;
+        self
+    }
 }
 
 /// You can use this as a SystemParam to identify whether you're running on the client or the server
@@ -98,11 +116,13 @@ impl Plugin for SharedPlugin {
         // REFLECTION
         app.register_type::<Mode>()
             .register_type::<SharedConfig>()
+            .register_type::<NetIdMode>()
             .register_type::<TickConfig>()
             .register_type::<PingConfig>()
             .register_type::<IoStats>()
             .register_type::<IoState>()
             .register_type::<LinkConditionerConfig>()
+            .register_type::<LinkConditionerProfile>()
             .register_type::<CompressionConfig>();
 
         // PLUGINS
@@ -122,8 +142,12 @@ impl Plugin for SharedPlugin {
                 Duration::default()
             };
         app.insert_resource(ChannelRegistry::new(input_send_interval));
-        app.insert_resource(ComponentRegistry::default());
-        app.insert_resource(MessageRegistry::default());
+        let mut component_registry = ComponentRegistry::default();
+        component_registry.kind_map.mode = self.net_id_mode;
+        app.insert_resource(component_registry);
+        let mut message_registry = MessageRegistry::default();
+        message_registry.kind_map.mode = self.net_id_mode;
+        app.insert_resource(message_registry);
         // NOTE: this tick duration must be the same as any previous existing fixed timesteps
         app.insert_resource(Time::<Fixed>::from_seconds(
             self.config.tick.tick_duration.as_secs_f64(),
@@ -159,5 +183,6 @@ impl Plugin for SharedPlugin {
 
         // check that the protocol was built correctly
         app.world().resource::<ComponentRegistry>().check();
+        app.world().resource::<MessageRegistry>().check();
     }
 }
@@ -95,6 +95,7 @@ pub(crate) struct ReplicatedComponent {
     pub(crate) delta_compression: bool,
     pub(crate) replicate_once: bool,
     pub(crate) override_target: Option<ComponentId>,
+    pub(crate) frequency: Option<ComponentId>,
     pub(crate) id: ComponentId,
     pub(crate) kind: ComponentKind,
     pub(crate) storage_type: StorageType,
@@ -176,7 +177,7 @@ impl<C: Component> ReplicatedArchetypes<C> {
                     // check per component metadata
                     let disabled = archetype
                         .components()
-                        .any(|c| c == replication_metadata.disabled_id);
+                        .any(|c| Some(c) == replication_metadata.disabled_id);
                     // we do not replicate the component
                     if disabled {
                         return;
@@ -184,18 +185,24 @@ impl<C: Component> ReplicatedArchetypes<C> {
                     // TODO: should we store the components in a hashmap for faster lookup?
                     let delta_compression = archetype
                         .components()
-                        .any(|c| c == replication_metadata.delta_compression_id);
+                        .any(|c| Some(c) == replication_metadata.delta_compression_id);
                     let replicate_once = archetype
                         .components()
-                        .any(|c| c == replication_metadata.replicate_once_id);
+                        .any(|c| Some(c) == replication_metadata.replicate_once_id);
                     let override_target = archetype
                         .components()
-                        .any(|c| c == replication_metadata.override_target_id)
-                        .then_some(replication_metadata.override_target_id);
+                        .any(|c| Some(c) == replication_metadata.override_target_id)
+                        .then_some(replication_metadata.override_target_id)
+                        .flatten();
+                    let frequency = archetype
+                        .components()
+                        .any(|c| Some(c) == replication_metadata.frequency_id)
+                        .then_some(replication_metadata.frequency_id)
+                        .flatten();
 
                     let disabled = archetype
                         .components()
-                        .any(|c| c == replication_metadata.disabled_id);
+                        .any(|c| Some(c) == replication_metadata.disabled_id);
                     // SAFETY: component ID obtained from this archetype.
                     let storage_type =
                         unsafe { archetype.get_storage_type(component).unwrap_unchecked() };
@@ -203,6 +210,7 @@ impl<C: Component> ReplicatedArchetypes<C> {
                         delta_compression,
                         replicate_once,
                         override_target,
+                        frequency,
                         id: component,
                         kind,
                         storage_type,
@@ -9,6 +9,8 @@ use crate::connection::id::ClientId;
 use crate::serialize::reader::Reader;
 use crate::serialize::{SerializationError, ToBytes};
 use crate::shared::replication::network_target::NetworkTarget;
+use crate::shared::replication::send::ComponentFrequency;
+use crate::shared::tick_manager::Tick;
 
 /// Marker component that indicates that the entity was initially spawned via replication
 /// (it was being replicated from a remote world)
@@ -49,6 +51,19 @@ impl Replicated {
     }
 }
 
+/// Component inserted on a replicated entity when the remote peer stopped replicating it to us because
+/// it is no longer relevant, with [`GracefulRelevanceLoss`](crate::prelude::server::GracefulRelevanceLoss).
+///
+/// The entity is not despawned: game code can fade it out, or keep it as a stale ghost. The entity stops
+/// receiving updates. If it becomes relevant again before the end of the grace period, the component is removed
+/// and the same entity keeps being replicated; otherwise the entity is despawned.
+#[derive(Component, Clone, Copy, PartialEq, Debug, Reflect)]
+#[reflect(Component)]
+pub struct RelevanceLost {
+    /// The remote tick at which the entity lost relevance
+    pub tick: Tick,
+}
+
 /// Marker component to indicate that the entity is under the control of the local peer
 #[derive(Component, Clone, Copy, PartialEq, Debug, Reflect, Serialize, Deserialize)]
 #[reflect(Component)]
@@ -194,6 +209,54 @@ impl<C> OverrideTargetComponent<C> {
     }
 }
 
+/// This component lets you send the updates of a specific component less often than the rest of
+/// the entity's [`ReplicationGroup`], and with a different priority.
+///
+/// For example a slow-changing component can be sent at 2Hz with a low priority, while the
+/// transforms of the entity are sent every frame.
+/// Inserts and removals of the component are not affected.
+// repr(C) so that the settings can be read without knowing `C`
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Component)]
+#[repr(C)]
+pub struct ReplicationFrequency<C> {
+    /// Minimum duration between two updates of the component
+    pub send_frequency: bevy::utils::Duration,
+    /// Priority of the component's updates. The updates of the group are sent with the
+    /// highest priority of the components they contain (components without a
+    /// [`ReplicationFrequency`] have a priority of 1.0)
+    pub priority: f32,
+    _marker: std::marker::PhantomData<C>,
+}
+
+impl<C> ReplicationFrequency<C> {
+    pub fn new(send_frequency: bevy::utils::Duration) -> Self {
+        Self {
+            send_frequency,
+            priority: 1.0,
+            _marker: Default::default(),
+        }
+    }
+
+    pub fn with_priority(mut self, priority: f32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Convert the send frequency to a number of ticks
+    pub(crate) fn to_tick_frequency(
+        &self,
+        tick_duration: bevy::utils::Duration,
+    ) -> ComponentFrequency {
+        let interval = (self.send_frequency.as_secs_f64() / tick_duration.as_secs_f64()).round();
+        ComponentFrequency {
+            // the tick difference is computed as an i16
+            interval: interval.min(i16::MAX as f64) as u16,
+            priority: self.priority,
+        }
+    }
+}
+
 #[derive(Debug, Default, Copy, Clone, PartialEq, Reflect)]
 pub enum ReplicationGroupIdBuilder {
     // the group id is the entity id
@@ -4,12 +4,14 @@ use crate::prelude::{ComponentRegistry, Message, Tick};
 use crate::protocol::component::ComponentKind;
 use crate::shared::replication::components::ReplicationGroupId;
 use bevy::ecs::entity::EntityHash;
+use bevy::math::{Quat, Vec2, Vec3, Vec4};
 use bevy::prelude::{Component, Entity};
 use bevy::ptr::Ptr;
 use bevy::utils::{hashbrown, HashMap};
 
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap as StdHashMap};
+use std::hash::{BuildHasher, Hash};
 use std::ptr::NonNull;
 
 #[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
@@ -57,6 +59,154 @@ pub trait Diffable: Clone {
     fn apply_diff(&mut self, delta: &Self::Delta);
 }
 
+/// Implement [`Diffable`] for a type whose delta is simply the new value
+macro_rules! impl_diffable_replace {
+    ($($ty:ty),*) => {
+        $(
+            impl Diffable for $ty {
+                type Delta = Self;
+
+                fn base_value() -> Self {
+                    Self::default()
+                }
+
+                fn diff(&self, new: &Self) -> Self::Delta {
+                    new.clone()
+                }
+
+                fn apply_diff(&mut self, delta: &Self::Delta) {
+                    *self = delta.clone();
+                }
+            }
+        )*
+    };
+}
+
+impl_diffable_replace!(
+    bool, char, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, String,
+    Vec2, Vec3, Vec4, Quat
+);
+
+/// Delta between two versions of a [`Vec`]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(bound(
+    serialize = "T: Serialize, T::Delta: Serialize",
+    deserialize = "T: Deserialize<'de>, T::Delta: Deserialize<'de>"
+))]
+pub struct VecDelta<T: Diffable> {
+    /// New length of the vec, if elements were removed from the end
+    pub truncate: Option<u32>,
+    /// Elements that were modified, with their index
+    pub modified: Vec<(u32, T::Delta)>,
+    /// Elements that were pushed at the end of the vec
+    pub added: Vec<T>,
+}
+
+impl<T: Diffable + PartialEq + Send + Sync + 'static> Diffable for Vec<T> {
+    type Delta = VecDelta<T>;
+
+    fn base_value() -> Self {
+        Vec::new()
+    }
+
+    fn diff(&self, new: &Self) -> Self::Delta {
+        let modified = self
+            .iter()
+            .zip(new.iter())
+            .enumerate()
+            .filter(|(_, (old, new))| old != new)
+            .map(|(i, (old, new))| (i as u32, old.diff(new)))
+            .collect();
+        VecDelta {
+            truncate: (new.len() < self.len()).then_some(new.len() as u32),
+            modified,
+            added: new.iter().skip(self.len()).cloned().collect(),
+        }
+    }
+
+    fn apply_diff(&mut self, delta: &Self::Delta) {
+        if let Some(len) = delta.truncate {
+            self.truncate(len as usize);
+        }
+        for (i, value_delta) in &delta.modified {
+            if let Some(value) = self.get_mut(*i as usize) {
+                value.apply_diff(value_delta);
+            }
+        }
+        self.extend(delta.added.iter().cloned());
+    }
+}
+
+/// Delta between two versions of a map
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(bound(
+    serialize = "K: Serialize, V: Serialize, V::Delta: Serialize",
+    deserialize = "K: Deserialize<'de>, V: Deserialize<'de>, V::Delta: Deserialize<'de>"
+))]
+pub struct MapDelta<K, V: Diffable> {
+    /// Entries that were inserted
+    pub added: Vec<(K, V)>,
+    /// Keys that were removed
+    pub removed: Vec<K>,
+    /// Entries whose value was modified
+    pub modified: Vec<(K, V::Delta)>,
+}
+
+macro_rules! impl_diffable_map {
+    ($($map:ident)::+) => {
+        impl<K, V, S> Diffable for $($map)::+<K, V, S>
+        where
+            K: Eq + Hash + Clone + Send + Sync + 'static,
+            V: Diffable + PartialEq + Send + Sync + 'static,
+            S: BuildHasher + Default + Clone,
+        {
+            type Delta = MapDelta<K, V>;
+
+            fn base_value() -> Self {
+                Self::default()
+            }
+
+            fn diff(&self, new: &Self) -> Self::Delta {
+                let mut delta = MapDelta {
+                    added: vec![],
+                    removed: vec![],
+                    modified: vec![],
+                };
+                for (key, new_value) in new.iter() {
+                    match self.get(key) {
+                        None => delta.added.push((key.clone(), new_value.clone())),
+                        Some(old_value) if old_value != new_value => delta
+                            .modified
+                            .push((key.clone(), old_value.diff(new_value))),
+                        _ => {}
+                    }
+                }
+                delta.removed = self
+                    .keys()
+                    .filter(|key| !new.contains_key(*key))
+                    .cloned()
+                    .collect();
+                delta
+            }
+
+            fn apply_diff(&mut self, delta: &Self::Delta) {
+                for key in &delta.removed {
+                    self.remove(key);
+                }
+                for (key, value_delta) in &delta.modified {
+                    if let Some(value) = self.get_mut(key) {
+                        value.apply_diff(value_delta);
+                    }
+                }
+                self.extend(delta.added.iter().cloned());
+            }
+        }
+    };
+}
+
+impl_diffable_map!(StdHashMap);
+impl_diffable_map!(hashbrown::HashMap);
+
 /// Store a history of past delta-component values so we can apply diffs properly
 #[derive(Component, Debug)]
 pub struct DeltaComponentHistory<C> {
@@ -279,4 +429,72 @@ mod tests {
         let retrieved_component = unsafe { retrieved.deref::<ComponentDeltaCompression>() };
         assert_eq!(retrieved_component, &component);
     }
+
+    #[derive(lightyear_macros::DiffableInternal, Clone, Debug, PartialEq)]
+    struct Inner {
+        a: u32,
+        b: Vec<u8>,
+    }
+
+    #[derive(lightyear_macros::DiffableInternal, Clone, Debug, PartialEq)]
+    struct Outer {
+        inner: Inner,
+        map: HashMap<u8, f32>,
+        #[diff(replace)]
+        name: Option<String>,
+        #[diff(skip)]
+        local: u64,
+    }
+
+    #[test]
+    fn test_derive_diffable() {
+        let old = Outer {
+            inner: Inner {
+                a: 1,
+                b: vec![1, 2, 3],
+            },
+            map: HashMap::from_iter([(0, 0.0), (1, 1.0)]),
+            name: None,
+            local: 5,
+        };
+        let mut new = old.clone();
+        new.inner.b = vec![1, 4];
+        new.map.remove(&0);
+        new.map.insert(2, 2.0);
+        new.name = Some("a".to_string());
+        new.local = 6;
+
+        let delta = old.diff(&new);
+        // only the changed fields are present
+        let inner = delta.inner.as_ref().unwrap();
+        assert!(inner.a.is_none());
+        assert_eq!(
+            inner.b,
+            Some(VecDelta {
+                truncate: Some(2),
+                modified: vec![(1, 4)],
+                added: vec![],
+            })
+        );
+        assert_eq!(delta.name, Some(Some("a".to_string())));
+
+        // the delta can be serialized
+        let bytes = bincode::serde::encode_to_vec(&delta, bincode::config::standard()).unwrap();
+        let (decoded, _): (OuterDelta, _) =
+            bincode::serde::decode_from_slice(&bytes, bincode::config::standard()).unwrap();
+        assert_eq!(decoded, delta);
+
+        let mut applied = old.clone();
+        applied.apply_diff(&decoded);
+        // skipped fields are not modified
+        assert_eq!(applied.local, 5);
+        applied.local = 6;
+        assert_eq!(applied, new);
+
+        // nothing changed: the delta is empty
+        let empty = new.diff(&new);
+        let bytes = bincode::serde::encode_to_vec(&empty, bincode::config::standard()).unwrap();
+        assert_eq!(bytes.len(), 1);
+        assert_eq!(Outer::base_value().local, 0);
+    }
 }
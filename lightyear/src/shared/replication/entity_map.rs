@@ -45,6 +45,17 @@ impl EntityMapper for ReceiveEntityMap {
     }
 }
 
+impl ReceiveEntityMap {
+    /// Map the entity, or return `None` if it was neither mapped by the sender nor present in the map
+    pub(crate) fn try_map_entity(&self, entity: Entity) -> Option<Entity> {
+        if RemoteEntityMap::is_mapped(entity) {
+            Some(RemoteEntityMap::mark_unmapped(entity))
+        } else {
+            self.0.get(&entity).copied()
+        }
+    }
+}
+
 #[derive(Default, Debug, Reflect)]
 /// Map between local and remote entities. (used mostly on client because it's when we receive entity updates)
 pub struct RemoteEntityMap {
@@ -11,7 +11,8 @@ use bytes::Bytes;
 use crate::connection::id::ClientId;
 use crate::packet::message::MessageId;
 use crate::prelude::Tick;
-use crate::protocol::component::ComponentNetId;
+use crate::protocol::component::{ComponentNetId, ComponentRegistry};
+use crate::protocol::version::PeerVersions;
 use crate::protocol::EventContext;
 use crate::serialize::reader::Reader;
 use crate::serialize::varint::{varint_len, VarIntReadExt, VarIntWriteExt};
@@ -111,6 +112,8 @@ pub(crate) enum SpawnAction {
     Despawn,
     // the u64 is the entity's bits (we cannot use Entity directly because it doesn't implement Encode/Decode)
     Reuse(Entity),
+    /// The entity is not relevant to the remote anymore, but it should not be despawned right away
+    RelevanceLost,
 }
 
 impl ToBytes for SpawnAction {
@@ -120,6 +123,7 @@ impl ToBytes for SpawnAction {
             SpawnAction::Spawn => 1,
             SpawnAction::Despawn => 1,
             SpawnAction::Reuse(entity) => 1 + entity.len(),
+            SpawnAction::RelevanceLost => 1,
         }
     }
 
@@ -132,6 +136,7 @@ impl ToBytes for SpawnAction {
                 buffer.write_u8(3)?;
                 entity.to_bytes(buffer)?;
             }
+            SpawnAction::RelevanceLost => buffer.write_u8(4)?,
         }
         Ok(())
     }
@@ -145,6 +150,7 @@ impl ToBytes for SpawnAction {
             1 => Ok(SpawnAction::Spawn),
             2 => Ok(SpawnAction::Despawn),
             3 => Ok(SpawnAction::Reuse(Entity::from_bytes(buffer)?)),
+            4 => Ok(SpawnAction::RelevanceLost),
             _ => Err(SerializationError::InvalidPacketType),
         }
     }
@@ -222,6 +228,23 @@ impl ToBytes for EntityActionsMessage {
     }
 }
 
+impl EntityActionsMessage {
+    /// Convert the inserted and updated components from the versions used by the peer to the current versions
+    pub(crate) fn upgrade(
+        &mut self,
+        component_registry: &ComponentRegistry,
+        peer_versions: &PeerVersions,
+    ) -> Result<(), SerializationError> {
+        for (_, actions) in self.actions.iter_mut() {
+            for component in actions.insert.iter_mut().chain(actions.updates.iter_mut()) {
+                *component =
+                    component_registry.upgrade(std::mem::take(component), peer_versions)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Same as EntityUpdatesMessage, but avoids having to convert a hashmap into a vec
 #[derive(Clone, PartialEq, Debug)]
 pub struct SendEntityUpdatesMessage {
@@ -300,6 +323,23 @@ impl ToBytes for EntityUpdatesMessage {
     }
 }
 
+impl EntityUpdatesMessage {
+    /// Convert the updated components from the versions used by the peer to the current versions
+    pub(crate) fn upgrade(
+        &mut self,
+        component_registry: &ComponentRegistry,
+        peer_versions: &PeerVersions,
+    ) -> Result<(), SerializationError> {
+        for (_, components) in self.updates.iter_mut() {
+            for component in components.iter_mut() {
+                *component =
+                    component_registry.upgrade(std::mem::take(component), peer_versions)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Trait for a service that participates in replication.
 pub(crate) trait ReplicationPeer: Resource {
     type Events: IterComponentInsertEvent<Self::EventContext>
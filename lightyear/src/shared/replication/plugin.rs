@@ -248,8 +248,9 @@ pub(crate) mod send {
 pub(crate) mod shared {
     use crate::client::replication::send::ReplicateToServer;
     use crate::prelude::{
-        NetworkRelevanceMode, PrePredicted, RemoteEntityMap, ReplicateHierarchy, Replicated,
-        ReplicationConfig, ReplicationGroup, ReplicationTarget, ShouldBePredicted, TargetEntity,
+        NetworkRelevanceMode, PrePredicted, RelevanceLost, RemoteEntityMap, ReplicateHierarchy,
+        Replicated, ReplicationConfig, ReplicationGroup, ReplicationTarget, ShouldBePredicted,
+        TargetEntity,
     };
     use crate::shared::replication::authority::{AuthorityPeer, HasAuthority};
     use crate::shared::replication::components::{
@@ -267,6 +268,7 @@ pub(crate) mod shared {
             // REFLECTION
             app.register_type::<TargetEntity>()
                 .register_type::<Replicated>()
+                .register_type::<RelevanceLost>()
                 .register_type::<Controlled>()
                 .register_type::<Replicating>()
                 .register_type::<ReplicationTarget>()
@@ -8,11 +8,13 @@ use crate::prelude::client::Confirmed;
 use crate::prelude::{
     ClientConnectionManager, ClientId, PrePredicted, ServerConnectionManager, Tick,
 };
-use crate::protocol::component::{ComponentKind, ComponentRegistry};
+use crate::protocol::component::{ComponentKind, ComponentNetId, ComponentRegistry};
 use crate::serialize::reader::Reader;
 use crate::shared::events::connection::ConnectionEvents;
 use crate::shared::replication::authority::{AuthorityPeer, HasAuthority};
-use crate::shared::replication::components::{InitialReplicated, Replicated, ReplicationGroupId};
+use crate::shared::replication::components::{
+    InitialReplicated, RelevanceLost, Replicated, ReplicationGroupId,
+};
 #[cfg(test)]
 use crate::utils::captures::Captures;
 use bevy::ecs::entity::EntityHash;
@@ -808,6 +810,8 @@ impl GroupChannel {
     ) {
         let group_id = message.group_id;
         debug!(?remote_tick, ?message, "Received replication actions");
+        // remote entities that regained relevance before the end of their grace period
+        let mut regained_relevance = EntityHashSet::default();
         // NOTE: order matters here, because some components can depend on other entities.
         // These components could even form a cycle, for example A.HasWeapon(B) and B.HasHolder(A)
         // Our solution is to first handle spawn for all entities separately.
@@ -817,7 +821,14 @@ impl GroupChannel {
             match actions.spawn {
                 SpawnAction::Spawn => {
                     if let Some(local_entity) = remote_entity_map.get_local(*remote_entity) {
-                        if world.get_entity(local_entity).is_ok() {
+                        if let Ok(mut entity_mut) = world.get_entity_mut(local_entity) {
+                            // the entity regained relevance before the end of its grace period:
+                            // keep using the same entity
+                            if entity_mut.take::<RelevanceLost>().is_some() {
+                                debug!(?remote_entity, ?local_entity, "Entity regained relevance");
+                                regained_relevance.insert(*remote_entity);
+                                continue;
+                            }
                             warn!(
                                 ?remote_entity,
                                 ?local_entity,
@@ -901,6 +912,17 @@ impl GroupChannel {
                 continue;
             }
 
+            // relevance lost: keep the entity, but mark it so that game code can handle it
+            if actions.spawn == SpawnAction::RelevanceLost {
+                debug!(remote_entity = ?entity, "Received entity relevance lost");
+                if let Some(mut local_entity_mut) = remote_entity_map.get_by_remote(world, entity) {
+                    local_entity_mut.insert(RelevanceLost { tick: remote_tick });
+                } else {
+                    error!("Received relevance lost for an entity that does not exist")
+                }
+                continue;
+            }
+
             // safety: we know by this point that the entity exists
             let Some(mut local_entity_mut) = remote_entity_map.get_by_remote(world, entity) else {
                 error!(?entity, "cannot find entity");
@@ -918,6 +940,7 @@ impl GroupChannel {
             // inserts
             // TODO: remove updates that are duplicate for the same component
             debug!(remote_entity = ?entity, "Received InsertComponent");
+            let mut inserted = vec![];
             for component in actions.insert {
                 // TODO: reuse a single reader that reads through the entire message
                 let mut reader = Reader::from(component);
@@ -931,6 +954,7 @@ impl GroupChannel {
                     )
                     .inspect_err(|e| error!("could not write the component to the entity: {:?}", e))
                 {
+                    inserted.push(kind);
                     // for pre-predicted, we need to update the local_entities data, because the entity
                     // is not spawned so the local_entities data is not updated
                     if kind == ComponentKind::of::<PrePredicted>() {
@@ -955,6 +979,16 @@ impl GroupChannel {
                 events.push_remove_component(local_entity_mut.id(), kind, Tick(0));
                 component_registry.raw_remove(kind, &mut local_entity_mut);
             }
+            // the spawn of an entity that regained relevance contains all its components: remove
+            // the ones that were removed on the remote while the entity was not relevant
+            if regained_relevance.contains(&entity) {
+                Self::remove_stale_components(
+                    &mut local_entity_mut,
+                    component_registry,
+                    &inserted,
+                    events,
+                );
+            }
 
             // updates
             debug!(remote_entity = ?entity, "Received UpdateComponent");
@@ -977,6 +1011,28 @@ impl GroupChannel {
         self.update_confirmed_tick(world, group_id, remote_tick, remote_entity_map);
     }
 
+    /// Remove the replicated components of the entity that are not in `kept`
+    fn remove_stale_components(
+        entity_mut: &mut EntityWorldMut,
+        component_registry: &ComponentRegistry,
+        kept: &[ComponentKind],
+        events: &mut ConnectionEvents,
+    ) {
+        let stale: Vec<ComponentNetId> = component_registry
+            .replication_map
+            .iter()
+            .filter(|(kind, metadata)| {
+                !kept.contains(kind) && entity_mut.contains_id(metadata.component_id)
+            })
+            .filter_map(|(kind, _)| component_registry.kind_map.net_id(kind).copied())
+            .collect();
+        for net_id in stale {
+            debug!(entity = ?entity_mut.id(), ?net_id, "Removing a component that was removed while the entity was not relevant");
+            events.push_remove_component(entity_mut.id(), net_id, Tick(0));
+            component_registry.raw_remove(net_id, entity_mut);
+        }
+    }
+
     // TODO: should we accept updates from the client that lost authority if they are from a
     //  tick before the moment where we changed authority? seems like we should?
     /// Check if we can accept updates for this entity, based on the authority
@@ -8,9 +8,11 @@ use bevy::prelude::{
     PreUpdate, Res, ResMut, Resource,
 };
 pub use command::{ReplicateResourceExt, StopReplicateResourceExt};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
-use crate::prelude::{ChannelKind, Message};
+use crate::prelude::{ChannelKind, Message, Tick};
+use crate::shared::replication::delta::{DeltaMessage, DeltaType, Diffable};
 use crate::shared::replication::network_target::NetworkTarget;
 use crate::shared::replication::ReplicationSend;
 use crate::shared::sets::{InternalMainSet, InternalReplicationSet};
@@ -75,12 +77,60 @@ impl<R> Default for DespawnResource<R> {
     }
 }
 
+/// Message that contains a delta-compressed update of the resource `R`
+#[derive(Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "R::Delta: Serialize",
+    deserialize = "R::Delta: DeserializeOwned"
+))]
+pub struct ResourceDelta<R: Diffable> {
+    /// Tick at which the value of the resource was sent
+    tick: Tick,
+    delta: DeltaMessage<R::Delta>,
+}
+
+/// Message sent back by the receiver of a [`ResourceDelta`], so that the sender can use the
+/// value at `tick` as the baseline of the next deltas.
+///
+/// If the delta could not be applied, the message is a nack: the sender should drop the
+/// baseline of that receiver and send it the full state instead.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourceDeltaAck<R> {
+    tick: Tick,
+    applied: bool,
+    _marker: PhantomData<R>,
+}
+
+impl<R> ResourceDeltaAck<R> {
+    fn ack(tick: Tick) -> Self {
+        Self {
+            tick,
+            applied: true,
+            _marker: PhantomData,
+        }
+    }
+
+    fn nack(tick: Tick) -> Self {
+        Self {
+            tick,
+            applied: false,
+            _marker: PhantomData,
+        }
+    }
+}
+
 pub(crate) mod send {
     use super::*;
 
     use crate::connection::client::{ClientConnection, NetClient};
+    use crate::prelude::server::ConnectionManager;
+    use crate::prelude::{ClientId, TickManager};
+    use crate::shared::events::components::MessageEvent;
     use crate::shared::message::MessageSend;
-    use bevy::prelude::resource_removed;
+    use crate::shared::sets::ServerMarker;
+    use bevy::prelude::{not, resource_exists, resource_removed, EventReader};
+    use bevy::utils::{Duration, HashMap, HashSet};
+    use std::collections::BTreeMap;
     use tracing::trace;
 
     pub(crate) struct ResourceSendPlugin<R> {
@@ -109,12 +159,210 @@ pub(crate) mod send {
             PostUpdate,
             (
                 send_resource_removal::<R, S>.run_if(resource_removed::<R>),
-                send_resource_update::<R, S>,
+                // delta-compressed resources are sent by `send_resource_delta_update` instead
+                send_resource_update::<R, S>
+                    .run_if(not(resource_exists::<ResourceDeltaSendState<R>>)),
             )
                 .in_set(InternalReplicationSet::<S::SetMarker>::BufferResourceUpdates),
         );
     }
 
+    /// Values of a delta-compressed resource that were sent to the clients, and the most recent
+    /// value acked by each client
+    #[derive(Resource)]
+    pub(crate) struct ResourceDeltaSendState<R> {
+        /// Values of the resource that can still be used as a baseline to compute a delta
+        pub(crate) history: BTreeMap<Tick, R>,
+        /// Tick of the most recent value acked by each client
+        pub(crate) acks: HashMap<ClientId, Tick>,
+        /// Clients that could not apply a delta, and need to receive the full state
+        pub(crate) nacked: HashSet<ClientId>,
+    }
+
+    impl<R> ResourceDeltaSendState<R> {
+        fn clear(&mut self) {
+            self.history.clear();
+            self.acks.clear();
+            self.nacked.clear();
+        }
+    }
+
+    // Implementing Default manually to not require R: Default
+    impl<R> Default for ResourceDeltaSendState<R> {
+        fn default() -> Self {
+            Self {
+                history: BTreeMap::new(),
+                acks: HashMap::default(),
+                nacked: HashSet::default(),
+            }
+        }
+    }
+
+    /// Add the systems to replicate a delta-compressed resource from the server to the clients
+    pub(crate) fn add_resource_delta_send_systems<R: Resource + Message + Diffable>(app: &mut App) {
+        app.init_resource::<ResourceDeltaSendState<R>>();
+        app.add_systems(
+            PreUpdate,
+            receive_resource_delta_acks::<R>
+                .in_set(InternalReplicationSet::<ServerMarker>::ReceiveResourceUpdates),
+        );
+        app.add_systems(
+            PostUpdate,
+            send_resource_delta_update::<R>
+                .in_set(InternalReplicationSet::<ServerMarker>::BufferResourceUpdates),
+        );
+    }
+
+    /// Values that were sent more than this long ago are dropped from the history, even if no client
+    /// acked them yet. The clients that ack them afterwards will receive the full state instead.
+    const DELTA_ACK_TIMEOUT: Duration = Duration::from_secs(2);
+
+    /// Update the baseline of each client from the acks they sent, and drop the values
+    /// that cannot be used as a baseline anymore
+    fn receive_resource_delta_acks<R: Resource + Message>(
+        tick_manager: Res<TickManager>,
+        connection_manager: Res<ConnectionManager>,
+        mut state: ResMut<ResourceDeltaSendState<R>>,
+        mut acks: EventReader<MessageEvent<ResourceDeltaAck<R>, ClientId>>,
+    ) {
+        for ack in acks.read() {
+            let client_id = *ack.context();
+            let tick = ack.message().tick;
+            if !ack.message().applied {
+                // the client could not apply a delta: drop its baseline so that it receives the
+                // full state, unless it already acked a more recent value
+                if state
+                    .acks
+                    .get(&client_id)
+                    .map_or(true, |previous| tick - *previous >= 0)
+                {
+                    trace!(?client_id, ?tick, "received resource delta nack");
+                    state.acks.remove(&client_id);
+                    state.nacked.insert(client_id);
+                }
+                continue;
+            }
+            // we can only use the acked value as a baseline if we still have it
+            if !state.history.contains_key(&tick) {
+                continue;
+            }
+            if state
+                .acks
+                .get(&client_id)
+                .map_or(true, |previous| tick - *previous > 0)
+            {
+                trace!(?client_id, ?tick, "received resource delta ack");
+                state.acks.insert(client_id, tick);
+            }
+        }
+        // forget about the clients that disconnected
+        let state = state.as_mut();
+        state
+            .acks
+            .retain(|client_id, _| connection_manager.connections.contains_key(client_id));
+        state
+            .nacked
+            .retain(|client_id| connection_manager.connections.contains_key(client_id));
+        // the values older than the oldest baseline will never be used again
+        if let Some(oldest) = state.acks.values().min().copied() {
+            state.history = state.history.split_off(&oldest);
+        }
+        // drop the values that have not been acked in time (this also avoids tick-wrapping issues),
+        // but keep the latest value since it is still a valid baseline if the resource doesn't change
+        let tick = tick_manager.tick();
+        let max_age = (DELTA_ACK_TIMEOUT.as_secs_f64()
+            / tick_manager.config.tick_duration.as_secs_f64())
+        .ceil()
+        .min((u16::MAX / 3) as f64) as i16;
+        let latest = state.history.keys().next_back().copied();
+        state
+            .history
+            .retain(|t, _| Some(*t) == latest || tick - *t <= max_age);
+        // the clients whose baseline was dropped will receive the full state
+        let history = &state.history;
+        state.acks.retain(|_, t| history.contains_key(t));
+    }
+
+    /// Send the updates of a delta-compressed resource.
+    ///
+    /// Each client receives the delta between the current value and the last value it acked.
+    /// Clients that have not acked any value yet (for example newly connected clients) receive
+    /// the delta from the [`Diffable::base_value`].
+    fn send_resource_delta_update<R: Resource + Message + Diffable>(
+        tick_manager: Res<TickManager>,
+        mut connection_manager: ResMut<ConnectionManager>,
+        mut state: ResMut<ResourceDeltaSendState<R>>,
+        replication_resource: Option<Res<ReplicateResourceMetadata<R>>>,
+        resource: Option<Res<R>>,
+        local_client_connection: Option<Res<ClientConnection>>,
+    ) {
+        let (Some(resource), Some(replication_resource)) = (resource, replication_resource) else {
+            // the resource was removed or is not replicated anymore: the clients forget their
+            // history, so the values that were sent cannot be used as a baseline anymore
+            if !state.history.is_empty() || !state.acks.is_empty() || !state.nacked.is_empty() {
+                state.clear();
+            }
+            return;
+        };
+        // send the resource to newly connected clients, and to the clients that could not apply a delta
+        let mut target = NetworkTarget::Only(connection_manager.new_connected_clients());
+        target.union(&NetworkTarget::Only(state.nacked.drain().collect()));
+        if resource.is_changed() {
+            target.union(&replication_resource.target);
+        }
+        // if running in host-server mode, we don't want to replicate the resource to the local client
+        if let Some(local_client) = local_client_connection.as_ref() {
+            target.exclude(&NetworkTarget::Single(local_client.client.id()));
+        }
+        if target.is_empty() {
+            return;
+        }
+        trace!(
+            "sending delta-compressed resource replication update: {:?}",
+            std::any::type_name::<R>()
+        );
+        let tick = tick_manager.tick();
+        state.history.insert(tick, resource.clone());
+
+        // group the clients by baseline, so that each delta is only computed once
+        let mut baselines: HashMap<Tick, Vec<ClientId>> = HashMap::default();
+        for (client_id, ack_tick) in state.acks.iter() {
+            if target.targets(client_id) && state.history.contains_key(ack_tick) {
+                baselines.entry(*ack_tick).or_default().push(*client_id);
+            }
+        }
+        for (previous_tick, client_ids) in baselines {
+            let delta = state.history[&previous_tick].diff(resource.as_ref());
+            let client_ids = NetworkTarget::Only(client_ids);
+            target.exclude(&client_ids);
+            let _ = connection_manager.erased_send_message_to_target(
+                &ResourceDelta::<R> {
+                    tick,
+                    delta: DeltaMessage {
+                        delta_type: DeltaType::Normal { previous_tick },
+                        delta,
+                    },
+                },
+                replication_resource.channel,
+                client_ids,
+            );
+        }
+        // the clients without a baseline receive the full state
+        if !target.is_empty() {
+            let _ = connection_manager.erased_send_message_to_target(
+                &ResourceDelta::<R> {
+                    tick,
+                    delta: DeltaMessage {
+                        delta_type: DeltaType::FromBase,
+                        delta: R::base_value().diff(resource.as_ref()),
+                    },
+                },
+                replication_resource.channel,
+                target,
+            );
+        }
+    }
+
     /// Send a message indicating that the resource was removed
     fn send_resource_removal<R: Resource + Message, S: MessageSend>(
         mut connection_manager: ResMut<S>,
@@ -185,9 +433,13 @@ pub(crate) mod receive {
     use crate::shared::events::components::MessageEvent;
     use crate::shared::message::MessageSend;
 
+    use crate::channel::builder::ResourceAckChannel;
+    use crate::prelude::client::ConnectionManager;
     use crate::shared::replication::ReplicationPeer;
-    use bevy::prelude::{DetectChangesMut, EventReader, Events};
-    use tracing::trace;
+    use crate::shared::sets::ClientMarker;
+    use bevy::prelude::{DetectChangesMut, EventReader, Events, Local};
+    use std::collections::BTreeMap;
+    use tracing::{error, trace};
 
     use super::*;
 
@@ -286,6 +538,120 @@ pub(crate) mod receive {
         }
     }
 
+    /// Values of a delta-compressed resource received from the server, that the server
+    /// can use as a baseline for the next deltas
+    pub(crate) struct ResourceDeltaHistory<R> {
+        buffer: BTreeMap<Tick, R>,
+        /// Tick of the value that is currently applied to the resource
+        latest_tick: Option<Tick>,
+    }
+
+    // Implementing Default manually to not require R: Default
+    impl<R> Default for ResourceDeltaHistory<R> {
+        fn default() -> Self {
+            Self {
+                buffer: BTreeMap::new(),
+                latest_tick: None,
+            }
+        }
+    }
+
+    /// Add the systems to receive a delta-compressed resource from the server
+    pub(crate) fn add_resource_delta_receive_systems<R: Resource + Message + Diffable>(
+        app: &mut App,
+        is_bidirectional: bool,
+    ) {
+        if is_bidirectional {
+            app.add_systems(
+                PreUpdate,
+                handle_resource_delta_message::<R, true>
+                    .in_set(InternalReplicationSet::<ClientMarker>::ReceiveResourceUpdates),
+            );
+        } else {
+            app.add_systems(
+                PreUpdate,
+                handle_resource_delta_message::<R, false>
+                    .in_set(InternalReplicationSet::<ClientMarker>::ReceiveResourceUpdates),
+            );
+        }
+    }
+
+    /// Apply the deltas received from the server, and ack them so that the server can use
+    /// them as a baseline.
+    ///
+    /// If `BIDIRECTIONAL` is true, the resource is written without triggering change detection.
+    fn handle_resource_delta_message<
+        R: Resource + Message + Diffable,
+        const BIDIRECTIONAL: bool,
+    >(
+        mut commands: Commands,
+        mut connection_manager: ResMut<ConnectionManager>,
+        mut update_message: EventReader<MessageEvent<ResourceDelta<R>>>,
+        mut remove_message: EventReader<MessageEvent<DespawnResource<R>>>,
+        mut resource: Option<ResMut<R>>,
+        mut history: Local<ResourceDeltaHistory<R>>,
+    ) {
+        for message in update_message.read() {
+            let ResourceDelta { tick, delta } = message.message();
+            let value = match delta.delta_type {
+                DeltaType::Normal { previous_tick } => {
+                    let Some(past_value) = history.buffer.get(&previous_tick) else {
+                        error!(
+                            "Missing the value at tick {previous_tick:?} to apply the delta of the resource {}",
+                            std::any::type_name::<R>()
+                        );
+                        // ask the server to send the full state instead
+                        let _ = connection_manager
+                            .send_message::<ResourceAckChannel, _>(
+                                &mut ResourceDeltaAck::<R>::nack(*tick),
+                            )
+                            .inspect_err(|e| error!("error sending resource delta nack: {:?}", e));
+                        continue;
+                    };
+                    let mut value = past_value.clone();
+                    value.apply_diff(&delta.delta);
+                    // the server has received our ack for previous_tick, so it will never
+                    // send a delta from an older value
+                    history.buffer = history.buffer.split_off(&previous_tick);
+                    value
+                }
+                DeltaType::FromBase => {
+                    let mut value = R::base_value();
+                    value.apply_diff(&delta.delta);
+                    value
+                }
+            };
+            trace!(?tick, "received resource delta replication message");
+            history.buffer.insert(*tick, value.clone());
+            let _ = connection_manager
+                .send_message::<ResourceAckChannel, _>(&mut ResourceDeltaAck::<R>::ack(*tick))
+                .inspect_err(|e| error!("error sending resource delta ack: {:?}", e));
+            // the messages can arrive out of order, only apply the most recent value
+            if history
+                .latest_tick
+                .is_some_and(|latest| *tick - latest <= 0)
+            {
+                continue;
+            }
+            history.latest_tick = Some(*tick);
+            if let Some(ref mut resource) = resource {
+                if BIDIRECTIONAL {
+                    // write the received value to the resource
+                    // without change detection to avoid an infinite loop
+                    *(resource.bypass_change_detection()) = value;
+                } else {
+                    **resource = value;
+                }
+            } else {
+                commands.insert_resource(value);
+            }
+        }
+        if !remove_message.is_empty() {
+            remove_message.clear();
+            *history = ResourceDeltaHistory::default();
+        }
+    }
+
     // TODO: upon disconnection, despawn the replicated resource?
     // /// If the entity that was driving the replication of the resource is despawned (usually when the
     // /// client disconnects from the server), despawn the resource
@@ -305,12 +671,16 @@ pub(crate) mod receive {
 
 #[cfg(test)]
 mod tests {
-    use super::StopReplicateResourceExt;
+    use super::send::ResourceDeltaSendState;
+    use super::{ResourceDeltaAck, StopReplicateResourceExt};
+    use crate::channel::builder::ResourceAckChannel;
+    use crate::prelude::client;
+    use crate::prelude::ClientId;
     use crate::shared::replication::network_target::NetworkTarget;
     use crate::shared::replication::resources::ReplicateResourceExt;
     use crate::tests::host_server_stepper::HostServerStepper;
-    use crate::tests::protocol::{Channel1, Resource1, Resource2};
-    use crate::tests::stepper::BevyStepper;
+    use crate::tests::protocol::{Channel1, Resource1, Resource2, ResourceDeltaCompression};
+    use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
     use bevy::prelude::*;
 
     #[test]
@@ -564,6 +934,230 @@ mod tests {
         assert_eq!(stepper.server_app.world().resource::<Resource1>().0, 2.0);
     }
 
+    /// Check that:
+    /// - a delta-compressed resource is replicated with the full state when the client has no baseline
+    /// - the client acks the values it received, and the next updates are computed from that baseline
+    #[test]
+    fn test_resource_delta_compression() {
+        let mut stepper = BevyStepper::default();
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+
+        let start_replicate_system =
+            stepper
+                .server_app
+                .world_mut()
+                .register_system(|mut commands: Commands| {
+                    commands.replicate_resource::<ResourceDeltaCompression, Channel1>(
+                        NetworkTarget::All,
+                    );
+                });
+        let _ = stepper
+            .server_app
+            .world_mut()
+            .run_system(start_replicate_system);
+        stepper
+            .server_app
+            .world_mut()
+            .insert_resource(ResourceDeltaCompression(vec![1, 2]));
+        stepper.frame_step();
+        stepper.frame_step();
+
+        // the full state was replicated, and the client acked it
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .resource::<ResourceDeltaCompression>()
+                .0,
+            vec![1, 2]
+        );
+        stepper.frame_step();
+        let acked_tick = *stepper
+            .server_app
+            .world()
+            .resource::<ResourceDeltaSendState<ResourceDeltaCompression>>()
+            .acks
+            .get(&client_id)
+            .expect("the client should have acked the resource");
+
+        // the update is sent as a delta from the acked value
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ResourceDeltaCompression>()
+            .0
+            .push(3);
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .resource::<ResourceDeltaCompression>()
+                .0,
+            vec![1, 2, 3]
+        );
+        stepper.frame_step();
+        let state = stepper
+            .server_app
+            .world()
+            .resource::<ResourceDeltaSendState<ResourceDeltaCompression>>();
+        let new_acked_tick = *state.acks.get(&client_id).unwrap();
+        assert!(new_acked_tick > acked_tick);
+        // the values older than the baseline are not kept
+        assert_eq!(state.history.keys().next(), Some(&new_acked_tick));
+
+        // without a baseline (for example for a newly connected client), the full state is sent again
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ResourceDeltaSendState<ResourceDeltaCompression>>()
+            .acks
+            .clear();
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ResourceDeltaCompression>()
+            .0
+            .push(4);
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .resource::<ResourceDeltaCompression>()
+                .0,
+            vec![1, 2, 3, 4]
+        );
+    }
+
+    /// Check that:
+    /// - a client that could not apply a delta receives the full state
+    /// - the values that were not acked in time are dropped from the history
+    /// - the history and the baselines are cleared when the resource is removed
+    #[test]
+    fn test_resource_delta_baseline_reset() {
+        let mut stepper = BevyStepper::default();
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+
+        let start_replicate_system =
+            stepper
+                .server_app
+                .world_mut()
+                .register_system(|mut commands: Commands| {
+                    commands.replicate_resource::<ResourceDeltaCompression, Channel1>(
+                        NetworkTarget::All,
+                    );
+                });
+        let _ = stepper
+            .server_app
+            .world_mut()
+            .run_system(start_replicate_system);
+        stepper
+            .server_app
+            .world_mut()
+            .insert_resource(ResourceDeltaCompression(vec![1, 2]));
+        stepper.frame_step();
+        stepper.frame_step();
+        stepper.frame_step();
+        let acked_tick = *stepper
+            .server_app
+            .world()
+            .resource::<ResourceDeltaSendState<ResourceDeltaCompression>>()
+            .acks
+            .get(&client_id)
+            .expect("the client should have acked the resource");
+
+        // the client could not apply a delta: the server drops its baseline and sends the full state
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<ResourceDeltaCompression>()
+            .0
+            .clear();
+        let _ = stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<client::ConnectionManager>()
+            .send_message::<ResourceAckChannel, _>(
+                &mut ResourceDeltaAck::<ResourceDeltaCompression>::nack(acked_tick),
+            );
+        stepper.frame_step();
+        assert!(!stepper
+            .server_app
+            .world()
+            .resource::<ResourceDeltaSendState<ResourceDeltaCompression>>()
+            .acks
+            .contains_key(&client_id));
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .resource::<ResourceDeltaCompression>()
+                .0,
+            vec![1, 2]
+        );
+
+        // without any ack, the old values are dropped but the latest one is kept
+        let tick = stepper.server_tick();
+        let mut state = stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ResourceDeltaSendState<ResourceDeltaCompression>>();
+        state.acks.clear();
+        let latest = *state.history.keys().next_back().unwrap();
+        state
+            .history
+            .insert(tick - 1000, ResourceDeltaCompression(vec![]));
+        stepper.frame_step();
+        let state = stepper
+            .server_app
+            .world()
+            .resource::<ResourceDeltaSendState<ResourceDeltaCompression>>();
+        assert_eq!(
+            state.history.keys().copied().collect::<Vec<_>>(),
+            vec![latest]
+        );
+
+        // the history and the baselines are cleared when the resource is removed
+        stepper.frame_step();
+        stepper
+            .server_app
+            .world_mut()
+            .remove_resource::<ResourceDeltaCompression>();
+        stepper.frame_step();
+        let state = stepper
+            .server_app
+            .world()
+            .resource::<ResourceDeltaSendState<ResourceDeltaCompression>>();
+        assert!(state.history.is_empty());
+        assert!(state.acks.is_empty());
+        stepper.frame_step();
+        assert!(stepper
+            .client_app
+            .world()
+            .get_resource::<ResourceDeltaCompression>()
+            .is_none());
+
+        // the resource is replicated again with the full state
+        stepper
+            .server_app
+            .world_mut()
+            .insert_resource(ResourceDeltaCompression(vec![3]));
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .resource::<ResourceDeltaCompression>()
+                .0,
+            vec![3]
+        );
+    }
+
     // /// Check that when a client disconnects, every resource that was spawned from replication
     // /// gets despawned.
     // #[test]
@@ -47,6 +47,17 @@ pub(crate) struct UpdateMessageMetadata {
     bevy_tick: BevyTick,
     /// The tick at which we buffered the message
     tick: Tick,
+    /// The components with a [`ReplicationFrequency`](crate::prelude::ReplicationFrequency)
+    /// included in the message
+    components: Vec<(Entity, ComponentKind)>,
+}
+
+/// Send settings of a component that has a [`ReplicationFrequency`](crate::prelude::ReplicationFrequency)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ComponentFrequency {
+    /// Minimum number of ticks between two updates of the component
+    pub(crate) interval: u16,
+    pub(crate) priority: f32,
 }
 
 #[derive(Debug)]
@@ -118,6 +129,7 @@ impl ReplicationSender {
                 group_id,
                 bevy_tick,
                 tick,
+                components: vec![],
             },
         );
         // If we don't have a bandwidth cap, buffering a message is equivalent to sending it
@@ -140,6 +152,45 @@ impl ReplicationSender {
         })
     }
 
+    /// Returns true if the changes of a component that happened after `change_tick` should be
+    /// included in the updates of the group.
+    ///
+    /// Components with a [`ComponentFrequency`] are only updated every `interval` ticks, and
+    /// keep track of their own `send_tick`, independently of the rest of the group.
+    pub(crate) fn should_send_component_update(
+        &mut self,
+        entity: Entity,
+        group_id: ReplicationGroupId,
+        kind: ComponentKind,
+        frequency: Option<ComponentFrequency>,
+        change_tick: BevyTick,
+        system_current_tick: BevyTick,
+        tick: Tick,
+    ) -> bool {
+        let channel = self.group_channels.entry(group_id).or_default();
+        let send_tick = match frequency {
+            None => channel.send_tick,
+            Some(frequency) => {
+                let component_channel = channel
+                    .component_channels
+                    .entry((entity, kind))
+                    .or_default();
+                if component_channel
+                    .last_update_tick
+                    .is_some_and(|last| (tick - last) < frequency.interval as i16)
+                {
+                    return false;
+                }
+                // until we send a first update for the component, it is checked every tick, so we can
+                // rely on the group's send_tick (which is set when the component insert is sent)
+                component_channel.send_tick.or(channel.send_tick)
+            }
+        };
+        send_tick.map_or(true, |send_tick| {
+            change_tick.is_newer_than(send_tick, system_current_tick)
+        })
+    }
+
     /// Internal bookkeeping:
     /// 1. handle all nack update messages
     pub(crate) fn update(&mut self, world_tick: BevyTick) {
@@ -149,7 +200,8 @@ impl ReplicationSender {
             if let Some(UpdateMessageMetadata {
                 group_id,
                 bevy_tick,
-                ..
+                tick,
+                components,
             }) = self.updates_message_id_to_group_id.remove(&message_id)
             {
                 if let SendUpdatesMode::SinceLastSend = self.replication_config.send_updates_mode {
@@ -168,6 +220,18 @@ impl ReplicationSender {
                         {
                             channel.send_tick = channel.ack_bevy_tick;
                         }
+                        // same thing for the components that are updated at their own frequency
+                        for key in components {
+                            if let Some(component_channel) =
+                                channel.component_channels.get_mut(&key)
+                            {
+                                if component_channel.ack_bevy_tick.is_some_and(|ack_tick| {
+                                    bevy_tick.is_newer_than(ack_tick, world_tick)
+                                }) {
+                                    component_channel.send_tick = component_channel.ack_bevy_tick;
+                                }
+                            }
+                        }
 
                         // TODO: if all clients lost a given message, than we can immediately drop the delta-compression data
                         //  for that tick
@@ -198,7 +262,8 @@ impl ReplicationSender {
             if let Some(UpdateMessageMetadata {
                 group_id,
                 bevy_tick,
-                ..
+                tick,
+                components,
             }) = self.updates_message_id_to_group_id.get(&message_id)
             {
                 if let Some(channel) = self.group_channels.get_mut(group_id) {
@@ -210,6 +275,7 @@ impl ReplicationSender {
                         "successfully sent message for replication group! Updating send_tick"
                     );
                     channel.send_tick = Some(*bevy_tick);
+                    channel.set_components_send_tick(components, *bevy_tick, *tick);
                     channel.accumulated_priority = 0.0;
                 } else {
                     error!(?message_id, ?group_id, "Received a send message-id notification but the corresponding group channel does not exist");
@@ -237,6 +303,7 @@ impl ReplicationSender {
                 group_id,
                 bevy_tick,
                 tick,
+                components,
             }) = self.updates_message_id_to_group_id.remove(&message_id)
             {
                 if let Some(channel) = self.group_channels.get_mut(&group_id) {
@@ -244,6 +311,11 @@ impl ReplicationSender {
                     debug!(?group_id, ?bevy_tick, ?tick, "Update channel ack_tick");
                     channel.ack_bevy_tick = Some(bevy_tick);
                     channel.ack_tick = Some(tick);
+                    for key in components {
+                        if let Some(component_channel) = channel.component_channels.get_mut(&key) {
+                            component_channel.ack_bevy_tick = Some(bevy_tick);
+                        }
+                    }
 
                     // update the acks for the delta manager
                     delta_manager.receive_ack(tick, group_id, component_registry);
@@ -297,6 +369,15 @@ impl ReplicationSender {
             .base_priority = priority;
     }
 
+    /// Update the priority multiplier of a group for this remote peer.
+    ///
+    /// Does nothing if the group is not replicated to the remote peer.
+    pub(crate) fn update_dynamic_priority(&mut self, group_id: ReplicationGroupId, priority: f32) {
+        if let Some(channel) = self.group_channels.get_mut(&group_id) {
+            channel.dynamic_priority = priority;
+        }
+    }
+
     // TODO: how can I emit metrics here that contain the channel kind?
     //  use a OnceCell that gets set with the channel name mapping when the protocol is finalized?
     //  the other option is to have wrappers in Connection, but that's pretty ugly
@@ -337,13 +418,27 @@ impl ReplicationSender {
     #[cfg_attr(feature = "trace", instrument(level = Level::INFO, skip_all))]
     pub(crate) fn prepare_entity_despawn(&mut self, entity: Entity, group_id: ReplicationGroupId) {
         self.group_with_actions.insert(group_id);
-        self.group_channels
-            .entry(group_id)
-            .or_default()
-            .pending_actions
-            .entry(entity)
-            .or_default()
-            .spawn = SpawnAction::Despawn;
+        let channel = self.group_channels.entry(group_id).or_default();
+        channel
+            .component_channels
+            .retain(|(component_entity, _), _| *component_entity != entity);
+        channel.pending_actions.entry(entity).or_default().spawn = SpawnAction::Despawn;
+    }
+
+    /// The entity is not relevant to the remote anymore, but the remote should keep it until
+    /// the end of its grace period
+    #[cfg_attr(feature = "trace", instrument(level = Level::INFO, skip_all))]
+    pub(crate) fn prepare_entity_relevance_lost(
+        &mut self,
+        entity: Entity,
+        group_id: ReplicationGroupId,
+    ) {
+        self.group_with_actions.insert(group_id);
+        let channel = self.group_channels.entry(group_id).or_default();
+        channel
+            .component_channels
+            .retain(|(component_entity, _), _| *component_entity != entity);
+        channel.pending_actions.entry(entity).or_default().spawn = SpawnAction::RelevanceLost;
     }
 
     // we want to send all component inserts that happen together for the same entity in a single message
@@ -402,6 +497,26 @@ impl ReplicationSender {
             .push(raw_data);
     }
 
+    /// Keep track that the pending updates of the group contain an update for a component
+    /// with a [`ComponentFrequency`]
+    pub(crate) fn prepare_frequency_component_update(
+        &mut self,
+        entity: Entity,
+        group_id: ReplicationGroupId,
+        kind: ComponentKind,
+        frequency: ComponentFrequency,
+    ) {
+        let channel = self.group_channels.entry(group_id).or_default();
+        // the `last_update_tick` is only updated once the update is actually sent
+        channel
+            .component_channels
+            .entry((entity, kind))
+            .or_default();
+        channel
+            .pending_frequency_updates
+            .push(((entity, kind), frequency.priority));
+    }
+
     /// Create a component update.
     #[cfg_attr(feature = "trace", instrument(level = Level::INFO, skip_all))]
     #[allow(clippy::too_many_arguments)]
@@ -503,6 +618,7 @@ impl ReplicationSender {
                 // guaranteed to be sent at some point. (since the actions channel is reliable)
                 channel.send_tick = Some(bevy_tick);
                 channel.ack_tick = Some(tick);
+                channel.ack_frequency_updates(bevy_tick);
                 let priority = channel.accumulated_priority;
                 let message_id = channel.actions_next_send_message_id;
                 channel.actions_next_send_message_id += 1;
@@ -539,12 +655,13 @@ impl ReplicationSender {
         let priority_multiplier = 1.0;
         self.group_channels.values_mut().for_each(|channel| {
             trace!(
-                "in accumulate priority: accumulated={:?} base={:?} multiplier={:?}, send_interval={:?}, time_manager_delta={:?}",
-                channel.accumulated_priority, channel.base_priority, priority_multiplier,
+                "in accumulate priority: accumulated={:?} base={:?} dynamic={:?} multiplier={:?}, send_interval={:?}, time_manager_delta={:?}",
+                channel.accumulated_priority, channel.base_priority, channel.dynamic_priority, priority_multiplier,
                 self.replication_config.send_interval.as_nanos(),
                 time_manager.delta().as_nanos()
             );
-            channel.accumulated_priority += channel.base_priority * priority_multiplier;
+            channel.accumulated_priority +=
+                channel.base_priority * channel.dynamic_priority * priority_multiplier;
         });
     }
 
@@ -591,6 +708,7 @@ impl ReplicationSender {
             //      - tick 4: C2 insert. C1 update. (if we send all updates since last_ack) !!!! We need to update the ack from the Insert only AFTER all the Updates are prepared!!!
             //      - tick 5: Before, we would send C1 update again, since we didn't receive an ack for C1 yet. But now we stop sending it because we know that the message from tick 4 will be received.
             channel.ack_tick = Some(tick);
+            channel.ack_frequency_updates(bevy_tick);
             let priority = channel.accumulated_priority;
             let message_id = channel.actions_next_send_message_id;
             channel.actions_next_send_message_id += 1;
@@ -642,7 +760,9 @@ impl ReplicationSender {
             let updates = std::mem::take(&mut channel.pending_updates);
 
             trace!(?group_id, "pending updates: {:?}", updates);
-            let priority = channel.accumulated_priority;
+            let priority =
+                channel.accumulated_priority * channel.pending_updates_priority(&updates);
+            channel.pending_frequency_updates.clear();
             (
                 EntityUpdatesMessage {
                     group_id,
@@ -671,7 +791,13 @@ impl ReplicationSender {
             let channel = self.group_channels.get_mut(&group_id).unwrap();
             let updates = std::mem::take(&mut channel.pending_updates);
             trace!(?group_id, "pending updates: {:?}", updates);
-            let priority = channel.accumulated_priority;
+            let priority =
+                channel.accumulated_priority * channel.pending_updates_priority(&updates);
+            let components = channel
+                .pending_frequency_updates
+                .drain(..)
+                .map(|(key, _)| key)
+                .collect::<Vec<_>>();
             let message = SendEntityUpdatesMessage {
                 group_id,
                 // TODO: as an optimization (to avoid 1 byte for the Option), we can use `last_action_tick = tick`
@@ -703,12 +829,16 @@ impl ReplicationSender {
                 ?tick,
                 "Send replication update"
             );
+            if !self.bandwidth_cap_enabled {
+                channel.set_components_send_tick(&components, bevy_tick, tick);
+            }
             self.updates_message_id_to_group_id.insert(
                 message_id,
                 UpdateMessageMetadata {
                     group_id,
                     bevy_tick,
                     tick,
+                    components,
                 },
             );
             // If we don't have a bandwidth cap, buffering a message is equivalent to sending it
@@ -766,6 +896,74 @@ pub struct GroupChannel {
     /// for this group because of the bandwidth cap, in which case it will be accumulated.
     pub accumulated_priority: f32,
     pub base_priority: f32,
+    /// Priority multiplier of the group for this remote peer, computed every send interval
+    /// by the replication priority functions
+    pub dynamic_priority: f32,
+
+    /// Send state of the components of the group that have a
+    /// [`ReplicationFrequency`](crate::prelude::ReplicationFrequency), keyed by (network entity, component)
+    pub(crate) component_channels: HashMap<(Entity, ComponentKind), ComponentChannel>,
+    /// Components with a [`ReplicationFrequency`](crate::prelude::ReplicationFrequency) that are
+    /// included in the `pending_updates`, along with their priority
+    pub(crate) pending_frequency_updates: Vec<((Entity, ComponentKind), f32)>,
+}
+
+impl GroupChannel {
+    /// Priority multiplier of the pending updates: the highest priority of the components they
+    /// contain (components without a [`ComponentFrequency`] have a priority of 1.0)
+    fn pending_updates_priority(&self, updates: &EntityHashMap<Entity, Vec<Bytes>>) -> f32 {
+        let Some(max_priority) = self
+            .pending_frequency_updates
+            .iter()
+            .map(|(_, priority)| *priority)
+            .reduce(f32::max)
+        else {
+            return 1.0;
+        };
+        let num_updates: usize = updates.values().map(Vec::len).sum();
+        if num_updates > self.pending_frequency_updates.len() {
+            max_priority.max(1.0)
+        } else {
+            max_priority
+        }
+    }
+
+    /// Called when an update message containing these components was actually sent
+    fn set_components_send_tick(
+        &mut self,
+        components: &[(Entity, ComponentKind)],
+        bevy_tick: BevyTick,
+        tick: Tick,
+    ) {
+        for key in components {
+            if let Some(component_channel) = self.component_channels.get_mut(key) {
+                component_channel.send_tick = Some(bevy_tick);
+                component_channel.last_update_tick = Some(tick);
+            }
+        }
+    }
+
+    /// The pending updates are sent reliably as part of an actions message, so we can consider
+    /// them acked right away
+    fn ack_frequency_updates(&mut self, bevy_tick: BevyTick) {
+        for (key, _) in self.pending_frequency_updates.drain(..) {
+            if let Some(component_channel) = self.component_channels.get_mut(&key) {
+                component_channel.send_tick = Some(bevy_tick);
+                component_channel.ack_bevy_tick = Some(bevy_tick);
+            }
+        }
+    }
+}
+
+/// Send state of a component that is updated at its own frequency
+#[derive(Debug, Default)]
+pub(crate) struct ComponentChannel {
+    /// Tick at which we last sent an update for this component
+    pub(crate) last_update_tick: Option<Tick>,
+    /// Same as [`GroupChannel::send_tick`], for this component only
+    pub(crate) send_tick: Option<BevyTick>,
+    /// Same as [`GroupChannel::ack_bevy_tick`], for this component only
+    pub(crate) ack_bevy_tick: Option<BevyTick>,
 }
 
 impl Default for GroupChannel {
@@ -780,6 +978,9 @@ impl Default for GroupChannel {
             last_action_tick: None,
             accumulated_priority: 0.0,
             base_priority: 1.0,
+            dynamic_priority: 1.0,
+            component_channels: HashMap::default(),
+            pending_frequency_updates: Vec::new(),
         }
     }
 }
@@ -958,7 +1159,8 @@ mod tests {
             Some(&UpdateMessageMetadata {
                 group_id: group_1,
                 bevy_tick: bevy_tick_1,
-                tick: tick_1
+                tick: tick_1,
+                components: vec![],
             })
         );
         assert_eq!(group.send_tick, Some(bevy_tick_1));
@@ -972,7 +1174,8 @@ mod tests {
             Some(&UpdateMessageMetadata {
                 group_id: group_1,
                 bevy_tick: bevy_tick_2,
-                tick: tick_2
+                tick: tick_2,
+                components: vec![],
             })
         );
         assert_eq!(group.send_tick, Some(bevy_tick_2));
@@ -996,7 +1199,8 @@ mod tests {
             Some(&UpdateMessageMetadata {
                 group_id: group_1,
                 bevy_tick: bevy_tick_3,
-                tick: tick_3
+                tick: tick_3,
+                components: vec![],
             })
         );
         assert_eq!(group.send_tick, Some(bevy_tick_3));
@@ -1053,7 +1257,8 @@ mod tests {
             Some(&UpdateMessageMetadata {
                 group_id: group_1,
                 bevy_tick: bevy_tick_1,
-                tick: tick_1
+                tick: tick_1,
+                components: vec![],
             })
         );
         assert_eq!(group.send_tick, None);
@@ -0,0 +1,314 @@
+//! Request/response messaging between the client and the server.
+//!
+//! A request type `Req` is registered together with its response type `Resp` with
+//! [`AppMessageExt::register_request`](crate::prelude::AppMessageExt::register_request).
+//!
+//! The peer that sends the request gets back a [`RequestHandle`] from `send_request`.
+//! The remote peer receives a [`RequestEvent`] and answers it with `respond`; the response
+//! (or the reason why no response was received) is then emitted as a [`ResponseEvent`] on the
+//! peer that sent the request.
+//!
+//! Each request has a timeout, and all the pending requests to a peer fail when we get disconnected
+//! from that peer.
+use std::any::TypeId;
+use std::fmt::{Debug, Formatter};
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+use bevy::prelude::Event;
+use bevy::utils::{Duration, HashMap};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::packet::message::Message;
+
+/// Identifier used to match a response with its request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct RequestId(pub u32);
+
+/// Handle to a request.
+///
+/// On the peer that sent the request, it identifies the request in the [`ResponseEvent`].
+/// On the peer that received the request, it is used to send the response back.
+pub struct RequestHandle<Req, Ctx = ()> {
+    id: RequestId,
+    context: Ctx,
+    _marker: PhantomData<fn() -> Req>,
+}
+
+impl<Req, Ctx> RequestHandle<Req, Ctx> {
+    pub(crate) fn new(id: RequestId, context: Ctx) -> Self {
+        Self {
+            id,
+            context,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn id(&self) -> RequestId {
+        self.id
+    }
+
+    /// The remote peer that the request was sent to (or received from)
+    pub fn context(&self) -> &Ctx {
+        &self.context
+    }
+}
+
+impl<Req, Ctx: Debug> Debug for RequestHandle<Req, Ctx> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RequestHandle")
+            .field("id", &self.id)
+            .field("context", &self.context)
+            .finish()
+    }
+}
+
+impl<Req, Ctx: Clone> Clone for RequestHandle<Req, Ctx> {
+    fn clone(&self) -> Self {
+        Self::new(self.id, self.context.clone())
+    }
+}
+
+impl<Req, Ctx: Copy> Copy for RequestHandle<Req, Ctx> {}
+
+impl<Req, Ctx: PartialEq> PartialEq for RequestHandle<Req, Ctx> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id && self.context == other.context
+    }
+}
+
+/// Reason why a request did not receive a response
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestError {
+    #[error("no response was received before the request timed out")]
+    TimedOut,
+    #[error("the remote peer disconnected before sending a response")]
+    Disconnected,
+}
+
+/// Bevy [`Event`] emitted when a request is received from the remote peer
+#[derive(Event, Debug)]
+pub struct RequestEvent<Req: Message, Ctx = ()> {
+    request: Req,
+    handle: RequestHandle<Req, Ctx>,
+}
+
+impl<Req: Message, Ctx: Copy> RequestEvent<Req, Ctx> {
+    pub fn new(request: Req, handle: RequestHandle<Req, Ctx>) -> Self {
+        Self { request, handle }
+    }
+
+    pub fn request(&self) -> &Req {
+        &self.request
+    }
+
+    /// Handle to use to respond to the request
+    pub fn handle(&self) -> RequestHandle<Req, Ctx> {
+        self.handle
+    }
+
+    pub fn context(&self) -> &Ctx {
+        self.handle.context()
+    }
+}
+
+/// Bevy [`Event`] emitted when a request that we sent is completed: either we received
+/// the response or the request failed
+#[derive(Event, Debug)]
+pub struct ResponseEvent<Req: Message, Resp: Message, Ctx = ()> {
+    handle: RequestHandle<Req, Ctx>,
+    response: Result<Resp, RequestError>,
+}
+
+impl<Req: Message, Resp: Message, Ctx: Copy> ResponseEvent<Req, Resp, Ctx> {
+    pub fn new(handle: RequestHandle<Req, Ctx>, response: Result<Resp, RequestError>) -> Self {
+        Self { handle, response }
+    }
+
+    /// Handle that was returned when the request was sent
+    pub fn handle(&self) -> RequestHandle<Req, Ctx> {
+        self.handle
+    }
+
+    pub fn response(&self) -> Result<&Resp, RequestError> {
+        self.response.as_ref().map_err(|e| *e)
+    }
+
+    pub fn context(&self) -> &Ctx {
+        self.handle.context()
+    }
+}
+
+/// Message used to send a request over the network
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct RpcRequest<Req> {
+    pub(crate) id: RequestId,
+    pub(crate) request: Req,
+}
+
+/// Message used to send a response over the network
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct RpcResponse<Req, Resp> {
+    pub(crate) id: RequestId,
+    pub(crate) response: Resp,
+    #[serde(skip)]
+    _marker: PhantomData<fn() -> Req>,
+}
+
+impl<Req, Resp> RpcResponse<Req, Resp> {
+    pub(crate) fn new(id: RequestId, response: Resp) -> Self {
+        Self {
+            id,
+            response,
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PendingRequest {
+    /// TypeId of the request
+    kind: TypeId,
+    /// Time left before the request times out
+    remaining: Duration,
+}
+
+/// Keeps track of the requests that we sent and that are waiting for a response
+#[derive(Debug)]
+pub(crate) struct RpcManager<Ctx> {
+    next_id: RequestId,
+    /// Pending requests, keyed by the peer they were sent to and their id
+    pending: HashMap<(Ctx, RequestId), PendingRequest>,
+}
+
+impl<Ctx> Default for RpcManager<Ctx> {
+    fn default() -> Self {
+        Self {
+            next_id: RequestId::default(),
+            pending: HashMap::default(),
+        }
+    }
+}
+
+impl<Ctx: Copy + Eq + Hash> RpcManager<Ctx> {
+    /// Reserve the id of the next request
+    pub(crate) fn next_id(&mut self) -> RequestId {
+        let id = self.next_id;
+        self.next_id = RequestId(id.0.wrapping_add(1));
+        id
+    }
+
+    /// Start waiting for the response to a request that was sent
+    pub(crate) fn add<Req: 'static>(
+        &mut self,
+        id: RequestId,
+        context: Ctx,
+        timeout: Duration,
+    ) -> RequestHandle<Req, Ctx> {
+        self.pending.insert(
+            (context, id),
+            PendingRequest {
+                kind: TypeId::of::<Req>(),
+                remaining: timeout,
+            },
+        );
+        RequestHandle::new(id, context)
+    }
+
+    /// Complete the pending request that a response was received for.
+    ///
+    /// Returns None if there is no such pending request, for example if the request already timed out
+    /// or if the response was sent by a different peer.
+    pub(crate) fn receive<Req: 'static>(
+        &mut self,
+        id: RequestId,
+        context: Ctx,
+    ) -> Option<RequestHandle<Req, Ctx>> {
+        let pending = self.pending.get(&(context, id))?;
+        if pending.kind != TypeId::of::<Req>() {
+            return None;
+        }
+        self.pending.remove(&(context, id));
+        Some(RequestHandle::new(id, context))
+    }
+
+    /// Advance the timers of the pending requests of type `Req` by `delta`, and fail the requests
+    /// that timed out or whose peer is not connected anymore.
+    pub(crate) fn fail<Req: 'static>(
+        &mut self,
+        delta: Duration,
+        is_connected: impl Fn(&Ctx) -> bool,
+    ) -> Vec<(RequestHandle<Req, Ctx>, RequestError)> {
+        let mut failed = vec![];
+        self.pending.retain(|(context, id), pending| {
+            if pending.kind != TypeId::of::<Req>() {
+                return true;
+            }
+            let error = if !is_connected(context) {
+                RequestError::Disconnected
+            } else {
+                pending.remaining = pending.remaining.saturating_sub(delta);
+                if !pending.remaining.is_zero() {
+                    return true;
+                }
+                RequestError::TimedOut
+            };
+            failed.push((RequestHandle::new(*id, *context), error));
+            false
+        });
+        failed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rpc_manager() {
+        let mut manager = RpcManager::<u64>::default();
+        let a = manager.next_id();
+        let b = manager.next_id();
+        let c = manager.next_id();
+        manager.add::<u8>(a, 1, Duration::from_millis(100));
+        manager.add::<u8>(b, 2, Duration::from_millis(300));
+        manager.add::<u16>(c, 1, Duration::from_millis(100));
+
+        // the response must come from the peer that received the request
+        assert!(manager.receive::<u8>(a, 2).is_none());
+        // and must be for the correct request type
+        assert!(manager.receive::<u16>(a, 1).is_none());
+        assert_eq!(manager.receive::<u8>(a, 1), Some(RequestHandle::new(a, 1)));
+        assert!(manager.receive::<u8>(a, 1).is_none());
+
+        assert!(manager
+            .fail::<u8>(Duration::from_millis(200), |_| true)
+            .is_empty());
+        assert_eq!(
+            manager.fail::<u8>(Duration::from_millis(200), |_| true),
+            vec![(RequestHandle::new(b, 2), RequestError::TimedOut)]
+        );
+        // requests of other types are unaffected
+        assert_eq!(
+            manager.fail::<u16>(Duration::ZERO, |peer| *peer != 1),
+            vec![(RequestHandle::new(c, 1), RequestError::Disconnected)]
+        );
+    }
+
+    #[test]
+    fn test_rpc_manager_peers() {
+        let mut manager = RpcManager::<u64>::default();
+        let a = manager.next_id();
+        let b = manager.next_id();
+        manager.add::<u8>(a, 1, Duration::from_millis(100));
+        manager.add::<u8>(b, 2, Duration::from_millis(100));
+
+        // concurrent requests of the same type to different peers are failed independently
+        assert_eq!(
+            manager.fail::<u8>(Duration::ZERO, |peer| *peer != 2),
+            vec![(RequestHandle::new(b, 2), RequestError::Disconnected)]
+        );
+        assert_eq!(manager.receive::<u8>(a, 1), Some(RequestHandle::new(a, 1)));
+    }
+}
@@ -0,0 +1,78 @@
+//! Replicate bevy triggers over the network.
+//!
+//! An [`Event`] registered with
+//! [`AppMessageExt::register_trigger`](crate::prelude::AppMessageExt::register_trigger) can be sent
+//! to the remote peer with `send_trigger`, along with the entities that it targets.
+//! The target entities are mapped to the corresponding local entities of the remote peer, and the
+//! event is then triggered there as a [`RemoteTrigger`] on those entities, so that it can be handled
+//! with observers:
+//!
+//! ```rust,ignore
+//! app.add_observer(|trigger: Trigger<RemoteTrigger<Hit>>| {
+//!     info!("entity {:?} was hit", trigger.entity());
+//! });
+//! ```
+use bevy::ecs::entity::{EntityMapper, MapEntities};
+use bevy::prelude::{Entity, Event};
+use bevy::ptr::PtrMut;
+use serde::{Deserialize, Serialize};
+
+use crate::shared::replication::entity_map::ReceiveEntityMap;
+
+/// Message used to send a triggered event over the network
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct TriggerMessage<E> {
+    pub(crate) trigger: E,
+    pub(crate) target_entities: Vec<Entity>,
+}
+
+impl<E> MapEntities for TriggerMessage<E> {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.target_entities
+            .iter_mut()
+            .for_each(|entity| *entity = entity_mapper.map_entity(*entity));
+    }
+}
+
+impl<E> TriggerMessage<E> {
+    /// Returns true if one of the target entities could not be mapped to a local entity
+    pub(crate) fn has_unmapped_targets(&self) -> bool {
+        self.target_entities.contains(&Entity::PLACEHOLDER)
+    }
+}
+
+/// Map the target entities of a received [`TriggerMessage`] to local entities.
+///
+/// Contrary to the usual entity mapping, a target that can't be mapped is replaced with
+/// [`Entity::PLACEHOLDER`] instead of being kept as is, because it would then refer to an
+/// unrelated local entity.
+///
+/// SAFETY: the PtrMut must be a valid pointer to a value of type `TriggerMessage<E>`
+pub(crate) unsafe fn receive_map_trigger_entities<E: 'static>(
+    message: PtrMut,
+    entity_map: &mut ReceiveEntityMap,
+) {
+    let message = message.deref_mut::<TriggerMessage<E>>();
+    message.target_entities.iter_mut().for_each(|entity| {
+        *entity = entity_map
+            .try_map_entity(*entity)
+            .unwrap_or(Entity::PLACEHOLDER)
+    });
+}
+
+/// Bevy [`Event`] triggered when the remote peer sent a trigger.
+///
+/// It is triggered on the local entities that correspond to the entities targeted by the remote peer,
+/// or as a global trigger if the remote peer didn't target any entity.
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct RemoteTrigger<E, Ctx = ()> {
+    pub trigger: E,
+    /// The remote peer that sent the trigger
+    pub from: Ctx,
+}
+
+impl<E, Ctx> RemoteTrigger<E, Ctx> {
+    pub fn new(trigger: E, from: Ctx) -> Self {
+        Self { trigger, from }
+    }
+}
@@ -0,0 +1,19 @@
+//! Clock local to the test thread, so that the tests running in parallel don't advance each
+//! other's time (unlike the global mock clock)
+use std::cell::Cell;
+
+use bevy::utils::Duration;
+use mock_instant::global::Instant;
+
+thread_local! {
+    static START: Instant = Instant::now();
+    static ELAPSED: Cell<Duration> = const { Cell::new(Duration::ZERO) };
+}
+
+pub(crate) fn now() -> Instant {
+    START.with(|start| *start) + ELAPSED.with(Cell::get)
+}
+
+pub(crate) fn advance(duration: Duration) {
+    ELAPSED.with(|elapsed| elapsed.set(elapsed.get() + duration));
+}
@@ -2,6 +2,7 @@
 #![allow(unused_variables)]
 #![allow(dead_code)]
 
+pub(crate) mod clock;
 pub(crate) mod host_server_stepper;
 mod integration;
 
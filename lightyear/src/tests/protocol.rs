@@ -2,7 +2,7 @@ use std::ops::{Add, Mul};
 
 use bevy::app::{App, Plugin};
 use bevy::ecs::entity::MapEntities;
-use bevy::prelude::{default, Component, Entity, EntityMapper, Reflect, Resource};
+use bevy::prelude::{default, Component, Entity, EntityMapper, Event, Reflect, Resource};
 use bevy::utils::HashSet;
 use byteorder::{NetworkEndian, ReadBytesExt, WriteBytesExt};
 use cfg_if::cfg_if;
@@ -24,6 +24,10 @@ pub struct StringMessage(pub String);
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Reflect)]
 pub struct EntityMessage(pub Entity);
 
+// Triggers
+#[derive(Event, Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct StringTrigger(pub String);
+
 impl MapEntities for EntityMessage {
     fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
         self.0 = entity_mapper.map_entity(self.0);
@@ -167,6 +171,26 @@ pub(crate) fn deserialize_resource2(reader: &mut Reader) -> Result<Resource2, Se
     Ok(Resource2(data))
 }
 
+/// Resource that is replicated with delta-compression
+#[derive(Resource, Serialize, Deserialize, Debug, PartialEq, Clone, Reflect)]
+pub struct ResourceDeltaCompression(pub Vec<usize>);
+
+impl Diffable for ResourceDeltaCompression {
+    type Delta = Vec<usize>;
+
+    fn base_value() -> Self {
+        Self(vec![])
+    }
+
+    fn diff(&self, other: &Self) -> Self::Delta {
+        Vec::from_iter(other.0[self.0.len()..].iter().cloned())
+    }
+
+    fn apply_diff(&mut self, delta: &Self::Delta) {
+        self.0.extend(delta);
+    }
+}
+
 // Inputs
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Reflect)]
@@ -204,6 +228,9 @@ impl Plugin for ProtocolPlugin {
         app.register_message::<StringMessage>(ChannelDirection::Bidirectional);
         app.register_message::<EntityMessage>(ChannelDirection::Bidirectional)
             .add_map_entities();
+        app.register_request::<StringMessage, usize>(ChannelDirection::Bidirectional);
+        // triggers
+        app.register_trigger::<StringTrigger>(ChannelDirection::Bidirectional);
         // inputs
         app.add_plugins(InputPlugin::<MyInput>::default());
         // components
@@ -252,6 +279,8 @@ impl Plugin for ProtocolPlugin {
                 serialize_map_entities: None,
             },
         );
+        app.register_resource::<ResourceDeltaCompression>(ChannelDirection::ServerToClient)
+            .add_delta_compression();
         // channels
         app.add_channel::<Channel1>(ChannelSettings {
             mode: ChannelMode::UnorderedUnreliable,
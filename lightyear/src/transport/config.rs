@@ -1,5 +1,7 @@
+use crate::transport::middleware::capture::PacketCapture;
 use crate::transport::middleware::compression::CompressionConfig;
 use crate::transport::middleware::conditioner::LinkConditionerConfig;
+use crate::transport::middleware::recorder::PacketRecorder;
 use bevy::prelude::Reflect;
 
 #[derive(Clone, Debug, Default, Reflect)]
@@ -9,6 +11,13 @@ pub struct SharedIoConfig<T> {
     pub transport: T,
     pub conditioner: Option<LinkConditionerConfig>,
     pub compression: CompressionConfig,
+    /// Record the netcode payloads sent and received (before compression and encryption),
+    /// for example to train a compression dictionary
+    #[reflect(ignore)]
+    pub recorder: Option<PacketRecorder>,
+    /// Write the packets sent and received to a capture, for example to replay a session
+    #[reflect(ignore)]
+    pub capture: Option<PacketCapture>,
 }
 
 impl<T> SharedIoConfig<T> {
@@ -17,6 +26,8 @@ impl<T> SharedIoConfig<T> {
             transport,
             conditioner: None,
             compression: CompressionConfig::default(),
+            recorder: None,
+            capture: None,
         }
     }
     pub fn with_conditioner(mut self, conditioner_config: LinkConditionerConfig) -> Self {
@@ -28,4 +39,14 @@ impl<T> SharedIoConfig<T> {
         self.compression = compression_config;
         self
     }
+
+    pub fn with_packet_recorder(mut self, recorder: PacketRecorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    pub fn with_packet_capture(mut self, capture: PacketCapture) -> Self {
+        self.capture = Some(capture);
+        self
+    }
 }
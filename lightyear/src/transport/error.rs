@@ -9,6 +9,9 @@ pub enum Error {
     #[cfg(all(feature = "webtransport", not(target_family = "wasm")))]
     #[error(transparent)]
     WebTransport(#[from] wtransport::error::ConnectingError),
+    #[cfg(all(feature = "quic", not(target_family = "wasm")))]
+    #[error(transparent)]
+    Quic(#[from] quinn::ConnectionError),
     #[cfg(all(feature = "websocket", not(target_family = "wasm")))]
     #[error(transparent)]
     WebSocket(#[from] tokio_tungstenite::tungstenite::error::Error),
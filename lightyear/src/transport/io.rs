@@ -8,7 +8,9 @@ use bevy::prelude::*;
 #[cfg(feature = "metrics")]
 use metrics;
 
-use crate::transport::{PacketReceiver, PacketSender};
+use crate::protocol::channel::ChannelId;
+use crate::transport::middleware::conditioner::{LinkConditionerHandle, LinkConditionerProfile};
+use crate::transport::{PacketReceiver, PacketSender, TransportCapabilities};
 
 use super::error::Result;
 use super::{BoxedReceiver, BoxedSender};
@@ -22,6 +24,8 @@ pub struct BaseIo<T: Send + Sync> {
     pub(crate) state: IoState,
     pub(crate) stats: IoStats,
     pub(crate) context: T,
+    /// Handle to update the conditions of the link conditioner, if there is one
+    pub(crate) conditioner: Option<LinkConditionerHandle>,
 }
 
 // TODO: add stats/compression to middleware
@@ -46,6 +50,16 @@ impl<T: Send + Sync> BaseIo<T> {
     pub fn stats(&self) -> &IoStats {
         &self.stats
     }
+
+    /// Replace the conditions of the link conditioner with `profile`, or go back to the
+    /// initial conditions if `None`.
+    ///
+    /// Does nothing if the io doesn't have a link conditioner.
+    pub(crate) fn set_conditioner_profile(&self, profile: Option<LinkConditionerProfile>) {
+        if let Some(conditioner) = &self.conditioner {
+            conditioner.set_profile(profile);
+        }
+    }
 }
 
 impl<T: Send + Sync> Debug for BaseIo<T> {
@@ -84,6 +98,32 @@ impl<T: Send + Sync> PacketSender for BaseIo<T> {
         self.stats.packets_sent += 1;
         self.sender.as_mut().send(payload, address)
     }
+
+    fn send_on_stream(
+        &mut self,
+        payload: &[u8],
+        address: &SocketAddr,
+        channel_id: ChannelId,
+    ) -> Result<()> {
+        #[cfg(feature = "metrics")]
+        {
+            metrics::counter!("transport.packets_sent").increment(1);
+            metrics::gauge!("transport.bytes_sent").increment(payload.len() as f64);
+        }
+        self.stats.bytes_sent += payload.len();
+        self.stats.packets_sent += 1;
+        self.sender
+            .as_mut()
+            .send_on_stream(payload, address, channel_id)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.sender.as_mut().flush()
+    }
+
+    fn capabilities(&self, address: &SocketAddr) -> TransportCapabilities {
+        self.sender.capabilities(address)
+    }
 }
 
 pub struct IoDiagnosticsPlugin;
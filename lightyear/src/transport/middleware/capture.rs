@@ -0,0 +1,722 @@
+//! Contains the [`PacketCapture`], which writes every packet sent and received by an io to a file,
+//! and the [`Capture`], which reads it back to inspect it or to replay it with
+//! [`ClientTransport::Replay`](crate::client::io::config::ClientTransport::Replay).
+//!
+//! ```rust,ignore
+//! let capture = PacketCapture::create("session.lycap")?;
+//! let io = IoConfig::from_transport(transport).with_packet_capture(capture);
+//! // ... run a session, then reproduce it:
+//! let capture = Capture::load("session.lycap")?;
+//! let net_config = NetConfig::Netcode {
+//!     auth: Authentication::Token(capture.connect_token().unwrap()),
+//!     io: IoConfig::from_transport(ClientTransport::Replay(capture)),
+//!     config: NetcodeConfig::default(),
+//! };
+//! ```
+//!
+//! # Format
+//!
+//! All integers are little-endian unless specified otherwise.
+//! The file starts with the magic bytes `LYCP` followed by the version of the format (`1`, one byte),
+//! then contains a list of records:
+//!
+//! | field     | size           | description                                                       |
+//! |-----------|----------------|-------------------------------------------------------------------|
+//! | timestamp | u64            | microseconds since the first record                               |
+//! | kind      | u8             | 0: packet sent, 1: packet received, 2: netcode connect token      |
+//! | address   | 7 or 19 bytes  | ip version (4 or 6, one byte), ip (4 or 16 bytes), port (u16)     |
+//! | length    | u32            | number of bytes of data                                           |
+//! | data      | length         | the packet, or the connect token                                  |
+//!
+//! Packets are captured as they are written and read by netcode: the io-level compression
+//! ([`CompressionConfig::Zstd`], [`CompressionConfig::Lz4`]) is not visible in the capture.
+//! The first byte of a netcode packet contains its kind (low 4 bits: 0 = connection request,
+//! 1 = denied, 2 = challenge, 3 = response, 4 = keep-alive, 5 = payload, 6 = disconnect) and the number
+//! of bytes of its sequence number (high 4 bits), followed by the sequence number. The rest of the packet
+//! is encrypted, except for connection requests.
+//!
+//! Clients also write their connect token (2048 bytes) when they connect. It contains the keys used to
+//! decrypt the packets: `client_to_server_key` for the packets sent and `server_to_client_key` for the
+//! packets received.
+//!
+//! With [`CompressionConfig::ZstdDictionary`], the payloads are compressed before being encrypted, so a
+//! decrypted payload starts with a tag byte: 0 if the rest of the payload is raw, 1 if it is a zstd frame
+//! compressed with the dictionary.
+//!
+//! A decrypted (and decompressed) payload packet contains a lightyear packet (big-endian):
+//! - a header of 11 bytes: packet type (u8, 0 = data, 1 = fragment, 2 = MTU probe), packet id (u16),
+//!   last acked packet id (u16), ack bitfield (u32), tick (u16)
+//! - for MTU probes: zeros up to the probed size
+//! - for fragment packets: the channel net id (varint), the message id (u16), the fragment index and
+//!   the number of fragments (u8, or u16 with the `big_messages` feature) and the fragment bytes
+//!   (varint length + bytes)
+//! - then for each channel: the channel net id (varint), the number of messages (varint), and for each
+//!   message a flag byte (1 if followed by a u16 message id) and the message bytes (varint length + bytes)
+//!
+//! The bytes of a message start with the net id (varint) of the message in the `MessageRegistry`.
+//! The messages sent by a client are first prefixed with the
+//! [`NetworkTarget`](crate::prelude::NetworkTarget) they should be re-broadcast to.
+//! [`Capture::describe`] uses a [`ProtocolSchema`] to print the channels and message kinds of each packet,
+//! and the [`CompressionConfig`] of the session to decompress the payloads.
+use std::fmt::{Debug, Formatter, Write as _};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::Path;
+use std::sync::Arc;
+
+use bevy::utils::Duration;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use bytes::Bytes;
+use cfg_if::cfg_if;
+use parking_lot::Mutex;
+use tracing::error;
+
+use crate::channel::builder::{
+    Channel, EntityActionsChannel, EntityUpdatesChannel, PingChannel, PongChannel,
+};
+use crate::connection::netcode::{decrypt_packet, ConnectToken};
+use crate::packet::header::PacketHeader;
+use crate::packet::message::{FragmentData, SingleData};
+use crate::packet::packet_type::PacketType;
+use crate::protocol::channel::ChannelId;
+use crate::protocol::registry::NetId;
+use crate::protocol::schema::ProtocolSchema;
+use crate::serialize::reader::Reader;
+use crate::serialize::varint::VarIntReadExt;
+use crate::serialize::{SerializationError, ToBytes};
+use crate::shared::replication::network_target::NetworkTarget;
+use crate::transport::error::Result;
+use crate::transport::middleware::compression::{CompressionConfig, PayloadCompression};
+use crate::transport::middleware::{PacketReceiverWrapper, PacketSenderWrapper};
+use crate::transport::{
+    PacketReceiver, PacketSender, TransportCapabilities, LOCAL_SOCKET, MAX_STREAM_PACKET_SIZE,
+};
+
+cfg_if! {
+    if #[cfg(test)] {
+        use mock_instant::global::Instant;
+    } else {
+        use bevy::utils::Instant;
+    }
+}
+
+const MAGIC: &[u8; 4] = b"LYCP";
+const VERSION: u8 = 1;
+/// Maximum size of the data of a record: the packets sent on a stream are the biggest ones
+const MAX_RECORD_SIZE: usize = MAX_STREAM_PACKET_SIZE;
+
+/// What a [`CaptureRecord`] contains
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CaptureRecordKind {
+    /// A packet sent to `address`
+    Sent,
+    /// A packet received from `address`
+    Received,
+    /// The netcode connect token of a client
+    ConnectToken,
+}
+
+impl CaptureRecordKind {
+    fn to_byte(self) -> u8 {
+        match self {
+            CaptureRecordKind::Sent => 0,
+            CaptureRecordKind::Received => 1,
+            CaptureRecordKind::ConnectToken => 2,
+        }
+    }
+
+    fn from_byte(byte: u8) -> std::io::Result<Self> {
+        match byte {
+            0 => Ok(CaptureRecordKind::Sent),
+            1 => Ok(CaptureRecordKind::Received),
+            2 => Ok(CaptureRecordKind::ConnectToken),
+            _ => Err(std::io::Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid capture record kind {byte}"),
+            )),
+        }
+    }
+}
+
+/// A packet, or a connect token, written in a capture
+#[derive(Clone, Debug, PartialEq)]
+pub struct CaptureRecord {
+    /// Time elapsed since the first record of the capture
+    pub timestamp: Duration,
+    pub kind: CaptureRecordKind,
+    /// Address of the remote peer
+    pub address: SocketAddr,
+    pub data: Vec<u8>,
+}
+
+impl CaptureRecord {
+    fn write_to(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        writer.write_u64::<LittleEndian>(self.timestamp.as_micros() as u64)?;
+        writer.write_u8(self.kind.to_byte())?;
+        match self.address.ip() {
+            IpAddr::V4(ip) => {
+                writer.write_u8(4)?;
+                writer.write_all(&ip.octets())?;
+            }
+            IpAddr::V6(ip) => {
+                writer.write_u8(6)?;
+                writer.write_all(&ip.octets())?;
+            }
+        }
+        writer.write_u16::<LittleEndian>(self.address.port())?;
+        writer.write_u32::<LittleEndian>(self.data.len() as u32)?;
+        writer.write_all(&self.data)
+    }
+
+    /// Read the next record, or return `None` at the end of the capture
+    fn read_from(reader: &mut impl Read) -> std::io::Result<Option<Self>> {
+        let timestamp = match reader.read_u64::<LittleEndian>() {
+            Ok(timestamp) => Duration::from_micros(timestamp),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let kind = CaptureRecordKind::from_byte(reader.read_u8()?)?;
+        let ip = match reader.read_u8()? {
+            4 => {
+                let mut octets = [0; 4];
+                reader.read_exact(&mut octets)?;
+                IpAddr::V4(Ipv4Addr::from(octets))
+            }
+            6 => {
+                let mut octets = [0; 16];
+                reader.read_exact(&mut octets)?;
+                IpAddr::V6(Ipv6Addr::from(octets))
+            }
+            version => {
+                return Err(std::io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("invalid ip version {version}"),
+                ))
+            }
+        };
+        let port = reader.read_u16::<LittleEndian>()?;
+        let len = reader.read_u32::<LittleEndian>()? as usize;
+        // don't trust the length of a corrupt capture
+        if len > MAX_RECORD_SIZE {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidData,
+                format!("capture record of {len} bytes is too big"),
+            ));
+        }
+        let mut data = vec![0; len];
+        reader.read_exact(&mut data)?;
+        Ok(Some(Self {
+            timestamp,
+            kind,
+            address: SocketAddr::new(ip, port),
+            data,
+        }))
+    }
+}
+
+struct CaptureWriter {
+    writer: Box<dyn Write + Send + Sync>,
+    /// Time of the first record
+    start: Option<Instant>,
+    /// Source of the current time, used to timestamp the records
+    clock: fn() -> Instant,
+}
+
+impl CaptureWriter {
+    fn record(&mut self, kind: CaptureRecordKind, address: SocketAddr, data: &[u8]) {
+        let now = (self.clock)();
+        let start = *self.start.get_or_insert(now);
+        let record = CaptureRecord {
+            timestamp: now - start,
+            kind,
+            address,
+            data: data.to_vec(),
+        };
+        if let Err(e) = record.write_to(&mut self.writer) {
+            error!("could not write the packet capture: {e:?}");
+        }
+    }
+}
+
+/// Writes the packets sent and received by an io to a capture file, with their timestamps and
+/// the addresses of the remote peers.
+///
+/// The capture is a handle: the same capture can be shared by several ios.
+#[derive(Clone)]
+pub struct PacketCapture(Arc<Mutex<CaptureWriter>>);
+
+impl Debug for PacketCapture {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PacketCapture").finish_non_exhaustive()
+    }
+}
+
+impl PacketCapture {
+    /// Create a capture that writes to the file at `path`
+    pub fn create(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+
+    /// Create a capture that writes to `writer`
+    pub fn new(mut writer: impl Write + Send + Sync + 'static) -> std::io::Result<Self> {
+        writer.write_all(MAGIC)?;
+        writer.write_u8(VERSION)?;
+        Ok(Self(Arc::new(Mutex::new(CaptureWriter {
+            writer: Box::new(writer),
+            start: None,
+            clock: Instant::now,
+        }))))
+    }
+
+    /// Use a different source for the current time
+    #[cfg(test)]
+    pub(crate) fn with_clock(self, clock: fn() -> Instant) -> Self {
+        self.0.lock().clock = clock;
+        self
+    }
+
+    /// Write the connect token of a netcode client, which can be used to decrypt its packets
+    pub(crate) fn record_connect_token(&self, token: &ConnectToken) {
+        match token.clone().try_into_bytes() {
+            Ok(bytes) => {
+                self.0
+                    .lock()
+                    .record(CaptureRecordKind::ConnectToken, LOCAL_SOCKET, &bytes)
+            }
+            Err(e) => error!("could not write the connect token to the packet capture: {e:?}"),
+        }
+    }
+
+    fn record(&self, kind: CaptureRecordKind, address: SocketAddr, data: &[u8]) {
+        self.0.lock().record(kind, address, data);
+    }
+
+    /// Flush the buffered records to the underlying writer
+    pub fn flush(&self) -> std::io::Result<()> {
+        self.0.lock().writer.flush()
+    }
+}
+
+pub(crate) struct CapturedPacketSender<T: PacketSender> {
+    inner: T,
+    capture: PacketCapture,
+}
+
+impl<T: PacketSender> PacketSender for CapturedPacketSender<T> {
+    fn send(&mut self, payload: &[u8], address: &SocketAddr) -> Result<()> {
+        self.capture
+            .record(CaptureRecordKind::Sent, *address, payload);
+        self.inner.send(payload, address)
+    }
+
+    fn send_on_stream(
+        &mut self,
+        payload: &[u8],
+        address: &SocketAddr,
+        channel_id: ChannelId,
+    ) -> Result<()> {
+        self.capture
+            .record(CaptureRecordKind::Sent, *address, payload);
+        self.inner.send_on_stream(payload, address, channel_id)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.capture.flush()?;
+        self.inner.flush()
+    }
+
+    fn capabilities(&self, address: &SocketAddr) -> TransportCapabilities {
+        self.inner.capabilities(address)
+    }
+}
+
+impl<T: PacketSender> PacketSenderWrapper<T> for PacketCapture {
+    fn wrap(self, sender: T) -> impl PacketSender {
+        CapturedPacketSender {
+            inner: sender,
+            capture: self,
+        }
+    }
+}
+
+pub(crate) struct CapturedPacketReceiver<T: PacketReceiver> {
+    inner: T,
+    capture: PacketCapture,
+}
+
+impl<T: PacketReceiver> PacketReceiver for CapturedPacketReceiver<T> {
+    fn recv(&mut self) -> Result<Option<(&mut [u8], SocketAddr)>> {
+        let packet = self.inner.recv()?;
+        if let Some((payload, address)) = &packet {
+            self.capture
+                .record(CaptureRecordKind::Received, *address, payload);
+        }
+        Ok(packet)
+    }
+}
+
+impl<T: PacketReceiver> PacketReceiverWrapper<T> for PacketCapture {
+    fn wrap(self, receiver: T) -> impl PacketReceiver {
+        CapturedPacketReceiver {
+            inner: receiver,
+            capture: self,
+        }
+    }
+}
+
+/// The records of a capture written by a [`PacketCapture`]
+#[derive(Clone, Default)]
+pub struct Capture {
+    records: Arc<Vec<CaptureRecord>>,
+}
+
+impl Debug for Capture {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Capture")
+            .field("records", &self.records.len())
+            .finish()
+    }
+}
+
+impl Capture {
+    /// Load the capture file at `path`
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Self::read_from(BufReader::new(File::open(path)?))
+    }
+
+    pub fn read_from(mut reader: impl Read) -> std::io::Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidData,
+                "not a lightyear packet capture",
+            ));
+        }
+        let version = reader.read_u8()?;
+        if version != VERSION {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidData,
+                format!("unsupported packet capture version {version}"),
+            ));
+        }
+        let mut records = Vec::new();
+        while let Some(record) = CaptureRecord::read_from(&mut reader)? {
+            records.push(record);
+        }
+        Ok(Self {
+            records: Arc::new(records),
+        })
+    }
+
+    pub fn records(&self) -> &[CaptureRecord] {
+        &self.records
+    }
+
+    /// The connect token of the client that wrote the capture.
+    ///
+    /// Using it to connect a client that replays the capture lets netcode decrypt the recorded packets.
+    pub fn connect_token(&self) -> Option<ConnectToken> {
+        self.records
+            .iter()
+            .find(|record| record.kind == CaptureRecordKind::ConnectToken)
+            .and_then(|record| ConnectToken::try_from_bytes(&record.data).ok())
+    }
+
+    /// Describe each packet of the capture: the netcode packet kind and, for the payload packets
+    /// of a client capture, the channels and the kinds of the messages it contains.
+    ///
+    /// `compression` is the compression used during the session, which is needed to read the
+    /// payloads compressed with [`CompressionConfig::ZstdDictionary`].
+    pub fn describe(&self, schema: &ProtocolSchema, compression: &CompressionConfig) -> String {
+        let token = self.connect_token();
+        let mut out = String::new();
+        let mut compression = match PayloadCompression::new(compression, None) {
+            Ok(compression) => compression,
+            Err(e) => {
+                let _ = writeln!(out, "could not load the compression: {e:?}");
+                return out;
+            }
+        };
+        for record in self.records.iter() {
+            let key = match (&token, record.kind) {
+                (_, CaptureRecordKind::ConnectToken) => {
+                    let _ = writeln!(out, "{:>10.3?} connect token", record.timestamp);
+                    continue;
+                }
+                (Some(token), CaptureRecordKind::Sent) => Some(token.client_to_server_key),
+                (Some(token), CaptureRecordKind::Received) => Some(token.server_to_client_key),
+                (None, _) => None,
+            };
+            let direction = if record.kind == CaptureRecordKind::Sent {
+                "sent to"
+            } else {
+                "received from"
+            };
+            let _ = write!(
+                out,
+                "{:>10.3?} {direction} {} ({} bytes): ",
+                record.timestamp,
+                record.address,
+                record.data.len()
+            );
+            let (Some(token), Some(key)) = (&token, key) else {
+                let _ = writeln!(out, "encrypted packet");
+                continue;
+            };
+            let mut data = record.data.clone();
+            match decrypt_packet(&mut data, token.protocol_id, key) {
+                Ok((kind, None)) => {
+                    let _ = writeln!(out, "{kind}");
+                }
+                Ok((kind, Some(payload))) => {
+                    let _ = writeln!(out, "{kind}");
+                    let payload = match compression.decompress(Bytes::from(payload)) {
+                        Ok(payload) => payload,
+                        Err(e) => {
+                            let _ = writeln!(out, "    could not decompress payload: {e:?}");
+                            continue;
+                        }
+                    };
+                    let client_to_server = record.kind == CaptureRecordKind::Sent;
+                    if let Err(e) = describe_payload(payload, schema, client_to_server, &mut out) {
+                        let _ = writeln!(out, "    invalid payload: {e:?}");
+                    }
+                }
+                Err(e) => {
+                    let _ = writeln!(out, "could not decrypt packet: {e:?}");
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Describe the lightyear packet contained in a netcode payload packet
+fn describe_payload(
+    payload: Bytes,
+    schema: &ProtocolSchema,
+    client_to_server: bool,
+    out: &mut String,
+) -> std::result::Result<(), SerializationError> {
+    let mut reader = Reader::from(payload);
+    let header = PacketHeader::from_bytes(&mut reader)?;
+    let _ = writeln!(
+        out,
+        "    {:?} packet {} at tick {}",
+        header.get_packet_type(),
+        header.packet_id.0,
+        header.tick.0
+    );
+    if header.get_packet_type() == PacketType::MtuProbe {
+        let _ = writeln!(out, "    padding: {} bytes", reader.remaining());
+        return Ok(());
+    }
+    if header.get_packet_type() == PacketType::DataFragment {
+        let channel_id = ChannelId::from_bytes(&mut reader)?;
+        let fragment = FragmentData::from_bytes(&mut reader)?;
+        let _ = write!(
+            out,
+            "    {}: fragment {}/{} of message {}",
+            channel_name(channel_id, schema),
+            fragment.fragment_id as u32 + 1,
+            fragment.num_fragments,
+            fragment.message_id.0
+        );
+        // only the first fragment starts with the message net id
+        if fragment.fragment_id == 0 {
+            let _ = write!(
+                out,
+                " ({})",
+                message_name(fragment.bytes, channel_id, schema, client_to_server)
+            );
+        }
+        let _ = writeln!(out);
+    }
+    while reader.has_remaining() {
+        let channel_id = ChannelId::from_bytes(&mut reader)?;
+        let num_messages = reader.read_varint()?;
+        let mut messages = Vec::with_capacity(num_messages as usize);
+        for _ in 0..num_messages {
+            let single_data = SingleData::from_bytes(&mut reader)?;
+            messages.push(message_name(
+                single_data.bytes,
+                channel_id,
+                schema,
+                client_to_server,
+            ));
+        }
+        let _ = writeln!(
+            out,
+            "    {}: {}",
+            channel_name(channel_id, schema),
+            messages.join(", ")
+        );
+    }
+    Ok(())
+}
+
+fn channel_name(channel_id: ChannelId, schema: &ProtocolSchema) -> String {
+    schema
+        .channels
+        .iter()
+        .find(|channel| channel.net_id == channel_id)
+        .map_or_else(
+            || format!("unknown channel {channel_id}"),
+            |channel| channel.type_path.clone(),
+        )
+}
+
+/// Name of the message contained in `bytes`
+fn message_name(
+    bytes: Bytes,
+    channel_id: ChannelId,
+    schema: &ProtocolSchema,
+    client_to_server: bool,
+) -> String {
+    // the replication and ping channels don't contain messages from the `MessageRegistry`
+    let channel = channel_name(channel_id, schema);
+    if [
+        EntityActionsChannel::name(),
+        EntityUpdatesChannel::name(),
+        PingChannel::name(),
+        PongChannel::name(),
+    ]
+    .contains(&channel.as_str())
+    {
+        return format!("{} bytes", bytes.len());
+    }
+    let mut reader = Reader::from(bytes);
+    if client_to_server && NetworkTarget::from_bytes(&mut reader).is_err() {
+        return "invalid message".to_string();
+    }
+    let Ok(net_id) = NetId::from_bytes(&mut reader) else {
+        return "invalid message".to_string();
+    };
+    schema
+        .messages
+        .iter()
+        .chain(schema.inputs.iter())
+        .find(|message| message.net_id == net_id)
+        .map_or_else(
+            || format!("unknown message {net_id}"),
+            |message| message.type_path.clone(),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::client::{ClientConfig, ConnectionManager, NetConfig};
+    use crate::tests::clock::{advance, now};
+    use crate::tests::protocol::{Channel1, StringMessage};
+    use crate::tests::stepper::BevyStepper;
+
+    struct Noop;
+
+    impl PacketSender for Noop {
+        fn send(&mut self, _: &[u8], _: &SocketAddr) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Writer that can be read back after the capture is done
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_capture_roundtrip() {
+        let buffer = SharedBuffer::default();
+        let capture = PacketCapture::new(buffer.clone()).unwrap().with_clock(now);
+        let mut sender = PacketSenderWrapper::wrap(capture.clone(), Noop);
+        let address_v6: SocketAddr = "[::1]:5000".parse().unwrap();
+
+        sender.send(&[1, 2, 3], &LOCAL_SOCKET).unwrap();
+        advance(Duration::from_millis(20));
+        sender.send(&[4], &address_v6).unwrap();
+        sender.flush().unwrap();
+
+        let capture = Capture::read_from(buffer.0.lock().as_slice()).unwrap();
+        let records = capture.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].kind, CaptureRecordKind::Sent);
+        assert_eq!(records[0].address, LOCAL_SOCKET);
+        assert_eq!(records[0].data, vec![1, 2, 3]);
+        assert_eq!(records[1].address, address_v6);
+        assert_eq!(records[1].data, vec![4]);
+        assert_eq!(records[0].timestamp, Duration::ZERO);
+        assert_eq!(records[1].timestamp, Duration::from_millis(20));
+        assert!(capture.connect_token().is_none());
+    }
+
+    #[test]
+    fn test_describe_client_capture() {
+        let buffer = SharedBuffer::default();
+        let mut stepper = BevyStepper::default();
+        stepper.stop();
+        if let NetConfig::Netcode { io, .. } = &mut stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<ClientConfig>()
+            .net
+        {
+            io.capture = Some(PacketCapture::new(buffer.clone()).unwrap());
+        }
+        stepper.start();
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<ConnectionManager>()
+            .send_message::<Channel1, StringMessage>(&mut StringMessage("a".to_string()))
+            .unwrap();
+        stepper.frame_step();
+        stepper.frame_step();
+
+        let capture = Capture::read_from(buffer.0.lock().as_slice()).unwrap();
+        assert_eq!(capture.records()[0].kind, CaptureRecordKind::ConnectToken);
+        assert!(capture.connect_token().is_some());
+        let description = capture.describe(
+            &ProtocolSchema::from_world(stepper.client_app.world()),
+            &CompressionConfig::None,
+        );
+        assert!(description.contains("connection request"));
+        assert!(description.contains("challenge packet"));
+        assert!(description.contains("payload packet"));
+        assert!(description.contains(&format!(
+            "Channel1: {}",
+            std::any::type_name::<StringMessage>()
+        )));
+    }
+
+    #[test]
+    fn test_invalid_capture() {
+        assert!(Capture::read_from(b"LYCQ\x01".as_slice()).is_err());
+        assert!(Capture::read_from(b"LYCP\x02".as_slice()).is_err());
+
+        // a corrupt length doesn't allocate a huge buffer
+        let mut capture = b"LYCP\x01".to_vec();
+        CaptureRecord {
+            timestamp: Duration::ZERO,
+            kind: CaptureRecordKind::Received,
+            address: LOCAL_SOCKET,
+            data: vec![],
+        }
+        .write_to(&mut capture)
+        .unwrap();
+        let len = capture.len();
+        capture[len - 4..].copy_from_slice(&u32::MAX.to_le_bytes());
+        let error = Capture::read_from(capture.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+}
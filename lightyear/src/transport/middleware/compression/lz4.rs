@@ -2,6 +2,7 @@
 
 use crate::connection::netcode::MAX_PKT_BUF_SIZE;
 use crate::transport::error::{Error, Result};
+use crate::transport::MAX_STREAM_PACKET_SIZE;
 use std::net::SocketAddr;
 
 pub(crate) use compression::Compressor;
@@ -9,9 +10,10 @@ pub(crate) use decompression::Decompressor;
 
 pub(crate) mod compression {
     use super::*;
+    use crate::protocol::channel::ChannelId;
     use crate::transport::middleware::PacketSenderWrapper;
-    use crate::transport::PacketSender;
-    use lz4_flex::block::compress_into;
+    use crate::transport::{PacketSender, TransportCapabilities};
+    use lz4_flex::block::{compress_into, get_maximum_output_size};
     use tracing::error;
 
     pub(crate) struct Compressor {
@@ -35,6 +37,11 @@ pub(crate) mod compression {
             //     data.len(),
             //     res.len()
             // );
+            // packets sent on a stream can be bigger than the MTU
+            let max_size = get_maximum_output_size(data.len());
+            if self.result.len() < max_size {
+                self.result.resize(max_size, 0);
+            }
             let size = compress_into(data, &mut self.result)?;
             Ok(&self.result[..size])
         }
@@ -50,6 +57,24 @@ pub(crate) mod compression {
             let compressed = self.compressor.compress(payload)?;
             self.inner.send(compressed, address)
         }
+
+        fn send_on_stream(
+            &mut self,
+            payload: &[u8],
+            address: &SocketAddr,
+            channel_id: ChannelId,
+        ) -> Result<()> {
+            let compressed = self.compressor.compress(payload)?;
+            self.inner.send_on_stream(compressed, address, channel_id)
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            self.inner.flush()
+        }
+
+        fn capabilities(&self, address: &SocketAddr) -> TransportCapabilities {
+            self.inner.capabilities(address)
+        }
     }
 
     impl<T: PacketSender> PacketSenderWrapper<T> for Compressor {
@@ -66,7 +91,7 @@ pub(crate) mod decompression {
     use super::*;
     use crate::transport::middleware::PacketReceiverWrapper;
     use crate::transport::PacketReceiver;
-    use lz4_flex::block::decompress_into;
+    use lz4_flex::block::{decompress_into, DecompressError};
 
     pub(crate) struct Decompressor {
         result: Vec<u8>,
@@ -83,7 +108,16 @@ pub(crate) mod decompression {
 
     impl Decompressor {
         pub fn decompress(&mut self, data: &[u8]) -> Result<&mut [u8]> {
-            let size = decompress_into(data, &mut self.result)?;
+            let size = match decompress_into(data, &mut self.result) {
+                // the packet was sent on a stream, which is not limited by the MTU
+                Err(DecompressError::OutputTooSmall { .. })
+                    if self.result.len() < MAX_STREAM_PACKET_SIZE =>
+                {
+                    self.result.resize(MAX_STREAM_PACKET_SIZE, 0);
+                    decompress_into(data, &mut self.result)?
+                }
+                result => result?,
+            };
             Ok(&mut self.result[..size])
         }
     }
@@ -130,6 +164,8 @@ mod tests {
             transport: config,
             conditioner: None,
             compression: CompressionConfig::Lz4,
+            recorder: None,
+            capture: None,
         };
         let mut io = io_config.connect().unwrap();
         let msg = b"hello world".as_slice();
@@ -1,18 +1,107 @@
 use bevy::prelude::Reflect;
 use serde::{Deserialize, Serialize};
 
+use crate::packet::packet_builder::RecvPayload;
+use crate::transport::error::Result;
+use crate::transport::middleware::recorder::PacketRecorder;
+
 #[cfg(feature = "zstd")]
 pub(crate) mod zstd;
 
 #[cfg(feature = "lz4")]
 pub(crate) mod lz4;
 
-#[derive(Clone, Copy, Debug, Default, Reflect, Serialize, Deserialize)]
+#[cfg(feature = "zstd")]
+pub use zstd::ZstdDictionary;
+
+/// Number of bytes that [`PayloadCompression`] can add to a payload: with
+/// [`CompressionConfig::ZstdDictionary`], each payload starts with a tag indicating if it is compressed.
+///
+/// The lightyear packets must leave room for it so that they still fit in a netcode payload.
+pub(crate) const PAYLOAD_COMPRESSION_OVERHEAD: usize = 1;
+
+#[derive(Clone, Debug, Default, Reflect, Serialize, Deserialize)]
 pub enum CompressionConfig {
     #[default]
     None,
     #[cfg(feature = "zstd")]
     Zstd { level: i32 },
+    /// Zstd compression using a dictionary that was trained on packets similar to the ones
+    /// we send. This compresses small packets much better than [`CompressionConfig::Zstd`].
+    ///
+    /// The client and the server must use the same dictionary: a hash of its content is checked
+    /// during the connection handshake.
+    ///
+    /// Unlike the other compression modes, which compress the packets sent by the io, this
+    /// compresses the netcode payloads before they get encrypted (encrypted data cannot be compressed).
+    /// It is only available for netcode connections.
+    #[cfg(feature = "zstd")]
+    ZstdDictionary {
+        level: i32,
+        dictionary: ZstdDictionary,
+    },
     #[cfg(feature = "lz4")]
     Lz4,
 }
+
+/// Processing applied to the netcode payloads, before they get encrypted and after they get decrypted.
+///
+/// Encrypted packets look random, so the compression modes that depend on the content of the
+/// packets ([`CompressionConfig::ZstdDictionary`]) and the [`PacketRecorder`] have to operate on
+/// the payloads instead of on the packets sent by the io.
+pub(crate) struct PayloadCompression {
+    recorder: Option<PacketRecorder>,
+    #[cfg(feature = "zstd")]
+    zstd: Option<(
+        zstd::compression::ZstdCompressor,
+        zstd::decompression::ZstdDecompressor,
+    )>,
+}
+
+impl PayloadCompression {
+    /// Returns an error if the compression dictionary cannot be loaded
+    #[cfg_attr(not(feature = "zstd"), allow(unused_variables))]
+    pub(crate) fn new(
+        compression: &CompressionConfig,
+        recorder: Option<PacketRecorder>,
+    ) -> Result<Self> {
+        Ok(Self {
+            recorder,
+            #[cfg(feature = "zstd")]
+            zstd: match compression {
+                CompressionConfig::ZstdDictionary { level, dictionary } => Some((
+                    zstd::compression::ZstdCompressor::with_dictionary(*level, dictionary)?,
+                    zstd::decompression::ZstdDecompressor::with_dictionary(dictionary)?,
+                )),
+                _ => None,
+            },
+        })
+    }
+
+    /// Record and compress a payload before it gets encrypted
+    pub(crate) fn compress<'a>(&'a mut self, payload: &'a [u8]) -> Result<&'a [u8]> {
+        if let Some(recorder) = &self.recorder {
+            recorder.record(payload);
+        }
+        #[cfg(feature = "zstd")]
+        if let Some((compressor, _)) = &mut self.zstd {
+            return compressor.compress(payload);
+        }
+        Ok(payload)
+    }
+
+    /// Decompress and record a payload after it was decrypted
+    pub(crate) fn decompress(&mut self, payload: RecvPayload) -> Result<RecvPayload> {
+        #[cfg(feature = "zstd")]
+        let payload = match &mut self.zstd {
+            Some((_, decompressor)) => {
+                bytes::Bytes::copy_from_slice(decompressor.decompress(&payload)?)
+            }
+            None => payload,
+        };
+        if let Some(recorder) = &self.recorder {
+            recorder.record(&payload);
+        }
+        Ok(payload)
+    }
+}
@@ -2,17 +2,70 @@
 
 use crate::connection::netcode::MAX_PKT_BUF_SIZE;
 use crate::transport::error::{Error, Result};
+use crate::transport::MAX_STREAM_PACKET_SIZE;
+use bevy::prelude::Reflect;
+use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
 
+/// Magic number at the start of a dictionary in the zstd format
+const DICTIONARY_MAGIC: [u8; 4] = 0xEC30A437_u32.to_le_bytes();
+
+/// In dictionary mode, each packet starts with a tag indicating if the payload is compressed.
+/// Packets that don't get smaller when compressed (for example encrypted data) are sent as is.
+const RAW: u8 = 0;
+const COMPRESSED: u8 = 1;
+
+/// A dictionary used for zstd compression, usually trained on payloads recorded with a
+/// [`PacketRecorder`](crate::transport::middleware::recorder::PacketRecorder)
+#[derive(Clone, Debug, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub struct ZstdDictionary(Vec<u8>);
+
+impl ZstdDictionary {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+
+    /// Train a dictionary of at most `max_size` bytes on a list of sample payloads
+    pub fn train<S: AsRef<[u8]>>(samples: &[S], max_size: usize) -> Result<Self> {
+        Ok(Self(zstd::dict::from_samples(samples, max_size)?))
+    }
+
+    /// Id of the dictionary, stored in its header.
+    ///
+    /// Returns 0 for raw content dictionaries, which don't have a header
+    pub fn id(&self) -> u32 {
+        match self.0.get(..8) {
+            Some(header) if header[..4] == DICTIONARY_MAGIC => {
+                u32::from_le_bytes(header[4..8].try_into().unwrap())
+            }
+            _ => 0,
+        }
+    }
+
+    /// Hash of the content of the dictionary, used to check that two peers use the same dictionary.
+    ///
+    /// Unlike the [`id`](Self::id), it is different for two different raw content dictionaries.
+    pub fn fingerprint(&self) -> u64 {
+        seahash::hash(&self.0)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
 pub(crate) mod compression {
     use super::*;
+    use crate::protocol::channel::ChannelId;
     use crate::transport::middleware::PacketSenderWrapper;
-    use crate::transport::PacketSender;
+    use crate::transport::{PacketSender, TransportCapabilities};
     use zstd::bulk::Compressor;
 
     pub(crate) struct ZstdCompressor {
         result: Vec<u8>,
         compressor: Compressor<'static>,
+        /// Buffer for the compressed payload when using a dictionary
+        compressed: Option<Vec<u8>>,
     }
 
     impl ZstdCompressor {
@@ -20,13 +73,48 @@ pub(crate) mod compression {
             ZstdCompressor {
                 result: Vec::with_capacity(MAX_PKT_BUF_SIZE),
                 compressor: Compressor::new(level).unwrap(),
+                compressed: None,
             }
         }
 
+        /// Returns an error if the dictionary cannot be loaded
+        pub fn with_dictionary(level: i32, dictionary: &ZstdDictionary) -> Result<Self> {
+            let mut compressor = Compressor::with_dictionary(level, dictionary.as_bytes())?;
+            // the dictionary is checked during the handshake, no need to send its id in every packet
+            compressor.include_dictid(false)?;
+            Ok(ZstdCompressor {
+                result: Vec::with_capacity(MAX_PKT_BUF_SIZE),
+                compressor,
+                compressed: Some(Vec::with_capacity(MAX_PKT_BUF_SIZE)),
+            })
+        }
+
         pub fn compress(&mut self, data: &[u8]) -> Result<&[u8]> {
-            self.compressor
-                .compress_to_buffer(data, &mut self.result)
-                .map_err(|e| Error::Io(e))?;
+            let Some(compressed) = self.compressed.as_mut() else {
+                // packets sent on a stream can be bigger than the MTU
+                self.result.clear();
+                self.result
+                    .reserve(zstd::zstd_safe::compress_bound(data.len()));
+                self.compressor
+                    .compress_to_buffer(data, &mut self.result)
+                    .map_err(|e| Error::Io(e))?;
+                return Ok(&self.result);
+            };
+            self.result.clear();
+            // only keep the compressed payload if it is smaller
+            compressed.clear();
+            compressed.reserve(data.len());
+            match self.compressor.compress_to_buffer(data, compressed) {
+                Ok(_) if compressed.len() < data.len() => {
+                    self.result.push(COMPRESSED);
+                    self.result.extend_from_slice(compressed);
+                }
+                // the payload could not be compressed
+                _ => {
+                    self.result.push(RAW);
+                    self.result.extend_from_slice(data);
+                }
+            }
             Ok(&self.result)
         }
     }
@@ -41,6 +129,24 @@ pub(crate) mod compression {
             let compressed = self.compressor.compress(payload)?;
             self.inner.send(compressed, address)
         }
+
+        fn send_on_stream(
+            &mut self,
+            payload: &[u8],
+            address: &SocketAddr,
+            channel_id: ChannelId,
+        ) -> Result<()> {
+            let compressed = self.compressor.compress(payload)?;
+            self.inner.send_on_stream(compressed, address, channel_id)
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            self.inner.flush()
+        }
+
+        fn capabilities(&self, address: &SocketAddr) -> TransportCapabilities {
+            self.inner.capabilities(address)
+        }
     }
 
     impl<T: PacketSender> PacketSenderWrapper<T> for ZstdCompressor {
@@ -62,6 +168,7 @@ pub(crate) mod decompression {
     pub(crate) struct ZstdDecompressor {
         result: Vec<u8>,
         decompressor: Decompressor<'static>,
+        dictionary: bool,
     }
 
     impl ZstdDecompressor {
@@ -69,13 +176,55 @@ pub(crate) mod decompression {
             ZstdDecompressor {
                 result: Vec::with_capacity(MAX_PKT_BUF_SIZE),
                 decompressor: Decompressor::new().unwrap(),
+                dictionary: false,
+            }
+        }
+
+        /// Returns an error if the dictionary cannot be loaded
+        pub fn with_dictionary(dictionary: &ZstdDictionary) -> Result<Self> {
+            Ok(ZstdDecompressor {
+                result: Vec::with_capacity(MAX_PKT_BUF_SIZE),
+                decompressor: Decompressor::with_dictionary(dictionary.as_bytes())?,
+                dictionary: true,
+            })
+        }
+
+        /// Make sure that the result buffer can hold the content of the frame, which can be bigger
+        /// than the MTU for packets sent on a stream
+        fn reserve_frame(&mut self, frame: &[u8]) {
+            if let Ok(Some(size)) = zstd::zstd_safe::get_frame_content_size(frame) {
+                self.result.clear();
+                self.result
+                    .reserve((size as usize).min(MAX_STREAM_PACKET_SIZE));
             }
         }
 
         pub fn decompress(&mut self, data: &[u8]) -> Result<&mut [u8]> {
-            self.decompressor
-                .decompress_to_buffer(data, &mut self.result)
-                .map_err(|e| Error::Io(e))?;
+            if !self.dictionary {
+                self.reserve_frame(data);
+                self.decompressor
+                    .decompress_to_buffer(data, &mut self.result)
+                    .map_err(|e| Error::Io(e))?;
+                return Ok(&mut self.result);
+            }
+            match data.split_first() {
+                Some((&COMPRESSED, frame)) => {
+                    self.reserve_frame(frame);
+                    self.decompressor
+                        .decompress_to_buffer(frame, &mut self.result)
+                        .map_err(|e| Error::Io(e))?;
+                }
+                Some((&RAW, payload)) => {
+                    self.result.clear();
+                    self.result.extend_from_slice(payload);
+                }
+                _ => {
+                    return Err(Error::Io(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "invalid zstd dictionary packet",
+                    )))
+                }
+            }
             Ok(&mut self.result)
         }
     }
@@ -108,27 +257,192 @@ pub(crate) mod decompression {
 
 #[cfg(test)]
 mod tests {
-    use crate::prelude::{SharedIoConfig, TransportConfig};
-    use crate::transport::middleware::compression::CompressionConfig;
+    use super::compression::ZstdCompressor;
+    use super::decompression::ZstdDecompressor;
+    use super::*;
+    use crate::client::io::config::ClientTransport;
+    use crate::prelude::client::{ClientConfig, ConnectionManager, NetConfig, NetworkingState};
+    use crate::prelude::server::{self, Replicate, ServerConfig};
+    use crate::prelude::{PacketRecorder, SharedConfig};
+    use crate::tests::protocol::{Channel1, ComponentSyncModeFull, StringMessage};
+    use crate::tests::stepper::BevyStepper;
+    use crate::transport::config::SharedIoConfig;
+    use crate::transport::middleware::compression::{CompressionConfig, PayloadCompression};
     use crate::transport::LOCAL_SOCKET;
+    use bevy::prelude::{default, State};
+    use bevy::utils::Duration;
 
     #[test]
     fn test_compression() {
         let (send, recv) = crossbeam_channel::unbounded();
 
-        let config = TransportConfig::LocalChannel { send, recv };
-        let io_config = SharedIoConfig {
+        let config = ClientTransport::LocalChannel { send, recv };
+        let io_config = SharedIoConfig::<ClientTransport> {
             transport: config,
             conditioner: None,
             compression: CompressionConfig::Zstd { level: 0 },
+            recorder: None,
+            capture: None,
         };
         let mut io = io_config.connect().unwrap();
         let msg = b"hello world".as_slice();
         // send data
-        io.sender.send(&msg, &LOCAL_SOCKET).unwrap();
+        io.sender.send(msg, &LOCAL_SOCKET).unwrap();
 
         // receive data
-        let (data, addr) = io.receiver.recv().unwrap().unwrap();
+        let (data, _) = io.receiver.recv().unwrap().unwrap();
         assert_eq!(data.as_ref(), msg);
     }
+
+    #[test]
+    fn test_dictionary_compression() {
+        let samples: Vec<Vec<u8>> = (0..1000u32)
+            .map(|i| {
+                format!("{{\"entity\":{i},\"position\":[{i}.5,2.0],\"health\":100}}").into_bytes()
+            })
+            .collect();
+        let dictionary = ZstdDictionary::train(&samples, 4096).unwrap();
+        assert_ne!(dictionary.id(), 0);
+
+        let mut compressor = ZstdCompressor::with_dictionary(3, &dictionary).unwrap();
+        let mut decompressor = ZstdDecompressor::with_dictionary(&dictionary).unwrap();
+        let msg = b"{\"entity\":2000,\"position\":[2000.5,2.0],\"health\":100}";
+        let compressed = compressor.compress(msg).unwrap().to_vec();
+        assert_eq!(compressed[0], COMPRESSED);
+        assert!(compressed.len() < msg.len());
+        assert_eq!(decompressor.decompress(&compressed).unwrap(), msg);
+
+        // payloads that cannot be compressed are sent as is
+        let random: Vec<u8> = (0..100).map(|_| rand::random()).collect();
+        let compressed = compressor.compress(&random).unwrap().to_vec();
+        assert_eq!(compressed[0], RAW);
+        assert_eq!(
+            decompressor.decompress(&compressed).unwrap(),
+            random.as_slice()
+        );
+    }
+
+    #[test]
+    fn test_invalid_dictionary() {
+        // a dictionary with the zstd header but invalid content
+        let mut bytes = DICTIONARY_MAGIC.to_vec();
+        bytes.extend([1, 0, 0, 0, 255, 255, 255]);
+        let dictionary = ZstdDictionary::new(bytes);
+        assert!(ZstdDecompressor::with_dictionary(&dictionary).is_err());
+
+        // connecting with it returns an error instead of panicking
+        let config = CompressionConfig::ZstdDictionary {
+            level: 3,
+            dictionary,
+        };
+        assert!(PayloadCompression::new(&config, None).is_err());
+    }
+
+    /// Stepper where both the client and the server use `compression`, and the client
+    /// records its payloads with `recorder`
+    fn stepper_with_io(
+        compression: CompressionConfig,
+        recorder: Option<PacketRecorder>,
+    ) -> BevyStepper {
+        let frame_duration = Duration::from_millis(10);
+        let shared_config = SharedConfig {
+            tick: crate::prelude::TickConfig::new(frame_duration),
+            ..default()
+        };
+        let mut stepper = BevyStepper::new(shared_config, ClientConfig::default(), frame_duration);
+        // the io is only built when connecting, so we can still update the configs
+        if let NetConfig::Netcode { io, .. } = &mut stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<ClientConfig>()
+            .net
+        {
+            io.compression = compression.clone();
+            io.recorder = recorder;
+        }
+        for net in &mut stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ServerConfig>()
+            .net
+        {
+            #[allow(irrefutable_let_patterns)]
+            if let server::NetConfig::Netcode { io, .. } = net {
+                io.compression = compression.clone();
+            }
+        }
+        stepper.init();
+        stepper
+    }
+
+    /// The payloads are recorded and compressed before they get encrypted, so a dictionary
+    /// trained on a recorded session compresses the payloads of the next sessions
+    #[test]
+    fn test_dictionary_compression_connection() {
+        let recorder = PacketRecorder::new(10_000);
+        let mut stepper = stepper_with_io(CompressionConfig::None, Some(recorder.clone()));
+        let message = "the same message is sent every frame";
+        for _ in 0..100 {
+            stepper
+                .client_app
+                .world_mut()
+                .resource_mut::<ConnectionManager>()
+                .send_message::<Channel1, _>(&mut StringMessage(message.to_string()))
+                .unwrap();
+            stepper.frame_step();
+        }
+        let dictionary = recorder.train_zstd_dictionary(1024).unwrap();
+
+        // the recorded payloads are not encrypted: the ones containing the message can be
+        // compressed with the dictionary
+        let samples = recorder
+            .take_samples()
+            .into_iter()
+            .filter(|sample| sample.len() > message.len())
+            .collect::<Vec<_>>();
+        assert!(!samples.is_empty());
+        let mut compressor = ZstdCompressor::with_dictionary(3, &dictionary).unwrap();
+        for sample in &samples {
+            assert_eq!(compressor.compress(sample).unwrap()[0], COMPRESSED);
+        }
+
+        let mut stepper = stepper_with_io(
+            CompressionConfig::ZstdDictionary {
+                level: 3,
+                dictionary,
+            },
+            None,
+        );
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .resource::<State<NetworkingState>>()
+                .get(),
+            &NetworkingState::Connected
+        );
+        // the payloads are decompressed correctly
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((Replicate::default(), ComponentSyncModeFull(1.0)))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        let client_entity = stepper
+            .client_app
+            .world()
+            .resource::<crate::prelude::client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client");
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get::<ComponentSyncModeFull>(client_entity),
+            Some(&ComponentSyncModeFull(1.0))
+        );
+    }
 }
@@ -1,15 +1,35 @@
 //! Contains the `LinkConditioner` struct which can be used to simulate network conditions
-use bevy::reflect::Reflect;
+//!
+//! The conditioner can be applied to both the incoming and the outgoing packets of an io.
+//! It can model latency, jitter, uniform or bursty packet loss, duplication, reordering and a
+//! bandwidth cap.
+//!
+//! The conditions can be changed at runtime by inserting a [`LinkConditionerProfile`] resource
+//! in the client or server app, for example to degrade the link for a few seconds during a soak test:
+//! ```rust,ignore
+//! app.insert_resource(LinkConditionerProfile::default().then(
+//!     Duration::from_secs(5),
+//!     LinkConditionerConfig {
+//!         incoming: LinkConditions::default().with_loss(PacketLoss::Uniform(0.3)),
+//!         outgoing: LinkConditions::default().with_loss(PacketLoss::Uniform(0.3)),
+//!     },
+//! ));
+//! ```
 use std::net::SocketAddr;
+use std::sync::Arc;
 
+use bevy::prelude::{ReflectResource, Resource};
+use bevy::reflect::Reflect;
 use bevy::utils::Duration;
 use cfg_if::cfg_if;
+use parking_lot::RwLock;
 use rand;
 use rand::{thread_rng, Rng};
 
+use crate::protocol::channel::ChannelId;
 use crate::transport::error::Result;
-use crate::transport::middleware::PacketReceiverWrapper;
-use crate::transport::PacketReceiver;
+use crate::transport::middleware::{PacketReceiverWrapper, PacketSenderWrapper};
+use crate::transport::{PacketReceiver, PacketSender, TransportCapabilities};
 use crate::utils::ready_buffer::ReadyBuffer;
 
 cfg_if! {
@@ -21,60 +41,339 @@ cfg_if! {
 }
 
 /// Contains configuration required to initialize a LinkConditioner
-#[derive(Clone, Debug, Reflect)]
+#[derive(Clone, Debug, Default, PartialEq, Reflect)]
 pub struct LinkConditionerConfig {
-    /// Delay to receive incoming messages in milliseconds (half the RTT)
-    pub incoming_latency: Duration,
-    /// The maximum additional random latency to delay received incoming
-    /// messages in milliseconds. This may be added OR subtracted from the
-    /// latency determined in the `incoming_latency` property above
-    pub incoming_jitter: Duration,
-    /// The % chance that an incoming packet will be dropped.
+    /// Conditions applied to the packets we receive
+    pub incoming: LinkConditions,
+    /// Conditions applied to the packets we send
+    pub outgoing: LinkConditions,
+}
+
+/// Network conditions applied to the packets going through the link conditioner in one direction
+#[derive(Clone, Debug, Default, PartialEq, Reflect)]
+pub struct LinkConditions {
+    /// Delay added to every packet
+    pub latency: Duration,
+    /// The maximum additional random latency. This may be added OR subtracted from the
+    /// `latency` above
+    pub jitter: Duration,
+    /// How packets get dropped
+    pub loss: PacketLoss,
+    /// The % chance that a packet will be delivered twice.
     /// Represented as a value between 0 and 1
-    pub incoming_loss: f32,
+    pub duplication: f32,
+    /// The % chance that a packet will be held back for an extra `reordering_delay`, so that
+    /// it arrives after packets that were sent after it.
+    /// Represented as a value between 0 and 1
+    pub reordering: f32,
+    pub reordering_delay: Duration,
+    /// Limit on the throughput of the link. `None` means that the bandwidth is unlimited
+    pub bandwidth: Option<BandwidthLimit>,
+}
+
+impl LinkConditions {
+    pub fn with_latency(mut self, latency: Duration, jitter: Duration) -> Self {
+        self.latency = latency;
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn with_loss(mut self, loss: PacketLoss) -> Self {
+        self.loss = loss;
+        self
+    }
+
+    pub fn with_duplication(mut self, duplication: f32) -> Self {
+        self.duplication = duplication;
+        self
+    }
+
+    pub fn with_reordering(mut self, reordering: f32, reordering_delay: Duration) -> Self {
+        self.reordering = reordering;
+        self.reordering_delay = reordering_delay;
+        self
+    }
+
+    pub fn with_bandwidth(mut self, bandwidth: BandwidthLimit) -> Self {
+        self.bandwidth = Some(bandwidth);
+        self
+    }
+}
+
+/// Model used to drop packets
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub enum PacketLoss {
+    /// Each packet is dropped independently with the given probability (between 0 and 1)
+    Uniform(f32),
+    /// Bursty loss following the Gilbert-Elliott model: the link alternates between a good
+    /// and a bad state, and packets are dropped more often in the bad state.
+    ///
+    /// All values are probabilities between 0 and 1, evaluated for each packet.
+    GilbertElliott {
+        /// Chance to go from the good state to the bad state
+        good_to_bad: f32,
+        /// Chance to go from the bad state back to the good state
+        bad_to_good: f32,
+        /// Chance to drop a packet in the good state
+        good_loss: f32,
+        /// Chance to drop a packet in the bad state
+        bad_loss: f32,
+    },
+}
+
+impl Default for PacketLoss {
+    fn default() -> Self {
+        PacketLoss::Uniform(0.0)
+    }
+}
+
+/// Limits the throughput of the link: packets wait in a queue until the link is free to send
+/// them, and are dropped if the queue is full
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct BandwidthLimit {
+    pub bytes_per_second: u32,
+    /// Maximum number of bytes that can wait in the queue
+    pub queue_capacity: usize,
+}
+
+/// A sequence of link conditions that replace the [`LinkConditionerConfig`] of the io.
+///
+/// Each phase lasts for its `duration`. After the last phase, the io goes back to its
+/// base conditions, unless the profile repeats.
+///
+/// Insert (or modify) this resource in the client or server app to switch profiles at runtime;
+/// the profile (re)starts when the resource changes or when the io is started. This only has an
+/// effect if the io was configured with a [`LinkConditionerConfig`].
+#[derive(Resource, Clone, Debug, Default, PartialEq, Reflect)]
+#[reflect(Resource)]
+pub struct LinkConditionerProfile {
+    pub phases: Vec<LinkConditionerPhase>,
+    /// If true, go back to the first phase after the last one
+    pub repeat: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Reflect)]
+pub struct LinkConditionerPhase {
+    pub duration: Duration,
+    pub config: LinkConditionerConfig,
+}
+
+impl LinkConditionerProfile {
+    /// Use `config` until the profile is changed or removed
+    pub fn constant(config: LinkConditionerConfig) -> Self {
+        Self::default().then(Duration::MAX, config)
+    }
+
+    /// Add a phase that uses `config` for `duration`
+    pub fn then(mut self, duration: Duration, config: LinkConditionerConfig) -> Self {
+        self.phases.push(LinkConditionerPhase { duration, config });
+        self
+    }
+
+    pub fn repeating(mut self) -> Self {
+        self.repeat = true;
+        self
+    }
+
+    /// Config to use `elapsed` after the start of the profile, or `None` if the profile is over
+    fn config_at(&self, elapsed: Duration) -> Option<&LinkConditionerConfig> {
+        let total = self.phases.iter().fold(Duration::ZERO, |total, phase| {
+            total.saturating_add(phase.duration)
+        });
+        if total.is_zero() {
+            return None;
+        }
+        let mut elapsed = if self.repeat && total < Duration::MAX {
+            Duration::from_nanos((elapsed.as_nanos() % total.as_nanos()) as u64)
+        } else {
+            elapsed
+        };
+        for phase in &self.phases {
+            if elapsed < phase.duration {
+                return Some(&phase.config);
+            }
+            elapsed -= phase.duration;
+        }
+        None
+    }
+}
+
+#[derive(Debug)]
+struct ConditionerState {
+    base: LinkConditionerConfig,
+    profile: Option<(Instant, LinkConditionerProfile)>,
+}
+
+/// Handle to the conditions used by the link conditioners of an io.
+///
+/// It is shared between the incoming and outgoing conditioners, so that the conditions can be
+/// updated while the io is running.
+#[derive(Debug, Clone)]
+pub(crate) struct LinkConditionerHandle {
+    state: Arc<RwLock<ConditionerState>>,
+    /// Source of the current time, used to delay the packets and to run the profiles
+    clock: fn() -> Instant,
+}
+
+impl LinkConditionerHandle {
+    pub(crate) fn new(config: LinkConditionerConfig) -> Self {
+        Self {
+            state: Arc::new(RwLock::new(ConditionerState {
+                base: config,
+                profile: None,
+            })),
+            clock: Instant::now,
+        }
+    }
+
+    /// Use a different source for the current time
+    #[cfg(test)]
+    fn with_clock(mut self, clock: fn() -> Instant) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn now(&self) -> Instant {
+        (self.clock)()
+    }
+
+    /// Start a new profile (or go back to the base conditions if `None`)
+    pub(crate) fn set_profile(&self, profile: Option<LinkConditionerProfile>) {
+        self.state.write().profile = profile.map(|profile| (self.now(), profile));
+    }
+
+    /// The conditions to apply to packets going in `direction`
+    fn conditions(&self, direction: Direction) -> LinkConditions {
+        let state = self.state.read();
+        let config = state
+            .profile
+            .as_ref()
+            .and_then(|(start, profile)| profile.config_at(self.now() - *start))
+            .unwrap_or(&state.base);
+        match direction {
+            Direction::Incoming => config.incoming.clone(),
+            Direction::Outgoing => config.outgoing.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Direction {
+    Incoming,
+    Outgoing,
 }
 
 pub(crate) type PacketLinkConditioner = LinkConditioner<(SocketAddr, Box<[u8]>)>;
 
 pub(crate) struct LinkConditioner<P: Eq> {
-    config: LinkConditionerConfig,
+    handle: LinkConditionerHandle,
+    direction: Direction,
     pub time_queue: ReadyBuffer<Instant, P>,
     last_packet: Option<P>,
+    /// True if the Gilbert-Elliott loss model is in the bad state
+    bad_state: bool,
+    /// Instant at which the link will be done sending the packets waiting for bandwidth
+    link_free_at: Option<Instant>,
 }
 
-impl<P: Eq> LinkConditioner<P> {
-    pub fn new(config: LinkConditionerConfig) -> Self {
+impl<P: Eq + Clone> LinkConditioner<P> {
+    /// Conditioner for the packets we receive
+    pub fn incoming(handle: LinkConditionerHandle) -> Self {
+        Self::new(handle, Direction::Incoming)
+    }
+
+    /// Conditioner for the packets we send
+    pub fn outgoing(handle: LinkConditionerHandle) -> Self {
+        Self::new(handle, Direction::Outgoing)
+    }
+
+    fn new(handle: LinkConditionerHandle, direction: Direction) -> Self {
         LinkConditioner {
-            config,
+            handle,
+            direction,
             time_queue: ReadyBuffer::new(),
             last_packet: None,
+            bad_state: false,
+            link_free_at: None,
         }
     }
 
-    /// Add latency/jitter/loss to a packet
-    fn condition_packet(&mut self, packet: P) {
+    /// Returns true if the packet should be dropped
+    fn is_lost(&mut self, loss: PacketLoss, rng: &mut impl Rng) -> bool {
+        let loss = match loss {
+            PacketLoss::Uniform(loss) => loss,
+            PacketLoss::GilbertElliott {
+                good_to_bad,
+                bad_to_good,
+                good_loss,
+                bad_loss,
+            } => {
+                let transition = if self.bad_state {
+                    bad_to_good
+                } else {
+                    good_to_bad
+                };
+                if rng.gen_range(0.0..1.0) < transition {
+                    self.bad_state = !self.bad_state;
+                }
+                if self.bad_state {
+                    bad_loss
+                } else {
+                    good_loss
+                }
+            }
+        };
+        loss > 0.0 && rng.gen_range(0.0..1.0) <= loss
+    }
+
+    /// Add latency/jitter/loss/duplication/reordering to a packet of `size` bytes
+    fn condition_packet(&mut self, packet: P, size: usize) {
+        let conditions = self.handle.conditions(self.direction);
         let mut rng = thread_rng();
-        if rng.gen_range(0.0..1.0) <= self.config.incoming_loss {
+        if self.is_lost(conditions.loss, &mut rng) {
             return;
         }
-        let mut latency: i32 = self.config.incoming_latency.as_millis() as i32;
         // TODO: how can i use the virtual time here?
-        let mut packet_timestamp = Instant::now();
-        if self.config.incoming_jitter > Duration::default() {
-            let jitter: i32 = self.config.incoming_jitter.as_millis() as i32;
-            latency += rng.gen_range(-jitter..jitter);
+        let now = self.handle.now();
+        let mut send_timestamp = now;
+        if let Some(bandwidth) = conditions.bandwidth {
+            let link_free_at = self.link_free_at.filter(|t| *t > now).unwrap_or(now);
+            let bytes_per_second = bandwidth.bytes_per_second.max(1) as f64;
+            let queued_bytes = ((link_free_at - now).as_secs_f64() * bytes_per_second) as usize;
+            if queued_bytes + size > bandwidth.queue_capacity {
+                // the queue is full
+                return;
+            }
+            send_timestamp = link_free_at + Duration::from_secs_f64(size as f64 / bytes_per_second);
+            self.link_free_at = Some(send_timestamp);
         }
-        if latency > 0 {
-            packet_timestamp += Duration::from_millis(latency as u64);
+        let copies =
+            if conditions.duplication > 0.0 && rng.gen_range(0.0..1.0) <= conditions.duplication {
+                2
+            } else {
+                1
+            };
+        for _ in 0..copies {
+            let mut latency: i32 = conditions.latency.as_millis() as i32;
+            if conditions.jitter > Duration::default() {
+                let jitter: i32 = conditions.jitter.as_millis() as i32;
+                latency += rng.gen_range(-jitter..jitter);
+            }
+            let mut packet_timestamp = send_timestamp;
+            if latency > 0 {
+                packet_timestamp += Duration::from_millis(latency as u64);
+            }
+            if conditions.reordering > 0.0 && rng.gen_range(0.0..1.0) <= conditions.reordering {
+                packet_timestamp += conditions.reordering_delay;
+            }
+            self.time_queue.push(packet_timestamp, packet.clone());
         }
-        self.time_queue.push(packet_timestamp, packet);
     }
 
     /// Check if a packet is ready to be returned
     fn pop_packet(&mut self) -> Option<P> {
-        self.time_queue
-            .pop_item(&Instant::now())
-            .map(|(_, packet)| packet)
+        let now = self.handle.now();
+        self.time_queue.pop_item(&now).map(|(_, packet)| packet)
     }
 }
 
@@ -87,6 +386,15 @@ impl<T: PacketReceiver> PacketReceiverWrapper<T> for LinkConditioner<(SocketAddr
     }
 }
 
+impl<T: PacketSender> PacketSenderWrapper<T> for LinkConditioner<(SocketAddr, Box<[u8]>)> {
+    fn wrap(self, sender: T) -> impl PacketSender {
+        ConditionedPacketSender {
+            packet_sender: sender,
+            conditioner: self,
+        }
+    }
+}
+
 /// A wrapper around a packet receiver that simulates network conditions
 /// by adding latency, jitter and packet loss to incoming packets.
 pub struct ConditionedPacketReceiver<T: PacketReceiver, P: Eq> {
@@ -102,9 +410,11 @@ impl<T: PacketReceiver> PacketReceiver for ConditionedPacketReceiver<T, (SocketA
             match option {
                 None => break,
                 // add conditioning (put the packets in the time queue)
-                Some((data, addr)) => self
-                    .conditioner
-                    .condition_packet((addr, data.to_vec().into_boxed_slice())),
+                Some((data, addr)) => {
+                    let size = data.len();
+                    self.conditioner
+                        .condition_packet((addr, data.to_vec().into_boxed_slice()), size)
+                }
             }
         }
         // only return a packet if it is ready to be returned
@@ -122,43 +432,222 @@ impl<T: PacketReceiver> PacketReceiver for ConditionedPacketReceiver<T, (SocketA
     }
 }
 
+/// A wrapper around a packet sender that simulates network conditions on outgoing packets.
+///
+/// Packets are kept in a queue until they are ready to be sent; they are sent on the next
+/// call to `send` or `flush`.
+pub struct ConditionedPacketSender<T: PacketSender, P: Eq> {
+    packet_sender: T,
+    conditioner: LinkConditioner<P>,
+}
+
+impl<T: PacketSender> PacketSender for ConditionedPacketSender<T, (SocketAddr, Box<[u8]>)> {
+    fn send(&mut self, payload: &[u8], address: &SocketAddr) -> Result<()> {
+        self.conditioner
+            .condition_packet((*address, payload.into()), payload.len());
+        self.flush()
+    }
+
+    /// The streams are reliable, so their packets are not conditioned
+    fn send_on_stream(
+        &mut self,
+        payload: &[u8],
+        address: &SocketAddr,
+        channel_id: ChannelId,
+    ) -> Result<()> {
+        self.packet_sender
+            .send_on_stream(payload, address, channel_id)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        while let Some((addr, data)) = self.conditioner.pop_packet() {
+            self.packet_sender.send(&data, &addr)?;
+        }
+        self.packet_sender.flush()
+    }
+
+    fn capabilities(&self, address: &SocketAddr) -> TransportCapabilities {
+        self.packet_sender.capabilities(address)
+    }
+}
+
 impl LinkConditionerConfig {
-    /// Creates a new LinkConditionerConfig
+    /// Creates a new LinkConditionerConfig that only affects incoming packets
     pub fn new(incoming_latency: Duration, incoming_jitter: Duration, incoming_loss: f32) -> Self {
         LinkConditionerConfig {
-            incoming_latency,
-            incoming_jitter,
-            incoming_loss,
+            incoming: LinkConditions::default()
+                .with_latency(incoming_latency, incoming_jitter)
+                .with_loss(PacketLoss::Uniform(incoming_loss)),
+            outgoing: LinkConditions::default(),
         }
     }
 
     /// Creates a new LinkConditioner that simulates a connection which is in a
     /// good condition
     pub fn good_condition() -> Self {
-        LinkConditionerConfig {
-            incoming_latency: Duration::from_millis(40),
-            incoming_jitter: Duration::from_millis(6),
-            incoming_loss: 0.002,
-        }
+        Self::new(Duration::from_millis(40), Duration::from_millis(6), 0.002)
     }
 
     /// Creates a new `LinkConditioner` that simulates a connection which is in an
     /// average condition
     pub fn average_condition() -> Self {
-        LinkConditionerConfig {
-            incoming_latency: Duration::from_millis(170),
-            incoming_jitter: Duration::from_millis(45),
-            incoming_loss: 0.02,
-        }
+        Self::new(Duration::from_millis(170), Duration::from_millis(45), 0.02)
     }
 
     /// Creates a new `LinkConditioner` that simulates a connection which is in an
     /// poor condition
     pub fn poor_condition() -> Self {
-        LinkConditionerConfig {
-            incoming_latency: Duration::from_millis(300),
-            incoming_jitter: Duration::from_millis(84),
-            incoming_loss: 0.04,
+        Self::new(Duration::from_millis(300), Duration::from_millis(84), 0.04)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::clock::{advance, now};
+    use std::net::{IpAddr, Ipv4Addr};
+
+    const ADDR: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+
+    /// Sender that stores the packets it sends
+    struct Sent(Arc<RwLock<Vec<Vec<u8>>>>);
+
+    impl PacketSender for Sent {
+        fn send(&mut self, payload: &[u8], _: &SocketAddr) -> Result<()> {
+            self.0.write().push(payload.to_vec());
+            Ok(())
         }
     }
+
+    fn conditioner(outgoing: LinkConditions) -> (LinkConditionerHandle, PacketLinkConditioner) {
+        let handle = LinkConditionerHandle::new(LinkConditionerConfig {
+            incoming: LinkConditions::default(),
+            outgoing,
+        })
+        .with_clock(now);
+        (handle.clone(), LinkConditioner::outgoing(handle))
+    }
+
+    fn drain(conditioner: &mut PacketLinkConditioner) -> Vec<u8> {
+        std::iter::from_fn(|| conditioner.pop_packet())
+            .map(|(_, data)| data[0])
+            .collect()
+    }
+
+    #[test]
+    fn test_outgoing_latency() {
+        let (_, conditioner) = conditioner(
+            LinkConditions::default().with_latency(Duration::from_millis(100), Duration::ZERO),
+        );
+        let sent = Arc::new(RwLock::new(Vec::new()));
+        let mut sender = PacketSenderWrapper::wrap(conditioner, Sent(sent.clone()));
+        sender.send(&[1], &ADDR).unwrap();
+        advance(Duration::from_millis(50));
+        sender.flush().unwrap();
+        assert!(sent.read().is_empty());
+        advance(Duration::from_millis(50));
+        sender.flush().unwrap();
+        assert_eq!(*sent.read(), vec![vec![1]]);
+    }
+
+    #[test]
+    fn test_duplication() {
+        let (_, mut conditioner) = conditioner(LinkConditions::default().with_duplication(1.0));
+        conditioner.condition_packet((ADDR, Box::new([1])), 1);
+        assert_eq!(drain(&mut conditioner), vec![1, 1]);
+    }
+
+    #[test]
+    fn test_reordering() {
+        let (_, mut conditioner) =
+            conditioner(LinkConditions::default().with_reordering(1.0, Duration::from_millis(10)));
+        conditioner.condition_packet((ADDR, Box::new([1])), 1);
+        assert!(drain(&mut conditioner).is_empty());
+        advance(Duration::from_millis(10));
+        assert_eq!(drain(&mut conditioner), vec![1]);
+    }
+
+    #[test]
+    fn test_bursty_loss() {
+        // the link always switches to the bad state, where all packets are lost
+        let (_, mut conditioner) = conditioner(LinkConditions::default().with_loss(
+            PacketLoss::GilbertElliott {
+                good_to_bad: 1.0,
+                bad_to_good: 0.0,
+                good_loss: 0.0,
+                bad_loss: 1.0,
+            },
+        ));
+        for i in 0..10 {
+            conditioner.condition_packet((ADDR, Box::new([i])), 1);
+        }
+        assert!(drain(&mut conditioner).is_empty());
+        assert!(conditioner.bad_state);
+    }
+
+    #[test]
+    fn test_bandwidth_limit() {
+        // 100 bytes per second, with room for 2 packets of 10 bytes in the queue
+        let (_, mut conditioner) =
+            conditioner(LinkConditions::default().with_bandwidth(BandwidthLimit {
+                bytes_per_second: 100,
+                queue_capacity: 20,
+            }));
+        for i in 0..4 {
+            conditioner.condition_packet((ADDR, vec![i; 10].into_boxed_slice()), 10);
+        }
+        // each packet takes 100ms to be sent
+        advance(Duration::from_millis(100));
+        assert_eq!(drain(&mut conditioner), vec![0]);
+        advance(Duration::from_millis(100));
+        // the last packets were dropped because the queue was full
+        assert_eq!(drain(&mut conditioner), vec![1]);
+        advance(Duration::from_millis(200));
+        assert!(drain(&mut conditioner).is_empty());
+    }
+
+    #[test]
+    fn test_profile() {
+        let (handle, mut conditioner) = conditioner(LinkConditions::default());
+        // drop all packets for 5 seconds
+        handle.set_profile(Some(LinkConditionerProfile::default().then(
+            Duration::from_secs(5),
+            LinkConditionerConfig {
+                incoming: LinkConditions::default(),
+                outgoing: LinkConditions::default().with_loss(PacketLoss::Uniform(1.0)),
+            },
+        )));
+        conditioner.condition_packet((ADDR, Box::new([1])), 1);
+        assert!(drain(&mut conditioner).is_empty());
+
+        // the profile is over, we go back to the base conditions
+        advance(Duration::from_secs(5));
+        conditioner.condition_packet((ADDR, Box::new([2])), 1);
+        assert_eq!(drain(&mut conditioner), vec![2]);
+    }
+
+    #[test]
+    fn test_profile_phases() {
+        let lossy = LinkConditionerConfig {
+            incoming: LinkConditions::default().with_loss(PacketLoss::Uniform(1.0)),
+            outgoing: LinkConditions::default(),
+        };
+        let profile = LinkConditionerProfile::default()
+            .then(Duration::from_secs(1), LinkConditionerConfig::default())
+            .then(Duration::from_secs(2), lossy.clone())
+            .repeating();
+        assert_eq!(
+            profile.config_at(Duration::from_millis(500)),
+            Some(&LinkConditionerConfig::default())
+        );
+        assert_eq!(profile.config_at(Duration::from_millis(1500)), Some(&lossy));
+        assert_eq!(
+            profile.config_at(Duration::from_millis(3500)),
+            Some(&LinkConditionerConfig::default())
+        );
+        assert_eq!(
+            LinkConditionerProfile::constant(lossy.clone()).config_at(Duration::from_secs(1000)),
+            Some(&lossy)
+        );
+    }
 }
@@ -9,6 +9,12 @@ pub(crate) mod conditioner;
 /// Middleware that compresses packets before sending them.
 pub(crate) mod compression;
 
+/// Middleware that records the packets, to train compression dictionaries.
+pub(crate) mod recorder;
+
+/// Middleware that writes the packets to a capture file, to inspect or replay them.
+pub(crate) mod capture;
+
 pub trait PacketReceiverWrapper<T: PacketReceiver> {
     fn wrap(self, receiver: T) -> impl PacketReceiver;
 }
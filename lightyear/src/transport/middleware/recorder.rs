@@ -0,0 +1,112 @@
+//! Contains the [`PacketRecorder`], which records the netcode payloads sent and received by a connection.
+//!
+//! The payloads are recorded before they get compressed and encrypted, so they can be used to train
+//! a compression dictionary:
+//! ```rust,ignore
+//! let recorder = PacketRecorder::new(10_000);
+//! let io = IoConfig::from_transport(transport).with_packet_recorder(recorder.clone());
+//! // ... run a session
+//! let dictionary = recorder.train_zstd_dictionary(16 * 1024)?;
+//! std::fs::write("packets.dict", dictionary.as_bytes())?;
+//! ```
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+#[cfg(feature = "zstd")]
+use crate::transport::error::Result;
+
+#[derive(Default)]
+struct RecorderState {
+    samples: Vec<Vec<u8>>,
+    max_samples: usize,
+}
+
+impl RecorderState {
+    fn record(&mut self, payload: &[u8]) {
+        if self.samples.len() < self.max_samples {
+            self.samples.push(payload.to_vec());
+        }
+    }
+}
+
+/// Records the netcode payloads sent and received by a connection, before they get compressed and encrypted.
+///
+/// The recorder is a handle: clone it before adding it to the
+/// [`SharedIoConfig`](crate::transport::config::SharedIoConfig) to access the samples
+/// while the io is running.
+#[derive(Clone, Default)]
+pub struct PacketRecorder(Arc<Mutex<RecorderState>>);
+
+impl Debug for PacketRecorder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PacketRecorder")
+            .field("samples", &self.len())
+            .finish()
+    }
+}
+
+impl PacketRecorder {
+    /// Create a recorder that stops recording after `max_samples` payloads
+    pub fn new(max_samples: usize) -> Self {
+        Self(Arc::new(Mutex::new(RecorderState {
+            samples: Vec::new(),
+            max_samples,
+        })))
+    }
+
+    /// Number of payloads recorded
+    pub fn len(&self) -> usize {
+        self.0.lock().samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Take the recorded payloads, leaving the recorder empty
+    pub fn take_samples(&self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.0.lock().samples)
+    }
+
+    /// Train a zstd dictionary of at most `max_size` bytes on the recorded payloads
+    #[cfg(feature = "zstd")]
+    pub fn train_zstd_dictionary(
+        &self,
+        max_size: usize,
+    ) -> Result<crate::transport::middleware::compression::ZstdDictionary> {
+        let state = self.0.lock();
+        crate::transport::middleware::compression::ZstdDictionary::train(&state.samples, max_size)
+    }
+
+    pub(crate) fn record(&self, payload: &[u8]) {
+        self.0.lock().record(payload);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::middleware::compression::{CompressionConfig, PayloadCompression};
+
+    #[test]
+    fn test_recorder() {
+        let recorder = PacketRecorder::new(2);
+        let mut compression =
+            PayloadCompression::new(&CompressionConfig::None, Some(recorder.clone())).unwrap();
+        for i in 0..3 {
+            compression.compress(&[i]).unwrap();
+        }
+        // the recorder stops after `max_samples` payloads
+        assert_eq!(recorder.len(), 2);
+        assert_eq!(recorder.take_samples(), vec![vec![0], vec![1]]);
+        assert!(recorder.is_empty());
+
+        // received payloads are recorded too
+        compression
+            .decompress(bytes::Bytes::from_static(&[3]))
+            .unwrap();
+        assert_eq!(recorder.take_samples(), vec![vec![3]]);
+    }
+}
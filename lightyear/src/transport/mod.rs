@@ -9,11 +9,19 @@ use error::Result;
 
 // required import for enum dispatch to work
 use crate::client::io::transport::ClientTransportEnum;
+use crate::protocol::channel::ChannelId;
 use crate::server::io::transport::ServerTransportEnum;
 use crate::transport::channels::Channels;
 use crate::transport::dummy::DummyIo;
 use crate::transport::local::LocalChannel;
+#[cfg(all(feature = "quic", not(target_family = "wasm")))]
+use crate::transport::quic::client::{QuicClientSocket, QuicClientSocketBuilder};
+#[cfg(all(feature = "quic", not(target_family = "wasm")))]
+use crate::transport::quic::server::{QuicServerSocket, QuicServerSocketBuilder};
+use crate::transport::replay::ReplayTransport;
 use crate::transport::udp::UdpSocket;
+#[cfg(unix)]
+use crate::transport::unix::{UnixSocketClient, UnixSocketServer};
 #[cfg(feature = "websocket")]
 use crate::transport::websocket::client::{WebSocketClientSocket, WebSocketClientSocketBuilder};
 #[cfg(all(feature = "websocket", not(target_family = "wasm")))]
@@ -36,14 +44,26 @@ pub(crate) mod local;
 /// The transport is a UDP socket
 pub(crate) mod udp;
 
+/// The transport is a Unix datagram socket, for peers running on the same machine
+#[cfg(unix)]
+pub(crate) mod unix;
+
 /// The transport is a map of channels (used for server, during testing)
 pub(crate) mod channels;
 
+/// The transport replays a packet capture
+pub(crate) mod replay;
+
 /// The transport is using WebTransport
 #[cfg_attr(docsrs, doc(cfg(feature = "webtransport")))]
 #[cfg(feature = "webtransport")]
 pub(crate) mod webtransport;
 
+/// The transport is using native QUIC
+#[cfg_attr(docsrs, doc(cfg(feature = "quic")))]
+#[cfg(all(feature = "quic", not(target_family = "wasm")))]
+pub(crate) mod quic;
+
 pub(crate) mod middleware;
 
 pub mod config;
@@ -66,6 +86,10 @@ pub(crate) const MTU: usize = 1472;
 /// the discovery will start from.
 pub(crate) const MIN_MTU: usize = 1300;
 
+/// Maximum size in bytes of a packet sent on a reliable stream of the transport.
+/// Stream packets are not limited by the MTU.
+pub(crate) const MAX_STREAM_PACKET_SIZE: usize = 64 * 1024;
+
 pub(crate) type BoxedSender = Box<dyn PacketSender + Send + Sync>;
 pub(crate) type BoxedReceiver = Box<dyn PacketReceiver + Send + Sync>;
 
@@ -80,16 +104,64 @@ pub(crate) trait Transport {
     fn split(self) -> (BoxedSender, BoxedReceiver);
 }
 
+/// What the transport can do on the path to a remote peer, on top of sending datagrams
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TransportCapabilities {
+    /// The transport can send packets on reliable ordered streams (one per channel)
+    pub reliable_streams: bool,
+    /// Maximum size of the packets that can currently be sent as datagrams, if the transport limits it
+    pub max_datagram_size: Option<usize>,
+}
+
 /// Send data to a remote address
 pub trait PacketSender: Send + Sync {
     /// Send data on the socket to the remote address
     fn send(&mut self, payload: &[u8], address: &SocketAddr) -> Result<()>;
+
+    /// Send data to the remote address on the reliable stream dedicated to the channel `channel_id`.
+    ///
+    /// Transports without streams send the data like any other packet.
+    fn send_on_stream(
+        &mut self,
+        payload: &[u8],
+        address: &SocketAddr,
+        _channel_id: ChannelId,
+    ) -> Result<()> {
+        self.send(payload, address)
+    }
+
+    /// Send any data that was buffered by the sender (for example by the link conditioner)
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Capabilities of the transport on the path to the remote address
+    fn capabilities(&self, _address: &SocketAddr) -> TransportCapabilities {
+        TransportCapabilities::default()
+    }
 }
 
 impl PacketSender for BoxedSender {
     fn send(&mut self, payload: &[u8], address: &SocketAddr) -> Result<()> {
         (**self).send(payload, address)
     }
+
+    fn send_on_stream(
+        &mut self,
+        payload: &[u8],
+        address: &SocketAddr,
+        channel_id: ChannelId,
+    ) -> Result<()> {
+        (**self).send_on_stream(payload, address, channel_id)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        (**self).flush()
+    }
+
+    fn capabilities(&self, address: &SocketAddr) -> TransportCapabilities {
+        (**self).capabilities(address)
+    }
 }
 
 /// Receive data from a remote address
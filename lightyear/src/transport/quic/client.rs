@@ -0,0 +1,247 @@
+//! QUIC client implementation.
+use std::net::SocketAddr;
+use std::sync::{Arc, OnceLock};
+
+use async_compat::Compat;
+use bevy::tasks::IoTaskPool;
+use bytes::Bytes;
+use quinn::rustls::RootCertStore;
+use quinn::{ClientConfig, Connection, Endpoint};
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TryRecvError;
+use tracing::{debug, error, info, trace};
+
+use super::{read_streams, send_packets, transport_config, CertificateDer, OutgoingPacket};
+use crate::client::io::transport::{ClientTransportBuilder, ClientTransportEnum};
+use crate::client::io::{ClientIoEvent, ClientIoEventReceiver, ClientNetworkEventSender};
+use crate::protocol::channel::ChannelId;
+use crate::transport::error::{Error, Result};
+use crate::transport::io::IoState;
+use crate::transport::{
+    BoxedReceiver, BoxedSender, PacketReceiver, PacketSender, Transport, TransportCapabilities, MTU,
+};
+
+pub(crate) struct QuicClientSocketBuilder {
+    pub(crate) client_addr: SocketAddr,
+    pub(crate) server_addr: SocketAddr,
+    pub(crate) server_name: String,
+    pub(crate) server_certificate: CertificateDer<'static>,
+}
+
+impl ClientTransportBuilder for QuicClientSocketBuilder {
+    fn connect(
+        self,
+    ) -> Result<(
+        ClientTransportEnum,
+        IoState,
+        Option<ClientIoEventReceiver>,
+        Option<ClientNetworkEventSender>,
+    )> {
+        let (to_server_sender, to_server_receiver) = mpsc::unbounded_channel::<OutgoingPacket>();
+        let (from_server_sender, from_server_receiver) = mpsc::unbounded_channel();
+        let connection_handle = Arc::new(OnceLock::new());
+        let sender = QuicClientPacketSender {
+            to_server_sender,
+            connection: connection_handle.clone(),
+        };
+        // channels used to cancel the task
+        let (close_tx, close_rx) = async_channel::bounded(1);
+        // channels used to check the status of the io task
+        let (event_tx, event_rx) = async_channel::bounded(1);
+
+        let mut roots = RootCertStore::empty();
+        roots
+            .add(self.server_certificate)
+            .map_err(std::io::Error::other)?;
+        let mut config =
+            ClientConfig::with_root_certificates(Arc::new(roots)).map_err(std::io::Error::other)?;
+        config.transport_config(transport_config());
+
+        IoTaskPool::get()
+            .spawn(Compat::new(async move {
+                let mut endpoint = match Endpoint::client(self.client_addr) {
+                    Ok(e) => e,
+                    Err(e) => {
+                        error!("Error creating quic endpoint: {:?}", e);
+                        let _ = event_tx.send(ClientIoEvent::Disconnected(e.into())).await;
+                        return;
+                    }
+                };
+                endpoint.set_default_client_config(config);
+                info!(
+                    "Connecting to server via quic at server address: {}",
+                    self.server_addr
+                );
+                let connecting = match endpoint.connect(self.server_addr, &self.server_name) {
+                    Ok(c) => c,
+                    Err(e) => {
+                        error!("Error creating quic connection: {:?}", e);
+                        let _ = event_tx
+                            .send(ClientIoEvent::Disconnected(std::io::Error::other(e).into()))
+                            .await;
+                        return;
+                    }
+                };
+
+                tokio::select! {
+                    _ = close_rx.recv() => {
+                        info!("QUIC connection closed. Reason: client requested disconnection.");
+                        let _ = event_tx.send(ClientIoEvent::Disconnected(std::io::Error::other("received close signal").into())).await;
+                    }
+                    connection = connecting => {
+                        let connection = match connection {
+                            Ok(c) => c,
+                            Err(e) => {
+                                error!("Error creating quic connection: {:?}", e);
+                                let _ = event_tx.send(ClientIoEvent::Disconnected(e.into())).await;
+                                return;
+                            }
+                        };
+                        let _ = connection_handle.set(connection.clone());
+                        // signal that the io is connected
+                        event_tx.send(ClientIoEvent::Connected).await.unwrap();
+                        info!("Connected.");
+
+                        // we spawn separate tasks to receive and send packets, so that the futures
+                        // don't get cancelled by a `tokio::select!`
+                        let from_server_stream_sender = from_server_sender.clone();
+                        let streams_handle = IoTaskPool::get().spawn(Compat::new(read_streams(
+                            connection.clone(),
+                            move |data| {
+                                let _ = from_server_stream_sender.send(data);
+                            },
+                        )));
+                        let connection_recv = connection.clone();
+                        let recv_handle = IoTaskPool::get().spawn(Compat::new(async move {
+                            loop {
+                                match connection_recv.read_datagram().await {
+                                    Ok(data) => {
+                                        trace!("receive datagram from server: {:?}", &data);
+                                        if from_server_sender.send(data).is_err() {
+                                            // the client was stopped
+                                            debug!("quic client receiver dropped, stop reading datagrams");
+                                            return;
+                                        }
+                                    }
+                                    Err(e) => {
+                                        // all the ConnectionErrors are related to the connection being closed
+                                        error!("read_datagram connection error: {:?}", e);
+                                        return;
+                                    }
+                                }
+                            }
+                        }));
+                        let send_handle = IoTaskPool::get().spawn(Compat::new(send_packets(
+                            connection.clone(),
+                            to_server_receiver,
+                        )));
+                        // Wait for a close signal from the close channel, or for the quic connection to be closed
+                        tokio::select! {
+                            reason = connection.closed() => {
+                                info!("QUIC connection closed. Reason: {reason:?}. Shutting down quic tasks.");
+                                let _ = event_tx.send(ClientIoEvent::Disconnected(Error::Quic(reason))).await;
+                            },
+                            _ = close_rx.recv() => {
+                                info!("QUIC connection closed. Reason: client requested disconnection. Shutting down quic tasks.");
+                                connection.close(0u32.into(), b"client disconnected");
+                            }
+                        }
+                        recv_handle.cancel().await;
+                        streams_handle.cancel().await;
+                        send_handle.cancel().await;
+                        debug!("QUIC tasks shut down.");
+                    }
+                }
+            }))
+            .detach();
+
+        let receiver = QuicClientPacketReceiver {
+            server_addr: self.server_addr,
+            from_server_receiver,
+            buffer: Vec::with_capacity(MTU),
+        };
+        Ok((
+            ClientTransportEnum::QuicClient(QuicClientSocket {
+                local_addr: self.client_addr,
+                sender,
+                receiver,
+            }),
+            IoState::Connecting,
+            Some(ClientIoEventReceiver(event_rx)),
+            Some(ClientNetworkEventSender(close_tx)),
+        ))
+    }
+}
+
+/// QUIC client socket
+pub struct QuicClientSocket {
+    local_addr: SocketAddr,
+    sender: QuicClientPacketSender,
+    receiver: QuicClientPacketReceiver,
+}
+
+impl Transport for QuicClientSocket {
+    fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    fn split(self) -> (BoxedSender, BoxedReceiver) {
+        (Box::new(self.sender), Box::new(self.receiver))
+    }
+}
+
+struct QuicClientPacketSender {
+    to_server_sender: mpsc::UnboundedSender<OutgoingPacket>,
+    /// The QUIC connection, once it is established
+    connection: Arc<OnceLock<Connection>>,
+}
+
+impl PacketSender for QuicClientPacketSender {
+    fn send(&mut self, payload: &[u8], _: &SocketAddr) -> Result<()> {
+        self.to_server_sender
+            .send(OutgoingPacket::Datagram(payload.into()))
+            .map_err(|e| std::io::Error::other(format!("send_datagram error: {:?}", e)).into())
+    }
+
+    fn send_on_stream(
+        &mut self,
+        payload: &[u8],
+        _: &SocketAddr,
+        channel_id: ChannelId,
+    ) -> Result<()> {
+        self.to_server_sender
+            .send(OutgoingPacket::Stream(channel_id, payload.into()))
+            .map_err(|e| std::io::Error::other(format!("send on stream error: {:?}", e)).into())
+    }
+
+    fn capabilities(&self, _: &SocketAddr) -> TransportCapabilities {
+        TransportCapabilities {
+            reliable_streams: true,
+            max_datagram_size: self
+                .connection
+                .get()
+                .and_then(|connection| connection.max_datagram_size()),
+        }
+    }
+}
+
+struct QuicClientPacketReceiver {
+    server_addr: SocketAddr,
+    from_server_receiver: mpsc::UnboundedReceiver<Bytes>,
+    /// Packets received on a stream can be bigger than the MTU
+    buffer: Vec<u8>,
+}
+
+impl PacketReceiver for QuicClientPacketReceiver {
+    fn recv(&mut self) -> Result<Option<(&mut [u8], SocketAddr)>> {
+        match self.from_server_receiver.try_recv() {
+            Ok(data) => {
+                self.buffer.clear();
+                self.buffer.extend_from_slice(data.as_ref());
+                Ok(Some((&mut self.buffer, self.server_addr)))
+            }
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(e) => Err(std::io::Error::other(format!("read_datagram error: {:?}", e)).into()),
+        }
+    }
+}
@@ -0,0 +1,271 @@
+//! Transport using native QUIC connections (based on [`quinn`])
+//!
+//! Packets are sent as unreliable QUIC datagrams, so this transport behaves like the
+//! UDP socket but benefits from QUIC's encryption, congestion control and connection migration.
+//!
+//! The packets of the reliable channels that use [`transport_stream`](crate::channel::builder::ReliableSettings::transport_stream)
+//! only contain the messages of their channel, and are sent on a unidirectional QUIC stream
+//! dedicated to that channel (each packet is prefixed by its length). QUIC retransmits them, and
+//! a lost packet only delays the packets of its own channel.
+//! The packets sent on a stream are not limited by the MTU, so messages bigger than the MTU are
+//! sent whole instead of being fragmented (up to about 64KB).
+//!
+//! The size of the lightyear packets is capped by [`quinn::Connection::max_datagram_size`], which
+//! grows with QUIC's own path MTU discovery.
+use std::sync::Arc;
+
+use async_compat::Compat;
+use bevy::tasks::IoTaskPool;
+use bevy::utils::HashMap;
+use bytes::Bytes;
+use quinn::rustls::pki_types::PrivatePkcs8KeyDer;
+pub use quinn::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use quinn::Connection;
+use tokio::sync::mpsc;
+use tracing::{error, trace};
+
+use crate::protocol::channel::ChannelId;
+use crate::transport::error::Result;
+use crate::transport::{MAX_STREAM_PACKET_SIZE, MIN_MTU};
+
+pub mod client;
+pub mod server;
+
+/// Certificate chain and private key used by the QUIC server to authenticate itself
+#[derive(Debug)]
+pub struct QuicIdentity {
+    pub certificate_chain: Vec<CertificateDer<'static>>,
+    pub private_key: PrivateKeyDer<'static>,
+}
+
+impl QuicIdentity {
+    /// Generate a self-signed certificate for the given domain names.
+    ///
+    /// The clients must add [`QuicIdentity::certificate`] to their trusted certificates.
+    pub fn self_signed(names: impl Into<Vec<String>>) -> Result<Self> {
+        let rcgen::CertifiedKey { cert, key_pair } =
+            rcgen::generate_simple_self_signed(names).map_err(std::io::Error::other)?;
+        Ok(Self {
+            certificate_chain: vec![cert.der().clone()],
+            private_key: PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key_pair.serialize_der())),
+        })
+    }
+
+    /// The end-entity certificate of the server
+    pub fn certificate(&self) -> &CertificateDer<'static> {
+        &self.certificate_chain[0]
+    }
+
+    pub fn clone_identity(&self) -> Self {
+        Self {
+            certificate_chain: self.certificate_chain.clone(),
+            private_key: self.private_key.clone_key(),
+        }
+    }
+}
+
+/// QUIC transport config shared by the client and the server
+fn transport_config() -> Arc<quinn::TransportConfig> {
+    let mut config = quinn::TransportConfig::default();
+    config.initial_mtu(MIN_MTU as u16).min_mtu(MIN_MTU as u16);
+    Arc::new(config)
+}
+
+/// Packet to send on a QUIC connection
+enum OutgoingPacket {
+    Datagram(Box<[u8]>),
+    /// Packet sent on the stream dedicated to the channel
+    Stream(ChannelId, Box<[u8]>),
+}
+
+/// Send the packets on the connection, opening a unidirectional stream for each channel that
+/// sends packets on a stream
+async fn send_packets(
+    connection: Connection,
+    mut packets: mpsc::UnboundedReceiver<OutgoingPacket>,
+) {
+    let mut streams: HashMap<ChannelId, mpsc::UnboundedSender<Box<[u8]>>> = HashMap::new();
+    while let Some(packet) = packets.recv().await {
+        match packet {
+            OutgoingPacket::Datagram(data) => {
+                trace!("send datagram: {:?}", &data);
+                connection.send_datagram(data.into()).unwrap_or_else(|e| {
+                    error!("send_datagram via quic error: {:?}", e);
+                });
+            }
+            OutgoingPacket::Stream(channel_id, data) => {
+                let stream = streams.entry(channel_id).or_insert_with(|| {
+                    let (sender, receiver) = mpsc::unbounded_channel();
+                    IoTaskPool::get()
+                        .spawn(Compat::new(write_stream(connection.clone(), receiver)))
+                        .detach();
+                    sender
+                });
+                let _ = stream.send(data);
+            }
+        }
+    }
+}
+
+/// Write the packets of a channel on a new unidirectional stream, each packet prefixed by its length
+async fn write_stream(connection: Connection, mut packets: mpsc::UnboundedReceiver<Box<[u8]>>) {
+    let mut stream = match connection.open_uni().await {
+        Ok(stream) => stream,
+        Err(e) => {
+            error!("open_uni via quic error: {:?}", e);
+            return;
+        }
+    };
+    while let Some(data) = packets.recv().await {
+        trace!("send packet on stream {:?}: {:?}", stream.id(), &data);
+        let len = (data.len() as u32).to_le_bytes();
+        if let Err(e) = stream.write_all(&len).await {
+            error!("write to quic stream error: {:?}", e);
+            return;
+        }
+        if let Err(e) = stream.write_all(&data).await {
+            error!("write to quic stream error: {:?}", e);
+            return;
+        }
+    }
+    let _ = stream.finish();
+}
+
+/// Read the packets of the streams opened by the remote peer
+async fn read_streams(connection: Connection, on_packet: impl Fn(Bytes) + Clone + Send + 'static) {
+    while let Ok(mut stream) = connection.accept_uni().await {
+        let on_packet = on_packet.clone();
+        IoTaskPool::get()
+            .spawn(Compat::new(async move {
+                let mut len = [0; 4];
+                while stream.read_exact(&mut len).await.is_ok() {
+                    let len = u32::from_le_bytes(len) as usize;
+                    if len > MAX_STREAM_PACKET_SIZE {
+                        error!("packet received on quic stream is too big: {len} bytes");
+                        return;
+                    }
+                    let mut data = vec![0; len];
+                    if let Err(e) = stream.read_exact(&mut data).await {
+                        error!("read from quic stream error: {:?}", e);
+                        return;
+                    }
+                    trace!("receive packet on stream {:?}: {:?}", stream.id(), &data);
+                    on_packet(Bytes::from(data));
+                }
+            }))
+            .detach();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::client::io::transport::ClientTransportBuilder;
+    use crate::server::io::transport::ServerTransportBuilder;
+    use crate::transport::{Transport, MTU};
+    use bevy::tasks::{IoTaskPool, TaskPoolBuilder};
+    use bevy::utils::Duration;
+
+    use super::client::*;
+    use super::server::*;
+    use super::*;
+
+    #[tokio::test]
+    async fn test_quic() {
+        IoTaskPool::get_or_init(|| TaskPoolBuilder::default().build());
+
+        let identity = QuicIdentity::self_signed(vec!["localhost".to_string()]).unwrap();
+        let server_certificate = identity.certificate().clone();
+        let server_addr = "127.0.0.1:7010".parse().unwrap();
+        let client_addr = "127.0.0.1:8010".parse().unwrap();
+
+        let (server_socket, _, _server_events, _server_close) = QuicServerSocketBuilder {
+            server_addr,
+            identity,
+        }
+        .start()
+        .unwrap();
+        let (mut server_send, mut server_recv) = server_socket.split();
+
+        let (client_socket, _, _client_events, _client_close) = QuicClientSocketBuilder {
+            client_addr,
+            server_addr,
+            server_name: "localhost".to_string(),
+            server_certificate,
+        }
+        .connect()
+        .unwrap();
+        let (mut client_send, mut client_recv) = client_socket.split();
+
+        // wait for the QUIC handshake
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let msg = b"hello world";
+
+        // client to server
+        client_send.send(msg, &server_addr).unwrap();
+
+        // sleep a little to give time to the message to arrive in the socket
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let Ok(Some((recv_msg, address))) = server_recv.recv() else {
+            panic!("server expected to receive a packet from client");
+        };
+        assert_eq!(address, client_addr);
+        assert_eq!(recv_msg, msg);
+
+        // server to client
+        server_send.send(msg, &client_addr).unwrap();
+
+        // sleep a little to give time to the message to arrive in the socket
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let Ok(Some((recv_msg, address))) = client_recv.recv() else {
+            panic!("client expected to receive a packet from server");
+        };
+        assert_eq!(address, server_addr);
+        assert_eq!(recv_msg, msg);
+
+        // the size of the QUIC datagrams is known once connected
+        let capabilities = client_send.capabilities(&server_addr);
+        assert!(capabilities.reliable_streams);
+        assert!(capabilities
+            .max_datagram_size
+            .is_some_and(|size| size <= MTU));
+        assert!(server_send
+            .capabilities(&client_addr)
+            .max_datagram_size
+            .is_some_and(|size| size <= MTU));
+
+        // packets sent on the streams of two channels
+        client_send.send_on_stream(msg, &server_addr, 0).unwrap();
+        client_send
+            .send_on_stream(b"other", &server_addr, 1)
+            .unwrap();
+        server_send.send_on_stream(msg, &client_addr, 0).unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let mut received = vec![];
+        while let Ok(Some((recv_msg, address))) = server_recv.recv() {
+            assert_eq!(address, client_addr);
+            received.push(recv_msg.to_vec());
+        }
+        received.sort();
+        assert_eq!(received, vec![b"hello world".to_vec(), b"other".to_vec()]);
+        let Ok(Some((recv_msg, address))) = client_recv.recv() else {
+            panic!("client expected to receive a packet from server on the stream");
+        };
+        assert_eq!(address, server_addr);
+        assert_eq!(recv_msg, msg);
+
+        // packets sent on a stream can be bigger than the MTU
+        let big_msg = vec![7; 10 * MTU];
+        client_send
+            .send_on_stream(&big_msg, &server_addr, 0)
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let Ok(Some((recv_msg, _))) = server_recv.recv() else {
+            panic!("server expected to receive a big packet from client on the stream");
+        };
+        assert_eq!(recv_msg, big_msg.as_slice());
+    }
+}
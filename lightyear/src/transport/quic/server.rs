@@ -0,0 +1,284 @@
+//! QUIC server implementation.
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use async_compat::Compat;
+use bevy::tasks::IoTaskPool;
+use bevy::utils::HashMap;
+use bytes::Bytes;
+use quinn::{Connection, Endpoint, ServerConfig};
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TryRecvError;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tracing::{debug, error, info, trace};
+
+use super::{read_streams, send_packets, transport_config, OutgoingPacket, QuicIdentity};
+use crate::protocol::channel::ChannelId;
+use crate::server::io::transport::{ServerTransportBuilder, ServerTransportEnum};
+use crate::server::io::{ServerIoEvent, ServerIoEventReceiver, ServerNetworkEventSender};
+use crate::transport::error::Result;
+use crate::transport::io::IoState;
+use crate::transport::{
+    BoxedReceiver, BoxedSender, PacketReceiver, PacketSender, Transport, TransportCapabilities, MTU,
+};
+
+/// Channel to send packets to the io task of a client, and the QUIC connection of the client
+type ClientSenders = Arc<Mutex<HashMap<SocketAddr, (UnboundedSender<OutgoingPacket>, Connection)>>>;
+
+pub(crate) struct QuicServerSocketBuilder {
+    pub(crate) server_addr: SocketAddr,
+    pub(crate) identity: QuicIdentity,
+}
+
+impl ServerTransportBuilder for QuicServerSocketBuilder {
+    fn start(
+        self,
+    ) -> Result<(
+        ServerTransportEnum,
+        IoState,
+        Option<ServerIoEventReceiver>,
+        Option<ServerNetworkEventSender>,
+    )> {
+        let (from_client_sender, from_client_receiver) = mpsc::unbounded_channel();
+        // channels used to cancel the task
+        let (close_tx, close_rx) = async_channel::unbounded();
+        // channels used to check the status of the io task
+        let (status_tx, status_rx) = async_channel::unbounded();
+        let to_client_senders = Arc::new(Mutex::new(HashMap::new()));
+        let addr_to_task = Arc::new(Mutex::new(HashMap::new()));
+
+        let sender = QuicServerSocketSender {
+            to_client_senders: to_client_senders.clone(),
+        };
+        let receiver = QuicServerSocketReceiver {
+            buffer: Vec::with_capacity(MTU),
+            from_client_receiver,
+        };
+
+        let mut config = ServerConfig::with_single_cert(
+            self.identity.certificate_chain,
+            self.identity.private_key,
+        )
+        .map_err(std::io::Error::other)?;
+        config.transport_config(transport_config());
+        let server_addr = self.server_addr;
+        // need to run this with Compat because it requires the tokio reactor
+        IoTaskPool::get()
+            .spawn(Compat::new(async move {
+                let endpoint = match Endpoint::server(config, server_addr) {
+                    Ok(e) => e,
+                    Err(e) => {
+                        status_tx
+                            .send(ServerIoEvent::ServerDisconnected(e.into()))
+                            .await
+                            .unwrap();
+                        return;
+                    }
+                };
+                info!("Starting server quic task");
+                status_tx.send(ServerIoEvent::ServerConnected).await.unwrap();
+                loop {
+                    tokio::select! {
+                        // event from netcode
+                        Ok(event) = close_rx.recv() => {
+                            match event {
+                                ServerIoEvent::ServerDisconnected(e) => {
+                                    debug!("Stopping quic io task. Reason: {:?}", e);
+                                    endpoint.close(0u32.into(), b"server stopped");
+                                    drop(addr_to_task);
+                                    return;
+                                }
+                                ServerIoEvent::ClientDisconnected(addr) => {
+                                    debug!("Stopping quic io task associated with address: {:?} because we received a disconnection signal from netcode", addr);
+                                    addr_to_task.lock().unwrap().remove(&addr);
+                                }
+                                _ => {}
+                            }
+                        }
+                        // new client connecting
+                        Some(incoming) = endpoint.accept() => {
+                            let Ok(connection) = incoming
+                                .await
+                                .inspect_err(|e| {
+                                    error!("failed to accept new client: {:?}", e);
+                                }) else {
+                                continue;
+                            };
+                            let client_addr = connection.remote_address();
+                            let task = IoTaskPool::get()
+                                .spawn(Compat::new(QuicServerSocket::handle_client(
+                                    connection,
+                                    from_client_sender.clone(),
+                                    to_client_senders.clone(),
+                                    status_tx.clone(),
+                                )));
+                            addr_to_task.lock().unwrap().insert(client_addr, task);
+                        }
+                    }
+                }
+            }))
+            .detach();
+
+        Ok((
+            ServerTransportEnum::QuicServer(QuicServerSocket {
+                local_addr: self.server_addr,
+                sender,
+                receiver,
+            }),
+            IoState::Connecting,
+            Some(ServerIoEventReceiver(status_rx)),
+            Some(ServerNetworkEventSender(close_tx)),
+        ))
+    }
+}
+
+/// QUIC server socket
+pub struct QuicServerSocket {
+    local_addr: SocketAddr,
+    sender: QuicServerSocketSender,
+    receiver: QuicServerSocketReceiver,
+}
+
+impl QuicServerSocket {
+    async fn handle_client(
+        connection: Connection,
+        from_client_sender: UnboundedSender<(Bytes, SocketAddr)>,
+        to_client_channels: ClientSenders,
+        status_tx: async_channel::Sender<ServerIoEvent>,
+    ) {
+        let client_addr = connection.remote_address();
+        info!(
+            "Spawning new task to create connection with client: {}",
+            client_addr
+        );
+
+        // add a new channel for this client
+        let (to_client_sender, to_client_receiver) = mpsc::unbounded_channel::<OutgoingPacket>();
+        to_client_channels
+            .lock()
+            .unwrap()
+            .insert(client_addr, (to_client_sender, connection.clone()));
+
+        // connection established, waiting for data from client
+        let from_client_stream_sender = from_client_sender.clone();
+        let _from_client_streams_handle =
+            IoTaskPool::get().spawn(Compat::new(read_streams(connection.clone(), move |data| {
+                let _ = from_client_stream_sender.send((data, client_addr));
+            })));
+        let connection_recv = connection.clone();
+        let _from_client_handle = IoTaskPool::get().spawn(Compat::new(async move {
+            loop {
+                match connection_recv.read_datagram().await {
+                    Ok(data) => {
+                        trace!("received datagram from client: {:?}", data.as_ref());
+                        if from_client_sender.send((data, client_addr)).is_err() {
+                            // the server was stopped
+                            debug!("quic server receiver dropped, stop reading datagrams");
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        error!("read_datagram connection error: {:?}", e);
+                        break;
+                    }
+                }
+            }
+        }));
+        let _to_client_handle = IoTaskPool::get().spawn(Compat::new(send_packets(
+            connection.clone(),
+            to_client_receiver,
+        )));
+
+        // await for the quic connection to be closed for any reason
+        let reason = connection.closed().await;
+        info!(
+            "Connection with {} closed. Reason: {:?}",
+            client_addr, reason
+        );
+        // notify netcode that the io task got disconnected
+        let _ = status_tx
+            .send(ServerIoEvent::ClientDisconnected(client_addr))
+            .await;
+        to_client_channels.lock().unwrap().remove(&client_addr);
+        debug!("Dropping tasks");
+        // the handles being dropped cancels the tasks
+    }
+}
+
+impl Transport for QuicServerSocket {
+    fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    fn split(self) -> (BoxedSender, BoxedReceiver) {
+        (Box::new(self.sender), Box::new(self.receiver))
+    }
+}
+
+struct QuicServerSocketSender {
+    to_client_senders: ClientSenders,
+}
+
+impl QuicServerSocketSender {
+    fn send_packet(&mut self, packet: OutgoingPacket, address: &SocketAddr) -> Result<()> {
+        if let Some((to_client_sender, _)) = self.to_client_senders.lock().unwrap().get(address) {
+            to_client_sender.send(packet).map_err(|e| {
+                std::io::Error::other(format!("unable to send message to client: {}", e)).into()
+            })
+        } else {
+            // consider that if the channel doesn't exist, it's because the connection was closed
+            Ok(())
+        }
+    }
+}
+
+impl PacketSender for QuicServerSocketSender {
+    fn send(&mut self, payload: &[u8], address: &SocketAddr) -> Result<()> {
+        self.send_packet(OutgoingPacket::Datagram(payload.into()), address)
+    }
+
+    fn send_on_stream(
+        &mut self,
+        payload: &[u8],
+        address: &SocketAddr,
+        channel_id: ChannelId,
+    ) -> Result<()> {
+        self.send_packet(OutgoingPacket::Stream(channel_id, payload.into()), address)
+    }
+
+    fn capabilities(&self, address: &SocketAddr) -> TransportCapabilities {
+        TransportCapabilities {
+            reliable_streams: true,
+            max_datagram_size: self
+                .to_client_senders
+                .lock()
+                .unwrap()
+                .get(address)
+                .and_then(|(_, connection)| connection.max_datagram_size()),
+        }
+    }
+}
+
+struct QuicServerSocketReceiver {
+    /// Packets received on a stream can be bigger than the MTU
+    buffer: Vec<u8>,
+    from_client_receiver: UnboundedReceiver<(Bytes, SocketAddr)>,
+}
+
+impl PacketReceiver for QuicServerSocketReceiver {
+    fn recv(&mut self) -> Result<Option<(&mut [u8], SocketAddr)>> {
+        match self.from_client_receiver.try_recv() {
+            Ok((data, addr)) => {
+                self.buffer.clear();
+                self.buffer.extend_from_slice(data.as_ref());
+                Ok(Some((&mut self.buffer, addr)))
+            }
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(e) => Err(std::io::Error::other(format!(
+                "unable to receive message from client: {}",
+                e
+            ))
+            .into()),
+        }
+    }
+}
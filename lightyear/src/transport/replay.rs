@@ -0,0 +1,269 @@
+//! Transport that replays the packets received in a [`Capture`], at their original timing.
+//!
+//! The packets are replayed from the addresses they were recorded from, so a server capture
+//! yields the packets of each client under that client's address.
+//! The packets sent through the transport are dropped.
+use std::net::SocketAddr;
+
+use cfg_if::cfg_if;
+
+use crate::client::io::transport::{ClientTransportBuilder, ClientTransportEnum};
+use crate::client::io::{ClientIoEventReceiver, ClientNetworkEventSender};
+use crate::server::io::transport::{ServerTransportBuilder, ServerTransportEnum};
+use crate::server::io::{ServerIoEventReceiver, ServerNetworkEventSender};
+use crate::transport::error::Result;
+use crate::transport::io::IoState;
+use crate::transport::middleware::capture::{Capture, CaptureRecordKind};
+use crate::transport::{
+    BoxedReceiver, BoxedSender, PacketReceiver, PacketSender, Transport, LOCAL_SOCKET,
+};
+
+cfg_if! {
+    if #[cfg(test)] {
+        use mock_instant::global::Instant;
+    } else {
+        use bevy::utils::Instant;
+    }
+}
+
+pub(crate) struct ReplayBuilder {
+    pub(crate) capture: Capture,
+}
+
+impl ClientTransportBuilder for ReplayBuilder {
+    fn connect(
+        self,
+    ) -> Result<(
+        ClientTransportEnum,
+        IoState,
+        Option<ClientIoEventReceiver>,
+        Option<ClientNetworkEventSender>,
+    )> {
+        Ok((
+            ClientTransportEnum::Replay(ReplayTransport::new(self.capture)),
+            IoState::Connected,
+            None,
+            None,
+        ))
+    }
+}
+
+impl ServerTransportBuilder for ReplayBuilder {
+    fn start(
+        self,
+    ) -> Result<(
+        ServerTransportEnum,
+        IoState,
+        Option<ServerIoEventReceiver>,
+        Option<ServerNetworkEventSender>,
+    )> {
+        Ok((
+            ServerTransportEnum::Replay(ReplayTransport::new(self.capture)),
+            IoState::Connected,
+            None,
+            None,
+        ))
+    }
+}
+
+pub struct ReplayTransport {
+    receiver: ReplayReceiver,
+}
+
+impl ReplayTransport {
+    fn new(capture: Capture) -> Self {
+        Self {
+            receiver: ReplayReceiver {
+                capture,
+                next: 0,
+                start: Instant::now(),
+                clock: Instant::now,
+                buffer: vec![],
+            },
+        }
+    }
+
+    /// Use a different source for the current time
+    #[cfg(test)]
+    fn with_clock(mut self, clock: fn() -> Instant) -> Self {
+        self.receiver.clock = clock;
+        self.receiver.start = clock();
+        self
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn local_addr(&self) -> SocketAddr {
+        LOCAL_SOCKET
+    }
+
+    fn split(self) -> (BoxedSender, BoxedReceiver) {
+        (Box::new(ReplaySender), Box::new(self.receiver))
+    }
+}
+
+struct ReplaySender;
+
+impl PacketSender for ReplaySender {
+    fn send(&mut self, _: &[u8], _: &SocketAddr) -> Result<()> {
+        Ok(())
+    }
+}
+
+struct ReplayReceiver {
+    capture: Capture,
+    /// Index of the next record to replay
+    next: usize,
+    /// Time at which the replay started, which corresponds to the first record of the capture
+    start: Instant,
+    /// Source of the current time, used to replay the packets at their original timing
+    clock: fn() -> Instant,
+    buffer: Vec<u8>,
+}
+
+impl PacketReceiver for ReplayReceiver {
+    fn recv(&mut self) -> Result<Option<(&mut [u8], SocketAddr)>> {
+        let elapsed = (self.clock)() - self.start;
+        let records = self.capture.records();
+        while let Some(record) = records.get(self.next) {
+            if record.timestamp > elapsed {
+                return Ok(None);
+            }
+            self.next += 1;
+            if record.kind == CaptureRecordKind::Received {
+                self.buffer.clone_from(&record.data);
+                return Ok(Some((self.buffer.as_mut_slice(), record.address)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::io::transport::ClientTransportEnum;
+    use crate::tests::clock::{advance, now};
+    use crate::transport::middleware::capture::PacketCapture;
+    use crate::transport::middleware::PacketReceiverWrapper;
+    use bevy::utils::Duration;
+    use std::io::Write;
+    use std::sync::Arc;
+
+    use parking_lot::Mutex;
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Receiver returning the packets pushed in a queue, with their source address
+    struct Queue(Vec<(Vec<u8>, SocketAddr)>, Vec<u8>);
+
+    impl PacketReceiver for Queue {
+        fn recv(&mut self) -> Result<Option<(&mut [u8], SocketAddr)>> {
+            if self.0.is_empty() {
+                return Ok(None);
+            }
+            let (data, address) = self.0.remove(0);
+            self.1 = data;
+            Ok(Some((self.1.as_mut_slice(), address)))
+        }
+    }
+
+    #[test]
+    fn test_replay() {
+        let buffer = SharedBuffer::default();
+        let capture = PacketCapture::new(buffer.clone()).unwrap().with_clock(now);
+        let mut receiver = PacketReceiverWrapper::wrap(
+            capture.clone(),
+            Queue(
+                vec![(vec![1], LOCAL_SOCKET), (vec![2], LOCAL_SOCKET)],
+                vec![],
+            ),
+        );
+        receiver.recv().unwrap().unwrap();
+        advance(Duration::from_millis(100));
+        receiver.recv().unwrap().unwrap();
+        capture.flush().unwrap();
+
+        let capture = Capture::read_from(buffer.0.lock().as_slice()).unwrap();
+        let (transport, _, _, _) = ReplayBuilder {
+            capture: capture.clone(),
+        }
+        .connect()
+        .unwrap();
+        let ClientTransportEnum::Replay(transport) = transport else {
+            panic!("expected a replay transport");
+        };
+        let (_, mut receiver) = transport.with_clock(now).split();
+
+        // the first packet is replayed immediately, the second one at its original timing
+        let (data, _) = receiver.recv().unwrap().unwrap();
+        assert_eq!(data, &[1]);
+        assert!(receiver.recv().unwrap().is_none());
+        advance(Duration::from_millis(99));
+        assert!(receiver.recv().unwrap().is_none());
+        advance(Duration::from_millis(1));
+        let (data, _) = receiver.recv().unwrap().unwrap();
+        assert_eq!(data, &[2]);
+        assert!(receiver.recv().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_server_replay() {
+        let client_1: SocketAddr = "127.0.0.1:1001".parse().unwrap();
+        let client_2: SocketAddr = "127.0.0.1:1002".parse().unwrap();
+        let buffer = SharedBuffer::default();
+        let capture = PacketCapture::new(buffer.clone()).unwrap().with_clock(now);
+        let mut receiver = PacketReceiverWrapper::wrap(
+            capture.clone(),
+            Queue(
+                vec![
+                    (vec![1], client_1),
+                    (vec![2], client_2),
+                    (vec![3], client_1),
+                ],
+                vec![],
+            ),
+        );
+        while receiver.recv().unwrap().is_some() {}
+        capture.flush().unwrap();
+
+        let capture = Capture::read_from(buffer.0.lock().as_slice()).unwrap();
+        let (transport, state, _, _) = ServerTransportBuilder::start(ReplayBuilder {
+            capture: capture.clone(),
+        })
+        .unwrap();
+        assert_eq!(state, IoState::Connected);
+        let ServerTransportEnum::Replay(transport) = transport else {
+            panic!("expected a replay transport");
+        };
+        let (mut sender, mut receiver) = transport.with_clock(now).split();
+        // the packets sent to the clients are dropped
+        sender.send(&[0], &client_1).unwrap();
+
+        // the packets are replayed from the address of the client that sent them
+        advance(capture.records().last().unwrap().timestamp);
+        let mut replayed = vec![];
+        while let Some((data, address)) = receiver.recv().unwrap() {
+            replayed.push((data.to_vec(), address));
+        }
+        assert_eq!(
+            replayed,
+            vec![
+                (vec![1], client_1),
+                (vec![2], client_2),
+                (vec![3], client_1)
+            ]
+        );
+    }
+}
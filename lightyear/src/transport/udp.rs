@@ -138,7 +138,9 @@ mod tests {
     use crate::server::io::transport::ServerTransportBuilder;
     use bevy::utils::Duration;
 
-    use crate::transport::middleware::conditioner::{LinkConditioner, LinkConditionerConfig};
+    use crate::transport::middleware::conditioner::{
+        LinkConditioner, LinkConditionerConfig, LinkConditionerHandle,
+    };
     use crate::transport::middleware::PacketReceiverWrapper;
     use crate::transport::udp::UdpSocketBuilder;
     use crate::transport::{PacketReceiver, PacketSender, Transport};
@@ -191,12 +193,13 @@ mod tests {
         let server_addr = server_socket.local_addr();
         let (_, server_receiver) = server_socket.split();
 
-        let mut conditioned_server_receiver = LinkConditioner::new(LinkConditionerConfig {
-            incoming_latency: Duration::from_millis(100),
-            incoming_jitter: Duration::from_millis(0),
-            incoming_loss: 0.0,
-        })
-        .wrap(server_receiver);
+        let conditioner = LinkConditionerHandle::new(LinkConditionerConfig::new(
+            Duration::from_millis(100),
+            Duration::from_millis(0),
+            0.0,
+        ));
+        let mut conditioned_server_receiver =
+            LinkConditioner::incoming(conditioner).wrap(server_receiver);
 
         let msg = b"hello world";
         client_sender.send(msg, &server_addr).unwrap();
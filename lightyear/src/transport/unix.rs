@@ -0,0 +1,449 @@
+//! The transport is a Unix datagram socket, to connect peers that run on the same machine.
+//!
+//! Unix sockets are addressed by a path instead of a [`SocketAddr`], so the server assigns a virtual
+//! [`SocketAddr`] to the path of each client that sends it a packet; the rest of lightyear (netcode,
+//! the link conditioner, etc.) only sees these virtual addresses.
+//!
+//! The client sends all its packets to the path of the server, whatever the server address of the
+//! connect token is.
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use bevy::utils::HashMap;
+use tracing::debug;
+
+use crate::client::io::transport::{ClientTransportBuilder, ClientTransportEnum};
+use crate::client::io::{ClientIoEventReceiver, ClientNetworkEventSender};
+use crate::server::io::transport::{ServerTransportBuilder, ServerTransportEnum};
+use crate::server::io::{ServerIoEventReceiver, ServerNetworkEventSender};
+use crate::transport::io::IoState;
+use crate::transport::{
+    BoxedReceiver, BoxedSender, PacketReceiver, PacketSender, Transport, LOCAL_SOCKET, MTU,
+};
+
+use super::error::Result;
+
+/// A Unix datagram socket bound to a path. The path is removed when the socket is dropped.
+#[derive(Debug)]
+struct BoundSocket {
+    socket: UnixDatagram,
+    path: PathBuf,
+}
+
+impl BoundSocket {
+    fn bind(path: &Path) -> Result<Self> {
+        // remove the socket file that could have been left by a previous process
+        match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+        let socket = UnixDatagram::bind(path)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+impl Drop for BoundSocket {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Receive a datagram from the socket, and return the path of the sender
+fn recv_from<'a>(
+    socket: &UnixDatagram,
+    buffer: &'a mut [u8],
+) -> Result<Option<(&'a mut [u8], PathBuf)>> {
+    loop {
+        match socket.recv_from(buffer) {
+            Ok((recv_len, address)) => {
+                let Some(path) = address.as_pathname() else {
+                    debug!("ignoring a packet from an unnamed unix socket");
+                    continue;
+                };
+                return Ok(Some((&mut buffer[..recv_len], path.to_path_buf())));
+            }
+            // Nothing to receive on the socket
+            Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+pub(crate) struct UnixSocketClientBuilder {
+    pub(crate) client_path: PathBuf,
+    pub(crate) server_path: PathBuf,
+}
+
+impl ClientTransportBuilder for UnixSocketClientBuilder {
+    fn connect(
+        self,
+    ) -> Result<(
+        ClientTransportEnum,
+        IoState,
+        Option<ClientIoEventReceiver>,
+        Option<ClientNetworkEventSender>,
+    )> {
+        let socket = Arc::new(BoundSocket::bind(&self.client_path)?);
+        let server_addr = Arc::new(Mutex::new(LOCAL_SOCKET));
+        let transport = UnixSocketClient {
+            sender: UnixSocketClientSender {
+                socket: socket.clone(),
+                server_path: self.server_path,
+                server_addr: server_addr.clone(),
+            },
+            receiver: UnixSocketClientReceiver {
+                socket,
+                server_addr,
+                buffer: [0; MTU],
+            },
+        };
+        Ok((
+            ClientTransportEnum::UnixSocket(transport),
+            IoState::Connected,
+            None,
+            None,
+        ))
+    }
+}
+
+/// Client Unix datagram socket
+pub struct UnixSocketClient {
+    sender: UnixSocketClientSender,
+    receiver: UnixSocketClientReceiver,
+}
+
+impl Transport for UnixSocketClient {
+    fn local_addr(&self) -> SocketAddr {
+        LOCAL_SOCKET
+    }
+
+    fn split(self) -> (BoxedSender, BoxedReceiver) {
+        (Box::new(self.sender), Box::new(self.receiver))
+    }
+}
+
+struct UnixSocketClientSender {
+    socket: Arc<BoundSocket>,
+    server_path: PathBuf,
+    /// Address of the server used by netcode, which is returned as the origin of the received packets
+    server_addr: Arc<Mutex<SocketAddr>>,
+}
+
+impl PacketSender for UnixSocketClientSender {
+    fn send(&mut self, payload: &[u8], address: &SocketAddr) -> Result<()> {
+        *self.server_addr.lock().unwrap() = *address;
+        self.socket.socket.send_to(payload, &self.server_path)?;
+        Ok(())
+    }
+}
+
+struct UnixSocketClientReceiver {
+    socket: Arc<BoundSocket>,
+    server_addr: Arc<Mutex<SocketAddr>>,
+    buffer: [u8; MTU],
+}
+
+impl PacketReceiver for UnixSocketClientReceiver {
+    fn recv(&mut self) -> Result<Option<(&mut [u8], SocketAddr)>> {
+        let server_addr = *self.server_addr.lock().unwrap();
+        Ok(recv_from(&self.socket.socket, &mut self.buffer)?.map(|(data, _)| (data, server_addr)))
+    }
+}
+
+pub(crate) struct UnixSocketServerBuilder {
+    pub(crate) server_path: PathBuf,
+}
+
+impl ServerTransportBuilder for UnixSocketServerBuilder {
+    fn start(
+        self,
+    ) -> Result<(
+        ServerTransportEnum,
+        IoState,
+        Option<ServerIoEventReceiver>,
+        Option<ServerNetworkEventSender>,
+    )> {
+        let socket = Arc::new(BoundSocket::bind(&self.server_path)?);
+        let peers = Arc::new(Mutex::new(UnixPeers::default()));
+        let transport = UnixSocketServer {
+            sender: UnixSocketServerSender {
+                socket: socket.clone(),
+                peers: peers.clone(),
+            },
+            receiver: UnixSocketServerReceiver {
+                socket,
+                peers,
+                buffer: [0; MTU],
+            },
+        };
+        Ok((
+            ServerTransportEnum::UnixSocket(transport),
+            IoState::Connected,
+            None,
+            None,
+        ))
+    }
+}
+
+/// Virtual addresses of the clients that sent packets to the server
+#[derive(Debug, Default)]
+struct UnixPeers {
+    addresses: HashMap<PathBuf, SocketAddr>,
+    paths: HashMap<SocketAddr, PathBuf>,
+    /// Id used for the virtual address of the next client
+    next_id: u64,
+}
+
+impl UnixPeers {
+    /// Get the virtual address of a client, or assign a new one
+    fn address(&mut self, path: PathBuf) -> SocketAddr {
+        if let Some(address) = self.addresses.get(&path) {
+            return *address;
+        }
+        // use addresses from the unique local IPv6 range, which cannot collide with real peers
+        let id = self.next_id;
+        self.next_id += 1;
+        let ip = Ipv6Addr::from(0xfd00_0000_0000_0000_0000_0000_0000_0000u128 | id as u128);
+        let address = SocketAddr::new(IpAddr::V6(ip), 0);
+        debug!(?path, ?address, "new unix socket client");
+        self.addresses.insert(path.clone(), address);
+        self.paths.insert(address, path);
+        address
+    }
+
+    /// Forget a client whose socket doesn't exist anymore
+    fn remove(&mut self, address: &SocketAddr) {
+        if let Some(path) = self.paths.remove(address) {
+            debug!(?path, ?address, "unix socket client is gone");
+            self.addresses.remove(&path);
+        }
+    }
+}
+
+/// Server Unix datagram socket
+pub struct UnixSocketServer {
+    sender: UnixSocketServerSender,
+    receiver: UnixSocketServerReceiver,
+}
+
+impl Transport for UnixSocketServer {
+    fn local_addr(&self) -> SocketAddr {
+        LOCAL_SOCKET
+    }
+
+    fn split(self) -> (BoxedSender, BoxedReceiver) {
+        (Box::new(self.sender), Box::new(self.receiver))
+    }
+}
+
+struct UnixSocketServerSender {
+    socket: Arc<BoundSocket>,
+    peers: Arc<Mutex<UnixPeers>>,
+}
+
+impl PacketSender for UnixSocketServerSender {
+    fn send(&mut self, payload: &[u8], address: &SocketAddr) -> Result<()> {
+        let mut peers = self.peers.lock().unwrap();
+        let Some(path) = peers.paths.get(address) else {
+            // consider that the client is gone, so that the other clients still get their packets
+            debug!("no unix socket client with address {address}");
+            return Ok(());
+        };
+        match self.socket.socket.send_to(payload, path) {
+            Ok(_) => Ok(()),
+            // the socket of the client was closed: a client reusing the path will get a new address
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::NotFound | std::io::ErrorKind::ConnectionRefused
+                ) =>
+            {
+                debug!("the unix socket of the client {address} is closed: {e:?}");
+                peers.remove(address);
+                Ok(())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+struct UnixSocketServerReceiver {
+    socket: Arc<BoundSocket>,
+    peers: Arc<Mutex<UnixPeers>>,
+    buffer: [u8; MTU],
+}
+
+impl PacketReceiver for UnixSocketServerReceiver {
+    fn recv(&mut self) -> Result<Option<(&mut [u8], SocketAddr)>> {
+        let Some((data, path)) = recv_from(&self.socket.socket, &mut self.buffer)? else {
+            return Ok(None);
+        };
+        let address = self.peers.lock().unwrap().address(path);
+        Ok(Some((data, address)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::io::config::ClientTransport;
+    use crate::prelude::client::{ClientConfig, NetConfig, NetworkingState};
+    use crate::prelude::server::{self, ServerConfig, ServerTransport};
+    use crate::prelude::{LinkConditionerConfig, SharedConfig, TickConfig};
+    use crate::tests::protocol::{Channel1, StringMessage};
+    use crate::tests::stepper::BevyStepper;
+    use bevy::prelude::{default, Events, State};
+    use bevy::utils::Duration;
+
+    /// Directory containing the socket files of a test
+    fn socket_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lightyear-{test}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_unix_socket() {
+        let dir = socket_dir("unix");
+        let server_path = dir.join("server.sock");
+        let client_path = dir.join("client.sock");
+
+        let (server_socket, _, _, _) = UnixSocketServerBuilder {
+            server_path: server_path.clone(),
+        }
+        .start()
+        .expect("could not start the server socket");
+        let (mut server_sender, mut server_receiver) = server_socket.split();
+        let (client_socket, _, _, _) = UnixSocketClientBuilder {
+            client_path: client_path.clone(),
+            server_path: server_path.clone(),
+        }
+        .connect()
+        .expect("could not start the client socket");
+        let (mut client_sender, mut client_receiver) = client_socket.split();
+
+        // the client sends to the server, whatever the address
+        let server_addr = SocketAddr::from(([127, 0, 0, 1], 5000));
+        client_sender.send(b"hello", &server_addr).unwrap();
+        let Some((recv_msg, client_addr)) = server_receiver.recv().unwrap() else {
+            panic!("expected to receive a packet");
+        };
+        assert_eq!(recv_msg, b"hello");
+
+        // the server replies to the virtual address of the client
+        server_sender.send(b"world", &client_addr).unwrap();
+        let Some((recv_msg, address)) = client_receiver.recv().unwrap() else {
+            panic!("expected to receive a packet");
+        };
+        assert_eq!(recv_msg, b"world");
+        assert_eq!(address, server_addr);
+        assert!(client_receiver.recv().unwrap().is_none());
+
+        // the socket files are removed when the sockets are dropped
+        drop((client_sender, client_receiver));
+        assert!(!client_path.exists());
+
+        // the server forgets the clients whose socket is closed, without failing the send so that
+        // the packets to the other clients are still sent
+        server_sender.send(b"world", &client_addr).unwrap();
+        server_sender.send(b"world", &client_addr).unwrap();
+        let (client_socket, _, _, _) = UnixSocketClientBuilder {
+            client_path: client_path.clone(),
+            server_path: server_path.clone(),
+        }
+        .connect()
+        .expect("could not start the client socket");
+        let (mut client_sender, _client_receiver) = client_socket.split();
+        client_sender.send(b"hello", &server_addr).unwrap();
+        let Some((_, new_client_addr)) = server_receiver.recv().unwrap() else {
+            panic!("expected to receive a packet");
+        };
+        assert_ne!(new_client_addr, client_addr);
+        drop((client_sender, _client_receiver));
+
+        drop((server_sender, server_receiver));
+        assert!(!server_path.exists());
+        let _ = std::fs::remove_dir(&dir);
+    }
+
+    /// The client and the server connect through netcode over unix sockets, with a link conditioner
+    #[test]
+    fn test_unix_socket_connection() {
+        let dir = socket_dir("unix-connection");
+        let server_path = dir.join("server.sock");
+        let client_path = dir.join("client.sock");
+        let conditioner = LinkConditionerConfig::new(Duration::from_millis(20), default(), 0.0);
+
+        let frame_duration = Duration::from_millis(10);
+        let shared_config = SharedConfig {
+            tick: TickConfig::new(frame_duration),
+            ..default()
+        };
+        let mut stepper = BevyStepper::new(shared_config, ClientConfig::default(), frame_duration);
+        // the io is only built when connecting, so we can still update the configs
+        if let NetConfig::Netcode { io, .. } = &mut stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<ClientConfig>()
+            .net
+        {
+            io.transport = ClientTransport::UnixSocket {
+                client_path: client_path.clone(),
+                server_path: server_path.clone(),
+            };
+            io.conditioner = Some(conditioner.clone());
+        }
+        for net in &mut stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ServerConfig>()
+            .net
+        {
+            #[allow(irrefutable_let_patterns)]
+            if let server::NetConfig::Netcode { io, .. } = net {
+                io.transport = ServerTransport::UnixSocket(server_path.clone());
+                io.conditioner = Some(conditioner.clone());
+            }
+        }
+        stepper.init();
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .resource::<State<NetworkingState>>()
+                .get(),
+            &NetworkingState::Connected
+        );
+
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<crate::prelude::client::ConnectionManager>()
+            .send_message::<Channel1, _>(&mut StringMessage("a".to_string()))
+            .unwrap();
+        let mut received = vec![];
+        for _ in 0..10 {
+            stepper.frame_step();
+            received.extend(
+                stepper
+                    .server_app
+                    .world_mut()
+                    .resource_mut::<Events<server::MessageEvent<StringMessage>>>()
+                    .drain()
+                    .map(|event| event.message().0.clone()),
+            );
+        }
+        assert_eq!(received, vec!["a".to_string()]);
+
+        drop(stepper);
+        assert!(!client_path.exists());
+        assert!(!server_path.exists());
+        let _ = std::fs::remove_dir(&dir);
+    }
+}
@@ -0,0 +1,234 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Index, LitStr};
+
+use super::shared::{get_struct_type, StructType};
+
+/// Maximum number of diffed fields, so that the changed-fields bitmask fits in a u64
+const MAX_FIELDS: usize = 64;
+
+/// How a field participates in the diff
+enum FieldMode {
+    /// The field implements `Diffable`; only its own delta is sent
+    Diff,
+    /// The full value of the field is sent whenever it changes
+    Replace,
+    /// The field is never sent
+    Skip,
+}
+
+fn field_mode(field: &syn::Field) -> FieldMode {
+    let mut mode = FieldMode::Diff;
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("diff"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip") {
+                mode = FieldMode::Skip;
+                Ok(())
+            } else if meta.path.is_ident("replace") {
+                mode = FieldMode::Replace;
+                Ok(())
+            } else {
+                Err(meta.error("expected `skip` or `replace`"))
+            }
+        })
+        .unwrap_or_else(|e| panic!("Invalid #[diff] attribute: {e}"));
+    }
+    mode
+}
+
+pub fn diffable_impl(
+    input: proc_macro::TokenStream,
+    shared_crate_name: TokenStream,
+) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    if let StructType::UnitStruct = get_struct_type(&input) {
+        panic!("Cannot derive Diffable on a Unit struct");
+    }
+    if !input.generics.params.is_empty() {
+        panic!("Cannot derive Diffable on a generic struct");
+    }
+    let Data::Struct(data) = &input.data else {
+        unreachable!()
+    };
+
+    // Names
+    let struct_name = &input.ident;
+    let vis = &input.vis;
+    let delta_name = format_ident!("{}Delta", struct_name);
+    let expecting = LitStr::new(&format!("a delta for {struct_name}"), Span::call_site());
+    let diffable = quote! { #shared_crate_name::shared::replication::delta::Diffable };
+    let serde = quote! { #shared_crate_name::_internal::serde };
+
+    let mut delta_fields = vec![];
+    let mut base_fields = vec![];
+    let mut diff_fields = vec![];
+    let mut apply_fields = vec![];
+    let mut deserialize_fields = vec![];
+    let mut delta_idents = vec![];
+    for (i, field) in data.fields.iter().enumerate() {
+        let ty = &field.ty;
+        // the member used to access the field on the original struct
+        let member = match &field.ident {
+            Some(ident) => quote! { #ident },
+            None => {
+                let index = Index::from(i);
+                quote! { #index }
+            }
+        };
+        let mode = field_mode(field);
+        let base_value = match mode {
+            FieldMode::Diff => quote! { <#ty as #diffable>::base_value() },
+            _ => quote! { ::core::default::Default::default() },
+        };
+        base_fields.push(quote! { #member: #base_value });
+        if let FieldMode::Skip = mode {
+            continue;
+        }
+
+        let bit = delta_idents.len();
+        if bit >= MAX_FIELDS {
+            panic!(
+                "Diffable can only be derived on structs with at most {MAX_FIELDS} diffed fields"
+            );
+        }
+        let delta_ident = match &field.ident {
+            Some(ident) => ident.clone(),
+            None => format_ident!("field_{}", i),
+        };
+        let (delta_ty, diff, apply) = match mode {
+            FieldMode::Diff => (
+                quote! { <#ty as #diffable>::Delta },
+                quote! { #diffable::diff(&self.#member, &new.#member) },
+                quote! { #diffable::apply_diff(&mut self.#member, value) },
+            ),
+            _ => (
+                quote! { #ty },
+                quote! { ::core::clone::Clone::clone(&new.#member) },
+                quote! { self.#member = ::core::clone::Clone::clone(value) },
+            ),
+        };
+        delta_fields.push(quote! { pub #delta_ident: ::core::option::Option<#delta_ty> });
+        diff_fields.push(quote! {
+            #delta_ident: (self.#member != new.#member).then(|| #diff)
+        });
+        apply_fields.push(quote! {
+            if let ::core::option::Option::Some(value) = &delta.#delta_ident {
+                #apply;
+            }
+        });
+        deserialize_fields.push(quote! {
+            let #delta_ident = if changed & (1 << #bit) != 0 {
+                num_read += 1;
+                ::core::option::Option::Some(
+                    seq.next_element()?
+                        .ok_or_else(|| #serde::de::Error::invalid_length(num_read, &self))?,
+                )
+            } else {
+                ::core::option::Option::None
+            };
+        });
+        delta_idents.push(delta_ident);
+    }
+    let num_fields = delta_idents.len();
+
+    // Serialize the delta as a tuple `(changed, value_0, value_1, ...)` where `changed` is the bitmask
+    // of the fields that are present; only the present values are written
+    let serialize_present = delta_idents.iter().map(|ident| {
+        quote! {
+            if let ::core::option::Option::Some(value) = &self.#ident {
+                #serde::ser::SerializeTuple::serialize_element(&mut tuple, value)?;
+            }
+        }
+    });
+    let count_present = delta_idents.iter().enumerate().map(|(bit, ident)| {
+        quote! {
+            if self.#ident.is_some() {
+                changed |= 1 << #bit;
+                len += 1;
+            }
+        }
+    });
+    let deserialize_fields = deserialize_fields.into_iter();
+
+    let doc = LitStr::new(
+        &format!(
+            "Delta between two states of [`{struct_name}`]: only the fields that changed are present"
+        ),
+        Span::call_site(),
+    );
+    let gen = quote! {
+        #[doc = #doc]
+        #[derive(Clone, Debug, PartialEq)]
+        #vis struct #delta_name {
+            #(#delta_fields,)*
+        }
+
+        impl #serde::Serialize for #delta_name {
+            #[allow(unused_mut)]
+            fn serialize<S: #serde::Serializer>(&self, serializer: S) -> ::core::result::Result<S::Ok, S::Error> {
+                let mut changed: u64 = 0;
+                let mut len = 1;
+                #(#count_present)*
+                let mut tuple = #serde::Serializer::serialize_tuple(serializer, len)?;
+                #serde::ser::SerializeTuple::serialize_element(&mut tuple, &changed)?;
+                #(#serialize_present)*
+                #serde::ser::SerializeTuple::end(tuple)
+            }
+        }
+
+        impl<'de> #serde::Deserialize<'de> for #delta_name {
+            fn deserialize<D: #serde::Deserializer<'de>>(deserializer: D) -> ::core::result::Result<Self, D::Error> {
+                struct DeltaVisitor;
+
+                impl<'de> #serde::de::Visitor<'de> for DeltaVisitor {
+                    type Value = #delta_name;
+
+                    fn expecting(&self, formatter: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                        formatter.write_str(#expecting)
+                    }
+
+                    #[allow(unused)]
+                    fn visit_seq<A: #serde::de::SeqAccess<'de>>(self, mut seq: A) -> ::core::result::Result<Self::Value, A::Error> {
+                        let changed: u64 = seq
+                            .next_element()?
+                            .ok_or_else(|| #serde::de::Error::invalid_length(0, &self))?;
+                        let mut num_read = 0;
+                        #(#deserialize_fields)*
+                        ::core::result::Result::Ok(#delta_name {
+                            #(#delta_idents,)*
+                        })
+                    }
+                }
+
+                #serde::Deserializer::deserialize_tuple(deserializer, 1 + #num_fields, DeltaVisitor)
+            }
+        }
+
+        impl #diffable for #struct_name {
+            type Delta = #delta_name;
+
+            fn base_value() -> Self {
+                Self {
+                    #(#base_fields,)*
+                }
+            }
+
+            fn diff(&self, new: &Self) -> Self::Delta {
+                #delta_name {
+                    #(#diff_fields,)*
+                }
+            }
+
+            fn apply_diff(&mut self, delta: &Self::Delta) {
+                #(#apply_fields)*
+            }
+        }
+    };
+
+    proc_macro::TokenStream::from(gen)
+}
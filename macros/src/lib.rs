@@ -9,8 +9,10 @@ use quote::{format_ident, quote};
 use syn::{parse_macro_input, ItemEnum};
 
 use channel::channel_impl;
+use diffable::diffable_impl;
 
 mod channel;
+mod diffable;
 mod shared;
 
 // Channel
@@ -27,3 +29,23 @@ pub fn channel_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream
     let shared_crate_name = quote! { lightyear };
     channel_impl(input, shared_crate_name)
 }
+
+// Diffable
+#[doc(hidden)]
+#[proc_macro_derive(DiffableInternal, attributes(diff))]
+pub fn diffable_derive_internal(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let shared_crate_name = quote! { crate };
+    diffable_impl(input, shared_crate_name)
+}
+
+/// Derives the Diffable trait for a struct.
+///
+/// A `{Struct}Delta` type is generated, which only contains the fields that changed.
+/// Every field must implement `Diffable` and `PartialEq`, unless it is annotated with:
+/// - `#[diff(replace)]`: the full value of the field is sent when it changes
+/// - `#[diff(skip)]`: the field is not replicated (it is reset to its `Default` value in `base_value`)
+#[proc_macro_derive(Diffable, attributes(diff))]
+pub fn diffable_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let shared_crate_name = quote! { lightyear };
+    diffable_impl(input, shared_crate_name)
+}
@@ -0,0 +1,45 @@
+pub mod some_component {
+    use lightyear_macros::Diffable;
+
+    #[derive(Diffable, Clone, Debug, PartialEq)]
+    pub struct SomeComponent {
+        pub x: f32,
+        pub items: Vec<u32>,
+        #[diff(skip)]
+        pub cache: usize,
+    }
+
+    #[derive(Diffable, Clone, Debug, PartialEq)]
+    pub struct SomeTupleComponent(pub u32, #[diff(replace)] pub Option<u8>);
+}
+
+#[cfg(test)]
+mod tests {
+    use lightyear::prelude::Diffable;
+
+    use super::some_component::*;
+
+    #[test]
+    fn test_diffable_derive() {
+        let old = SomeComponent::base_value();
+        let new = SomeComponent {
+            x: 1.0,
+            items: vec![1, 2],
+            cache: 3,
+        };
+        let delta = old.diff(&new);
+        assert_eq!(delta.x, Some(1.0));
+
+        let mut applied = old.clone();
+        applied.apply_diff(&delta);
+        assert_eq!(applied.x, 1.0);
+        assert_eq!(applied.items, vec![1, 2]);
+        assert_eq!(applied.cache, 0);
+
+        let old = SomeTupleComponent(1, None);
+        let new = SomeTupleComponent(1, Some(2));
+        let delta = old.diff(&new);
+        assert_eq!(delta.field_0, None);
+        assert_eq!(delta.field_1, Some(Some(2)));
+    }
+}